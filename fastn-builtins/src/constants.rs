@@ -120,6 +120,8 @@ pub const FTD_IMAGE_SRC: &str = "ftd#image-src";
 pub const FTD_IMAGE_SRC_LIGHT: &str = "ftd#image-src.light";
 pub const FTD_IMAGE_SRC_DARK: &str = "ftd#image-src.dark";
 
+pub const FTD_SEARCH_RESULT: &str = "ftd#search-result";
+
 pub const FTD_IMAGE_FIT: &str = "ftd#image-fit";
 pub const FTD_IMAGE_FIT_NONE: &str = "ftd#image-fit.none";
 pub const FTD_IMAGE_FIT_COVER: &str = "ftd#image-fit.cover";
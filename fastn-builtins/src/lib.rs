@@ -1040,6 +1040,89 @@ pub fn default_bag() -> indexmap::IndexMap<String, fastn_resolved::Definition> {
                 external_implementation: true
             })
         ),
+        (
+            fastn_builtins::constants::FTD_SEARCH_RESULT.to_string(),
+            fastn_resolved::Definition::Record(fastn_resolved::Record {
+                name: fastn_builtins::constants::FTD_SEARCH_RESULT.to_string(),
+                fields: std::iter::IntoIterator::into_iter([
+                    fastn_resolved::Field {
+                        name: "title".to_string(),
+                        kind: fastn_resolved::Kind::string().into_kind_data().caption(),
+                        mutable: false,
+                        value: None,
+                        access_modifier: Default::default(),
+                        line_number: 0,
+                    },
+                    fastn_resolved::Field {
+                        name: "url".to_string(),
+                        kind: fastn_resolved::Kind::string().into_kind_data(),
+                        mutable: false,
+                        value: None,
+                        access_modifier: Default::default(),
+                        line_number: 0,
+                    },
+                    fastn_resolved::Field {
+                        name: "summary".to_string(),
+                        kind: fastn_resolved::Kind::string().into_kind_data().into_optional(),
+                        mutable: false,
+                        value: None,
+                        access_modifier: Default::default(),
+                        line_number: 0,
+                    },
+                ])
+                    .collect(),
+                line_number: 0,
+            }),
+        ),
+        (
+            "ftd#search".to_string(),
+            fastn_resolved::Definition::Function(fastn_resolved::Function {
+                name: "ftd#search".to_string(),
+                return_kind: fastn_resolved::KindData {
+                    kind: fastn_resolved::Kind::void(),
+                    caption: false,
+                    body: false,
+                },
+                arguments: vec![
+                    fastn_resolved::Argument {
+                        name: "query".to_string(),
+                        kind: fastn_resolved::KindData {
+                            kind: fastn_resolved::Kind::string(),
+                            caption: false,
+                            body: false,
+                        },
+                        mutable: false,
+                        value: None,
+                        access_modifier: Default::default(),
+                        line_number: 0,
+                    },
+                    fastn_resolved::Argument {
+                        name: "results".to_string(),
+                        kind: fastn_resolved::KindData {
+                            kind: fastn_resolved::Kind::record(
+                                fastn_builtins::constants::FTD_SEARCH_RESULT,
+                            )
+                            .into_list(),
+                            caption: false,
+                            body: false,
+                        },
+                        mutable: true,
+                        value: None,
+                        access_modifier: Default::default(),
+                        line_number: 0,
+                    },
+                ],
+                expression: vec![
+                    fastn_resolved::FunctionExpression {
+                        expression: "ftd.search(query, results)".to_string(),
+                        line_number: 0,
+                    }
+                ],
+                js: None,
+                line_number: 0,
+                external_implementation: true
+            })
+        ),
         (
             "ftd#copy-to-clipboard".to_string(),
             fastn_resolved::Definition::Function(fastn_resolved::Function {
@@ -8,9 +8,13 @@ pub async fn build(
     test: bool,
     check_build: bool,
     zip_url: Option<&str>,
+    compress: bool,
     preview_session_id: &Option<String>,
 ) -> fastn_core::Result<()> {
     let build_dir = config.ds.root().join(".build");
+    // endpoints the documents call can be wasm handlers, which are only timed out while the
+    // epoch ticks
+    fastn_wasm::start_epoch_ticker();
     // Default css and js
     default_build_files(
         build_dir.clone(),
@@ -26,9 +30,10 @@ pub async fn build(
 
         fastn_core::manifest::write_manifest_file(config, &build_dir, zip_url, &None).await?;
 
+        let mut diagnostics = vec![];
         match only_id {
             Some(id) => {
-                return handle_only_id(
+                handle_only_id(
                     id,
                     config,
                     base_url,
@@ -36,8 +41,10 @@ pub async fn build(
                     test,
                     documents,
                     preview_session_id,
+                    &mut diagnostics,
                 )
-                .await;
+                .await?;
+                return failed(diagnostics);
             }
             None => {
                 incremental_build(
@@ -47,8 +54,10 @@ pub async fn build(
                     ignore_failed,
                     test,
                     preview_session_id,
+                    &mut diagnostics,
                 )
                 .await?;
+                failed(diagnostics)?;
             }
         }
     }
@@ -83,6 +92,10 @@ pub async fn build(
         config.download_fonts(&None).await?;
     }
 
+    if compress {
+        fastn_core::compress::build_dir(&config.ds, &build_dir).await?;
+    }
+
     if check_build {
         return fastn_core::post_build_check(config).await;
     }
@@ -90,6 +103,15 @@ pub async fn build(
     Ok(())
 }
 
+/// `fastn build` goes on past the documents it cannot build, and fails at the end with the
+/// `diagnostics` of all of them.
+fn failed(diagnostics: Vec<ftd::interpreter::Diagnostic>) -> fastn_core::Result<()> {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    Err(ftd::interpreter::Error::Diagnostics(diagnostics).into())
+}
+
 mod build_dir {
     pub(crate) fn get_build_content() -> std::io::Result<std::collections::BTreeMap<String, String>>
     {
@@ -200,6 +222,15 @@ mod cache {
     pub(crate) struct Document {
         pub(crate) html_checksum: String,
         pub(crate) dependencies: Vec<String>,
+        /// files read by the `data-file` processor, path -> checksum
+        #[serde(default)]
+        pub(crate) data_files: std::collections::BTreeMap<String, String>,
+        /// the text of the document, kept for the search index of the documents not rebuilt
+        #[serde(default)]
+        pub(crate) search: Option<fastn_core::search::Document>,
+        /// the title, summary and date of the document, for the sitemap and the feeds
+        #[serde(default)]
+        pub(crate) metadata: Option<fastn_core::sitemap::seo::Metadata>,
     }
 }
 
@@ -232,6 +263,7 @@ async fn handle_dependency_file(
     name_without_package_name: String,
     processed: &mut Vec<String>,
     preview_session_id: &Option<String>,
+    diagnostics: &mut Vec<ftd::interpreter::Diagnostic>,
 ) -> fastn_core::Result<()> {
     for document in documents.values() {
         if remove_extension(document.get_id()).eq(name_without_package_name.as_str())
@@ -251,6 +283,7 @@ async fn handle_dependency_file(
                 true,
                 Some(cache),
                 preview_session_id,
+                diagnostics,
             )
             .await?;
             processed.push(id);
@@ -291,18 +324,18 @@ async fn remove_deleted_documents(
         let folder_parent = folder_path.parent();
         let file_path = &folder_path.with_extension("ftd");
 
-        config.ds.remove(file_path).await?;
-        config.ds.remove(&folder_path).await?;
+        config.ds.remove(file_path, &None).await?;
+        config.ds.remove(&folder_path, &None).await?;
 
         // If the parent folder of the file's output folder is also empty, delete it as well.
         if let Some(folder_parent) = folder_parent
             && config
                 .ds
-                .get_all_file_path(&folder_parent, &[])
-                .await
+                .get_all_file_path(&folder_parent, &[], &None)
+                .await?
                 .is_empty()
         {
-            config.ds.remove(&folder_parent).await?;
+            config.ds.remove(&folder_parent, &None).await?;
         }
 
         c.documents.remove(removed_doc_id);
@@ -311,7 +344,7 @@ async fn remove_deleted_documents(
     Ok(())
 }
 
-#[tracing::instrument(skip(config, documents))]
+#[tracing::instrument(skip(config, documents, diagnostics))]
 async fn incremental_build(
     config: &fastn_core::Config,
     documents: &std::collections::BTreeMap<String, fastn_core::File>,
@@ -319,6 +352,7 @@ async fn incremental_build(
     ignore_failed: bool,
     test: bool,
     preview_session_id: &Option<String>,
+    diagnostics: &mut Vec<ftd::interpreter::Diagnostic>,
 ) -> fastn_core::Result<()> {
     // https://fastn.com/rfc/incremental-build/
     use itertools::Itertools;
@@ -344,6 +378,7 @@ async fn incremental_build(
                     true,
                     Some(&mut c),
                     preview_session_id,
+                    diagnostics,
                 )
                 .await?;
                 continue;
@@ -395,6 +430,7 @@ async fn incremental_build(
                         unresolved_dependency.to_string(),
                         &mut processed,
                         preview_session_id,
+                        diagnostics,
                     )
                     .await?;
 
@@ -428,6 +464,7 @@ async fn incremental_build(
                         unresolved_dependency.to_string(),
                         &mut processed,
                         preview_session_id,
+                        diagnostics,
                     )
                     .await?;
 
@@ -461,18 +498,39 @@ async fn incremental_build(
                 true,
                 Some(&mut c),
                 preview_session_id,
+                diagnostics,
             )
             .await?;
             processed.push(id);
         }
     }
 
+    // the search index and the sitemap would miss the documents which failed
+    if diagnostics.is_empty() {
+        let search_documents = c
+            .documents
+            .values()
+            .filter_map(|d| d.search.clone())
+            .collect::<Vec<_>>();
+        fastn_core::search::write(config, &config.build_dir(), &search_documents).await?;
+
+        // sitemap.xml, robots.txt and the feeds of the sitemap sections
+        let metadata = c
+            .documents
+            .values()
+            .filter_map(|d| d.metadata.clone())
+            .collect::<Vec<_>>();
+        fastn_core::sitemap::seo::build(config, &config.build_dir(), &metadata, preview_session_id)
+            .await?;
+    }
+
     c.cache_it()?;
 
     Ok(())
 }
 
-#[tracing::instrument(skip(config, documents))]
+#[tracing::instrument(skip(config, documents, diagnostics))]
+#[allow(clippy::too_many_arguments)]
 async fn handle_only_id(
     id: &str,
     config: &fastn_core::Config,
@@ -481,6 +539,7 @@ async fn handle_only_id(
     test: bool,
     documents: std::collections::BTreeMap<String, fastn_core::File>,
     preview_session_id: &Option<String>,
+    diagnostics: &mut Vec<ftd::interpreter::Diagnostic>,
 ) -> fastn_core::Result<()> {
     for doc in documents.values() {
         if doc.get_id().eq(id) || doc.get_id_with_package().eq(id) {
@@ -493,6 +552,7 @@ async fn handle_only_id(
                 false,
                 None,
                 preview_session_id,
+                diagnostics,
             )
             .await;
         }
@@ -515,6 +575,7 @@ async fn handle_file(
     build_static_files: bool,
     cache: Option<&mut cache::Cache>,
     preview_session_id: &Option<String>,
+    diagnostics: &mut Vec<ftd::interpreter::Diagnostic>,
 ) -> fastn_core::Result<()> {
     let start = std::time::Instant::now();
     print!("Processing {} ... ", document.get_id_with_package());
@@ -536,20 +597,32 @@ async fn handle_file(
             start,
         );
     }
-    if process_status.is_err() {
+    if let Err(e) = process_status {
         fastn_core::utils::print_error(
             format!("Failed {}/{}", package_name.as_str(), document.get_id()).as_str(),
             start,
         );
-        return process_status;
+        // an error in a document is found again in each document importing it
+        let fastn_core::Error::FTDInterpreterError(ftd::interpreter::Error::Diagnostics(errors)) =
+            e
+        else {
+            return Err(e);
+        };
+        for error in errors {
+            if !diagnostics.contains(&error) {
+                diagnostics.push(error);
+            }
+        }
     }
     Ok(())
 }
 
-fn is_cached<'a>(
+async fn is_cached<'a>(
     cache: Option<&'a mut cache::Cache>,
     doc: &fastn_core::Document,
     file_path: &str,
+    config: &fastn_core::Config,
+    preview_session_id: &Option<String>,
 ) -> (Option<&'a mut cache::Cache>, bool) {
     let cache: &mut cache::Cache = match cache {
         Some(c) => c,
@@ -623,6 +696,20 @@ fn is_cached<'a>(
         }
     }
 
+    for (path, checksum) in &cached_doc.data_files {
+        match config
+            .ds
+            .read_content(&fastn_ds::Path::new(path), preview_session_id)
+            .await
+        {
+            Ok(content) if &fastn_core::utils::generate_hash(content) == checksum => {}
+            _ => {
+                // println!("cache miss: data file {} changed or removed", path);
+                return (Some(cache), false);
+            }
+        }
+    }
+
     // println!("cache hit");
     (Some(cache), true)
 }
@@ -657,7 +744,8 @@ async fn handle_file_(
                 fastn_core::utils::replace_last_n(doc.id.as_str(), 1, ".ftd", "/index.html")
             };
 
-            let (cache, is_cached) = is_cached(cache, doc, file_path.as_str());
+            let (cache, is_cached) =
+                is_cached(cache, doc, file_path.as_str(), config, preview_session_id).await;
             if is_cached {
                 return Ok(());
             }
@@ -674,13 +762,14 @@ async fn handle_file_(
                 return Ok(());
             }
 
-            let resp = {
+            let (resp, data_files, search, metadata) = {
                 let req = fastn_core::http::Request::default();
                 let mut req_config =
                     fastn_core::RequestConfig::new(config, &req, doc.id.as_str(), base_url);
                 req_config.current_document = Some(document.get_id().to_string());
+                req_config.collect_page_data = true;
 
-                fastn_core::package::package_doc::process_ftd(
+                let resp = fastn_core::package::package_doc::process_ftd(
                     &mut req_config,
                     doc,
                     base_url,
@@ -689,7 +778,13 @@ async fn handle_file_(
                     file_path.as_str(),
                     preview_session_id,
                 )
-                .await
+                .await;
+                (
+                    resp,
+                    req_config.data_files,
+                    req_config.search_document,
+                    req_config.page_metadata,
+                )
             };
 
             match (resp, ignore_failed) {
@@ -703,6 +798,9 @@ async fn handle_file_(
                             cache::Document {
                                 html_checksum: r.checksum(),
                                 dependencies,
+                                data_files,
+                                search,
+                                metadata,
                             },
                         );
                         cache.file_checksum.insert(
@@ -715,6 +813,32 @@ async fn handle_file_(
                     print!("Failed ");
                     return Ok(());
                 }
+                (
+                    Err(fastn_core::Error::FTDInterpreterError(
+                        ftd::interpreter::Error::Diagnostics(errors),
+                    )),
+                    _,
+                ) => {
+                    // rendering stops at the first error, interpreting the document again finds
+                    // all of them, but not those of the processors, which are not run
+                    let id = doc.id_with_package();
+                    let (source, line_number) =
+                        fastn_core::commands::check::prefixed_source(config, doc)?;
+                    let all = fastn_core::commands::check::interpret_diagnostics(
+                        config,
+                        doc,
+                        id.as_str(),
+                        source.as_str(),
+                        line_number,
+                    )
+                    .await;
+                    return Err(ftd::interpreter::Error::Diagnostics(if all.is_empty() {
+                        errors
+                    } else {
+                        all
+                    })
+                    .into());
+                }
                 (Err(e), _) => {
                     return Err(e);
                 }
@@ -862,6 +986,25 @@ async fn get_documents_for_current_package(
         documents.extend(files);
     }
 
+    // the build is served to everyone, so documents with sitemap `readers` are left out of it
+    documents.retain(|id, file| {
+        if id == "FASTN.ftd"
+            || !matches!(
+                file,
+                fastn_core::File::Ftd(_) | fastn_core::File::Markdown(_)
+            )
+        {
+            return true;
+        }
+        let path = fastn_core::utils::id_to_path(id).replace(std::path::MAIN_SEPARATOR, "/");
+        let readable =
+            fastn_core::sitemap::access::find(&config.package, path.as_str()).is_public();
+        if !readable {
+            println!("Skipping {id}, it is not world readable");
+        }
+        readable
+    });
+
     Ok(documents)
 }
 
@@ -889,15 +1032,20 @@ async fn process_static(
             .join(package.name.as_str());
 
         let full_file_path = build_path.join(sa.id.as_str());
-        ds.write_content(&full_file_path, &sa.content).await?;
+        ds.write_content(&full_file_path, &sa.content, &None)
+            .await?;
 
         {
             // TODO: need to remove this once download_base_url is removed
             let content = ds
                 .read_content(&sa.base_path.join(sa.id.as_str()), &None)
                 .await?;
-            ds.write_content(&base_path.join(".build").join(sa.id.as_str()), &content)
-                .await?;
+            ds.write_content(
+                &base_path.join(".build").join(sa.id.as_str()),
+                &content,
+                &None,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -2,7 +2,155 @@ pub const INDEX_FILE: &str = "index.html";
 pub const BUILD_FOLDER: &str = ".build";
 pub const IGNORED_DIRECTORIES: [&str; 4] = ["-", "images", "static", "assets"];
 
+/// `fastn check`: interprets every document of the package, without running the processors
+/// which talk to the network or a database, and reports what is wrong with them. Errors are what
+/// stops a document from rendering, e.g. unresolved imports, unknown components or kind
+/// mismatches, warnings are what [ftd::interpreter::lint] finds and the messages a translation
+/// is missing.
+///
+/// `format` is either `text`, code frames, or `json`, a list of [ftd::interpreter::Diagnostic].
+/// Returns an error if there are errors, or warnings with `deny_warnings`, so that CI fails.
+pub async fn check(
+    config: &fastn_core::Config,
+    format: &str,
+    deny_warnings: bool,
+) -> fastn_core::Result<()> {
+    if !matches!(format, "text" | "json") {
+        return Err(fastn_core::Error::UsageError {
+            message: format!("unknown format `{format}`, expected `text` or `json`"),
+        });
+    }
+
+    let mut diagnostics = translation_diagnostics(config).await?;
+    let mut documents = vec![];
+    for file in config.get_files(&config.package, &None).await? {
+        let fastn_core::File::Ftd(doc) = file else {
+            continue;
+        };
+        if doc.id.eq("FASTN.ftd") {
+            continue;
+        }
+
+        let id = doc.id_with_package();
+        let (source, line_number) = prefixed_source(config, &doc)?;
+        match ftd::interpreter::ParsedDocument::parse_with_line_number(
+            id.as_str(),
+            source.as_str(),
+            line_number,
+        ) {
+            Ok(parsed) => {
+                diagnostics.extend(ftd::interpreter::lint::lint(&parsed));
+                documents.push(parsed);
+            }
+            Err(e) => {
+                diagnostics.push(fastn_core::doc::error_diagnostic(
+                    e,
+                    id.as_str(),
+                    &source,
+                    line_number,
+                ));
+                continue;
+            }
+        }
+
+        diagnostics.extend(
+            interpret_diagnostics(config, &doc, id.as_str(), source.as_str(), line_number).await,
+        );
+    }
+    diagnostics.extend(ftd::interpreter::lint::unused_variables(
+        &documents.iter().collect::<Vec<_>>(),
+    ));
+    // an error in a document is found again in each document importing it
+    let diagnostics = diagnostics
+        .into_iter()
+        .fold(vec![], |mut unique, diagnostic| {
+            if !unique.contains(&diagnostic) {
+                unique.push(diagnostic);
+            }
+            unique
+        });
+
+    if format.eq("json") {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else if diagnostics.is_empty() {
+        println!("Checked {} documents, found no problems", documents.len());
+    } else {
+        println!("{}", ftd::interpreter::Diagnostic::render_all(&diagnostics));
+    }
+
+    // a library package has variables only its dependents use, so warnings do not fail by default
+    let failing = diagnostics
+        .iter()
+        .filter(|d| deny_warnings || d.is_error())
+        .count();
+    if failing == 0 {
+        return Ok(());
+    }
+    Err(fastn_core::Error::AssertError {
+        message: format!("`fastn check` found {failing} problems"),
+    })
+}
+
+/// The errors of `doc`, all of them, found by interpreting its `source` with the `id` and the
+/// `line_number` [prefixed_source] gives, without running the processors.
+pub(crate) async fn interpret_diagnostics(
+    config: &fastn_core::Config,
+    doc: &fastn_core::Document,
+    id: &str,
+    source: &str,
+    line_number: usize,
+) -> Vec<ftd::interpreter::Diagnostic> {
+    let req = fastn_core::http::Request::default();
+    let mut req_config = fastn_core::RequestConfig::new(config, &req, doc.id.as_str(), "/");
+    req_config.current_document = Some(doc.id.to_string());
+    req_config.static_check = true;
+    match fastn_core::doc::interpret_collecting_errors(
+        id,
+        source,
+        &mut req_config,
+        "/",
+        false,
+        line_number,
+        &None,
+    )
+    .await
+    {
+        Ok(_) => vec![],
+        Err(ftd::interpreter::Error::Diagnostics(errors)) => errors,
+        Err(e) => vec![fastn_core::doc::error_diagnostic(
+            e,
+            id,
+            source,
+            line_number,
+        )],
+    }
+}
+
+/// The source of `doc` as it is interpreted, with the auto imports of its package, and the number
+/// of lines these take before the content of `doc`.
+pub(crate) fn prefixed_source(
+    config: &fastn_core::Config,
+    doc: &fastn_core::Document,
+) -> fastn_core::Result<(String, usize)> {
+    let mut package = config.find_package_else_default(doc.package_name.as_str(), None);
+    package.auto_import_language(
+        config.package.requested_language.clone(),
+        config.package.selected_language.clone(),
+    )?;
+    let source =
+        package.get_prefixed_body(&config.package, doc.content.as_str(), doc.id.as_str(), true);
+    let source = package.fix_imports_in_body(source.as_str(), doc.id.as_str())?;
+    let line_number = source.split('\n').count() - doc.content.split('\n').count();
+    Ok((source, line_number))
+}
+
 pub async fn post_build_check(config: &fastn_core::Config) -> fastn_core::Result<()> {
+    // the messages a translation is missing, the same warnings `fastn check` reports
+    let warnings = translation_diagnostics(config).await?;
+    if !warnings.is_empty() {
+        println!("{}", ftd::interpreter::Diagnostic::render_all(&warnings));
+    }
+
     let build_path = config.ds.root().join(BUILD_FOLDER);
     println!("Post build index assertion started ...");
 
@@ -19,6 +167,84 @@ pub async fn post_build_check(config: &fastn_core::Config) -> fastn_core::Result
     Ok(())
 }
 
+/// A warning for each message of the primary language that a translation does not have, pages in
+/// that language show them in the primary language. The warning points at the message in the
+/// primary language.
+async fn translation_diagnostics(
+    config: &fastn_core::Config,
+) -> fastn_core::Result<Vec<ftd::interpreter::Diagnostic>> {
+    let root = config.ds.root();
+    let primary = fastn_core::library2022::processor::translate::primary_language(&config.package);
+    let primary_sources = fastn_core::library2022::processor::translate::read_sources(
+        &config.ds,
+        &root,
+        primary.as_str(),
+        &None,
+    )
+    .await?;
+    let primary_ids = fastn_core::library2022::processor::translate::message_ids(&primary_sources);
+
+    let mut diagnostics = vec![];
+
+    for language in
+        fastn_core::library2022::processor::translate::languages(&config.ds, &root).await
+    {
+        if language == primary {
+            continue;
+        }
+        let ids = fastn_core::library2022::processor::translate::message_ids(
+            &fastn_core::library2022::processor::translate::read_sources(
+                &config.ds,
+                &root,
+                language.as_str(),
+                &None,
+            )
+            .await?,
+        );
+        for id in primary_ids.difference(&ids) {
+            let message = format!(
+                "message `{id}` is missing in {}/{language}/, the `{primary}` one is used",
+                fastn_core::library2022::processor::translate::MESSAGES_DIR
+            );
+            diagnostics.push(message_diagnostic(&root, &primary_sources, id, message));
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// A warning on the line of `sources` where the message `id` is defined.
+fn message_diagnostic(
+    root: &fastn_ds::Path,
+    sources: &[fastn_core::library2022::processor::translate::Source],
+    id: &str,
+    message: String,
+) -> ftd::interpreter::Diagnostic {
+    let root = root.to_string();
+    let (doc_id, line_number, line) = sources
+        .iter()
+        .find_map(|source| {
+            let (index, line) = source.content.split('\n').enumerate().find(|(_, line)| {
+                line.split_once('=')
+                    .is_some_and(|(name, _)| name.trim() == id)
+            })?;
+            Some((source.path.as_str(), index + 1, Some(line.to_string())))
+        })
+        .unwrap_or((root.as_str(), 0, None));
+
+    ftd::interpreter::Diagnostic {
+        severity: ftd::interpreter::Severity::Warning,
+        doc_id: doc_id
+            .trim_start_matches(root.as_str())
+            .trim_start_matches('/')
+            .to_string(),
+        line_number,
+        span: None,
+        line,
+        message,
+    }
+}
+
 // Todo: Rewrite this code
 /*#[async_recursion::async_recursion]
 async fn check_index_in_folders(
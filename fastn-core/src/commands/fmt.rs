@@ -1,7 +1,14 @@
+/// `fastn fmt`: formats the ftd documents of the package, or only `file`, in place. See [format]
+/// for what changes.
+///
+/// With `check` nothing is written: the documents which are not formatted are printed as diffs,
+/// and an error is returned so that CI fails.
 pub async fn fmt(
     config: &fastn_core::Config,
     file: Option<&str>,
     no_indentation: bool,
+    check: bool,
+    wrap: Option<usize>,
 ) -> fastn_core::Result<()> {
     use colored::Colorize;
     use itertools::Itertools;
@@ -13,6 +20,8 @@ pub async fn fmt(
         .filter_map(|v| v.get_ftd_document())
         .collect_vec();
 
+    let mut failed = 0;
+    let mut unformatted = 0;
     for ftd_document in documents {
         if let Some(file) = file
             && !ftd_document.id.eq(file)
@@ -20,341 +29,790 @@ pub async fn fmt(
             continue;
         }
 
-        print!("Formatting {} ... ", ftd_document.id);
+        if !check {
+            print!("Formatting {} ... ", ftd_document.id);
+        }
+
+        let formatted = match format(
+            ftd_document.content.as_str(),
+            ftd_document.id.as_str(),
+            !no_indentation,
+            wrap,
+        ) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                failed += 1;
+                if check {
+                    println!("Could not format {}: {e}", ftd_document.id);
+                } else {
+                    println!("{}: {e}", "Failed".red());
+                }
+                continue;
+            }
+        };
+
+        if check {
+            if formatted != ftd_document.content {
+                unformatted += 1;
+                println!("Diff in {}:", ftd_document.id);
+                print!(
+                    "{}",
+                    diffy::create_patch(ftd_document.content.as_str(), formatted.as_str())
+                );
+            }
+            continue;
+        }
 
-        let parsed_content =
-            parsed_to_sections(format!("{}\n\n", ftd_document.content.as_str()).as_str());
-        let format_sections = format_sections(parsed_content, !no_indentation);
         config
             .ds
-            .write_content(&ftd_document.get_full_path(), &format_sections.into_bytes())
+            .write_content(
+                &ftd_document.get_full_path(),
+                &formatted.into_bytes(),
+                &None,
+            )
             .await?;
         println!("{}", "Done".green())
     }
 
+    if failed > 0 {
+        return Err(fastn_core::Error::AssertError {
+            message: format!("`fastn fmt` could not format {failed} documents"),
+        });
+    }
+    if unformatted > 0 {
+        return Err(fastn_core::Error::AssertError {
+            message: format!("{unformatted} documents are not formatted, run `fastn fmt`"),
+        });
+    }
     Ok(())
 }
 
-#[derive(Debug)]
-struct Section {
-    value: String,
-    kind: SectionKind,
+/// `fastn fmt --stdin`: formats the document read from stdin and prints it, for editors to format
+/// a buffer which may not be saved yet. This needs no package.
+pub fn fmt_stdin(no_indentation: bool, wrap: Option<usize>) -> fastn_core::Result<()> {
+    use std::io::Read;
+
+    let mut source = String::new();
+    std::io::stdin().read_to_string(&mut source)?;
+    print!(
+        "{}",
+        format(source.as_str(), "stdin", !no_indentation, wrap)?
+    );
+    Ok(())
+}
+
+/// Formats the ftd document `source`:
+///
+/// - section lines, `-- end:` lines and headers get canonical spacing, and, with `indentation`,
+///   the sections nested in others are indented by a tab per level (top level sections and their
+///   children are not),
+/// - the inline headers of a section are ordered `$processor$`, `for`, `if`, the rest and then
+///   the event handlers,
+/// - bodies are indented with their section, keeping the indentation of their lines relative to
+///   each other,
+/// - comments are indented like the line after them, blank lines are kept,
+/// - with `wrap`, the caption of a section without children or body, which makes its line longer
+///   than `wrap` columns, is moved to a `-- <section>.caption:` block and wrapped.
+///
+/// What each line starting with `-- ` is, and how deep it is nested, comes from the sections the
+/// parser gives, the comments and blank lines in between are kept where they are.
+///
+/// The formatted document is parsed again, and has to give the same sections as `source` did:
+/// this returns an error rather than a formatting which changes what the document means.
+pub fn format(
+    source: &str,
+    doc_id: &str,
+    indentation: bool,
+    wrap: Option<usize>,
+) -> fastn_core::Result<String> {
+    use itertools::Itertools;
+
+    let sections = ftd_p1::parse(source, doc_id)?;
+
+    let lines = source.lines().collect_vec();
+    let structural = lines
+        .iter()
+        .positions(|line| is_structural(line))
+        .collect_vec();
+    let roles = Walk::roles(&sections, &lines, &structural).ok_or_else(|| {
+        fastn_core::Error::generic(format!(
+            "{doc_id}: could not tell the sections of the document from its lines, the document \
+            is left as it is"
+        ))
+    })?;
+    let pad = |depth: usize| {
+        if indentation {
+            "\t".repeat(depth.saturating_sub(1))
+        } else {
+            String::new()
+        }
+    };
+
+    let mut output = vec![];
+    let first = structural.first().copied().unwrap_or(lines.len());
+    let (preface, comments) = split_leading_comments(&lines[..first], !structural.is_empty());
+    output.extend(format_body(preface, ""));
+    let first_pad = pad(roles.first().map(|v| v.depth).unwrap_or_default());
+    output.extend(comments.iter().map(|v| format!("{first_pad}{}", v.trim())));
+
+    for (position, &index) in structural.iter().enumerate() {
+        let line = lines[index];
+        let role = roles[position];
+        let line_pad = pad(role.depth);
+        let next = structural.get(position + 1).copied();
+        let next_role = roles.get(position + 1).copied();
+        let next_pad = pad(next_role.map(|v| v.depth).unwrap_or_default());
+        let (region, comments) = split_leading_comments(
+            &lines[index + 1..next.unwrap_or(lines.len())],
+            next.is_some(),
+        );
+
+        if role.kind == Kind::End {
+            output.push(format!("{line_pad}{}", canonical(line)));
+            output.extend(format_body(region, line_pad.as_str()));
+        } else {
+            let (headers, rest) =
+                region.split_at(region.iter().take_while(|v| !v.trim().is_empty()).count());
+            let caption = match wrap {
+                Some(width)
+                    if role.kind == (Kind::Section { has_end: false })
+                        && rest.iter().all(|v| v.trim().is_empty())
+                        // a caption block goes on until a line starting with `-- `, an indented
+                        // section after it would be read as part of the caption
+                        && next_pad.is_empty()
+                        && !next_role.is_some_and(|v| matches!(v.kind, Kind::BlockHeader { .. })) =>
+                {
+                    wrap_caption(line, line_pad.as_str(), width)
+                }
+                _ => None,
+            };
+            let is_section = matches!(role.kind, Kind::Section { .. });
+
+            match caption {
+                Some((line, block)) => {
+                    output.push(line);
+                    output.extend(format_headers(headers, line_pad.as_str(), true));
+                    output.extend(block);
+                    if rest.is_empty() && next.is_some() {
+                        output.push(String::new());
+                    }
+                }
+                None => {
+                    output.push(format!("{line_pad}{}", canonical(line)));
+                    output.extend(format_headers(headers, line_pad.as_str(), is_section));
+                }
+            }
+
+            if role.kind == (Kind::BlockHeader { caption: true }) {
+                // the lines of a caption block are its value as they are
+                output.extend(rest.iter().map(|v| v.to_string()));
+            } else {
+                output.extend(format_body(rest, line_pad.as_str()));
+            }
+        }
+
+        output.extend(comments.iter().map(|v| format!("{next_pad}{}", v.trim())));
+    }
+
+    let formatted = output.join("\n");
+    let formatted = formatted.trim_matches('\n');
+    let formatted = if formatted.is_empty() {
+        String::new()
+    } else {
+        format!("{formatted}\n")
+    };
+
+    let before = comparable(&sections, wrap.is_some());
+    let after = comparable(&ftd_p1::parse(formatted.as_str(), doc_id)?, wrap.is_some());
+    if let Some(changed) =
+        (0..before.len().max(after.len())).find(|&i| before.get(i) != after.get(i))
+    {
+        let line_number = sections
+            .get(changed)
+            .or(sections.last())
+            .map(|v| v.line_number)
+            .unwrap_or_default();
+        return Err(fastn_core::Error::generic(format!(
+            "{doc_id}:{line_number}: formatting would change what this section means, the \
+            document is left as it is"
+        )));
+    }
+    Ok(formatted)
 }
 
-#[derive(Debug)]
-enum SectionKind {
-    Comment,
-    Empty,
+/// What a line starting with `-- ` is, as the parser saw it, and how many sections it is nested
+/// in.
+#[derive(Debug, Clone, Copy)]
+struct Role {
+    depth: usize,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// `has_end`: the section has children, or is ended by an `-- end:` anyway.
     Section {
-        name: String,
-        end: bool,
-        sub_sections: Vec<Section>,
+        has_end: bool,
+    },
+    /// A `-- <section>.<header>:` line, `caption` for a caption block, whose lines are its value.
+    BlockHeader {
+        caption: bool,
     },
+    End,
+}
+
+/// Walks the sections the parser gave, in the order of the `structural` lines they take. The
+/// sections know their line, the block headers and `-- end:` lines are the other structural
+/// lines, in between.
+struct Walk<'a> {
+    lines: &'a [&'a str],
+    structural: &'a [usize],
+    /// the lines of all the sections, including those in the value of a header
+    section_lines: std::collections::HashSet<usize>,
+    roles: Vec<Option<Role>>,
+    next: usize,
 }
 
-impl Section {
-    fn new_comment(value: &str) -> Section {
-        Section {
-            value: value.to_string(),
-            kind: SectionKind::Comment,
+impl<'a> Walk<'a> {
+    /// The [Role] of each of the `structural` lines of `lines`, or `None` if these do not match
+    /// `sections`.
+    fn roles(
+        sections: &[ftd_p1::Section],
+        lines: &'a [&'a str],
+        structural: &'a [usize],
+    ) -> Option<Vec<Role>> {
+        fn section_lines(
+            sections: &[ftd_p1::Section],
+            found: &mut std::collections::HashSet<usize>,
+        ) {
+            for section in sections {
+                found.insert(section.line_number.saturating_sub(1));
+                for header in section.headers.0.iter() {
+                    if let ftd_p1::Header::Section(header) = header {
+                        section_lines(&header.section, found);
+                    }
+                }
+                section_lines(&section.sub_sections, found);
+            }
         }
+
+        let mut walk = Walk {
+            lines,
+            structural,
+            section_lines: Default::default(),
+            roles: vec![None; structural.len()],
+            next: 0,
+        };
+        section_lines(sections, &mut walk.section_lines);
+        walk.sections(sections, 0)?;
+        walk.roles.into_iter().collect()
     }
 
-    fn new_empty(value: &str) -> Section {
-        Section {
-            value: value.to_string(),
-            kind: SectionKind::Empty,
+    fn sections(&mut self, sections: &[ftd_p1::Section], depth: usize) -> Option<()> {
+        for section in sections {
+            self.section(section, depth)?;
         }
+        Some(())
     }
 
-    fn new_section(name: &str, value: &str) -> Section {
-        Section {
-            value: value.to_string(),
-            kind: SectionKind::Section {
-                name: name.to_string(),
-                end: false,
-                sub_sections: vec![],
-            },
+    fn section(&mut self, section: &ftd_p1::Section, depth: usize) -> Option<()> {
+        let at = self.next;
+        if self.peek()? + 1 != section.line_number {
+            return None;
         }
-    }
-}
+        self.take(depth, Kind::Section { has_end: false });
+
+        // the block headers are nested in the section if it has children, like these, which
+        // is found out once they are walked
+        let name = section.name.trim_start_matches('$');
+        // the last block header, an `-- end:` closes it if its value is sections, even none
+        let mut open = None;
+        while let Some(line) = self.peek()
+            && !self.section_lines.contains(&line)
+        {
+            let line = self.lines[line];
+            match end_name(line) {
+                Some(end) if open == Some(end) => {
+                    self.take(depth + 1, Kind::End);
+                    open = None;
+                }
+                Some(_) => break,
+                None => {
+                    let key = section_name(line).trim_start_matches('$');
+                    let caption = key == format!("{name}.caption");
+                    self.take(depth + 1, Kind::BlockHeader { caption });
+                    open = Some(key);
+                    if let Some(header) = self.peek().and_then(|line| {
+                        section.headers.0.iter().find_map(|header| match header {
+                            ftd_p1::Header::Section(header)
+                                if header
+                                    .section
+                                    .first()
+                                    .is_some_and(|v| v.line_number == line + 1) =>
+                            {
+                                Some(header)
+                            }
+                            _ => None,
+                        })
+                    }) {
+                        self.sections(&header.section, depth + 2)?;
+                        self.end(depth + 1)?;
+                        open = None;
+                    }
+                }
+            }
+        }
+        let block_headers = at + 1..self.next;
 
-fn format_sections(sections: Vec<Section>, indentation: bool) -> String {
-    let mut output = vec![];
-    for section in sections {
-        output.push(format_section(
-            &section,
-            if indentation { Some(-1) } else { None },
-        ))
+        self.sections(&section.sub_sections, depth + 1)?;
+        // a section without children may be ended anyway
+        if !section.sub_sections.is_empty()
+            || self.peek().and_then(|line| end_name(self.lines[line])) == Some(name)
+        {
+            self.end(depth)?;
+            self.roles[at] = Some(Role {
+                depth,
+                kind: Kind::Section { has_end: true },
+            });
+        } else {
+            for role in self.roles[block_headers].iter_mut().flatten() {
+                role.depth -= 1;
+            }
+        }
+        Some(())
     }
-    format!("{}\n", output.join("\n").trim_end())
-}
 
-fn format_section(section: &Section, indentation: Option<i32>) -> String {
-    match &section.kind {
-        SectionKind::Comment => add_indentation(section.value.as_str(), indentation),
-        SectionKind::Empty => section.value.to_string(),
-        SectionKind::Section {
-            name,
-            end,
-            sub_sections,
-        } => format_section_kind(
-            name.as_str(),
-            *end,
-            sub_sections.as_slice(),
-            section.value.as_str(),
-            indentation,
-        ),
+    fn end(&mut self, depth: usize) -> Option<()> {
+        end_name(self.lines[self.peek()?])?;
+        self.take(depth, Kind::End);
+        Some(())
     }
-}
 
-fn format_section_kind(
-    section_name: &str,
-    end: bool,
-    sub_sections: &[Section],
-    value: &str,
-    indentation: Option<i32>,
-) -> String {
-    let mut output = vec![add_indentation(value, indentation)];
-    for section in sub_sections {
-        output.push(format_section(section, indentation.map(|v| v + 1)));
+    /// The index in `lines` of the next structural line.
+    fn peek(&self) -> Option<usize> {
+        self.structural.get(self.next).copied()
     }
-    if end {
-        output.push(add_indentation(
-            format!("-- end: {section_name}").as_str(),
-            indentation,
-        ));
+
+    fn take(&mut self, depth: usize, kind: Kind) {
+        self.roles[self.next] = Some(Role { depth, kind });
+        self.next += 1;
     }
-    output.join("\n")
 }
 
-fn add_indentation(input: &str, indentation: Option<i32>) -> String {
-    let indentation = match indentation {
-        Some(indentation) if indentation > 0 => indentation,
-        _ => {
-            return input.to_string();
-        }
-    };
-    let mut value = vec![];
-    for i in input.split('\n') {
-        value.push(format!("{}{}", "\t".repeat(indentation as usize), i));
-    }
-    value.join("\n")
+fn is_structural(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("-- ") || line.starts_with("/-- ")
 }
 
-fn parsed_to_sections(input: &str) -> Vec<Section> {
-    let mut sections = vec![];
-    let mut input = input.to_string();
-    while !input.is_empty() {
-        if end_section(&mut input, &mut sections) {
-            continue;
-        } else if let Some(empty_section) = empty_section(&mut input) {
-            sections.push(empty_section);
-        } else if let Some(comment_section) = comment_section(&mut input) {
-            sections.push(comment_section);
-        } else if let Some(section) = section(&mut input) {
-            sections.push(section);
-        } else {
-            panic!(
-                "`{}`: can't parse",
-                input
-                    .split_once('\n')
-                    .map(|(v, _)| v.to_string())
-                    .unwrap_or_else(|| input.to_string())
-            );
-        }
-    }
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with(";;")
+}
 
-    sections
+/// The name of the section `foo` for `-- end: foo`.
+fn end_name(line: &str) -> Option<&str> {
+    line.trim_start()
+        .strip_prefix("-- ")?
+        .trim_start()
+        .strip_prefix("end:")
+        .map(|v| v.trim().trim_start_matches('$'))
 }
 
-fn end_section(input: &mut String, sections: &mut Vec<Section>) -> bool {
-    let mut remaining = None;
-    let first_line = if let Some((first_line, rem)) = input.split_once('\n') {
-        remaining = Some(rem.to_string());
-        first_line.to_string()
+/// The name of the section, without its kind, of the section line `line`.
+fn section_name(line: &str) -> &str {
+    let line = line.trim_start();
+    let line = line
+        .strip_prefix("/-- ")
+        .or_else(|| line.strip_prefix("-- "))
+        .unwrap_or(line);
+    let head = line.split_once(':').map_or(line, |v| v.0).trim();
+    head.rsplit(' ').next().unwrap_or(head)
+}
+
+/// `lines` without the comments at their end, when these are about the section which follows.
+fn split_leading_comments<'a, 'b>(
+    lines: &'a [&'b str],
+    section_follows: bool,
+) -> (&'a [&'b str], &'a [&'b str]) {
+    let count = if section_follows {
+        lines.iter().rev().take_while(|v| is_comment(v)).count()
     } else {
-        input.to_string()
+        0
     };
+    lines.split_at(lines.len() - count)
+}
 
-    let section_name = if let Some(section_name) = end_section_name(first_line.as_str()) {
-        section_name
+/// `line`, a section line or a header, with the spacing around the `-- ` and the `:` made
+/// canonical.
+fn canonical(line: &str) -> String {
+    let line = line.trim();
+    let (prefix, rest) = if let Some(rest) = line.strip_prefix("/--") {
+        ("/-- ", rest)
+    } else if let Some(rest) = line.strip_prefix("--") {
+        ("-- ", rest)
     } else {
-        return false;
+        ("", line)
     };
-
-    *input = remaining.unwrap_or_default();
-
-    let mut sub_sections = vec![];
-    while let Some(mut section) = sections.pop() {
-        match &mut section.kind {
-            SectionKind::Section {
-                name,
-                end,
-                sub_sections: s,
-            } if section_name
-                .trim_start_matches('$')
-                .eq(name.trim_start_matches('$'))
-                && !*end =>
-            {
-                *end = true;
-                *s = sub_sections;
-                sections.push(section);
-                return true;
-            }
-            _ => {
-                sub_sections.insert(0, section);
-            }
+    match rest.split_once(':') {
+        Some((head, value)) if !value.trim().is_empty() => {
+            format!("{prefix}{}: {}", head.trim(), value.trim())
         }
+        Some((head, _)) => format!("{prefix}{}:", head.trim()),
+        None => format!("{prefix}{}", rest.trim()),
     }
-
-    panic!("cannot find section {section_name} to end")
 }
 
-fn end_section_name(input: &str) -> Option<String> {
-    use itertools::Itertools;
+/// The inline headers of a section, with the comments above each header kept with it when
+/// `reorder` puts them in the canonical order.
+fn format_headers(headers: &[&str], pad: &str, reorder: bool) -> Vec<String> {
+    let mut entries: Vec<Vec<String>> = vec![];
+    let mut comments = vec![];
+    for header in headers {
+        if is_comment(header) {
+            comments.push(header.trim().to_string());
+            continue;
+        }
+        comments.push(canonical(header));
+        entries.push(std::mem::take(&mut comments));
+    }
+    if reorder {
+        entries.sort_by_key(|entry| {
+            let header = entry.last().map(|v| v.as_str()).unwrap_or_default();
+            let head = header.split_once(':').map_or(header, |v| v.0);
+            let head = head
+                .split_once(ftd_p1::utils::INLINE_IF)
+                .map_or(head, |v| v.0);
+            header_rank(head.rsplit(' ').next().unwrap_or(head))
+        });
+    }
+    entries
+        .into_iter()
+        .flatten()
+        .chain(comments)
+        .map(|v| format!("{pad}{v}"))
+        .collect()
+}
 
-    let input = input.split_whitespace().join(" ");
-    input.strip_prefix("-- end:").map(|v| v.trim().to_string())
+/// Where the header `key` goes in the canonical order of the inline headers of a section.
+fn header_rank(key: &str) -> u8 {
+    match key {
+        ftd_ast::utils::PROCESSOR => 0,
+        ftd_ast::utils::FOR | ftd_ast::utils::LOOP => 1,
+        ftd_p1::utils::IF => 2,
+        _ if key.starts_with("$on-") && key.ends_with('$') => 4,
+        _ => 3,
+    }
 }
 
-fn section(input: &mut String) -> Option<Section> {
-    let section_name = get_section_name(input)?;
-    let mut value = vec![];
-    let mut first_time_encounter_section = true;
-    let mut leading_spaces_count = 0;
-    while !input.is_empty() {
-        let (first_line, remaining) = match input.split_once('\n') {
-            Some((first_line, remaining)) => (first_line.to_string(), remaining.to_string()),
-            None => (input.to_string(), String::new()),
-        };
-        let mut trimmed_line = first_line.trim_start().to_string();
-        let current_leading_spaces_count = first_line.len() - trimmed_line.len();
-
-        if trimmed_line.starts_with("-- ") || trimmed_line.starts_with("/-- ") {
-            // If the first_time_encounter_section then store the indentation here in the
-            // `leading_spaces_count`
-            // Also, don't break code, for this is section definition line.
-            if first_time_encounter_section {
-                first_time_encounter_section = false;
-                *input = remaining.to_string();
-                value.push(trimmed_line.trim().to_string());
-                leading_spaces_count = current_leading_spaces_count;
-                continue;
+/// The body of a section, or the blank lines and comments between sections, indented by `pad`:
+/// the lines keep their indentation relative to each other.
+fn format_body(lines: &[&str], pad: &str) -> Vec<String> {
+    let indentation = lines
+        .iter()
+        .filter(|v| !v.trim().is_empty() && !is_comment(v))
+        .map(|v| v.len() - v.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else if is_comment(line) {
+                format!("{pad}{}", line.trim())
+            } else {
+                format!(
+                    "{pad}{}",
+                    line.get(indentation..).unwrap_or(line.trim_start())
+                )
             }
-            *input = format!("{first_line}\n{remaining}");
-            break;
-        }
+        })
+        .collect()
+}
 
-        // Use the indentation saved in `leading_spaces_count` and add indentation upto the
-        // extra space left when deducting the `leading_spaces_count`. This ensures the section body
-        // keeps the indentation as intended by user
-        if !trimmed_line.is_empty() {
-            trimmed_line = format!(
-                "{}{}",
-                " ".repeat(current_leading_spaces_count.saturating_sub(leading_spaces_count)),
-                trimmed_line.trim()
-            );
-        }
-        value.push(trimmed_line);
-        *input = remaining.to_string();
+/// The section line `line` without its caption, and the caption in a `-- <section>.caption:`
+/// block, wrapped at `width` columns, if the line is longer than that. The caption lines are not
+/// indented, these spaces would be part of the caption.
+fn wrap_caption(line: &str, pad: &str, width: usize) -> Option<(String, Vec<String>)> {
+    let line = canonical(line);
+    // a tab takes four columns in most editors
+    if pad.len() * 4 + line.chars().count() <= width {
+        return None;
     }
-
-    if !value.is_empty() {
-        let mut value = value.join("\n").to_string();
-        remove_comment_from_section_value_if_its_comment_for_other_section(input, &mut value);
-        Some(Section::new_section(section_name.as_str(), value.as_str()))
-    } else {
-        None
+    let (name, caption) = line.strip_prefix("-- ")?.split_once(": ")?;
+    if name.contains(' ') || caption.starts_with('$') || caption.contains(";;") {
+        return None;
     }
-}
 
-fn remove_comment_from_section_value_if_its_comment_for_other_section(
-    input: &mut String,
-    value: &mut String,
-) {
-    if !value.trim().is_empty()
-        && let Some((v, probable_comment_section)) = value.rsplit_once("\n\n")
-    {
-        let mut probable_comment_section = probable_comment_section.to_string();
-        if let Some(comment_section) = comment_section(&mut probable_comment_section)
-            && probable_comment_section.trim().is_empty()
-        {
-            *input = format!("{}\n{}", comment_section.value, input);
-            *value = format!("{v}\n");
+    let mut lines: Vec<String> = vec![];
+    for word in caption.split_whitespace() {
+        match lines.last_mut() {
+            Some(last) if last.chars().count() + 1 + word.chars().count() <= width => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
         }
     }
+    if lines
+        .iter()
+        .any(|v| is_structural(v) || is_comment(v) || v.starts_with('\\') || v.starts_with("--"))
+    {
+        return None;
+    }
+
+    let mut block = vec![
+        String::new(),
+        format!("{pad}-- {name}.caption:"),
+        String::new(),
+    ];
+    block.extend(lines);
+    Some((format!("{pad}-- {name}:"), block))
+}
+
+/// `sections` without what formatting changes: line numbers, the order of the inline headers, the
+/// whitespace in blank lines and, when captions are wrapped, the whitespace in captions.
+fn comparable(sections: &[ftd_p1::Section], wrap: bool) -> Vec<ftd_p1::Section> {
+    sections
+        .iter()
+        .map(|section| {
+            let mut section = section.without_line_number();
+            make_comparable(&mut section, wrap);
+            section
+        })
+        .collect()
 }
 
-fn get_section_name(input: &str) -> Option<String> {
+fn make_comparable(section: &mut ftd_p1::Section, wrap: bool) {
     use itertools::Itertools;
 
-    let first_line = if let Some((first_line, _)) = input.split_once('\n') {
-        first_line.trim().to_string()
-    } else {
-        input.trim().to_string()
-    };
-    if !first_line.starts_with("-- ") && !first_line.starts_with("/-- ") {
-        None
-    } else if let Some((section_name_kind, _)) = first_line.split_once(':') {
-        Some(
-            section_name_kind
-                .split_whitespace()
-                .join(" ")
-                .rsplit_once(' ')
-                .map(|(_, v)| v.to_string())
-                .unwrap_or_else(|| section_name_kind.to_string()),
+    let inline = section
+        .headers
+        .0
+        .iter()
+        .take_while(
+            |v| matches!(v, ftd_p1::Header::KV(kv) if kv.source == ftd_p1::KVSource::Header),
         )
-    } else {
-        None
+        .count();
+    section.headers.0[..inline].sort_by_key(|v| header_rank(v.get_key().as_str()));
+
+    if wrap
+        && let Some(ftd_p1::Header::KV(kv)) = section.caption.as_mut()
+        && let Some(value) = kv.value.as_mut()
+    {
+        *value = value.split_whitespace().join(" ");
+    }
+    if let Some(body) = section.body.as_mut() {
+        body.value = without_blank_whitespace(body.value.as_str());
     }
-}
 
-fn empty_section(input: &mut String) -> Option<Section> {
-    let mut value = vec![];
-    while !input.is_empty() {
-        let (first_line, remaining) = match input.split_once('\n') {
-            Some((first_line, remaining)) => (first_line.to_string(), remaining.to_string()),
-            None => (input.to_string(), String::new()),
-        };
-        let trimmed_line = first_line.trim().to_string();
-        if !trimmed_line.is_empty() {
-            *input = format!("{first_line}\n{remaining}");
-            break;
+    for header in section.headers.0.iter_mut() {
+        match header {
+            ftd_p1::Header::KV(kv) => {
+                kv.value = kv.value.as_deref().map(without_blank_whitespace);
+            }
+            ftd_p1::Header::Section(header) => {
+                for section in header.section.iter_mut() {
+                    make_comparable(section, wrap);
+                }
+            }
+            ftd_p1::Header::BlockRecordHeader(header) => {
+                header.body = (header.body.0.as_deref().map(without_blank_whitespace), None);
+                header.fields = header
+                    .fields
+                    .iter()
+                    .map(|v| v.without_line_number())
+                    .collect();
+            }
         }
-        value.push(trimmed_line);
-        *input = remaining.to_string();
     }
-
-    if !value.is_empty() {
-        Some(Section::new_empty(value.join("\n").as_str()))
-    } else {
-        None
+    for section in section.sub_sections.iter_mut() {
+        make_comparable(section, wrap);
     }
 }
 
-fn comment_section(input: &mut String) -> Option<Section> {
-    let mut value = vec![];
+fn without_blank_whitespace(value: &str) -> String {
+    value
+        .split('\n')
+        .map(|line| if line.trim().is_empty() { "" } else { line })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    while !input.is_empty() {
-        let (first_line, remaining) = match input.split_once('\n') {
-            Some((first_line, remaining)) => (first_line.to_string(), remaining.to_string()),
-            None => (input.to_string(), String::new()),
-        };
-        let trimmed_line = first_line.trim().to_string();
-        if trimmed_line.starts_with("-- ")
-            || trimmed_line.starts_with("/-- ")
-            || !trimmed_line.starts_with(";;")
-        {
-            *input = format!("{first_line}\n{remaining}");
-            break;
-        }
-        value.push(trimmed_line);
-        *input = remaining.to_string();
+#[cfg(test)]
+mod tests {
+    #[track_caller]
+    fn p(source: &str, expected: &str) {
+        let formatted = super::format(source, "foo", true, None).unwrap();
+        pretty_assertions::assert_eq!(formatted, expected);
+        // formatting a formatted document changes nothing
+        pretty_assertions::assert_eq!(
+            super::format(formatted.as_str(), "foo", true, None).unwrap(),
+            expected
+        );
     }
-    if !value.is_empty() {
-        Some(Section::new_comment(value.join("\n").as_str()))
-    } else {
-        None
+
+    #[test]
+    fn fbt_output() {
+        p(
+            include_str!("../../fbt-tests/18-fmt/input/index.ftd"),
+            include_str!("../../fbt-tests/18-fmt/output/index.ftd"),
+        );
+    }
+
+    #[test]
+    fn headers() {
+        p(
+            indoc::indoc!(
+                "
+                --   ftd.text:hello
+                  color :   red
+                ;; shown only for the first one
+                if: { $i == 0 }
+                $on-click$: $toggle($a = $open)
+                for:   $i in $items
+                "
+            ),
+            indoc::indoc!(
+                "
+                -- ftd.text: hello
+                for: $i in $items
+                ;; shown only for the first one
+                if: { $i == 0 }
+                color: red
+                $on-click$: $toggle($a = $open)
+                "
+            ),
+        );
+    }
+
+    #[test]
+    fn comments_and_bodies() {
+        p(
+            indoc::indoc!(
+                "
+                ;; the page
+
+
+                -- ftd.column:
+                padding.px: 10
+
+                    ;; the title
+                    -- ftd.text:
+
+                        first line
+                          indented
+
+                        third line
+                      ;; not part of the body
+
+                    -- ftd.column:
+
+                  -- ftd.text: nested
+                  -- end: ftd.column
+
+                -- end: ftd.column
+                "
+            ),
+            "\
+;; the page
+
+
+-- ftd.column:
+padding.px: 10
+
+;; the title
+-- ftd.text:
+
+first line
+  indented
+
+third line
+;; not part of the body
+
+-- ftd.column:
+
+\t-- ftd.text: nested
+-- end: ftd.column
+
+-- end: ftd.column
+",
+        );
+    }
+
+    #[test]
+    fn sections_in_headers() {
+        p(
+            indoc::indoc!(
+                "
+                -- ds.page: Hello
+                -- ds.page.footer:
+                -- toc-item:
+                name: A
+                -- toc-item.children:
+                -- toc-item:
+                name: B
+                -- end: toc-item.children
+                -- end: ds.page.footer
+                -- ftd.column:
+                -- ftd.text: hi
+                -- end: ftd.column
+                -- end: ds.page
+                "
+            ),
+            "\
+-- ds.page: Hello
+-- ds.page.footer:
+\t-- toc-item:
+\tname: A
+\t-- toc-item.children:
+\t\t-- toc-item:
+\t\tname: B
+\t-- end: toc-item.children
+-- end: ds.page.footer
+-- ftd.column:
+\t-- ftd.text: hi
+-- end: ftd.column
+-- end: ds.page
+",
+        );
+    }
+
+    #[test]
+    fn wrap() {
+        let source = indoc::indoc!(
+            "
+            -- ftd.text: The quick brown fox jumps over the lazy dog
+            color: red
+
+            -- ftd.text: short
+            "
+        );
+        let formatted = super::format(source, "foo", true, Some(20)).unwrap();
+        pretty_assertions::assert_eq!(
+            formatted,
+            indoc::indoc!(
+                "
+                -- ftd.text:
+                color: red
+
+                -- ftd.text.caption:
+
+                The quick brown fox
+                jumps over the lazy
+                dog
+
+                -- ftd.text: short
+                "
+            )
+        );
+        pretty_assertions::assert_eq!(
+            super::format(formatted.as_str(), "foo", true, Some(20)).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn not_ftd() {
+        assert!(super::format("hello\n", "foo", true, None).is_err());
     }
 }
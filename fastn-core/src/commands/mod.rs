@@ -4,4 +4,5 @@ pub mod fmt;
 pub mod query;
 pub mod serve;
 pub mod test;
+pub mod translation;
 pub mod translation_status;
@@ -11,6 +11,55 @@ fn handle_redirect(
         .map(|r| fastn_core::http::permanent_redirect(r.to_string()))
 }
 
+/// Enforces the sitemap access of the document at `path`: its `readers` for safe requests, and
+/// its `writers` for unsafe ones (`POST` etc), which change data through the processors of the
+/// document. Visitors are sent to the login page, logged in users without access get a 403.
+async fn check_access(
+    config: &mut fastn_core::RequestConfig,
+    path: &camino::Utf8Path,
+) -> Option<fastn_core::http::Response> {
+    let access = fastn_core::sitemap::access::find(&config.config.package, path.as_str());
+    let unsafe_method = !matches!(config.request.method(), "GET" | "HEAD" | "OPTIONS");
+    if access.is_public() && (!unsafe_method || access.writers.is_empty()) {
+        return None;
+    }
+
+    let ud = match config.user_data().await {
+        Ok(ud) => ud,
+        Err(e) => {
+            return Some(fastn_core::server_error!(
+                "fastn-Error: path: {}, {:?}",
+                path,
+                e
+            ));
+        }
+    };
+
+    let Some(ud) = ud else {
+        return Some(fastn_core::http::redirect_with_code(
+            config.config.login_url(config.request.uri.as_str()),
+            302,
+        ));
+    };
+
+    let identities = fastn_core::package::user_group::UserIdentity::from_user_data(&ud);
+    let groups = &config.config.package.user_groups;
+    if unsafe_method && access.can_change(groups, &identities) {
+        return None;
+    }
+    if !unsafe_method && access.can_read(groups, &identities) {
+        return None;
+    }
+
+    fastn_core::warning!(
+        "user {} can not {} {}",
+        ud.id,
+        if unsafe_method { "change" } else { "read" },
+        path
+    );
+    Some(actix_web::HttpResponse::Forbidden().body("you do not have access to this page"))
+}
+
 /// path: /-/<package-name>/<file-name>/
 /// path: /<file-name>/
 #[tracing::instrument(skip(config))]
@@ -151,7 +200,8 @@ async fn serve_file(
                 path = path.as_str(),
                 error = e.to_string()
             );
-            fastn_core::server_error!("fastn-Error: path: {}, {:?}", path, e)
+            // interpreter errors are code frames, the Debug output would mangle them
+            fastn_core::server_error!("fastn-Error: path: {}, {}", path, e)
         }
     }
 }
@@ -177,6 +227,7 @@ pub fn clear_session_cookie(req: &fastn_core::http::Request) -> fastn_core::http
 
     actix_web::HttpResponse::build(actix_web::http::StatusCode::OK)
         .cookie(cookie)
+        .append_header(("Set-Cookie", fastn_wasm::session::clear_cookie()))
         .append_header(("Content-Type", "text/html"))
         .body(r#"<meta http-equiv="refresh" content="0; url=/" />"#)
 }
@@ -196,6 +247,7 @@ pub async fn serve(
     }
 
     if req.path() == "/-/auth/logout/" {
+        destroy_server_session(config, &req).await;
         return Ok((clear_session_cookie(&req), false));
     }
 
@@ -208,7 +260,31 @@ pub async fn serve(
     }
 
     if let Some(default_response) = handle_default_route(&req, config.package.name.as_str()) {
-        return default_response.map(|r| (r, true));
+        return default_response.map(|r| {
+            let cache_control = fastn_core::http_cache::cache_control(
+                &config.package,
+                req.path(),
+                fastn_core::http_cache::Route::Static,
+            );
+            (
+                fastn_core::http_cache::conditional(&req, r, cache_control),
+                true,
+            )
+        });
+    }
+
+    // endpoints and apps (eg the login page) do their own auth, everything else, the static
+    // files of the dependencies too, is guarded by our sitemap
+    if let Some(r) = check_access(&mut req_config, &path).await {
+        return Ok((r, false));
+    }
+
+    if let Some(r) = fastn_core::sitemap::seo::serve(config, &req, preview_session_id).await {
+        return r.map(|r| (r, false));
+    }
+
+    if let Some(r) = fastn_core::search::serve(config, &req, preview_session_id).await {
+        return r.map(|r| (r, false));
     }
 
     if fastn_core::utils::is_static_path(req.path()) {
@@ -219,12 +295,35 @@ pub async fn serve(
             preview_session_id,
         )
         .await
-        .map(|r| (r, true));
+        .map(|r| {
+            let cache_control = fastn_core::http_cache::cache_control(
+                &config.package,
+                req.path(),
+                fastn_core::http_cache::Route::Static,
+            );
+            (
+                fastn_core::http_cache::conditional(&req, r, cache_control),
+                true,
+            )
+        });
     }
 
-    serve_helper(&mut req_config, only_js, path, preview_session_id)
-        .await
-        .map(|r| (r, req_config.response_is_cacheable))
+    if let Some(r) = check_csrf(config, &req).await {
+        return Ok((r, false));
+    }
+
+    let response = serve_helper(&mut req_config, only_js, path, preview_session_id).await?;
+    let cacheable = req_config.response_is_cacheable;
+    let cache_control = fastn_core::http_cache::cache_control(
+        &config.package,
+        req.path(),
+        fastn_core::http_cache::Route::Document { cacheable },
+    );
+
+    Ok((
+        fastn_core::http_cache::conditional(&req, response, cache_control),
+        cacheable,
+    ))
 }
 
 #[tracing::instrument(skip_all)]
@@ -326,7 +425,7 @@ fn shared_to_http(r: ft_sys_shared::Request) -> fastn_core::Result<fastn_core::h
     let mut resp = builder.status(r.method.parse().unwrap()).body(r.body);
 
     for (k, v) in r.headers {
-        resp.headers_mut().insert(
+        resp.headers_mut().append(
             k.parse().unwrap(),
             actix_web::http::header::HeaderValue::from_bytes(v.as_slice()).unwrap(),
         );
@@ -414,12 +513,12 @@ async fn handle_static_route(
     path: &str,
     package_name: &str,
     ds: &fastn_ds::DocumentStore,
-    session_id: &Option<String>,
+    preview_session_id: &Option<String>,
 ) -> fastn_core::Result<fastn_core::http::Response> {
-    return match handle_static_route_(path, package_name, ds, session_id).await {
+    return match handle_static_route_(path, package_name, ds, preview_session_id).await {
         Ok(r) => Ok(r),
         Err(fastn_ds::ReadError::NotFound(_)) => {
-            handle_not_found_image(path, package_name, ds, session_id).await
+            handle_not_found_image(path, package_name, ds, preview_session_id).await
         }
         Err(e) => Err(e.into()),
     };
@@ -428,10 +527,10 @@ async fn handle_static_route(
         path: &str,
         package_name: &str,
         ds: &fastn_ds::DocumentStore,
-        session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<fastn_core::http::Response, fastn_ds::ReadError> {
         if path == "/favicon.ico" {
-            return favicon(ds, session_id).await;
+            return favicon(ds, preview_session_id).await;
         }
 
         // the path can start with slash or -/. If later, it is a static file from our dependencies, so
@@ -447,7 +546,7 @@ async fn handle_static_route(
         static_file(
             ds,
             path.strip_prefix('/').unwrap_or(path.as_str()),
-            session_id,
+            preview_session_id,
         )
         .await
     }
@@ -456,19 +555,24 @@ async fn handle_static_route(
         path: &str,
         package_name: &str,
         ds: &fastn_ds::DocumentStore,
-        session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> fastn_core::Result<fastn_core::http::Response> {
         // todo: handle dark images using manifest
         if let Some(new_file_path) = generate_dark_image_path(path) {
-            return handle_static_route_(new_file_path.as_str(), package_name, ds, session_id)
-                .await
-                .or_else(|e| {
-                    if let fastn_ds::ReadError::NotFound(e) = e {
-                        Ok(fastn_core::http::not_found_without_warning(e))
-                    } else {
-                        Err(e.into())
-                    }
-                });
+            return handle_static_route_(
+                new_file_path.as_str(),
+                package_name,
+                ds,
+                preview_session_id,
+            )
+            .await
+            .or_else(|e| {
+                if let fastn_ds::ReadError::NotFound(e) = e {
+                    Ok(fastn_core::http::not_found_without_warning(e))
+                } else {
+                    Err(e.into())
+                }
+            });
         }
 
         Ok(fastn_core::http::not_found_without_warning("".to_string()))
@@ -494,12 +598,12 @@ async fn handle_static_route(
 
     async fn favicon(
         ds: &fastn_ds::DocumentStore,
-        session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<fastn_core::http::Response, fastn_ds::ReadError> {
-        match static_file(ds, "favicon.ico", session_id).await {
+        match static_file(ds, "favicon.ico", preview_session_id).await {
             Ok(r) => Ok(r),
             Err(fastn_ds::ReadError::NotFound(_)) => {
-                Ok(static_file(ds, "static/favicon.ico", session_id).await?)
+                Ok(static_file(ds, "static/favicon.ico", preview_session_id).await?)
             }
             Err(e) => Err(e),
         }
@@ -509,16 +613,23 @@ async fn handle_static_route(
     async fn static_file(
         ds: &fastn_ds::DocumentStore,
         path: &str,
-        session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<fastn_core::http::Response, fastn_ds::ReadError> {
-        ds.read_content(&fastn_ds::Path::new(path), session_id)
-            .await
-            .map(|r| {
-                fastn_core::http::ok_with_content_type(
-                    r,
-                    guess_mime_type(path.to_string().as_str()),
-                )
-            })
+        let file_path = fastn_ds::Path::new(path);
+        let content = ds.read_content(&file_path, preview_session_id).await?;
+        let mut response = fastn_core::http::ok_with_content_type(content, guess_mime_type(path));
+
+        if let Some(modified) = ds.modified(&file_path, preview_session_id).await
+            && let Ok(value) = actix_web::http::header::HeaderValue::from_str(
+                fastn_core::http_cache::last_modified(modified).as_str(),
+            )
+        {
+            response
+                .headers_mut()
+                .insert(actix_web::http::header::LAST_MODIFIED, value);
+        }
+
+        Ok(response)
     }
 }
 
@@ -526,7 +637,7 @@ async fn handle_static_route(
 async fn handle_endpoints(
     config: &fastn_core::Config,
     req: &fastn_core::http::Request,
-    session_id: &Option<String>,
+    preview_session_id: &Option<String>,
 ) -> Option<fastn_core::Result<fastn_core::http::Response>> {
     let matched_endpoint = config
         .package
@@ -534,10 +645,14 @@ async fn handle_endpoints(
         .iter()
         .find(|ep| req.path().starts_with(ep.mountpoint.trim_end_matches('/')));
 
-    let (endpoint, app_url) = match matched_endpoint {
+    let (endpoint, app_url, wasm_limits) = match matched_endpoint {
         Some(e) => {
             tracing::info!("matched endpoint: {:?}", e);
-            (e, e.mountpoint.clone())
+            (
+                e,
+                e.mountpoint.clone(),
+                fastn_core::utils::wasm_limits(e.wasm_fuel, e.wasm_memory, e.wasm_timeout),
+            )
         }
         None => {
             tracing::info!("no endpoint found in current package. Trying mounted apps");
@@ -575,7 +690,7 @@ async fn handle_endpoints(
 
             if !config
                 .ds
-                .exists(&fastn_ds::Path::new(&wasm_path), session_id)
+                .exists(&fastn_ds::Path::new(&wasm_path), preview_session_id)
                 .await
             {
                 tracing::info!("wasm file not found: {}", wasm_path);
@@ -593,8 +708,12 @@ async fn handle_endpoints(
                         app = app.mount_point.trim_end_matches('/')
                     ),
                     user_id: None, // idk if we're using this
+                    wasm_fuel: None,
+                    wasm_memory: None,
+                    wasm_timeout: None,
                 },
                 app_url,
+                app.wasm_limits,
             )
         }
     };
@@ -624,7 +743,9 @@ async fn handle_endpoints(
                 req,
                 app_url,
                 app_mounts,
-                session_id,
+                wasm_limits,
+                preview_session_id,
+                fastn_core::utils::secret_keys(&config.ds).await,
             )
             .await
         {
@@ -656,7 +777,7 @@ pub fn to_response(req: ft_sys_shared::Request) -> actix_web::HttpResponse {
     let mut resp = builder.status(req.method.parse().unwrap()).body(req.body);
 
     for (k, v) in req.headers {
-        resp.headers_mut().insert(
+        resp.headers_mut().append(
             k.parse().unwrap(),
             actix_http::header::HeaderValue::from_bytes(v.as_slice()).unwrap(),
         );
@@ -711,19 +832,29 @@ async fn actual_route(
 async fn route(
     req: actix_web::HttpRequest,
     body: actix_web::web::Bytes,
-    config: actix_web::web::Data<std::sync::Arc<fastn_core::Config>>,
+    config: actix_web::web::Data<std::sync::Arc<fastn_core::watcher::SharedConfig>>,
 ) -> fastn_core::Result<fastn_core::http::Response> {
-    actual_route(&config, req, body, &None).await
+    let current = config.get();
+    if current.live_reload && req.path().ends_with(fastn_core::watcher::LIVE_RELOAD_PATH) {
+        return Ok(config.live_reload_response());
+    }
+    actual_route(&current, req, body, &None).await
 }
 
+/// `watch`: reload the package and the open browser tabs when any file in the package changes,
+/// running `update_dependencies` first when it is `FASTN.ftd`.
 #[allow(clippy::too_many_arguments)]
 pub async fn listen(
     config: std::sync::Arc<fastn_core::Config>,
     bind_address: &str,
     port: Option<u16>,
+    watch: bool,
+    update_dependencies: Option<fastn_core::watcher::UpdateDependencies>,
 ) -> fastn_core::Result<()> {
     use colored::Colorize;
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    // wasm handlers can only be timed out while the epoch ticks
+    fastn_wasm::start_epoch_ticker();
 
     let tcp_listener = match fastn_core::http::get_available_port(port, bind_address) {
         Some(listener) => listener,
@@ -745,6 +876,21 @@ You can try without providing port, it will automatically pick unused port."#,
         }
     };
 
+    if has_wasm_handlers(&config.package) {
+        spawn_email_worker(&config.ds).await;
+    }
+
+    let config = std::sync::Arc::new(fastn_core::watcher::SharedConfig::new(config));
+
+    if watch {
+        let config = std::sync::Arc::clone(&config);
+        tokio::spawn(async move {
+            if let Err(e) = fastn_core::watcher::watch(config, update_dependencies).await {
+                eprintln!("{}: {e}", "failed to watch package for changes".red());
+            }
+        });
+    }
+
     let app = move || {
         actix_web::App::new()
             .app_data(actix_web::web::Data::new(std::sync::Arc::clone(&config)))
@@ -761,6 +907,9 @@ You can try without providing port, it will automatically pick unused port."#,
     };
 
     println!("### Server Started ###");
+    if watch {
+        println!("Watching for changes, open pages will reload automatically.");
+    }
     println!(
         "Go to: http://{}:{}",
         bind_address,
@@ -772,3 +921,106 @@ You can try without providing port, it will automatically pick unused port."#,
         .await?;
     Ok(())
 }
+
+fn has_wasm_handlers(package: &fastn_core::Package) -> bool {
+    !package.apps.is_empty()
+        || package
+            .endpoints
+            .iter()
+            .any(|e| e.endpoint.starts_with("wasm+proxy://"))
+}
+
+/// Emails sent by wasm handlers (`email_send`) are queued in the sqlite db, this sends them.
+async fn spawn_email_worker(ds: &fastn_ds::DocumentStore) {
+    use colored::Colorize;
+
+    let root: std::path::PathBuf = ds.root().to_string().into();
+    let transport = match fastn_wasm::email::from_env(&root) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}: {e}", "emails will not be sent".red());
+            return;
+        }
+    };
+
+    let db_path = match ds.wasm_db_path().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}: {e}", "emails will not be sent".red());
+            return;
+        }
+    };
+
+    tokio::spawn(fastn_wasm::email::worker::run(db_path, transport));
+}
+
+/// Unsafe requests (`POST` etc) to ftd documents, from a browser that has a server side session,
+/// have to carry the CSRF token of the session, in the `x-csrf-token` header or a `csrf-token`
+/// field of the (json or form) body. Wasm endpoints and apps check it themselves, using
+/// `hostn_session_verify_csrf`.
+async fn check_csrf(
+    config: &fastn_core::Config,
+    req: &fastn_core::http::Request,
+) -> Option<fastn_core::http::Response> {
+    if matches!(req.method(), "GET" | "HEAD" | "OPTIONS") {
+        return None;
+    }
+
+    let cookie = req.cookie(fastn_wasm::session::COOKIE_NAME)?;
+    let session = match config
+        .server_sessions()
+        .await
+        .and_then(|(sessions, conn)| Ok(sessions.get(&conn, Some(cookie.as_str()))?))
+    {
+        Ok(Some(session)) => session,
+        // an expired or forged cookie has no session to protect
+        Ok(None) => return None,
+        Err(e) => return Some(fastn_core::http::server_error_(e.to_string())),
+    };
+
+    let token = req
+        .headers()
+        .get(fastn_wasm::session::CSRF_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
+        .or_else(|| csrf_token_from_body(req));
+
+    if token.is_some_and(|t| session.verify_csrf(t.as_str())) {
+        return None;
+    }
+
+    fastn_core::warning!("csrf token missing or invalid: {}", req.path());
+    Some(actix_web::HttpResponse::Forbidden().body("invalid csrf token"))
+}
+
+fn csrf_token_from_body(req: &fastn_core::http::Request) -> Option<String> {
+    match req.content_type()?.essence_str() {
+        "application/x-www-form-urlencoded" => url::form_urlencoded::parse(req.body())
+            .find(|(k, _)| k == fastn_wasm::session::CSRF_FIELD)
+            .map(|(_, v)| v.into_owned()),
+        "application/json" => serde_json::from_slice::<serde_json::Value>(req.body())
+            .ok()?
+            .get(fastn_wasm::session::CSRF_FIELD)?
+            .as_str()
+            .map(ToString::to_string),
+        _ => None,
+    }
+}
+
+/// Logging out ends the server side session too.
+async fn destroy_server_session(config: &fastn_core::Config, req: &fastn_core::http::Request) {
+    let Some(cookie) = req.cookie(fastn_wasm::session::COOKIE_NAME) else {
+        return;
+    };
+
+    let destroyed = config.server_sessions().await.and_then(|(sessions, conn)| {
+        if let Some(session) = sessions.get(&conn, Some(cookie.as_str()))? {
+            sessions.destroy(&conn, session.id.as_str())?;
+        }
+        Ok(())
+    });
+
+    if let Err(e) = destroyed {
+        fastn_core::warning!("failed to destroy session: {e}");
+    }
+}
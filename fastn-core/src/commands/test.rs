@@ -128,7 +128,7 @@ impl fastn_core::Config {
             .get_root_for_package(&self.package)
             .join(fastn_core::commands::test::TEST_FOLDER)
             .join(fastn_core::commands::test::FIXTURE_FOLDER);
-        Ok(self.ds.get_all_file_path(&path, &[]).await)
+        Ok(self.ds.get_all_file_path(&path, &[], &None).await?)
     }
 
     /**
@@ -166,7 +166,10 @@ impl fastn_core::Config {
             .get_root_for_package(&self.package)
             .join(fastn_core::commands::test::TEST_FOLDER);
         let ignored_directories = ["fixtures".to_string()];
-        Ok(self.ds.get_all_file_path(&path, &ignored_directories).await)
+        Ok(self
+            .ds
+            .get_all_file_path(&path, &ignored_directories, &None)
+            .await?)
     }
 
     pub(crate) fn get_test_directory_path(&self) -> fastn_ds::Path {
@@ -995,7 +998,7 @@ async fn generate_script_file(
         content = content
     );
     let file_location = test_directory.join(test_file_name.replace(".test", ".script.html"));
-    ds.write_content(&file_location, &html_content.into_bytes())
+    ds.write_content(&file_location, &html_content.into_bytes(), &None)
         .await
         .unwrap();
 }
@@ -0,0 +1,373 @@
+/// Marks the translated `files` as up to date with the current version of the original package.
+///
+/// The mark is stored in the translation package itself: `.tracks/<file>.track` records the
+/// original version the translation was last reviewed against, and `.tracks/<file>.original`
+/// keeps a copy of the original as it was at that time, so `fastn translation diff` can later
+/// show what has changed since.
+pub async fn mark(
+    config: &fastn_core::Config,
+    files: &[String],
+    session_id: &Option<String>,
+) -> fastn_core::Result<()> {
+    let original = translation_of(config)?;
+    let original_root = config.original_path()?;
+    let versions = original_versions(config, original, session_id).await?;
+    let root = config.ds.root();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    for file in files {
+        let version = match versions.get(file) {
+            Some(version) => *version,
+            None => {
+                return fastn_core::usage_error(format!(
+                    "`{file}` is not a file of the original package `{}`",
+                    original.name
+                ));
+            }
+        };
+        if !config.ds.exists(&root.join(file), session_id).await {
+            return fastn_core::usage_error(format!(
+                "`{file}` is not translated yet, translate it before marking it"
+            ));
+        }
+
+        let original_content = config
+            .ds
+            .read_content(&original_root.join(file), session_id)
+            .await?;
+        fastn_core::utils::update(&marked_path(file, &root), &original_content, &config.ds).await?;
+        fastn_core::utils::update(
+            &fastn_core::utils::track_path(file, &root),
+            track_content(file, now, version).as_bytes(),
+            &config.ds,
+        )
+        .await?;
+        println!("Marked `{file}` as up to date");
+    }
+    Ok(())
+}
+
+/// Prints the changes made to the original of `file` since its translation was last marked.
+pub async fn diff(
+    config: &fastn_core::Config,
+    file: &str,
+    session_id: &Option<String>,
+) -> fastn_core::Result<()> {
+    translation_of(config)?;
+    let original_root = config.original_path()?;
+    let root = config.ds.root();
+
+    let track_path = fastn_core::utils::track_path(file, &root);
+    let last_merged_version =
+        fastn_core::tracker::get_tracks(config, &root, &track_path, session_id)
+            .await?
+            .get(file)
+            .and_then(|track| track.last_merged_version);
+    let Some(last_merged_version) = last_merged_version else {
+        return fastn_core::usage_error(format!(
+            "`{file}` has never been marked, use `fastn translation mark {file}` first"
+        ));
+    };
+
+    // prefer the copy saved by `fastn translation mark`, fall back to the original's history
+    let mut marked = marked_path(file, &root);
+    if !config.ds.exists(&marked, session_id).await {
+        marked = fastn_core::utils::history_path(file, &original_root, &last_merged_version);
+    }
+    if !config.ds.exists(&marked, session_id).await {
+        return fastn_core::usage_error(format!(
+            "The original of `{file}` as of the last mark is not available, mark it again"
+        ));
+    }
+
+    let marked_content = config.ds.read_to_string(&marked, session_id).await?;
+    let latest_content = config
+        .ds
+        .read_to_string(&original_root.join(file), session_id)
+        .await?;
+
+    if marked_content == latest_content {
+        println!("The original of `{file}` has not changed since it was last marked");
+    } else {
+        print!("{}", diffy::create_patch(&marked_content, &latest_content));
+    }
+    Ok(())
+}
+
+/// Prints a JSON coverage report for the translation package, or, when run in an original
+/// package, for each of its translations that is available locally.
+pub async fn coverage(
+    config: &fastn_core::Config,
+    session_id: &Option<String>,
+) -> fastn_core::Result<()> {
+    let mut report = Report::default();
+    if let Some(original) = config.package.translation_of.as_deref() {
+        let versions = original_versions(config, original, session_id).await?;
+        let status = fastn_core::commands::translation_status::get_translation_status(
+            config,
+            &versions,
+            &config.ds.root(),
+            session_id,
+        )
+        .await?;
+        report
+            .translations
+            .push(Coverage::new(&config.package, &status));
+    } else if !config.package.translations.is_empty() {
+        let versions = original_versions(config, &config.package, session_id).await?;
+        for translation in config.package.translations.iter() {
+            let root = config.get_root_for_package(translation);
+            if !config.ds.exists(&root.join("FASTN.ftd"), session_id).await {
+                report.not_available.push(translation.name.clone());
+                continue;
+            }
+            let status = fastn_core::commands::translation_status::get_translation_status(
+                config, &versions, &root, session_id,
+            )
+            .await?;
+            report
+                .translations
+                .push(Coverage::new(translation, &status));
+        }
+    } else {
+        return fastn_core::usage_error(
+            "`translation coverage` works only when either `translation` or `translation-of` is set."
+                .to_string(),
+        );
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("coverage report is serializable")
+    );
+    Ok(())
+}
+
+/// What `fastn translation coverage` prints.
+#[derive(serde::Serialize, Debug, Default)]
+pub struct Report {
+    pub translations: Vec<Coverage>,
+    /// Translations of the original package that are not available locally, so have no coverage.
+    #[serde(rename = "not-available")]
+    pub not_available: Vec<String>,
+}
+
+/// Translation coverage of one translation package.
+#[derive(serde::Serialize, Debug, PartialEq)]
+pub struct Coverage {
+    pub package: String,
+    pub language: Option<String>,
+    pub total: usize,
+    pub missing: usize,
+    #[serde(rename = "never-marked")]
+    pub never_marked: usize,
+    #[serde(rename = "out-dated")]
+    pub out_dated: usize,
+    #[serde(rename = "upto-date")]
+    pub upto_date: usize,
+    /// Percentage of the original files whose translation is up to date.
+    pub coverage: f64,
+    pub files: std::collections::BTreeMap<String, &'static str>,
+}
+
+impl Coverage {
+    pub(crate) fn new(
+        package: &fastn_core::Package,
+        status: &std::collections::BTreeMap<
+            String,
+            fastn_core::commands::translation_status::TranslationStatus,
+        >,
+    ) -> Coverage {
+        let count = |s: fastn_core::commands::translation_status::TranslationStatus| {
+            status.values().filter(|v| **v == s).count()
+        };
+        let total = status.len();
+        let upto_date =
+            count(fastn_core::commands::translation_status::TranslationStatus::UptoDate);
+        Coverage {
+            package: package.name.clone(),
+            language: package.lang.as_ref().map(|l| l.default_lang.clone()),
+            total,
+            missing: count(fastn_core::commands::translation_status::TranslationStatus::Missing),
+            never_marked: count(
+                fastn_core::commands::translation_status::TranslationStatus::NeverMarked,
+            ),
+            out_dated: count(fastn_core::commands::translation_status::TranslationStatus::Outdated),
+            upto_date,
+            coverage: if total == 0 {
+                100.0
+            } else {
+                (upto_date * 10000 / total) as f64 / 100.0
+            },
+            files: status
+                .iter()
+                .map(|(file, s)| (file.clone(), s.as_key()))
+                .collect(),
+        }
+    }
+}
+
+fn translation_of(config: &fastn_core::Config) -> fastn_core::Result<&fastn_core::Package> {
+    match config.package.translation_of.as_deref() {
+        Some(original) => Ok(original),
+        None => fastn_core::usage_error(
+            "`translation mark` and `translation diff` work only in a package with `translation-of` set."
+                .to_string(),
+        ),
+    }
+}
+
+/// Returns the current version of every file of the `original` package.
+pub(crate) async fn original_versions(
+    config: &fastn_core::Config,
+    original: &fastn_core::Package,
+    session_id: &Option<String>,
+) -> fastn_core::Result<std::collections::BTreeMap<String, u128>> {
+    let root = config.get_root_for_package(original);
+    let paths = config.get_all_file_paths(original).await?;
+    versions(&config.ds, &root, paths, session_id).await
+}
+
+/// The version of a file is the timestamp of its latest snapshot in `.history`, if the package
+/// at `root` keeps one, otherwise the last modified time of the file.
+async fn versions(
+    ds: &fastn_ds::DocumentStore,
+    root: &fastn_ds::Path,
+    paths: Vec<fastn_ds::Path>,
+    session_id: &Option<String>,
+) -> fastn_core::Result<std::collections::BTreeMap<String, u128>> {
+    let snapshots = fastn_core::snapshot::get_latest_snapshots(ds, root, session_id).await?;
+    if !snapshots.is_empty() {
+        return Ok(snapshots);
+    }
+
+    let mut versions = std::collections::BTreeMap::new();
+    for path in paths {
+        let Some(file) = path.strip_prefix(root) else {
+            continue;
+        };
+        let file = file.to_string().trim_start_matches('/').to_string();
+        if file.eq("FASTN.ftd") {
+            continue;
+        }
+        let version = ds
+            .modified(&path, session_id)
+            .await
+            .and_then(|t| t.duration_since(std::time::SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        versions.insert(file, version);
+    }
+    Ok(versions)
+}
+
+fn marked_path(file: &str, root: &fastn_ds::Path) -> fastn_ds::Path {
+    root.join(".tracks").join(format!("{file}.original"))
+}
+
+fn track_content(file: &str, self_timestamp: u128, last_merged_version: u128) -> String {
+    format!(
+        indoc::indoc! {"
+            -- import: fastn
+
+            -- fastn.track: {file}
+            self-timestamp: {self_timestamp}
+            last-merged-version: {last_merged_version}
+        "},
+        file = file,
+        self_timestamp = self_timestamp,
+        last_merged_version = last_merged_version,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn track_content() {
+        assert_eq!(
+            super::track_content("docs/index.ftd", 20, 10),
+            "-- import: fastn\n\n-- fastn.track: docs/index.ftd\nself-timestamp: 20\nlast-merged-version: 10\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn versions() {
+        let dir =
+            std::env::temp_dir().join(format!("fastn-translation-versions-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(".history")).unwrap();
+        std::fs::write(dir.join("FASTN.ftd"), "-- import: fastn").unwrap();
+        std::fs::write(dir.join("index.ftd"), "-- ftd.text: hello").unwrap();
+        let dir = camino::Utf8PathBuf::try_from(dir).unwrap();
+
+        let ds = fastn_ds::DocumentStore::new(&dir, actix_web::web::Data::new(Default::default()));
+        let root = ds.root();
+        let paths = vec![root.join("FASTN.ftd"), root.join("index.ftd")];
+
+        // without a `.history`, the version of a file is when it was last modified
+        let modified = std::fs::metadata(dir.join("index.ftd"))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        assert_eq!(
+            super::versions(&ds, &root, paths.clone(), &None)
+                .await
+                .unwrap(),
+            std::collections::BTreeMap::from([("index.ftd".to_string(), modified)])
+        );
+
+        std::fs::write(
+            dir.join(".history/.latest.ftd"),
+            "-- import: fastn\n\n-- fastn.snapshot: index.ftd\ntimestamp: 42\n",
+        )
+        .unwrap();
+        assert_eq!(
+            super::versions(&ds, &root, paths, &None).await.unwrap(),
+            std::collections::BTreeMap::from([("index.ftd".to_string(), 42)])
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn coverage() {
+        let mut package = fastn_core::Package::new("hi.example.com");
+        package.lang = Some(fastn_core::package::Lang {
+            default_lang: "hi".to_string(),
+            available_languages: Default::default(),
+        });
+        let status = std::collections::BTreeMap::from([
+            (
+                "a.ftd".to_string(),
+                fastn_core::commands::translation_status::TranslationStatus::UptoDate,
+            ),
+            (
+                "b.ftd".to_string(),
+                fastn_core::commands::translation_status::TranslationStatus::Outdated,
+            ),
+            (
+                "c.ftd".to_string(),
+                fastn_core::commands::translation_status::TranslationStatus::Missing,
+            ),
+        ]);
+
+        let coverage = super::Coverage::new(&package, &status);
+        assert_eq!(coverage.language.as_deref(), Some("hi"));
+        assert_eq!(coverage.total, 3);
+        assert_eq!(
+            (coverage.missing, coverage.never_marked, coverage.out_dated),
+            (1, 0, 1)
+        );
+        assert_eq!(coverage.upto_date, 1);
+        assert_eq!(coverage.coverage, 33.33);
+        assert_eq!(coverage.files["b.ftd"], "out-dated");
+
+        let empty = super::Coverage::new(&package, &Default::default());
+        assert_eq!(empty.coverage, 100.0);
+    }
+}
@@ -21,14 +21,15 @@ async fn translation_package_status(
     config: &fastn_core::Config,
     session_id: &Option<String>,
 ) -> fastn_core::Result<()> {
-    let original_snapshots = fastn_core::snapshot::get_latest_snapshots(
-        &config.ds,
-        &config.original_path()?,
-        session_id,
-    )
-    .await?;
+    let original = config
+        .package
+        .translation_of
+        .as_ref()
+        .expect("translation package must have `translation-of`");
+    let original_versions =
+        fastn_core::commands::translation::original_versions(config, original, session_id).await?;
     let translation_status =
-        get_translation_status(config, &original_snapshots, &config.ds.root(), session_id).await?;
+        get_translation_status(config, &original_versions, &config.ds.root(), session_id).await?;
     print_translation_status(&translation_status);
     Ok(())
 }
@@ -86,6 +87,7 @@ fn print_translation_status(
     }
 }
 
+#[derive(Debug, PartialEq)]
 pub(crate) enum TranslationStatus {
     Missing,
     NeverMarked,
@@ -102,4 +104,14 @@ impl TranslationStatus {
             TranslationStatus::UptoDate => "Up to date",
         }
     }
+
+    /// The key used for this status in `FASTN.ftd` and in machine-readable reports.
+    pub(crate) fn as_key(&self) -> &'static str {
+        match self {
+            TranslationStatus::Missing => "missing",
+            TranslationStatus::NeverMarked => "never-marked",
+            TranslationStatus::Outdated => "out-dated",
+            TranslationStatus::UptoDate => "upto-date",
+        }
+    }
 }
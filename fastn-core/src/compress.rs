@@ -0,0 +1,185 @@
+//! Pre-compressed copies of the files in `.build`, for `fastn build --compress`.
+//!
+//! For every text file (html, js, css etc.) we write a `.gz` and a `.br` file next to it, so
+//! static hosts that support it (nginx `gzip_static`/`brotli_static`, caddy `precompressed`,
+//! most CDNs) can serve them directly instead of compressing on every request. `fastn serve`
+//! does not need these, it compresses the response based on the `Accept-Encoding` header.
+
+/// Files smaller than this are not worth compressing, the headers are bigger than the saving.
+const MIN_SIZE: usize = 1024;
+
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "html", "htm", "css", "js", "mjs", "json", "map", "svg", "xml", "txt", "csv", "md", "ftd",
+    "wasm", "ico",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    const ALL: [Encoding; 2] = [Encoding::Gzip, Encoding::Brotli];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gz",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    fn compress(&self, content: &[u8]) -> std::io::Result<Vec<u8>> {
+        use std::io::Write;
+
+        match self {
+            Encoding::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut output = vec![];
+                {
+                    // quality 11 is slow, but we only do it once per build
+                    let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+                    encoder.write_all(content)?;
+                }
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Writes `<file>.gz` and `<file>.br` for every compressible file in `build_dir`, and removes
+/// the ones whose source file is gone (eg a document that was deleted since the last build).
+///
+/// Files that have not changed since their compressed copies were written are skipped, so
+/// running this after an incremental build only compresses what was rebuilt.
+#[tracing::instrument(skip(ds))]
+pub async fn build_dir(
+    ds: &fastn_ds::DocumentStore,
+    build_dir: &fastn_ds::Path,
+) -> fastn_core::Result<()> {
+    let files = ds.get_all_file_path(build_dir, &[], &None).await?;
+    let all: std::collections::HashSet<String> = files.iter().map(|f| f.to_string()).collect();
+
+    let mut compressed = 0;
+    for file in files {
+        let name = file.to_string();
+
+        if let Some(source) = compressed_source(name.as_str()) {
+            if !all.contains(source) {
+                ds.remove(&file, &None).await?;
+            }
+            continue;
+        }
+
+        if !is_compressible(name.as_str()) {
+            continue;
+        }
+
+        let modified = ds.modified(&file, &None).await;
+        let mut content = None;
+
+        for encoding in Encoding::ALL {
+            let target = fastn_ds::Path::new(format!("{name}.{}", encoding.extension()));
+            if is_fresh(ds, &target, modified).await {
+                continue;
+            }
+
+            let content = match content {
+                Some(ref content) => content,
+                None => content.insert(ds.read_content(&file, &None).await?),
+            };
+
+            let output = if content.len() < MIN_SIZE {
+                None
+            } else {
+                Some(encoding.compress(content)?).filter(|output| output.len() < content.len())
+            };
+
+            let Some(output) = output else {
+                // a stale copy from an earlier build would be served instead of the new file
+                if ds.exists(&target, &None).await {
+                    ds.remove(&target, &None).await?;
+                }
+                continue;
+            };
+
+            ds.write_content(&target, &output, &None).await?;
+            compressed += 1;
+        }
+    }
+
+    if compressed > 0 {
+        println!("Wrote {compressed} pre-compressed files");
+    }
+
+    Ok(())
+}
+
+/// `a/index.html` for `a/index.html.gz`, `None` if `path` is not a compressed copy.
+fn compressed_source(path: &str) -> Option<&str> {
+    Encoding::ALL.iter().find_map(|encoding| {
+        path.strip_suffix(encoding.extension())
+            .and_then(|p| p.strip_suffix('.'))
+            .filter(|p| is_compressible(p))
+    })
+}
+
+fn is_compressible(path: &str) -> bool {
+    path.rsplit_once('.')
+        .map(|(_, ext)| COMPRESSIBLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+async fn is_fresh(
+    ds: &fastn_ds::DocumentStore,
+    target: &fastn_ds::Path,
+    source_modified: Option<std::time::SystemTime>,
+) -> bool {
+    match (source_modified, ds.modified(target, &None).await) {
+        (Some(source), Some(target)) => target >= source,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn compressed_source() {
+        assert_eq!(
+            super::compressed_source("a/index.html.gz"),
+            Some("a/index.html")
+        );
+        assert_eq!(super::compressed_source("a/b.js.br"), Some("a/b.js"));
+        assert_eq!(super::compressed_source("a/index.html"), None);
+        // a `.gz` file that is part of the package, not something we wrote
+        assert_eq!(super::compressed_source("archive.tar.gz"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        use std::io::Read;
+
+        let content = "<html>hello world</html>".repeat(100);
+
+        let gz = super::Encoding::Gzip.compress(content.as_bytes()).unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(gz.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+
+        let br = super::Encoding::Brotli
+            .compress(content.as_bytes())
+            .unwrap();
+        let mut decoded = String::new();
+        brotli::Decompressor::new(br.as_slice(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+    }
+}
@@ -43,6 +43,7 @@ impl ConfigTemp {
         ds.write_content(
             &config_json_path,
             &serde_json::ser::to_vec_pretty(&config_temp)?,
+            &None,
         )
         .await?;
 
@@ -38,6 +38,8 @@ pub struct Config {
     pub ftd_external_css: Vec<String>,
     pub ftd_inline_css: Vec<String>,
     pub test_command_running: bool,
+    /// set by `fastn serve --watch`, pages subscribe to the live reload endpoint when this is on
+    pub live_reload: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +49,19 @@ pub struct RequestConfig {
     pub downloaded_assets: std::collections::BTreeMap<String, String>,
     pub current_document: Option<String>,
     pub dependencies_during_render: Vec<String>,
+    /// files read by the `data-file` processor, path -> checksum of the content read, so
+    /// `fastn build` rebuilds the document when one of them changes
+    pub data_files: std::collections::BTreeMap<String, String>,
+    /// the text of the document rendered, for the search index `fastn build` writes, only
+    /// collected when `collect_page_data` is set
+    pub search_document: Option<fastn_core::search::Document>,
+    /// the title, summary and date of the document rendered, for the sitemap and the feeds, only
+    /// collected when `collect_page_data` is set
+    pub page_metadata: Option<fastn_core::sitemap::seo::Metadata>,
+    /// set by `fastn build`, the search index and the page metadata are not needed when serving
+    pub collect_page_data: bool,
+    /// the Fluent bundles the `translate` processor has used for this request
+    pub(crate) translation_bundles: fastn_core::library2022::processor::translate::Bundles,
     pub request: fastn_core::http::Request,
     pub config: Config,
     /// If the current module being parsed is a markdown file, `.markdown` contains the name and
@@ -61,6 +76,12 @@ pub struct RequestConfig {
     pub processor_set_response: Option<ft_sys_shared::Request>,
     /// we use this to determine if the response is cacheable or not
     pub response_is_cacheable: bool,
+    /// the server side session, once a processor has loaded (or created) it
+    pub server_session: Option<fastn_wasm::session::Session>,
+    /// set by `fastn check`, and by `fastn build` to find all the errors of a document it could not
+    /// build, processors which talk to the network or a database are not run, they give an empty
+    /// value of their kind instead
+    pub static_check: bool,
 }
 
 impl RequestConfig {
@@ -94,6 +115,11 @@ impl RequestConfig {
             downloaded_assets: Default::default(),
             current_document: None,
             dependencies_during_render: vec![],
+            data_files: Default::default(),
+            search_document: None,
+            page_metadata: None,
+            collect_page_data: false,
+            translation_bundles: Default::default(),
             request: request.clone(),
             config: config.clone(),
             markdown: None,
@@ -104,6 +130,8 @@ impl RequestConfig {
             processor_set_cookies: Default::default(),
             processor_set_response: None,
             response_is_cacheable: true,
+            server_session: None,
+            static_check: false,
         }
     }
 
@@ -266,9 +294,115 @@ impl RequestConfig {
     pub(crate) fn session_id(&self) -> Option<String> {
         self.request.cookie(fastn_core::http::SESSION_COOKIE_NAME)
     }
+
+    /// The server side session of the request (see `fastn_wasm::session`), shared with the wasm
+    /// apps. If `create` is set and the request has no session, a new one is created and its
+    /// cookie is added to the response.
+    pub(crate) async fn server_session(
+        &mut self,
+        create: bool,
+    ) -> fastn_core::Result<Option<fastn_wasm::session::Session>> {
+        // the response depends on the session cookie
+        self.response_is_cacheable = false;
+
+        if let Some(ref session) = self.server_session {
+            return Ok(Some(session.clone()));
+        }
+
+        let (sessions, conn) = self.config.server_sessions().await?;
+        let cookie = self.request.cookie(fastn_wasm::session::COOKIE_NAME);
+
+        let session = if create {
+            let (session, set_cookie) = sessions.get_or_create(&conn, cookie.as_deref())?;
+            self.processor_set_cookies.extend(set_cookie);
+            Some(session)
+        } else {
+            sessions.get(&conn, cookie.as_deref())?
+        };
+
+        self.server_session.clone_from(&session);
+        Ok(session)
+    }
+
+    /// The logged in user, `None` if no one is logged in.
+    pub(crate) async fn user_data(
+        &mut self,
+    ) -> fastn_core::Result<Option<ft_sys_shared::UserData>> {
+        // the response depends on who is asking
+        self.response_is_cacheable = false;
+
+        Ok(self
+            .config
+            .ds
+            .ud(self.config.get_db_url().await.as_str(), &self.session_id())
+            .await?)
+    }
+
+    /// Identities of the logged in user, the ones `fastn.user-group` members are listed by.
+    pub(crate) async fn user_identities(
+        &mut self,
+    ) -> fastn_core::Result<Vec<fastn_core::package::user_group::UserIdentity>> {
+        Ok(self
+            .user_data()
+            .await?
+            .map(|ud| fastn_core::package::user_group::UserIdentity::from_user_data(&ud))
+            .unwrap_or_default())
+    }
+
+    /// Can the current user read the document at `path`, as per the sitemap `readers`.
+    pub(crate) async fn can_read(&mut self, path: &str) -> fastn_core::Result<bool> {
+        let access = fastn_core::sitemap::access::find(&self.config.package, path);
+        if access.is_public() {
+            return Ok(true);
+        }
+
+        let identities = self.user_identities().await?;
+        Ok(access.can_read(&self.config.package.user_groups, &identities))
+    }
+
+    /// The sitemap of the package without the items the current user can not read.
+    pub(crate) async fn readable_sitemap(
+        &mut self,
+    ) -> fastn_core::Result<Option<fastn_core::sitemap::Sitemap>> {
+        if self
+            .config
+            .package
+            .sitemap
+            .as_ref()
+            .is_none_or(fastn_core::sitemap::access::is_public)
+        {
+            return Ok(self.config.package.sitemap.clone());
+        }
+
+        let identities = self.user_identities().await?;
+        let groups = &self.config.package.user_groups;
+        Ok(self.config.package.sitemap.as_ref().map(|sitemap| {
+            fastn_core::sitemap::access::readable(sitemap, &mut |access| {
+                access.can_read(groups, &identities)
+            })
+        }))
+    }
 }
 
 impl Config {
+    /// The server side sessions (see `fastn_wasm::session`), they live in the same sqlite db the
+    /// wasm apps use.
+    pub(crate) async fn server_sessions(
+        &self,
+    ) -> fastn_core::Result<(fastn_wasm::session::Sessions, rusqlite::Connection)> {
+        let db_path =
+            self.ds
+                .wasm_db_path()
+                .await
+                .map_err(|e| fastn_core::Error::DatabaseError {
+                    message: e.to_string(),
+                })?;
+        let conn =
+            rusqlite::Connection::open(db_path).map_err(fastn_wasm::session::SessionError::from)?;
+        let keys = fastn_core::utils::secret_keys(&self.ds).await;
+        Ok((fastn_wasm::session::Sessions::from_keys(keys), conn))
+    }
+
     /// `build_dir` is where the static built files are stored. `fastn build` command creates this
     /// folder and stores its output here.
     pub fn build_dir(&self) -> fastn_ds::Path {
@@ -596,8 +730,9 @@ impl Config {
             .get_all_file_path(
                 &self.get_root_for_package(package),
                 ignored_files.as_slice(),
+                &None,
             )
-            .await)
+            .await?)
     }
 
     // Input
@@ -1043,6 +1178,33 @@ impl Config {
         config
     }
 
+    pub fn set_live_reload(self) -> Self {
+        let mut config = self;
+        config.live_reload = true;
+        config
+    }
+
+    /// `reload()` reads the package again from disk, keeping the options that were passed on the
+    /// command line (edition, extra js/css etc.). Used by `fastn serve --watch` when `FASTN.ftd`
+    /// or any other package file changes.
+    #[tracing::instrument(name = "Config::reload", skip_all)]
+    pub async fn reload(&self) -> fastn_core::Result<fastn_core::Config> {
+        let ds = self.ds.clone();
+        // the wasm files may have changed too, so we do not want to serve the old compiled modules
+        ds.wasm_modules.clear();
+        let mut config = Config::read(ds, false, &None).await?;
+
+        config.ftd_edition = self.ftd_edition.clone();
+        config.ftd_external_js.clone_from(&self.ftd_external_js);
+        config.ftd_inline_js.clone_from(&self.ftd_inline_js);
+        config.ftd_external_css.clone_from(&self.ftd_external_css);
+        config.ftd_inline_css.clone_from(&self.ftd_inline_css);
+        config.test_command_running = self.test_command_running;
+        config.live_reload = self.live_reload;
+
+        Ok(config)
+    }
+
     /// `read()` is the way to read a Config.
     #[tracing::instrument(name = "Config::read", skip_all)]
     pub async fn read(
@@ -1068,6 +1230,7 @@ impl Config {
             ftd_external_css: Default::default(),
             ftd_inline_css: Default::default(),
             test_command_running: false,
+            live_reload: false,
             ds,
         };
         // Update global_ids map from the current package files
@@ -1285,6 +1448,22 @@ impl Config {
 
         Ok(mounts)
     }
+
+    /// The login page of the `lets-auth` app, `/-/auth/login/` unless it is mounted elsewhere,
+    /// which sends the user back to `next` once they have logged in.
+    pub fn login_url(&self, next: &str) -> String {
+        let mount_point = self
+            .package
+            .apps
+            .iter()
+            .find(|a| a.package.system.as_deref() == Some("lets-auth"))
+            .map_or("-/auth", |a| a.mount_point.trim_matches('/'));
+
+        format!(
+            "/{mount_point}/login/?next={}",
+            url::form_urlencoded::byte_serialize(next.as_bytes()).collect::<String>()
+        )
+    }
 }
 
 #[cfg(feature = "use-config-json")]
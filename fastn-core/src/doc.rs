@@ -45,11 +45,117 @@ pub async fn interpret_helper(
     download_assets: bool,
     line_number: usize,
     preview_session_id: &Option<String>,
+) -> ftd::interpreter::Result<ftd::interpreter::Document> {
+    interpret(
+        name,
+        source,
+        lib,
+        base_url,
+        download_assets,
+        line_number,
+        preview_session_id,
+        false,
+    )
+    .await
+}
+
+/// Like [interpret_helper], but goes on past errors and returns all of them as
+/// [ftd::interpreter::Error::Diagnostics]. For `fastn check` and the language server, which
+/// report errors instead of rendering the document.
+#[tracing::instrument(skip(lib))]
+pub async fn interpret_collecting_errors(
+    name: &str,
+    source: &str,
+    lib: &mut fastn_core::Library2022,
+    base_url: &str,
+    download_assets: bool,
+    line_number: usize,
+    preview_session_id: &Option<String>,
+) -> ftd::interpreter::Result<ftd::interpreter::Document> {
+    interpret(
+        name,
+        source,
+        lib,
+        base_url,
+        download_assets,
+        line_number,
+        preview_session_id,
+        true,
+    )
+    .await
+}
+
+/// `error`, which [interpret_helper] returned for the document `id`, as
+/// [ftd::interpreter::Error::Diagnostics], so that it renders as a code frame. `source` is what was
+/// interpreted, the first `line_number` lines of it are the auto imports.
+pub(crate) fn error_diagnostics(
+    error: ftd::interpreter::Error,
+    id: &str,
+    source: &str,
+    line_number: usize,
+) -> ftd::interpreter::Error {
+    match error {
+        ftd::interpreter::Error::Diagnostics(_) => error,
+        error => ftd::interpreter::Error::Diagnostics(vec![error_diagnostic(
+            error,
+            id,
+            source,
+            line_number,
+        )]),
+    }
+}
+
+/// `error` as a diagnostic of the document `id`, for the errors the interpreter does not collect.
+pub(crate) fn error_diagnostic(
+    error: ftd::interpreter::Error,
+    id: &str,
+    source: &str,
+    line_number: usize,
+) -> ftd::interpreter::Diagnostic {
+    // only the lines are needed, for the code frame
+    let document = ftd::interpreter::ParsedDocument {
+        name: id.to_string(),
+        lines: source
+            .split('\n')
+            .skip(line_number)
+            .map(|v| v.trim_end_matches('\r').to_string())
+            .collect(),
+        ..Default::default()
+    };
+    ftd::interpreter::Diagnostic::from_error(
+        &error,
+        id,
+        &std::collections::BTreeMap::from([(id.to_string(), document)]),
+    )
+    .unwrap_or_else(|| ftd::interpreter::Diagnostic {
+        severity: ftd::interpreter::Severity::Error,
+        doc_id: id.to_string(),
+        line_number: 0,
+        span: None,
+        line: None,
+        message: error.to_string(),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn interpret(
+    name: &str,
+    source: &str,
+    lib: &mut fastn_core::Library2022,
+    base_url: &str,
+    download_assets: bool,
+    line_number: usize,
+    preview_session_id: &Option<String>,
+    collect_errors: bool,
 ) -> ftd::interpreter::Result<ftd::interpreter::Document> {
     let doc = cached_parse(name, source, line_number)?;
 
     let builtin_overrides = package_dependent_builtins(&lib.config, lib.request.path());
-    let mut s = ftd::interpreter::interpret_with_line_number(name, doc, Some(builtin_overrides))?;
+    let mut s = if collect_errors {
+        ftd::interpreter::interpret_collecting_errors(name, doc, Some(builtin_overrides))?
+    } else {
+        ftd::interpreter::interpret_with_line_number(name, doc, Some(builtin_overrides))?
+    };
     lib.module_package_map.insert(
         name.trim_matches('/').to_string(),
         lib.config.package.name.to_string(),
@@ -69,7 +175,7 @@ pub async fn interpret_helper(
             } => {
                 tracing::info!("stuck on import: {module}");
                 // TODO: also check if module in in dependencies of this package
-                let caller_module = if module.starts_with("inherited-")
+                let package_caller_module = if module.starts_with("inherited-")
                     || caller_module.starts_with("inherited-")
                 {
                     // We want to use the main package name as the caller_module for this as the
@@ -81,17 +187,31 @@ pub async fn interpret_helper(
                 };
 
                 let (source, path, foreign_variable, foreign_function, ignore_line_numbers) =
-                    resolve_import_2022(
+                    match resolve_import_2022(
                         lib,
                         &mut st,
                         module.as_str(),
-                        caller_module,
+                        package_caller_module,
                         preview_session_id,
                     )
-                    .await?;
+                    .await
+                    {
+                        Ok(v) => v,
+                        Err(e) => {
+                            s = st.continue_after_import_error(&module, &caller_module, e)?;
+                            continue;
+                        }
+                    };
                 tracing::info!("import resolved: {module} -> {path}");
                 lib.dependencies_during_render.push(path);
-                let doc = cached_parse(module.as_str(), source.as_str(), ignore_line_numbers)?;
+                let doc = match cached_parse(module.as_str(), source.as_str(), ignore_line_numbers)
+                {
+                    Ok(doc) => doc,
+                    Err(e) => {
+                        s = st.continue_after_import_error(&module, &caller_module, e)?;
+                        continue;
+                    }
+                };
                 s = st.continue_after_import(
                     module.as_str(),
                     doc,
@@ -180,6 +300,8 @@ pub async fn resolve_import_2022(
                 "figma-cs-token-old".to_string(),
                 "http".to_string(),
                 "get-data".to_string(),
+                "data-file".to_string(),
+                "translate".to_string(),
                 "toc".to_string(),
                 "sitemap".to_string(),
                 "full-sitemap".to_string(),
@@ -194,6 +316,8 @@ pub async fn resolve_import_2022(
                 "document-suffix".to_string(),
                 "document-name".to_string(),
                 "user-details".to_string(),
+                "session".to_string(),
+                "csrf-token".to_string(),
                 "fastn-apps".to_string(),
                 "is-reader".to_string(),
                 "sql-query".to_string(),
@@ -263,6 +387,8 @@ pub async fn resolve_import_2022(
                 "toc".to_string(),
                 "include".to_string(),
                 "get-data".to_string(),
+                "data-file".to_string(),
+                "translate".to_string(),
                 "sitemap".to_string(),
                 "full-sitemap".to_string(),
                 "user-groups".to_string(),
@@ -281,6 +407,8 @@ pub async fn resolve_import_2022(
                 "cr-meta".to_string(),
                 "request-data".to_string(),
                 "user-details".to_string(),
+                "session".to_string(),
+                "csrf-token".to_string(),
                 "fastn-apps".to_string(),
                 "is-reader".to_string(),
                 "current-language".to_string(),
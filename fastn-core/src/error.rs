@@ -36,6 +36,9 @@ pub enum Error {
     #[error("IgnoreError: {}", _0)]
     IgnoreError(#[from] ignore::Error),
 
+    #[error("NotifyError: {}", _0)]
+    NotifyError(#[from] notify::Error),
+
     #[error("FromPathBufError: {}", _0)]
     FromPathBufError(#[from] camino::FromPathBufError),
 
@@ -120,6 +123,9 @@ pub enum Error {
     #[error("ds::RenameError: {}", _0)]
     DSRenameError(#[from] fastn_ds::RenameError),
 
+    #[error("ds::ListFilesError: {}", _0)]
+    DSListFilesError(#[from] fastn_ds::ListFilesError),
+
     #[error("ds::CreatePoolError: {}", _0)]
     CreatePool(#[from] fastn_ds::CreatePoolError),
 
@@ -143,6 +149,12 @@ pub enum Error {
 
     #[error("MigrationError: {0}")]
     MigrationError(#[from] fastn_core::migrations::MigrationError),
+
+    #[error("SessionError: {0}")]
+    SessionError(#[from] fastn_wasm::session::SessionError),
+
+    #[error("UserDataError: {0}")]
+    UserDataError(#[from] fastn_ds::UserDataError),
 }
 
 impl From<std::convert::Infallible> for Error {
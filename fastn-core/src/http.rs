@@ -387,18 +387,82 @@ pub(crate) fn url_regex() -> regex::Regex {
     ).unwrap()
 }
 
-#[tracing::instrument(skip(req_config, headers, body))]
-pub async fn http_post_with_cookie(
+/// How the `http` processor talks to the upstream server.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpOptions {
+    /// Give up on a request that takes longer than this, `None` waits for ever.
+    pub timeout: Option<std::time::Duration>,
+    /// Try again this many times when the request fails, times out, or gets a 429 or 5xx.
+    pub retries: u32,
+    /// Retry POST and PATCH requests too, which the server may end up handling twice. Only the
+    /// idempotent methods are retried otherwise.
+    pub retry_any_method: bool,
+}
+
+impl HttpOptions {
+    /// How many times a `method` request may be retried.
+    fn retries_for(&self, method: &str) -> u32 {
+        let idempotent = matches!(
+            method.to_uppercase().as_str(),
+            "GET" | "HEAD" | "PUT" | "DELETE" | "OPTIONS"
+        );
+        if idempotent || self.retry_any_method {
+            self.retries
+        } else {
+            0
+        }
+    }
+}
+
+/// Sends `request`, retrying with an exponential backoff (100ms, 200ms, 400ms...) as
+/// `options.retries` allows, for the idempotent methods or if `options.retry_any_method` is set.
+async fn send_with_retries(
+    ds: &fastn_ds::DocumentStore,
+    url: url::Url,
+    request: &fastn_core::http::Request,
+    options: &HttpOptions,
+) -> Result<fastn_ds::HttpResponse, fastn_ds::HttpError> {
+    let retries = options.retries_for(request.method());
+    let mut attempt = 0;
+    loop {
+        let res = ds
+            .http_with_timeout(url.clone(), request, &Default::default(), options.timeout)
+            .await;
+
+        let should_retry = match &res {
+            Ok(r) => {
+                r.status().is_server_error() || r.status() == http::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(_) => true,
+        };
+        if !should_retry || attempt >= retries {
+            return res;
+        }
+
+        attempt += 1;
+        tracing::info!(%url, attempt, "retrying http request");
+        tokio::time::sleep(std::time::Duration::from_millis(
+            100 << (attempt - 1).min(10),
+        ))
+        .await;
+    }
+}
+
+/// Sends a `method` request with `body` to `url`, forwarding the cookies of the current request.
+#[tracing::instrument(skip(req_config, headers, body, options))]
+pub async fn http_with_body_and_cookie(
     req_config: &fastn_core::RequestConfig,
+    method: &str,
     url: &str,
     headers: &std::collections::HashMap<String, String>,
     body: &str,
+    options: &HttpOptions,
 ) -> fastn_core::Result<(fastn_core::Result<bytes::Bytes>, Vec<String>)> {
     pub use fastn_ds::RequestType;
 
     let cookies = req_config.request.cookies().clone();
     let mut http_request = fastn_core::http::Request::default();
-    http_request.set_method("post");
+    http_request.set_method(method);
     http_request.set_cookies(&cookies);
     http_request.set_headers(headers);
     http_request.set_ip(req_config.request.ip.clone());
@@ -407,10 +471,7 @@ pub async fn http_post_with_cookie(
     http_request.set_x_fastn_root(req_config.config.ds.root_str().as_str());
 
     let http_url = url::Url::parse(url).map_err(|e| fastn_core::Error::DSHttpError(e.into()))?;
-    let res = req_config
-        .config
-        .ds
-        .http(http_url, &http_request, &Default::default())
+    let res = send_with_retries(&req_config.config.ds, http_url, &http_request, options)
         .await
         .map_err(fastn_core::Error::DSHttpError)?;
 
@@ -423,7 +484,7 @@ pub async fn http_post_with_cookie(
         }
     });
 
-    if !res.status().eq(&http::StatusCode::OK) {
+    if !res.status().is_success() {
         let message = format!(
             "url: {}, response_status: {}, response: {:?}",
             url,
@@ -443,21 +504,30 @@ pub async fn http_post_with_cookie(
 pub async fn http_get(ds: &fastn_ds::DocumentStore, url: &str) -> fastn_core::Result<bytes::Bytes> {
     tracing::debug!("http_get {}", &url);
 
-    http_get_with_cookie(ds, &Default::default(), url, &Default::default(), true)
-        .await?
-        .0
+    http_get_with_cookie(
+        ds,
+        &Default::default(),
+        url,
+        &Default::default(),
+        true,
+        &Default::default(),
+    )
+    .await?
+    .0
 }
 
 static NOT_FOUND_CACHE: once_cell::sync::Lazy<antidote::RwLock<std::collections::HashSet<String>>> =
     once_cell::sync::Lazy::new(|| antidote::RwLock::new(Default::default()));
 
-#[tracing::instrument(skip(ds, req, headers))]
+/// `GET` `url`, forwarding the cookies of `req`.
+#[tracing::instrument(skip(ds, req, headers, options))]
 pub async fn http_get_with_cookie(
     ds: &fastn_ds::DocumentStore,
     req: &fastn_core::http::Request,
     url: &str,
     headers: &std::collections::HashMap<String, String>,
     use_cache: bool,
+    options: &HttpOptions,
 ) -> fastn_core::Result<(fastn_core::Result<bytes::Bytes>, Vec<String>)> {
     pub use fastn_ds::RequestType;
     if use_cache && NOT_FOUND_CACHE.read().contains(url) {
@@ -477,8 +547,7 @@ pub async fn http_get_with_cookie(
     http_request.set_x_fastn_root(ds.root_str().as_str());
     http_request.set_ip(req.ip.clone());
     let http_url = url::Url::parse(url).map_err(|e| fastn_core::Error::DSHttpError(e.into()))?;
-    let res = ds
-        .http(http_url, &http_request, &Default::default())
+    let res = send_with_retries(ds, http_url, &http_request, options)
         .await
         .map_err(fastn_core::Error::DSHttpError)?;
 
@@ -649,4 +718,25 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn retries_only_idempotent_methods() {
+        let options = fastn_core::http::HttpOptions {
+            retries: 3,
+            ..Default::default()
+        };
+        for method in ["GET", "HEAD", "PUT", "DELETE", "OPTIONS", "get"] {
+            assert_eq!(options.retries_for(method), 3, "{method}");
+        }
+        for method in ["POST", "PATCH", "post"] {
+            assert_eq!(options.retries_for(method), 0, "{method}");
+        }
+
+        let options = fastn_core::http::HttpOptions {
+            retry_any_method: true,
+            ..options
+        };
+        assert_eq!(options.retries_for("POST"), 3);
+        assert_eq!(options.retries_for("PATCH"), 3);
+    }
 }
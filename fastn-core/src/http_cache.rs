@@ -0,0 +1,245 @@
+/// What kind of response we are sending, decides the default `Cache-Control` header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Route {
+    /// Files served as-is from the package or its dependencies, and our own js/css.
+    Static,
+    /// Rendered ftd documents. `cacheable` is `RequestConfig::response_is_cacheable`, it is false
+    /// when the document used something request specific, like cookies or the database.
+    Document { cacheable: bool },
+}
+
+/// The `Cache-Control` header for `path`: a matching `fastn.cache-control` rule from
+/// `FASTN.ftd` if any, else our defaults. Either way the browser revalidates using the `ETag`
+/// unless told otherwise, which costs one round trip but no body.
+///
+/// What the sitemap gives readers to is only sent after an access check, so it is `private`
+/// whatever the rule says: a shared cache would give it to anyone.
+pub(crate) fn cache_control(package: &fastn_core::Package, path: &str, route: Route) -> String {
+    if route == (Route::Document { cacheable: false }) {
+        // the content depends on who is asking, caching it anywhere is a bad idea
+        return "no-store".to_string();
+    }
+
+    let guarded = !fastn_core::sitemap::access::find(package, path).is_public();
+    if let Some(rule) = fastn_core::package::cache_control::find(&package.cache_control, path) {
+        return fastn_core::package::cache_control::CacheControl {
+            private: rule.private || guarded,
+            ..rule.clone()
+        }
+        .header_value();
+    }
+
+    match route {
+        Route::Static if !guarded => "public, no-cache".to_string(),
+        Route::Static | Route::Document { .. } => "private, no-cache".to_string(),
+    }
+}
+
+/// Adds `Cache-Control`, `ETag` validators to a successful `GET`/`HEAD` response, and turns it
+/// into a `304 Not Modified` if the client already has this version (`If-None-Match`, or
+/// `If-Modified-Since` when the response carries a `Last-Modified` header).
+///
+/// A `Cache-Control` header already set on the response (eg our hashed js/css files, or
+/// headers sent by the document itself) is kept as is.
+pub(crate) fn conditional(
+    req: &fastn_core::http::Request,
+    response: fastn_core::http::Response,
+    cache_control: String,
+) -> fastn_core::http::Response {
+    if response.status() != actix_web::http::StatusCode::OK
+        || !matches!(req.method().to_uppercase().as_str(), "GET" | "HEAD")
+    {
+        return response;
+    }
+
+    let (mut response, body) = response.into_parts();
+
+    if !response
+        .headers()
+        .contains_key(actix_web::http::header::CACHE_CONTROL)
+        && let Ok(value) = actix_web::http::header::HeaderValue::from_str(cache_control.as_str())
+    {
+        response
+            .headers_mut()
+            .insert(actix_web::http::header::CACHE_CONTROL, value);
+    }
+
+    if cache_control == "no-store" {
+        return response.set_body(body);
+    }
+
+    let body = match actix_web::body::MessageBody::try_into_bytes(body) {
+        Ok(body) => body,
+        // streaming body, we can not hash it without buffering it all, so no validators
+        Err(body) => return response.set_body(body),
+    };
+
+    let etag = etag(&body);
+    if let Ok(value) = actix_web::http::header::HeaderValue::from_str(etag.as_str()) {
+        response
+            .headers_mut()
+            .insert(actix_web::http::header::ETAG, value);
+    }
+
+    let not_modified = match header(req, "if-none-match") {
+        // If-Modified-Since is ignored when If-None-Match is present: rfc 9110 section 13.1.3
+        Some(if_none_match) => etag_matches(if_none_match.as_str(), etag.as_str()),
+        None => match (
+            header(req, "if-modified-since"),
+            response
+                .headers()
+                .get(actix_web::http::header::LAST_MODIFIED)
+                .and_then(|v| v.to_str().ok()),
+        ) {
+            (Some(since), Some(last_modified)) => not_modified_since(&since, last_modified),
+            _ => false,
+        },
+    };
+
+    if not_modified {
+        let mut not_modified = actix_web::HttpResponse::NotModified().finish();
+        for name in [
+            actix_web::http::header::CACHE_CONTROL,
+            actix_web::http::header::ETAG,
+            actix_web::http::header::LAST_MODIFIED,
+            actix_web::http::header::VARY,
+        ] {
+            if let Some(value) = response.headers().get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        return not_modified;
+    }
+
+    response.set_body(actix_web::body::BoxBody::new(body))
+}
+
+/// `Last-Modified` header value for a file's modification time.
+pub(crate) fn last_modified(modified: std::time::SystemTime) -> String {
+    actix_web::http::header::HttpDate::from(modified).to_string()
+}
+
+/// Same bytes, same `ETag`, regardless of which file or document produced them. It is a weak
+/// validator as the body is hashed before the compress middleware encodes it, and a strong one
+/// would have to differ between the gzip, brotli and identity versions of the response.
+fn etag(body: &[u8]) -> String {
+    format!("W/\"{}\"", &fastn_core::utils::generate_hash(body)[..32])
+}
+
+fn header(req: &fastn_core::http::Request, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/// `If-None-Match` uses the weak comparison: `W/"x"` matches `"x"`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .map(|candidate| candidate.trim().trim_start_matches("W/"))
+            .any(|candidate| candidate == etag)
+}
+
+fn not_modified_since(since: &str, last_modified: &str) -> bool {
+    use std::str::FromStr;
+
+    match (
+        actix_web::http::header::HttpDate::from_str(since),
+        actix_web::http::header::HttpDate::from_str(last_modified),
+    ) {
+        (Ok(since), Ok(last_modified)) => {
+            std::time::SystemTime::from(last_modified) <= std::time::SystemTime::from(since)
+        }
+        // an unparsable date means we can not tell, send the full response
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn etag_matches() {
+        let etag = "W/\"ABC\"";
+        assert!(super::etag_matches("\"ABC\"", etag));
+        assert!(super::etag_matches("W/\"ABC\"", etag));
+        assert!(super::etag_matches("\"ABC\"", "\"ABC\""));
+        assert!(super::etag_matches("\"XYZ\", \"ABC\"", etag));
+        assert!(super::etag_matches("*", etag));
+        assert!(!super::etag_matches("\"XYZ\"", etag));
+        assert!(!super::etag_matches("ABC", etag));
+    }
+
+    #[test]
+    fn etag_is_weak() {
+        // the compress middleware encodes the body after we hash it
+        let etag = super::etag(b"hello");
+        assert!(etag.starts_with("W/\""), "{etag}");
+        assert_eq!(etag, super::etag(b"hello"));
+        assert_ne!(etag, super::etag(b"world"));
+    }
+
+    #[test]
+    fn not_modified_since() {
+        let last_modified = "Sun, 06 Nov 1994 08:49:37 GMT";
+        assert!(super::not_modified_since(last_modified, last_modified));
+        assert!(super::not_modified_since(
+            "Mon, 07 Nov 1994 08:49:37 GMT",
+            last_modified
+        ));
+        assert!(!super::not_modified_since(
+            "Sat, 05 Nov 1994 08:49:37 GMT",
+            last_modified
+        ));
+        assert!(!super::not_modified_since("yesterday", last_modified));
+    }
+
+    #[test]
+    fn cache_control() {
+        let mut package = fastn_core::Package::new("foo.com");
+        use super::Route;
+
+        assert_eq!(
+            super::cache_control(&package, "/a.css", Route::Static),
+            "public, no-cache"
+        );
+        assert_eq!(
+            super::cache_control(&package, "/", Route::Document { cacheable: true }),
+            "private, no-cache"
+        );
+
+        package.cache_control = vec![fastn_core::package::cache_control::CacheControl {
+            path: "/".to_string(),
+            max_age: Some(60),
+            private: false,
+            no_store: false,
+            immutable: false,
+        }];
+
+        assert_eq!(
+            super::cache_control(&package, "/", Route::Document { cacheable: true }),
+            "public, max-age=60"
+        );
+        assert_eq!(
+            super::cache_control(&package, "/", Route::Document { cacheable: false }),
+            "no-store"
+        );
+
+        // only readers get these, after an access check
+        package.sitemap = Some(fastn_core::sitemap::Sitemap {
+            readers: vec!["staff".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            super::cache_control(&package, "/a.css", Route::Static),
+            "private, max-age=60"
+        );
+        package.cache_control.clear();
+        assert_eq!(
+            super::cache_control(&package, "/a.css", Route::Static),
+            "private, no-cache"
+        );
+    }
+}
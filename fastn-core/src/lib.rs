@@ -19,11 +19,13 @@ pub mod http;
 mod ds;
 mod error;
 pub mod library;
+pub mod search;
 pub mod sitemap;
 mod snapshot;
 mod tracker;
 mod translation;
 mod version;
+pub mod watcher;
 // mod wasm;
 pub mod catch_panic;
 // pub(crate) mod google_sheets;
@@ -31,6 +33,8 @@ mod library2022;
 mod migrations;
 
 pub(crate) mod host_builtins;
+pub(crate) mod compress;
+pub(crate) mod http_cache;
 
 pub(crate) use auto_import::AutoImport;
 pub use commands::{
@@ -333,6 +333,12 @@ impl Library2022 {
         let line_number = ast.line_number();
         let (_processor, variable_name, value, kind) = get_processor_data(ast, doc)?;
         match processor.as_str() {
+            "http" | "sql-query" | "sql-execute" | "sql-batch" | "request-data"
+            | "get-identities" | "is-reader" | "user-details" | "session" | "csrf-token"
+                if self.static_check =>
+            {
+                processor::placeholder::process(value, kind, doc)
+            }
             "figma-typo-token" => {
                 processor::figma_typography_tokens::process_typography_tokens(value, kind, doc)
             }
@@ -345,8 +351,16 @@ impl Library2022 {
             "current-language" => processor::lang::process(value, kind, doc, self).await,
             "toc" => processor::toc::process(value, kind, doc),
             "get-data" => processor::get_data::process(value, kind, doc, self),
-            "sitemap" => processor::sitemap::process(value, kind, doc, self),
-            "full-sitemap" => processor::sitemap::full_sitemap_process(value, kind, doc, self),
+            "data-file" => {
+                processor::data_file::process(value, kind, doc, self, preview_session_id).await
+            }
+            "translate" => {
+                processor::translate::process(value, kind, doc, self, preview_session_id).await
+            }
+            "sitemap" => processor::sitemap::process(value, kind, doc, self).await,
+            "full-sitemap" => {
+                processor::sitemap::full_sitemap_process(value, kind, doc, self).await
+            }
             "request-data" => {
                 processor::request_data::process(variable_name, value, kind, doc, self)
             }
@@ -378,6 +392,8 @@ impl Library2022 {
                 processor::fetch_file::fetch_files(value, kind, doc, self, preview_session_id).await
             }
             "user-details" => processor::user_details::process(value, kind, doc, self).await,
+            "session" => processor::session::process(value, kind, doc, self).await,
+            "csrf-token" => processor::session::csrf_token(value, kind, doc, self).await,
             "fastn-apps" => processor::apps::process(value, kind, doc, self),
             "is-reader" => processor::user_group::is_reader(value, kind, doc, self).await,
             "sql-query" | "sql-execute" | "sql-batch" if preview_session_id.is_some() => {
@@ -390,7 +406,6 @@ impl Library2022 {
             "sql-execute" => processor::sql::process(value, kind, doc, self, "sql-execute").await,
             "sql-batch" => processor::sql::process(value, kind, doc, self, "sql-batch").await,
             // "package-query" => processor::package_query::process(value, kind, doc, self).await,
-            // "pg" => processor::pg::process(value, kind, doc, self).await,
             "query" => processor::query::process(value, kind, doc, self, preview_session_id).await,
             t => Err(ftd::interpreter::Error::ParseError {
                 doc_id: self.document_id.to_string(),
@@ -0,0 +1,860 @@
+//! `data-file` processor: loads a CSV, JSON, YAML or TOML file of the package, or of one of its
+//! dependencies, into a typed ftd variable.
+//!
+//! ```ftd
+//! -- record person:
+//! string name:
+//! integer age:
+//! optional string email:
+//!
+//! -- person list people:
+//! $processor$: data-file
+//! file: data/people.csv
+//! name: Full Name
+//! ```
+//!
+//! Headers:
+//!
+//! - `file`: path of the file, relative to the package root (required)
+//! - `package`: name of the dependency package the file belongs to, defaults to this package
+//! - `format`: one of `csv`, `json`, `yaml` or `toml`, by default guessed from the extension
+//! - `delimiter`: the CSV field delimiter, `,` by default
+//! - `key`: dotted path of the value to load, for files that contain more than the list (say
+//!   `[[people]]` in a TOML file)
+//!
+//! Any other header maps a record field to the column (or object key) holding its value, by
+//! default the field is read from the column with the same name.
+//!
+//! Values are coerced to the kind of the field they go to, so `"42"` can be read into an
+//! `integer`, and an empty CSV cell into an `optional` field is `NULL`. Values that can not be
+//! coerced are reported with the line of the data file they are on.
+
+const FILE_HEADER: &str = "file";
+const PACKAGE_HEADER: &str = "package";
+const FORMAT_HEADER: &str = "format";
+const DELIMITER_HEADER: &str = "delimiter";
+const KEY_HEADER: &str = "key";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Csv { delimiter: u8 },
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    fn from_name(name: &str, delimiter: u8) -> Option<Format> {
+        Some(match name.to_lowercase().as_str() {
+            "csv" => Format::Csv { delimiter },
+            "tsv" => Format::Csv { delimiter: b'\t' },
+            "json" => Format::Json,
+            "yaml" | "yml" => Format::Yaml,
+            "toml" => Format::Toml,
+            _ => return None,
+        })
+    }
+}
+
+/// The kind of the variable the data file is loaded into, with the records resolved, so
+/// deserializing does not need the document.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    String,
+    Integer,
+    Decimal,
+    Boolean,
+    /// `object`: any value, as is
+    Any,
+    Optional(Box<Shape>),
+    List(Box<Shape>),
+    Record(Vec<Column>),
+    /// the value at `key` of an object
+    At(String, Box<Shape>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Column {
+    pub field: String,
+    /// the column, or object key, the field is read from
+    pub column: String,
+    pub shape: Shape,
+}
+
+impl Shape {
+    pub(crate) fn from_kind(
+        kind: &fastn_resolved::Kind,
+        doc: &ftd::interpreter::TDoc,
+        mapping: &std::collections::BTreeMap<String, String>,
+        line_number: usize,
+    ) -> ftd::interpreter::Result<Shape> {
+        Shape::from_kind_(kind, doc, Some(mapping), &mut vec![], line_number)
+    }
+
+    fn from_kind_(
+        kind: &fastn_resolved::Kind,
+        doc: &ftd::interpreter::TDoc,
+        // only the outermost record, the one the rows are loaded into, is mapped
+        mapping: Option<&std::collections::BTreeMap<String, String>>,
+        records: &mut Vec<String>,
+        line_number: usize,
+    ) -> ftd::interpreter::Result<Shape> {
+        Ok(match kind {
+            fastn_resolved::Kind::String => Shape::String,
+            fastn_resolved::Kind::Integer => Shape::Integer,
+            fastn_resolved::Kind::Decimal => Shape::Decimal,
+            fastn_resolved::Kind::Boolean => Shape::Boolean,
+            fastn_resolved::Kind::Object => Shape::Any,
+            fastn_resolved::Kind::Constant { kind } => {
+                Shape::from_kind_(kind, doc, mapping, records, line_number)?
+            }
+            fastn_resolved::Kind::Optional { kind } => Shape::Optional(Box::new(
+                Shape::from_kind_(kind, doc, mapping, records, line_number)?,
+            )),
+            fastn_resolved::Kind::List { kind } => Shape::List(Box::new(Shape::from_kind_(
+                kind,
+                doc,
+                mapping,
+                records,
+                line_number,
+            )?)),
+            fastn_resolved::Kind::Record { name } => {
+                if records.contains(name) {
+                    return ftd::interpreter::utils::e2(
+                        format!("`data-file` does not support recursive record `{name}`"),
+                        doc.name,
+                        line_number,
+                    );
+                }
+                records.push(name.to_string());
+
+                let record = doc.get_record(name, line_number)?;
+                let mut columns = vec![];
+                for field in record.fields {
+                    columns.push(Column {
+                        column: mapping
+                            .and_then(|m| m.get(field.name.as_str()))
+                            .cloned()
+                            .unwrap_or_else(|| field.name.clone()),
+                        shape: Shape::from_kind_(
+                            &field.kind.kind,
+                            doc,
+                            None,
+                            records,
+                            line_number,
+                        )?,
+                        field: field.name,
+                    });
+                }
+
+                if let Some(mapping) = mapping
+                    && let Some(unknown) = mapping
+                        .keys()
+                        .find(|k| !columns.iter().any(|c| &c.field == *k))
+                {
+                    return ftd::interpreter::utils::e2(
+                        format!("record `{name}` has no field `{unknown}`"),
+                        doc.name,
+                        line_number,
+                    );
+                }
+
+                records.pop();
+                Shape::Record(columns)
+            }
+            t => {
+                return ftd::interpreter::utils::e2(
+                    format!("`data-file` can not load a {}", t.get_name()),
+                    doc.name,
+                    line_number,
+                );
+            }
+        })
+    }
+
+    /// `key` as in the `key` header: `a.b` is the value at `b` in the object at `a`.
+    pub(crate) fn at(self, key: &str) -> Shape {
+        key.rsplit('.')
+            .filter(|k| !k.is_empty())
+            .fold(self, |shape, k| Shape::At(k.to_string(), Box::new(shape)))
+    }
+
+    fn is_optional(&self) -> bool {
+        matches!(self, Shape::Optional(_) | Shape::Any)
+    }
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shape::String => write!(f, "string"),
+            Shape::Integer => write!(f, "integer"),
+            Shape::Decimal => write!(f, "decimal"),
+            Shape::Boolean => write!(f, "boolean"),
+            Shape::Any => write!(f, "object"),
+            Shape::Optional(s) => write!(f, "optional {s}"),
+            Shape::List(s) => write!(f, "{s} list"),
+            Shape::Record(_) => write!(f, "record"),
+            Shape::At(k, _) => write!(f, "object with key `{k}`"),
+        }
+    }
+}
+
+/// Deserializes any self describing format into the json the interpreter takes, coercing values
+/// to the kind they are loaded into.
+#[derive(Clone, Copy)]
+struct Seed<'a> {
+    shape: &'a Shape,
+    /// the record field being read, for error messages
+    field: Option<&'a str>,
+}
+
+impl<'a> Seed<'a> {
+    fn new(shape: &'a Shape) -> Self {
+        Seed { shape, field: None }
+    }
+
+    fn inner(&self, shape: &'a Shape) -> Self {
+        Seed {
+            shape,
+            field: self.field,
+        }
+    }
+}
+
+impl<'de> serde::de::DeserializeSeed<'de> for Seed<'_> {
+    type Value = serde_json::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match self.shape {
+            Shape::Any => serde::Deserialize::deserialize(deserializer),
+            Shape::Optional(_) => deserializer.deserialize_option(self),
+            _ => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for Seed<'_> {
+    type Value = serde_json::Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.shape)?;
+        if let Some(field) = self.field {
+            write!(f, " for `{field}`")?;
+        }
+        Ok(())
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        match self.shape {
+            Shape::Boolean => Ok(v.into()),
+            Shape::String => Ok(v.to_string().into()),
+            Shape::Optional(s) => self.inner(s).visit_bool(v),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Bool(v), &self)),
+        }
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        match self.shape {
+            Shape::Integer => Ok(v.into()),
+            Shape::Decimal => Ok((v as f64).into()),
+            Shape::String => Ok(v.to_string().into()),
+            Shape::Optional(s) => self.inner(s).visit_i64(v),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => match self.shape {
+                Shape::Decimal => Ok((v as f64).into()),
+                Shape::String => Ok(v.to_string().into()),
+                Shape::Optional(s) => self.inner(s).visit_u64(v),
+                _ => Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self)),
+            },
+        }
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        match self.shape {
+            Shape::Decimal => Ok(v.into()),
+            Shape::Integer if v.fract() == 0.0 && v.abs() < i64::MAX as f64 => {
+                Ok((v as i64).into())
+            }
+            Shape::String => Ok(v.to_string().into()),
+            Shape::Optional(s) => self.inner(s).visit_f64(v),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Float(v), &self)),
+        }
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let invalid = || E::invalid_value(serde::de::Unexpected::Str(v), &self);
+        match self.shape {
+            Shape::String => Ok(v.into()),
+            Shape::Integer => v
+                .trim()
+                .parse::<i64>()
+                .map(Into::into)
+                .map_err(|_| invalid()),
+            Shape::Decimal => v
+                .trim()
+                .parse::<f64>()
+                .map(Into::into)
+                .map_err(|_| invalid()),
+            Shape::Boolean => match v.trim().to_lowercase().as_str() {
+                "true" => Ok(true.into()),
+                "false" => Ok(false.into()),
+                _ => Err(invalid()),
+            },
+            // empty CSV cells and the like
+            Shape::Optional(_) if v.trim().is_empty() => Ok(serde_json::Value::Null),
+            Shape::Optional(s) => self.inner(s).visit_str(v),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        match self.shape {
+            Shape::Optional(_) => Ok(serde_json::Value::Null),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Option, &self)),
+        }
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        match self.shape {
+            Shape::Optional(_) => Ok(serde_json::Value::Null),
+            _ => Err(E::invalid_type(serde::de::Unexpected::Unit, &self)),
+        }
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match self.shape {
+            Shape::Optional(s) => {
+                serde::de::DeserializeSeed::deserialize(self.inner(s), deserializer)
+            }
+            _ => serde::de::DeserializeSeed::deserialize(self, deserializer),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        match self.shape {
+            Shape::List(s) => {
+                let mut list = vec![];
+                while let Some(v) = seq.next_element_seed(Seed::new(s))? {
+                    list.push(v);
+                }
+                Ok(serde_json::Value::Array(list))
+            }
+            Shape::Optional(s) => self.inner(s).visit_seq(seq),
+            _ => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Seq,
+                &self,
+            )),
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        match self.shape {
+            Shape::Record(columns) => {
+                let mut record = serde_json::Map::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    match columns.iter().find(|c| c.column == key) {
+                        Some(c) => {
+                            let value = map.next_value_seed(Seed {
+                                shape: &c.shape,
+                                field: Some(c.field.as_str()),
+                            })?;
+                            record.insert(c.field.clone(), value);
+                        }
+                        None => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                for c in columns {
+                    if record.contains_key(c.field.as_str()) {
+                        continue;
+                    }
+                    let value = match &c.shape {
+                        s if s.is_optional() => serde_json::Value::Null,
+                        Shape::List(_) => serde_json::Value::Array(vec![]),
+                        _ => {
+                            return Err(serde::de::Error::custom(format!(
+                                "missing `{}` for `{}`",
+                                c.column, c.field
+                            )));
+                        }
+                    };
+                    record.insert(c.field.clone(), value);
+                }
+
+                Ok(serde_json::Value::Object(record))
+            }
+            Shape::At(k, s) => {
+                let mut found = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if &key == k {
+                        found = Some(map.next_value_seed(Seed::new(s))?);
+                    } else {
+                        map.next_value::<serde::de::IgnoredAny>()?;
+                    }
+                }
+                match found {
+                    Some(v) => Ok(v),
+                    None if s.is_optional() => Ok(serde_json::Value::Null),
+                    None => Err(serde::de::Error::custom(format!("missing key `{k}`"))),
+                }
+            }
+            Shape::Optional(s) => self.inner(s).visit_map(map),
+            _ => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Map,
+                &self,
+            )),
+        }
+    }
+}
+
+/// Parses `content` of `file` as `format`, into the json for `shape`. Errors mention the line of
+/// `file` they are on.
+pub(crate) fn parse(
+    file: &str,
+    content: &str,
+    format: Format,
+    shape: &Shape,
+) -> Result<serde_json::Value, String> {
+    use serde::de::DeserializeSeed;
+
+    match format {
+        Format::Csv { delimiter } => parse_csv(file, content, delimiter, shape),
+        Format::Json => {
+            let mut de = serde_json::Deserializer::from_str(content);
+            let value = Seed::new(shape)
+                .deserialize(&mut de)
+                .and_then(|v| de.end().map(|_| v))
+                .map_err(|e| format!("{file}: {e}"))?;
+            Ok(value)
+        }
+        Format::Yaml => Seed::new(shape)
+            .deserialize(serde_yaml::Deserializer::from_str(content))
+            .map_err(|e| format!("{file}: {e}")),
+        Format::Toml => Seed::new(shape)
+            .deserialize(toml::Deserializer::new(content))
+            .map_err(|e| format!("{file}: {e}")),
+    }
+}
+
+fn parse_csv(
+    file: &str,
+    content: &str,
+    delimiter: u8,
+    shape: &Shape,
+) -> Result<serde_json::Value, String> {
+    use serde::de::DeserializeSeed;
+
+    let (row, many) = match shape {
+        Shape::List(row) => (row.as_ref(), true),
+        Shape::Optional(row) => (row.as_ref(), false),
+        row => (row, false),
+    };
+    if !matches!(row, Shape::Record(_)) {
+        return Err(format!(
+            "{file}: CSV files can only be loaded into a record, or a list of records"
+        ));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::Headers)
+        .from_reader(content.as_bytes());
+    let columns = reader
+        .headers()
+        .map_err(|e| format!("{file}:1: {e}"))?
+        .clone();
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| match e.position() {
+            Some(p) => format!("{file}:{}: {e}", p.line()),
+            None => format!("{file}: {e}"),
+        })?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let cells = columns
+            .iter()
+            .zip(record.iter())
+            .map(|(c, v)| (c.to_string(), v.to_string()));
+        let row = Seed::new(row)
+            .deserialize(serde::de::value::MapDeserializer::<
+                _,
+                serde::de::value::Error,
+            >::new(cells))
+            .map_err(|e| format!("{file}:{line}: {e}"))?;
+        rows.push(row);
+    }
+
+    if many {
+        return Ok(serde_json::Value::Array(rows));
+    }
+
+    match rows.len() {
+        1 => Ok(rows.remove(0)),
+        0 if shape.is_optional() => Ok(serde_json::Value::Null),
+        len => Err(format!("{file}: has {len} rows, expected one row")),
+    }
+}
+
+pub async fn process(
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
+    preview_session_id: &Option<String>,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    let (headers, line_number) = if let Ok(val) = value.get_record(doc.name) {
+        (val.2.to_owned(), val.5.to_owned())
+    } else {
+        (ftd_ast::HeaderValues::new(vec![]), value.line_number())
+    };
+
+    let file = match headers.get_optional_string_by_key(FILE_HEADER, doc.name, line_number)? {
+        Some(file) => file,
+        None => {
+            return ftd::interpreter::utils::e2(
+                format!(
+                    "'file' key is required when using `{}: data-file`",
+                    ftd::PROCESSOR_MARKER
+                ),
+                doc.name,
+                line_number,
+            );
+        }
+    };
+    if !is_inside_package(file.as_str()) {
+        return ftd::interpreter::utils::e2(
+            format!("`{file}` is not a path inside the package"),
+            doc.name,
+            line_number,
+        );
+    }
+
+    let delimiter =
+        match headers.get_optional_string_by_key(DELIMITER_HEADER, doc.name, line_number)? {
+            None => b',',
+            Some(d) if d == "\\t" => b'\t',
+            Some(d) if d.len() == 1 && d.is_ascii() => d.as_bytes()[0],
+            Some(d) => {
+                return ftd::interpreter::utils::e2(
+                    format!("`delimiter` must be a single character, found: `{d}`"),
+                    doc.name,
+                    line_number,
+                );
+            }
+        };
+
+    let format = match headers.get_optional_string_by_key(FORMAT_HEADER, doc.name, line_number)? {
+        Some(f) => Format::from_name(f.as_str(), delimiter),
+        None => camino::Utf8Path::new(file.as_str())
+            .extension()
+            .and_then(|e| Format::from_name(e, delimiter)),
+    };
+    let format = match format {
+        Some(format) => format,
+        None => {
+            return ftd::interpreter::utils::e2(
+                format!(
+                    "can not tell the format of {file}, pass `format`: csv, json, yaml or toml"
+                ),
+                doc.name,
+                line_number,
+            );
+        }
+    };
+
+    let mut mapping = std::collections::BTreeMap::new();
+    for header in headers.0.iter() {
+        if matches!(
+            header.key.as_str(),
+            FILE_HEADER | PACKAGE_HEADER | FORMAT_HEADER | DELIMITER_HEADER | KEY_HEADER
+        ) || header.key == ftd::PROCESSOR_MARKER
+        {
+            continue;
+        }
+        mapping.insert(
+            header.key.to_string(),
+            header.value.string(doc.name)?.to_string(),
+        );
+    }
+
+    let mut shape = Shape::from_kind(&kind, doc, &mapping, line_number)?;
+    if let Some(key) = headers.get_optional_string_by_key(KEY_HEADER, doc.name, line_number)? {
+        if matches!(format, Format::Csv { .. }) {
+            return ftd::interpreter::utils::e2(
+                "`key` can not be used with CSV files",
+                doc.name,
+                line_number,
+            );
+        }
+        shape = shape.at(key.as_str());
+    }
+
+    let config = &req_config.config;
+    let root = match headers.get_optional_string_by_key(PACKAGE_HEADER, doc.name, line_number)? {
+        None => config.ds.root(),
+        Some(p) if p == config.package.name => config.ds.root(),
+        Some(p) => match config.all_packages.get(p.as_str()) {
+            Some(package) => config.get_root_for_package(package.get()),
+            None => {
+                return ftd::interpreter::utils::e2(
+                    format!("`{p}` is not a dependency of {}", config.package.name),
+                    doc.name,
+                    line_number,
+                );
+            }
+        },
+    };
+    let path = root.join(file.as_str());
+
+    let content = config
+        .ds
+        .read_content(&path, preview_session_id)
+        .await
+        .map_err(|e| ftd::interpreter::Error::ParseError {
+            message: format!("can not read {file}: {e}"),
+            doc_id: doc.name.to_string(),
+            line_number,
+        })?;
+    // so `fastn build` rebuilds this document when the data file changes
+    req_config.data_files.insert(
+        path.to_string(),
+        fastn_core::utils::generate_hash(content.as_slice()),
+    );
+
+    let content = String::from_utf8(content).map_err(|_| ftd::interpreter::Error::ParseError {
+        message: format!("{file} is not utf-8"),
+        doc_id: doc.name.to_string(),
+        line_number,
+    })?;
+
+    let json = parse(file.as_str(), content.as_str(), format, &shape).map_err(|message| {
+        ftd::interpreter::Error::ParseError {
+            message,
+            doc_id: doc.name.to_string(),
+            line_number,
+        }
+    })?;
+
+    doc.from_json(&json, &kind, &value)
+}
+
+/// `file` is relative to the package root, and must not get out of it (eg `../secrets.json`).
+fn is_inside_package(file: &str) -> bool {
+    camino::Utf8Path::new(file).components().all(|c| {
+        matches!(
+            c,
+            camino::Utf8Component::Normal(_) | camino::Utf8Component::CurDir
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    fn person() -> super::Shape {
+        super::Shape::Record(vec![
+            super::Column {
+                field: "name".to_string(),
+                column: "Full Name".to_string(),
+                shape: super::Shape::String,
+            },
+            super::Column {
+                field: "age".to_string(),
+                column: "age".to_string(),
+                shape: super::Shape::Integer,
+            },
+            super::Column {
+                field: "email".to_string(),
+                column: "email".to_string(),
+                shape: super::Shape::Optional(Box::new(super::Shape::String)),
+            },
+        ])
+    }
+
+    fn people() -> super::Shape {
+        super::Shape::List(Box::new(person()))
+    }
+
+    fn csv(content: &str, shape: &super::Shape) -> Result<serde_json::Value, String> {
+        super::parse(
+            "people.csv",
+            content,
+            super::Format::Csv { delimiter: b',' },
+            shape,
+        )
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(
+            super::Format::from_name("CSV", b';'),
+            Some(super::Format::Csv { delimiter: b';' })
+        );
+        assert_eq!(
+            super::Format::from_name("tsv", b','),
+            Some(super::Format::Csv { delimiter: b'\t' })
+        );
+        assert_eq!(
+            super::Format::from_name("yml", b','),
+            Some(super::Format::Yaml)
+        );
+        assert_eq!(super::Format::from_name("xml", b','), None);
+    }
+
+    #[test]
+    fn csv_rows() {
+        let content = "Full Name,age,email,ignored\nAlice, 30 ,alice@example.com,x\nBob,41,,y\n";
+        assert_eq!(
+            csv(content, &people()).unwrap(),
+            serde_json::json!([
+                {"name": "Alice", "age": 30, "email": "alice@example.com"},
+                {"name": "Bob", "age": 41, "email": null},
+            ])
+        );
+
+        // a single record needs exactly one row
+        assert_eq!(
+            csv("Full Name,age\nAlice,30\n", &person()).unwrap(),
+            serde_json::json!({"name": "Alice", "age": 30, "email": null})
+        );
+        assert_eq!(
+            csv(content, &person()).unwrap_err(),
+            "people.csv: has 2 rows, expected one row"
+        );
+        assert_eq!(
+            csv(
+                "Full Name,age\n",
+                &super::Shape::Optional(Box::new(person()))
+            )
+            .unwrap(),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn csv_errors() {
+        let err = csv("Full Name,age\nAlice,30\nBob,forty\n", &people()).unwrap_err();
+        assert!(err.starts_with("people.csv:3: "), "{err}");
+        assert!(err.contains("\"forty\""), "{err}");
+        assert!(err.contains("integer for `age`"), "{err}");
+
+        let err = csv("Name,age\nAlice,30\n", &people()).unwrap_err();
+        assert_eq!(err, "people.csv:2: missing `Full Name` for `name`");
+
+        let err = csv("Full Name,age\nAlice,30\n", &super::Shape::String).unwrap_err();
+        assert!(err.contains("only be loaded into a record"), "{err}");
+    }
+
+    #[test]
+    fn json() {
+        let content = r#"[
+            {"Full Name": "Alice", "age": "30"},
+            {"Full Name": "Bob", "age": 41.0, "email": null, "extra": [1, 2]}
+        ]"#;
+        assert_eq!(
+            super::parse("people.json", content, super::Format::Json, &people()).unwrap(),
+            serde_json::json!([
+                {"name": "Alice", "age": 30, "email": null},
+                {"name": "Bob", "age": 41, "email": null},
+            ])
+        );
+
+        let content = "[\n  {\"Full Name\": \"Alice\", \"age\": 30},\n  {\"Full Name\": true, \"age\": 1.5}\n]";
+        let err = super::parse("people.json", content, super::Format::Json, &people()).unwrap_err();
+        assert!(err.starts_with("people.json: "), "{err}");
+        assert!(err.contains("integer for `age`"), "{err}");
+        assert!(err.contains("line 3"), "{err}");
+    }
+
+    #[test]
+    fn key() {
+        let shape = people().at("data.people");
+        assert_eq!(
+            shape,
+            super::Shape::At(
+                "data".to_string(),
+                Box::new(super::Shape::At("people".to_string(), Box::new(people())))
+            )
+        );
+
+        let content = r#"{"data": {"count": 1, "people": [{"Full Name": "Alice", "age": 30}]}}"#;
+        assert_eq!(
+            super::parse("people.json", content, super::Format::Json, &shape).unwrap(),
+            serde_json::json!([{"name": "Alice", "age": 30, "email": null}])
+        );
+
+        let err = super::parse(
+            "people.json",
+            r#"{"data": {}}"#,
+            super::Format::Json,
+            &shape,
+        )
+        .unwrap_err();
+        assert!(err.contains("missing key `people`"), "{err}");
+    }
+
+    #[test]
+    fn yaml_and_toml() {
+        let yaml = "- Full Name: Alice\n  age: 30\n  email: alice@example.com\n- Full Name: Bob\n  age: 41\n";
+        assert_eq!(
+            super::parse("people.yaml", yaml, super::Format::Yaml, &people()).unwrap(),
+            serde_json::json!([
+                {"name": "Alice", "age": 30, "email": "alice@example.com"},
+                {"name": "Bob", "age": 41, "email": null},
+            ])
+        );
+
+        let err = super::parse(
+            "people.yaml",
+            "- Full Name: Alice\n  age: thirty\n",
+            super::Format::Yaml,
+            &people(),
+        )
+        .unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+
+        let toml = "[[people]]\n\"Full Name\" = \"Alice\"\nage = 30\n\n[[people]]\n\"Full Name\" = \"Bob\"\nage = 41\n";
+        assert_eq!(
+            super::parse(
+                "people.toml",
+                toml,
+                super::Format::Toml,
+                &people().at("people")
+            )
+            .unwrap(),
+            serde_json::json!([
+                {"name": "Alice", "age": 30, "email": null},
+                {"name": "Bob", "age": 41, "email": null},
+            ])
+        );
+    }
+    #[test]
+    fn is_inside_package() {
+        assert!(super::is_inside_package("data/people.csv"));
+        assert!(super::is_inside_package("./people.json"));
+        assert!(!super::is_inside_package("../people.csv"));
+        assert!(!super::is_inside_package("data/../../people.csv"));
+        assert!(!super::is_inside_package("/etc/passwd"));
+    }
+}
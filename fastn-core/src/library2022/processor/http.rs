@@ -1,6 +1,12 @@
 use ftd::interpreter::FunctionExt;
 use ftd::interpreter::{PropertyValueExt, ValueExt};
 
+const TIMEOUT_HEADER: &str = "$timeout$";
+const RETRIES_HEADER: &str = "$retries$";
+const RETRY_ANY_METHOD_HEADER: &str = "$retry-any-method$";
+const CACHE_TTL_HEADER: &str = "$cache-ttl$";
+const DEFAULT_TIMEOUT: &str = "30s";
+
 #[tracing::instrument(name = "http_processor", skip_all)]
 pub async fn process(
     value: ftd_ast::VariableValue,
@@ -8,10 +14,6 @@ pub async fn process(
     doc: &ftd::interpreter::TDoc<'_>,
     req_config: &mut fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    // we can in future do a more fine-grained analysis if the response
-    // is cacheable or not, say depending on HTTP Vary header, etc.
-    req_config.response_is_cacheable = false;
-
     let (headers, line_number) = if let Ok(val) = value.get_record(doc.name) {
         (val.2.to_owned(), val.5.to_owned())
     } else {
@@ -23,13 +25,27 @@ pub async fn process(
         .unwrap_or_else(|| "GET".to_string())
         .to_lowercase();
 
-    if method.as_str().ne("get") && method.as_str().ne("post") {
+    if !matches!(method.as_str(), "get" | "post" | "put" | "patch" | "delete") {
         return ftd::interpreter::utils::e2(
-            format!("only GET and POST methods are allowed, found: {method}"),
+            format!("only GET, POST, PUT, PATCH and DELETE methods are allowed, found: {method}"),
             doc.name,
             line_number,
         );
     }
+    // the rest go as query parameters
+    let has_body = matches!(method.as_str(), "post" | "put" | "patch");
+
+    let options = http_options(&headers, doc, line_number, req_config).await?;
+    let cache_ttl =
+        match headers.get_optional_string_by_key(CACHE_TTL_HEADER, doc.name, line_number)? {
+            Some(v) => Some(duration_header(
+                CACHE_TTL_HEADER,
+                &v,
+                doc.name,
+                line_number,
+            )?),
+            None => None,
+        };
 
     let url = match headers.get_optional_string_by_key("url", doc.name, line_number)? {
         Some(v) if v.starts_with('$') => {
@@ -128,6 +144,10 @@ pub async fn process(
         if header.key.as_str() == ftd::PROCESSOR_MARKER
             || header.key.as_str() == "url"
             || header.key.as_str() == "method"
+            || header.key.as_str() == TIMEOUT_HEADER
+            || header.key.as_str() == RETRIES_HEADER
+            || header.key.as_str() == RETRY_ANY_METHOD_HEADER
+            || header.key.as_str() == CACHE_TTL_HEADER
         {
             tracing::info!("Skipping header: {}", header.key);
             continue;
@@ -154,7 +174,7 @@ pub async fn process(
                 .to_serde_value(doc)?
             {
                 tracing::info!("Resolved variable in header: {}: {:?}", header.key, value);
-                if method.as_str().eq("post") {
+                if has_body {
                     body.insert(header.key, value);
                 } else {
                     let value = match value {
@@ -175,7 +195,7 @@ pub async fn process(
         } else {
             tracing::info!("Using static value in header: {}: {}", header.key, value);
 
-            if method.as_str().eq("post") {
+            if has_body {
                 body.insert(
                     header.key,
                     serde_json::Value::String(fastn_core::utils::escape_string(value)),
@@ -192,7 +212,41 @@ pub async fn process(
         println!("calling `http` processor with url: {url}");
     }
 
-    let resp = if url.scheme() == "wasm+proxy" {
+    // only GET responses are cached, and only when asked to
+    let cache_key = match cache_ttl {
+        Some(_) if method == "get" => Some(fastn_core::library2022::processor::http_cache::key(
+            method.as_str(),
+            url.as_str(),
+            &conf,
+            req_config.request.cookies(),
+        )),
+        _ => None,
+    };
+    // with the upstream response cached for everyone, so can be the page using it
+    if cache_key.is_none() || !req_config.request.cookies().is_empty() {
+        req_config.response_is_cacheable = false;
+    }
+
+    let mock = if req_config.config.test_command_running && matches!(url.scheme(), "http" | "https")
+    {
+        fastn_core::library2022::processor::http_mock::find(&req_config.config, &method, &url)
+            .await
+            .map_err(|e| ftd::interpreter::Error::OtherError(e.to_string()))?
+    } else {
+        None
+    };
+    let cached = cache_key
+        .as_deref()
+        .and_then(fastn_core::library2022::processor::http_cache::get);
+    let from_upstream = mock.is_none() && cached.is_none();
+
+    let resp = if let Some(mock) = mock {
+        tracing::info!("Using http mock for: {url}");
+        Ok((mock.response(), vec![]))
+    } else if let Some(cached) = cached {
+        tracing::info!("Using cached response for: {url}");
+        Ok((Ok(cached), vec![]))
+    } else if url.scheme() == "wasm+proxy" {
         tracing::info!("Calling wasm+proxy with url: {url}");
         let mountpoint = mountpoint.ok_or(ftd::interpreter::Error::OtherError(
             "Mountpoint not found!".to_string(),
@@ -201,8 +255,16 @@ pub async fn process(
             .config
             .app_mounts()
             .map_err(|e| ftd::interpreter::Error::OtherError(e.to_string()))?;
+        let wasm_limits = req_config
+            .config
+            .package
+            .apps
+            .iter()
+            .find(|a| a.mount_point.trim_end_matches('/') == mountpoint.trim_end_matches('/'))
+            .map(|a| a.wasm_limits)
+            .unwrap_or_default();
 
-        if method == "post" {
+        if has_body {
             req_config.request.body = serde_json::to_vec(&body)
                 .map_err(|e| ftd::interpreter::Error::Serde { source: e })?
                 .into();
@@ -217,9 +279,11 @@ pub async fn process(
                 &req_config.request,
                 mountpoint,
                 app_mounts,
+                wasm_limits,
                 // FIXME: we don't know how to handle unsaved wasm files. Maybe there is no way
                 // that an unsaved .wasm file can exist and this is fine.
                 &None,
+                fastn_core::utils::secret_keys(&req_config.config.ds).await,
             )
             .await
         {
@@ -258,27 +322,35 @@ pub async fn process(
             }
             e => todo!("error: {e:?}"),
         }
-    } else if method.as_str().eq("post") {
-        tracing::info!("Calling POST request with url: {url}");
-        fastn_core::http::http_post_with_cookie(
-            req_config,
+    } else if method.as_str().eq("get") {
+        tracing::info!("Calling GET request with url: {url}");
+        fastn_core::http::http_get_with_cookie(
+            &req_config.config.ds,
+            &req_config.request,
             url.as_str(),
             &conf,
-            &serde_json::to_string(&body)
-                .map_err(|e| ftd::interpreter::Error::Serde { source: e })?,
+            false, // disable cache
+            &options,
         )
         .await
         .map_err(|e| ftd::interpreter::Error::DSHttpError {
             message: format!("{e:?}"),
         })
     } else {
-        tracing::info!("Calling GET request with url: {url}");
-        fastn_core::http::http_get_with_cookie(
-            &req_config.config.ds,
-            &req_config.request,
+        tracing::info!("Calling {method} request with url: {url}");
+        let body = if has_body {
+            serde_json::to_string(&body)
+                .map_err(|e| ftd::interpreter::Error::Serde { source: e })?
+        } else {
+            String::new()
+        };
+        fastn_core::http::http_with_body_and_cookie(
+            req_config,
+            method.as_str(),
             url.as_str(),
             &conf,
-            false, // disable cache
+            &body,
+            &options,
         )
         .await
         .map_err(|e| ftd::interpreter::Error::DSHttpError {
@@ -289,31 +361,114 @@ pub async fn process(
     let response = match resp {
         Ok((Ok(v), cookies)) => {
             req_config.processor_set_cookies.extend(cookies);
+            if from_upstream && let (Some(key), Some(ttl)) = (cache_key, cache_ttl) {
+                fastn_core::library2022::processor::http_cache::insert(key, v.clone(), ttl);
+            }
             v
         }
         Ok((Err(e), cookies)) => {
             req_config.processor_set_cookies.extend(cookies);
             return ftd::interpreter::utils::e2(
-                format!("HTTP::get failed: {e:?}"),
+                format!("HTTP::{method} failed: {e:?}"),
                 doc.name,
                 line_number,
             );
         }
         Err(e) => {
             return ftd::interpreter::utils::e2(
-                format!("HTTP::get failed: {e:?}"),
+                format!("HTTP::{method} failed: {e:?}"),
                 doc.name,
                 line_number,
             );
         }
     };
 
-    let response_json: serde_json::Value = serde_json::from_slice(&response)
-        .map_err(|e| ftd::interpreter::Error::Serde { source: e })?;
+    // eg `204 No Content` for a DELETE
+    let response_json: serde_json::Value = if response.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&response)
+            .map_err(|e| ftd::interpreter::Error::Serde { source: e })?
+    };
 
     doc.from_json(&response_json, &kind, &value)
 }
 
+/// `$timeout$` and `$retries$` headers, else the `FASTN_HTTP_TIMEOUT` and `FASTN_HTTP_RETRIES`
+/// environment variables, and the `$retry-any-method$` header. A timeout of `0` waits for ever.
+async fn http_options(
+    headers: &ftd_ast::HeaderValues,
+    doc: &ftd::interpreter::TDoc<'_>,
+    line_number: usize,
+    req_config: &fastn_core::RequestConfig,
+) -> ftd::interpreter::Result<fastn_core::http::HttpOptions> {
+    let ds = &req_config.config.ds;
+
+    let timeout = match headers.get_optional_string_by_key(TIMEOUT_HEADER, doc.name, line_number)? {
+        Some(v) => v,
+        None => ds
+            .env("FASTN_HTTP_TIMEOUT")
+            .await
+            .unwrap_or_else(|_| DEFAULT_TIMEOUT.to_string()),
+    };
+    let timeout = duration_header(TIMEOUT_HEADER, &timeout, doc.name, line_number)?;
+
+    let retries = match headers.get_optional_string_by_key(RETRIES_HEADER, doc.name, line_number)? {
+        Some(v) => Some(v),
+        None => ds.env("FASTN_HTTP_RETRIES").await.ok(),
+    };
+    let retries = match retries {
+        Some(v) => match v.trim().parse::<u32>() {
+            Ok(v) => v,
+            Err(_) => {
+                return ftd::interpreter::utils::e2(
+                    format!("`{RETRIES_HEADER}: {v}` is not a valid number of retries"),
+                    doc.name,
+                    line_number,
+                );
+            }
+        },
+        None => 0,
+    };
+
+    let retry_any_method =
+        match headers.get_optional_string_by_key(RETRY_ANY_METHOD_HEADER, doc.name, line_number)? {
+            Some(v) => match v.trim().parse::<bool>() {
+                Ok(v) => v,
+                Err(_) => {
+                    return ftd::interpreter::utils::e2(
+                        format!("`{RETRY_ANY_METHOD_HEADER}: {v}` must be `true` or `false`"),
+                        doc.name,
+                        line_number,
+                    );
+                }
+            },
+            None => false,
+        };
+
+    Ok(fastn_core::http::HttpOptions {
+        timeout: Some(timeout).filter(|t| !t.is_zero()),
+        retries,
+        retry_any_method,
+    })
+}
+
+fn duration_header(
+    key: &str,
+    value: &str,
+    doc_name: &str,
+    line_number: usize,
+) -> ftd::interpreter::Result<std::time::Duration> {
+    match fastn_core::library2022::processor::http_cache::parse_duration(value) {
+        Some(d) => Ok(d),
+        None => ftd::interpreter::utils::e2(
+            format!("`{key}: {value}` is not a valid duration, eg 500ms, 30s, 5m or 1h"),
+            doc_name,
+            line_number,
+        ),
+    }
+}
+
 /// Parse a function call string like "$function_name(arg1 = value1, arg2 = value2)" into name and named arguments
 fn parse_function_call(expr: &str) -> Option<(String, Vec<(String, String)>)> {
     if !expr.starts_with('$') {
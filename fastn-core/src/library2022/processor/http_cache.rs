@@ -0,0 +1,152 @@
+//! Responses of the `http` processor, kept in memory for `$cache-ttl$` so every request to a page
+//! does not hit the upstream server.
+
+#[derive(Debug, Clone)]
+struct Entry {
+    expires_at: std::time::Instant,
+    body: bytes::Bytes,
+}
+
+static CACHE: once_cell::sync::Lazy<antidote::RwLock<std::collections::HashMap<String, Entry>>> =
+    once_cell::sync::Lazy::new(|| antidote::RwLock::new(Default::default()));
+
+/// Requests with the same method, url, headers and cookies get the same cached response. Cookies
+/// are part of the key as they are forwarded upstream, and the response may depend on them.
+pub(crate) fn key(
+    method: &str,
+    url: &str,
+    headers: &std::collections::HashMap<String, String>,
+    cookies: &std::collections::HashMap<String, String>,
+) -> String {
+    let headers = headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), v))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    let cookies = cookies.iter().collect::<std::collections::BTreeMap<_, _>>();
+
+    fastn_core::utils::generate_hash(format!(
+        "{} {url}\n{headers:?}\n{cookies:?}",
+        method.to_uppercase()
+    ))
+}
+
+pub(crate) fn get(key: &str) -> Option<bytes::Bytes> {
+    CACHE
+        .read()
+        .get(key)
+        .filter(|e| e.expires_at > std::time::Instant::now())
+        .map(|e| e.body.clone())
+}
+
+pub(crate) fn insert(key: String, body: bytes::Bytes, ttl: std::time::Duration) {
+    let now = std::time::Instant::now();
+    let mut cache = CACHE.write();
+    cache.retain(|_, e| e.expires_at > now);
+    cache.insert(
+        key,
+        Entry {
+            expires_at: now + ttl,
+            body,
+        },
+    );
+}
+
+/// `$timeout$` and `$cache-ttl$` values: `500ms`, `30s`, `5m`, `1h`, `1d`, or plain seconds.
+pub(crate) fn parse_duration(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+
+    let seconds = match unit.trim() {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 60.0 * 60.0,
+        "d" => 24.0 * 60.0 * 60.0,
+        _ => return None,
+    };
+
+    std::time::Duration::try_from_secs_f64(number.parse::<f64>().ok()? * seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_duration() {
+        let d = std::time::Duration::from_millis;
+        assert_eq!(super::parse_duration("500ms"), Some(d(500)));
+        assert_eq!(super::parse_duration("30"), Some(d(30_000)));
+        assert_eq!(super::parse_duration("2.5s"), Some(d(2_500)));
+        assert_eq!(super::parse_duration(" 5m "), Some(d(300_000)));
+        assert_eq!(super::parse_duration("1h"), Some(d(3_600_000)));
+        assert_eq!(super::parse_duration("1d"), Some(d(86_400_000)));
+        assert_eq!(super::parse_duration("5 weeks"), None);
+        assert_eq!(super::parse_duration("s"), None);
+        assert_eq!(super::parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn cache() {
+        let headers = std::collections::HashMap::from([
+            ("Authorization".to_string(), "token".to_string()),
+            ("accept".to_string(), "application/json".to_string()),
+        ]);
+        let key = super::key(
+            "get",
+            "https://example.com/a",
+            &headers,
+            &Default::default(),
+        );
+
+        assert_eq!(
+            key,
+            super::key(
+                "GET",
+                "https://example.com/a",
+                &std::collections::HashMap::from([
+                    ("accept".to_string(), "application/json".to_string()),
+                    ("authorization".to_string(), "token".to_string()),
+                ]),
+                &Default::default()
+            )
+        );
+        assert_ne!(
+            key,
+            super::key(
+                "GET",
+                "https://example.com/a",
+                &headers,
+                &std::collections::HashMap::from([("sid".to_string(), "1".to_string())])
+            )
+        );
+        assert_ne!(
+            key,
+            super::key(
+                "GET",
+                "https://example.com/b",
+                &headers,
+                &Default::default()
+            )
+        );
+
+        assert_eq!(super::get(key.as_str()), None);
+        super::insert(
+            key.clone(),
+            bytes::Bytes::from_static(b"{}"),
+            std::time::Duration::from_secs(60),
+        );
+        assert_eq!(
+            super::get(key.as_str()),
+            Some(bytes::Bytes::from_static(b"{}"))
+        );
+
+        super::insert(
+            key.clone(),
+            bytes::Bytes::from_static(b"{}"),
+            std::time::Duration::ZERO,
+        );
+        assert_eq!(super::get(key.as_str()), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Canned responses for the `http` processor, so `fastn test` does not need the real upstream.
+//!
+//! While `fastn test` runs, every `.json` file in `_tests/fixtures/http/` is a mock:
+//!
+//! ```json
+//! {
+//!     "method": "GET",
+//!     "url": "https://api.example.com/users/1",
+//!     "status": 200,
+//!     "body": {"name": "Alice"}
+//! }
+//! ```
+//!
+//! `method` defaults to `GET` and `status` to `200`. A mock `url` without a query string matches
+//! the url with any query string. Requests no mock matches go to the real upstream.
+
+pub(crate) const HTTP_FIXTURE_FOLDER: &str = "http";
+
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Mock {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub body: serde_json::Value,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl Mock {
+    pub(crate) fn matches(&self, method: &str, url: &url::Url) -> bool {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+
+        let Ok(mock_url) = url::Url::parse(self.url.as_str()) else {
+            return false;
+        };
+
+        if mock_url.query().is_some() {
+            return &mock_url == url;
+        }
+
+        let mut url = url.clone();
+        url.set_query(None);
+        url.set_fragment(None);
+        mock_url == url
+    }
+
+    /// The response as `http_get_with_cookie` and friends would have returned it.
+    pub(crate) fn response(&self) -> fastn_core::Result<bytes::Bytes> {
+        if !(200..300).contains(&self.status) {
+            return Err(fastn_core::Error::APIResponseError(format!(
+                "url: {}, response_status: {}, response: {} (mocked)",
+                self.url, self.status, self.body
+            )));
+        }
+
+        Ok(serde_json::to_vec(&self.body)?.into())
+    }
+}
+
+/// The first mock, in file name order, matching `method` and `url`.
+pub(crate) async fn find(
+    config: &fastn_core::Config,
+    method: &str,
+    url: &url::Url,
+) -> fastn_core::Result<Option<Mock>> {
+    let folder = config
+        .get_test_directory_path()
+        .join(fastn_core::commands::test::FIXTURE_FOLDER)
+        .join(HTTP_FIXTURE_FOLDER);
+
+    let mut files = config
+        .ds
+        .get_all_file_path(&folder, &[], &None)
+        .await?
+        .into_iter()
+        .filter(|p| p.extension().as_deref() == Some("json"))
+        .collect::<Vec<_>>();
+    files.sort_by_key(|p| p.to_string());
+
+    for file in files {
+        let mock: Mock = serde_json::from_slice(&config.ds.read_content(&file, &None).await?)
+            .map_err(|e| fastn_core::Error::PackageError {
+                message: format!("invalid http mock {file}: {e}"),
+            })?;
+        if mock.matches(method, url) {
+            return Ok(Some(mock));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    fn mock(json: serde_json::Value) -> super::Mock {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn url(url: &str) -> url::Url {
+        url::Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn matches() {
+        let m = mock(serde_json::json!({"url": "https://api.example.com/users/1"}));
+        assert_eq!(m.method, "GET");
+        assert_eq!(m.status, 200);
+        assert!(m.matches("get", &url("https://api.example.com/users/1")));
+        assert!(m.matches("GET", &url("https://api.example.com/users/1?fields=name")));
+        assert!(!m.matches("DELETE", &url("https://api.example.com/users/1")));
+        assert!(!m.matches("GET", &url("https://api.example.com/users/2")));
+
+        let m = mock(serde_json::json!({
+            "method": "PUT",
+            "url": "https://api.example.com/users?id=1",
+        }));
+        assert!(m.matches("put", &url("https://api.example.com/users?id=1")));
+        assert!(!m.matches("put", &url("https://api.example.com/users?id=2")));
+        assert!(!m.matches("put", &url("https://api.example.com/users")));
+    }
+
+    #[test]
+    fn response() {
+        let m = mock(serde_json::json!({
+            "url": "https://api.example.com/users/1",
+            "body": {"name": "Alice"},
+        }));
+        assert_eq!(m.response().unwrap().as_ref(), br#"{"name":"Alice"}"#);
+
+        let m = mock(serde_json::json!({
+            "url": "https://api.example.com/users/1",
+            "status": 404,
+        }));
+        assert!(m.response().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod apps;
+pub(crate) mod data_file;
 pub(crate) mod document;
 pub(crate) mod fetch_file;
 pub(crate) mod figma_tokens;
@@ -6,16 +7,20 @@ pub(crate) mod figma_typography_tokens;
 pub(crate) mod get_data;
 // pub(crate) mod google_sheets;
 pub(crate) mod http;
+pub(crate) mod http_cache;
+pub(crate) mod http_mock;
 pub(crate) mod lang;
 pub(crate) mod lang_details;
+pub(crate) mod placeholder;
 // pub(crate) mod package_query;
-// pub(crate) mod pg;
 pub(crate) mod query;
 pub(crate) mod request_data;
+pub(crate) mod session;
 pub(crate) mod sitemap;
 pub(crate) mod sql;
 pub(crate) mod sqlite;
 pub(crate) mod toc;
+pub(crate) mod translate;
 pub(crate) mod user_details;
 pub(crate) mod user_group;
 
@@ -0,0 +1,53 @@
+/// The value `fastn check` gives the processors it does not run, the ones which talk to the
+/// network or a database: an empty value of the kind of the variable.
+pub fn process(
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    empty_value(&kind, doc, value.line_number())
+}
+
+fn empty_value(
+    kind: &fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc,
+    line_number: usize,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    Ok(match kind {
+        fastn_resolved::Kind::String => fastn_resolved::Value::new_string(""),
+        fastn_resolved::Kind::Integer => fastn_resolved::Value::Integer { value: 0 },
+        fastn_resolved::Kind::Decimal => fastn_resolved::Value::Decimal { value: 0.0 },
+        fastn_resolved::Kind::Boolean => fastn_resolved::Value::Boolean { value: false },
+        fastn_resolved::Kind::List { kind } => fastn_resolved::Value::List {
+            data: vec![],
+            kind: fastn_resolved::KindData::new(kind.as_ref().clone()),
+        },
+        fastn_resolved::Kind::Optional { kind } => {
+            fastn_resolved::Value::new_none(fastn_resolved::KindData::new(kind.as_ref().clone()))
+        }
+        fastn_resolved::Kind::Constant { kind } => empty_value(kind, doc, line_number)?,
+        fastn_resolved::Kind::Record { name } => {
+            let record = doc.get_record(name, line_number)?;
+            let mut fields: fastn_resolved::Map<fastn_resolved::PropertyValue> = Default::default();
+            for field in record.fields {
+                let value = match field.value {
+                    Some(value) => value,
+                    None => empty_value(&field.kind.kind, doc, line_number)?
+                        .into_property_value(false, line_number),
+                };
+                fields.insert(field.name, value);
+            }
+            fastn_resolved::Value::Record {
+                name: record.name,
+                fields,
+            }
+        }
+        t => {
+            return ftd::interpreter::utils::e2(
+                format!("`fastn check` can not make up a value of kind `{t:?}`"),
+                doc.name,
+                line_number,
+            );
+        }
+    })
+}
@@ -0,0 +1,72 @@
+/// The data stored in the server side session of the request, the same data wasm apps get and
+/// set using `hostn_session_get` / `hostn_session_set`. With a `key` header, only that value.
+///
+/// ```ftd
+/// -- string cart:
+/// $processor$: pr.session
+/// key: cart
+/// ```
+pub async fn process(
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    let headers = match value.get_record(doc.name) {
+        Ok(val) => val.2.to_owned(),
+        Err(_e) => ftd_ast::HeaderValues::new(vec![]),
+    };
+    let key = headers.get_optional_string_by_key("key", doc.name, value.line_number())?;
+
+    // reading the session does not create one, pages without a session get empty data
+    let data = match req_config.server_session(false).await {
+        Ok(session) => session.map(|s| s.data).unwrap_or_default(),
+        Err(e) => {
+            return ftd::interpreter::utils::e2(
+                format!("failed to load session: {e}"),
+                doc.name,
+                value.line_number(),
+            );
+        }
+    };
+
+    match key {
+        Some(key) => doc.from_json(
+            data.get(key.as_str()).unwrap_or(&serde_json::Value::Null),
+            &kind,
+            &value,
+        ),
+        None => doc.from_json(&serde_json::Value::Object(data), &kind, &value),
+    }
+}
+
+/// The CSRF token of the session, creating the session if needed. Forms that `POST` to ftd
+/// pages send it back in a `csrf-token` field (or the `x-csrf-token` header).
+pub async fn csrf_token(
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    if !kind.is_string() {
+        return ftd::interpreter::utils::e2(
+            format!("Expected kind is `string`, found: `{kind:?}`"),
+            doc.name,
+            value.line_number(),
+        );
+    }
+
+    match req_config.server_session(true).await {
+        Ok(Some(session)) => Ok(fastn_resolved::Value::String {
+            text: session.csrf_token,
+        }),
+        Ok(None) => {
+            ftd::interpreter::utils::e2("failed to create a session", doc.name, value.line_number())
+        }
+        Err(e) => ftd::interpreter::utils::e2(
+            format!("failed to load session: {e}"),
+            doc.name,
+            value.line_number(),
+        ),
+    }
+}
@@ -1,10 +1,21 @@
-pub fn process(
+pub async fn process(
     value: ftd_ast::VariableValue,
     kind: fastn_resolved::Kind,
-    doc: &ftd::interpreter::TDoc,
-    req_config: &fastn_core::RequestConfig,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    if let Some(ref sitemap) = req_config.config.package.sitemap {
+    // the items the user can not read are not shown to them
+    let sitemap = match req_config.readable_sitemap().await {
+        Ok(sitemap) => sitemap,
+        Err(e) => {
+            return ftd::interpreter::utils::e2(
+                format!("failed to read the sitemap: {e:?}"),
+                doc.name,
+                value.line_number(),
+            );
+        }
+    };
+    if let Some(ref sitemap) = sitemap {
         let doc_id = req_config
             .current_document
             .clone()
@@ -28,13 +39,24 @@ pub fn process(
     )
 }
 
-pub fn full_sitemap_process(
+pub async fn full_sitemap_process(
     value: ftd_ast::VariableValue,
     kind: fastn_resolved::Kind,
-    doc: &ftd::interpreter::TDoc,
-    req_config: &fastn_core::RequestConfig,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    if let Some(ref sitemap) = req_config.config.package.sitemap {
+    // the items the user can not read are not shown to them
+    let sitemap = match req_config.readable_sitemap().await {
+        Ok(sitemap) => sitemap,
+        Err(e) => {
+            return ftd::interpreter::utils::e2(
+                format!("failed to read the sitemap: {e:?}"),
+                doc.name,
+                value.line_number(),
+            );
+        }
+    };
+    if let Some(ref sitemap) = sitemap {
         let doc_id = req_config
             .current_document
             .clone()
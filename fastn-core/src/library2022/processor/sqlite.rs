@@ -35,7 +35,7 @@ pub(crate) fn result_to_value(
     doc: &ftd::interpreter::TDoc<'_>,
     value: &ftd_ast::VariableValue,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    if kind.is_list() {
+    if kind.is_list() || kind.is_map() {
         doc.rows_to_value(result.as_slice(), &kind, value)
     } else {
         match result.len() {
@@ -141,25 +141,27 @@ fn resolve_param(
         .or_else(|_| resolve_variable_from_doc(param_name, doc, line_number))
 }
 
+/// Replaces `$name` parameters in `query` with `sub` placeholders (`?1` for SQLite, `$1` for
+/// Postgres), and resolves their values from the headers or the document.
 pub fn extract_named_parameters(
     query: &str,
+    sub: char,
     doc: &ftd::interpreter::TDoc,
     headers: ftd_ast::HeaderValues,
     line_number: usize,
 ) -> ftd::interpreter::Result<(String, Vec<ft_sys_shared::SqliteRawValue>)> {
     let mut params: Vec<ft_sys_shared::SqliteRawValue> = Vec::new();
 
-    let (query, args) =
-        match fastn_utils::sql::extract_arguments(query, fastn_utils::sql::SQLITE_SUB) {
-            Ok(v) => v,
-            Err(e) => {
-                return ftd::interpreter::utils::e2(
-                    format!("Error parsing query: {e:?}"),
-                    doc.name,
-                    line_number,
-                );
-            }
-        };
+    let (query, args) = match fastn_utils::sql::extract_arguments(query, sub) {
+        Ok(v) => v,
+        Err(e) => {
+            return ftd::interpreter::utils::e2(
+                format!("Error parsing query: {e:?}"),
+                doc.name,
+                line_number,
+            );
+        }
+    };
 
     for (param_name, _) in args {
         params.push(resolve_param(&param_name, doc, &headers, line_number)?);
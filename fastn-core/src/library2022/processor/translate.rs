@@ -0,0 +1,495 @@
+//! `translate` processor: looks up a message of the package's [Fluent](https://projectfluent.org)
+//! files, in the language of the request.
+//!
+//! ```ftd
+//! -- string inbox-title:
+//! $processor$: translate
+//! key: inbox-title
+//! count: $unread-count
+//! ```
+//!
+//! Messages of a language live in the `.ftl` files of `i18n/<language code>/`, say
+//! `i18n/en/main.ftl`:
+//!
+//! ```ftl
+//! inbox-title = { $count ->
+//!     [one] One unread message
+//!    *[other] { $count } unread messages
+//! }
+//! ```
+//!
+//! Headers:
+//!
+//! - `key`: id of the message, `<id>.<attribute>` for an attribute of it (required)
+//! - `package`: name of the dependency package the messages belong to, defaults to this package
+//!
+//! Any other header is an argument of the message. Arguments of kind `integer` or `decimal`, a
+//! variable of that kind or a header like `integer count: 3`, are numbers and select plural
+//! variants by the plural rules of the language, all others are passed as text, so `007` stays
+//! `007`. Messages missing in the language of the request
+//! are looked up in the primary language of the package (its `default-language`, `en` if it has
+//! none).
+
+const KEY_HEADER: &str = "key";
+const PACKAGE_HEADER: &str = "package";
+/// Fluent files of a language are in `i18n/<language code>/`
+pub(crate) const MESSAGES_DIR: &str = "i18n";
+const DEFAULT_LANGUAGE: &str = "en";
+
+type Bundle = fluent::concurrent::FluentBundle<fluent::FluentResource>;
+
+/// The bundles a request has used, by package and language, so a document with many messages
+/// reads and parses the `.ftl` files once.
+#[derive(Default, Clone)]
+pub(crate) struct Bundles(std::collections::HashMap<(String, String), std::sync::Arc<Bundle>>);
+
+impl std::fmt::Debug for Bundles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// A Fluent file: its path, for errors, and its content.
+#[derive(Debug, Clone)]
+pub(crate) struct Source {
+    pub(crate) path: String,
+    pub(crate) content: String,
+}
+
+/// The primary language of `package`, messages missing in other languages come from it.
+pub(crate) fn primary_language(package: &fastn_core::Package) -> String {
+    package
+        .lang
+        .as_ref()
+        .map(|l| l.default_lang.to_string())
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+/// The Fluent files of `language` in the package at `root`, sorted by path.
+pub(crate) async fn read_sources(
+    ds: &fastn_ds::DocumentStore,
+    root: &fastn_ds::Path,
+    language: &str,
+    session_id: &Option<String>,
+) -> fastn_core::Result<Vec<Source>> {
+    let dir = root.join(MESSAGES_DIR).join(language);
+    // a language without messages is not an error, its messages fall back to the primary one
+    let mut paths = match ds.read_dir(&dir, session_id).await {
+        Ok(paths) => paths,
+        Err(_) => return Ok(vec![]),
+    };
+    paths.retain(|p| p.extension().as_deref() == Some("ftl"));
+    paths.sort_by_key(|p| p.to_string());
+
+    let mut sources = vec![];
+    for path in paths {
+        let content = ds.read_to_string(&path, session_id).await?;
+        sources.push(Source {
+            path: path.to_string(),
+            content,
+        });
+    }
+
+    Ok(sources)
+}
+
+/// The languages the package at `root` has Fluent files for.
+pub(crate) async fn languages(ds: &fastn_ds::DocumentStore, root: &fastn_ds::Path) -> Vec<String> {
+    let mut languages = match ds.read_dir(&root.join(MESSAGES_DIR), &None).await {
+        // `i18n/hi.ftd` is a translation module, not a language of messages
+        Ok(paths) => paths
+            .iter()
+            .filter(|p| p.extension().is_none())
+            .filter_map(|p| p.file_name())
+            .collect(),
+        Err(_) => vec![],
+    };
+    languages.sort();
+    languages
+}
+
+fn line_of(content: &str, position: usize) -> usize {
+    content[..position.min(content.len())].matches('\n').count() + 1
+}
+
+fn bundle(language: &str, sources: &[Source]) -> Result<Bundle, String> {
+    let locale = language
+        .parse()
+        .map_err(|_| format!("`{language}` is not a language code"))?;
+    let mut bundle = Bundle::new_concurrent(vec![locale]);
+    // the unicode isolation marks fluent puts around arguments would end up in the html
+    bundle.set_use_isolating(false);
+
+    for source in sources {
+        let resource = match fluent::FluentResource::try_new(source.content.clone()) {
+            Ok(resource) => resource,
+            Err((_, errors)) => {
+                return Err(errors
+                    .iter()
+                    .map(|e| {
+                        format!(
+                            "{}:{}: {e}",
+                            source.path,
+                            line_of(source.content.as_str(), e.pos.start)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"));
+            }
+        };
+        bundle.add_resource(resource).map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| format!("{}: {e}", source.path))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+    }
+
+    Ok(bundle)
+}
+
+/// The message `key` (`<id>` or `<id>.<attribute>`) of the first bundle that has it.
+fn format_message(
+    bundles: &[&Bundle],
+    key: &str,
+    args: &fluent::FluentArgs,
+) -> Result<Option<String>, String> {
+    let (id, attribute) = match key.split_once('.') {
+        Some((id, attribute)) => (id, Some(attribute)),
+        None => (key, None),
+    };
+
+    for bundle in bundles {
+        let Some(message) = bundle.get_message(id) else {
+            continue;
+        };
+        let pattern = match attribute {
+            Some(attribute) => message.get_attribute(attribute).map(|a| a.value()),
+            None => message.value(),
+        };
+        let Some(pattern) = pattern else {
+            continue;
+        };
+
+        let mut errors = vec![];
+        let value = bundle.format_pattern(pattern, Some(args), &mut errors);
+        if !errors.is_empty() {
+            return Err(errors
+                .iter()
+                .map(|e| format!("{key}: {e}"))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        return Ok(Some(value.to_string()));
+    }
+
+    Ok(None)
+}
+
+/// The ids of the messages in `sources`.
+pub(crate) fn message_ids(sources: &[Source]) -> std::collections::BTreeSet<String> {
+    let mut ids = std::collections::BTreeSet::new();
+    for source in sources {
+        // files that do not parse are reported when a message of them is looked up
+        let resource = match fluent::FluentResource::try_new(source.content.clone()) {
+            Ok(resource) => resource,
+            Err((resource, _)) => resource,
+        };
+        for entry in resource.entries() {
+            if let fluent_syntax::ast::Entry::Message(message) = entry {
+                ids.insert(message.id.name.to_string());
+            }
+        }
+    }
+    ids
+}
+
+/// `value` as an argument of a message, a number only if it is an ftd `integer` or `decimal`.
+fn argument(value: &serde_json::Value, number: bool) -> Option<fluent::FluentValue<'static>> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Number(n) if number => n.as_f64().map(fluent::FluentValue::from),
+        serde_json::Value::String(s) if number => Some(match s.trim().parse::<f64>() {
+            Ok(n) => fluent::FluentValue::from(n),
+            Err(_) => fluent::FluentValue::from(s.to_string()),
+        }),
+        serde_json::Value::String(s) => Some(fluent::FluentValue::from(s.to_string())),
+        v => Some(fluent::FluentValue::from(v.to_string())),
+    }
+}
+
+/// The bundle of `language` in `package` (whose root is `root`), from the bundles of the request
+/// if it has used it before.
+async fn cached_bundle(
+    req_config: &mut fastn_core::RequestConfig,
+    package: &str,
+    root: &fastn_ds::Path,
+    language: &str,
+    session_id: &Option<String>,
+) -> Result<std::sync::Arc<Bundle>, String> {
+    let key = (package.to_string(), language.to_string());
+    if let Some(bundle) = req_config.translation_bundles.0.get(&key) {
+        return Ok(bundle.clone());
+    }
+
+    let sources = read_sources(&req_config.config.ds, root, language, session_id)
+        .await
+        .map_err(|e| format!("can not read the messages of `{language}`: {e}"))?;
+    // so `fastn build` rebuilds this document when a message changes
+    for source in sources.iter() {
+        req_config.data_files.insert(
+            source.path.to_string(),
+            fastn_core::utils::generate_hash(source.content.as_str()),
+        );
+    }
+
+    let bundle = std::sync::Arc::new(bundle(language, &sources)?);
+    req_config.translation_bundles.0.insert(key, bundle.clone());
+    Ok(bundle)
+}
+
+pub async fn process(
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
+    preview_session_id: &Option<String>,
+) -> ftd::interpreter::Result<fastn_resolved::Value> {
+    let (headers, line_number) = if let Ok(val) = value.get_record(doc.name) {
+        (val.2.to_owned(), val.5.to_owned())
+    } else {
+        (ftd_ast::HeaderValues::new(vec![]), value.line_number())
+    };
+
+    let key = match headers.get_optional_string_by_key(KEY_HEADER, doc.name, line_number)? {
+        Some(key) => key,
+        None => {
+            return ftd::interpreter::utils::e2(
+                format!(
+                    "'key' key is required when using `{}: translate`",
+                    ftd::PROCESSOR_MARKER
+                ),
+                doc.name,
+                line_number,
+            );
+        }
+    };
+
+    let config = &req_config.config;
+    let package = match headers.get_optional_string_by_key(PACKAGE_HEADER, doc.name, line_number)? {
+        None => config.package.clone(),
+        Some(p) if p == config.package.name => config.package.clone(),
+        Some(p) => match config.all_packages.get(p.as_str()) {
+            Some(package) => package.get().clone(),
+            None => {
+                return ftd::interpreter::utils::e2(
+                    format!("`{p}` is not a dependency of {}", config.package.name),
+                    doc.name,
+                    line_number,
+                );
+            }
+        },
+    };
+    let root = config.get_root_for_package(&package);
+
+    let primary = primary_language(&package);
+    let current = req_config
+        .current_language()
+        .unwrap_or_else(|| primary.to_string());
+    let mut languages = vec![current];
+    if !languages.contains(&primary) {
+        languages.push(primary);
+    }
+
+    let mut bundles = vec![];
+    for language in languages {
+        bundles.push(
+            cached_bundle(
+                req_config,
+                package.name.as_str(),
+                &root,
+                language.as_str(),
+                preview_session_id,
+            )
+            .await
+            .map_err(|message| ftd::interpreter::Error::ParseError {
+                message,
+                doc_id: doc.name.to_string(),
+                line_number,
+            })?,
+        );
+    }
+
+    let mut args = fluent::FluentArgs::new();
+    for header in headers.0.iter() {
+        if matches!(header.key.as_str(), KEY_HEADER | PACKAGE_HEADER)
+            || header.key == ftd::PROCESSOR_MARKER
+        {
+            continue;
+        }
+        let value = header.value.string(doc.name)?;
+        // `count: $unread-count` passes the value of the variable
+        let (value, number) = if value.starts_with('$') {
+            let value = doc.get_value(header.line_number, value)?;
+            let kind = value.kind().inner();
+            (
+                value
+                    .to_serde_value(doc)?
+                    .unwrap_or(serde_json::Value::Null),
+                kind.is_integer() || kind.is_decimal(),
+            )
+        } else {
+            (
+                serde_json::Value::String(value.to_string()),
+                header
+                    .kind
+                    .as_deref()
+                    .and_then(|kind| kind.split_whitespace().last())
+                    .is_some_and(|kind| matches!(kind, "integer" | "decimal")),
+            )
+        };
+        if let Some(value) = argument(&value, number) {
+            args.set(header.key.to_string(), value);
+        }
+    }
+
+    let message = format_message(
+        &bundles.iter().map(|b| b.as_ref()).collect::<Vec<_>>(),
+        key.as_str(),
+        &args,
+    )
+    .map_err(|message| ftd::interpreter::Error::ParseError {
+        message,
+        doc_id: doc.name.to_string(),
+        line_number,
+    })?;
+
+    match message {
+        Some(message) => doc.from_json(&message, &kind, &value),
+        None => ftd::interpreter::utils::e2(
+            format!(
+                "message `{key}` not found in {}/{MESSAGES_DIR}/",
+                package.name
+            ),
+            doc.name,
+            line_number,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn sources(content: &str) -> Vec<super::Source> {
+        vec![super::Source {
+            path: "i18n/en/main.ftl".to_string(),
+            content: content.to_string(),
+        }]
+    }
+
+    fn format(
+        bundles: &[&super::Bundle],
+        key: &str,
+        args: &[(&str, f64)],
+    ) -> Result<Option<String>, String> {
+        let mut fluent_args = fluent::FluentArgs::new();
+        for (k, v) in args {
+            fluent_args.set(k.to_string(), fluent::FluentValue::from(*v));
+        }
+        super::format_message(bundles, key, &fluent_args)
+    }
+
+    const EN: &str = indoc::indoc! {"
+        hello = Hello!
+        inbox = { $count ->
+            [one] One unread message
+           *[other] { $count } unread messages
+        }
+        login = Log in
+            .title = Log in to your account
+        only-english = Only in English
+    "};
+
+    #[test]
+    fn plurals_and_attributes() {
+        let en = super::bundle("en", &sources(EN)).unwrap();
+
+        assert_eq!(format(&[&en], "hello", &[]).unwrap().unwrap(), "Hello!");
+        assert_eq!(
+            format(&[&en], "inbox", &[("count", 1.0)]).unwrap().unwrap(),
+            "One unread message"
+        );
+        assert_eq!(
+            format(&[&en], "inbox", &[("count", 5.0)]).unwrap().unwrap(),
+            "5 unread messages"
+        );
+        assert_eq!(
+            format(&[&en], "login.title", &[]).unwrap().unwrap(),
+            "Log in to your account"
+        );
+        assert_eq!(format(&[&en], "missing", &[]).unwrap(), None);
+        assert_eq!(format(&[&en], "login.missing", &[]).unwrap(), None);
+        // a missing argument is an error, not a half formatted message
+        assert!(format(&[&en], "inbox", &[]).is_err());
+    }
+
+    #[test]
+    fn fallback() {
+        let en = super::bundle("en", &sources(EN)).unwrap();
+        let hi = super::bundle(
+            "hi",
+            &sources(indoc::indoc! {"
+                hello = नमस्ते!
+            "}),
+        )
+        .unwrap();
+
+        assert_eq!(format(&[&hi, &en], "hello", &[]).unwrap().unwrap(), "नमस्ते!");
+        assert_eq!(
+            format(&[&hi, &en], "only-english", &[]).unwrap().unwrap(),
+            "Only in English"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let e = super::bundle("en", &sources("hello = Hello\nbroken = {\n")).unwrap_err();
+        assert!(e.starts_with("i18n/en/main.ftl:2: "), "{e}");
+
+        let e = super::bundle("en", &sources("hello = Hello\nhello = Again\n")).unwrap_err();
+        assert!(e.starts_with("i18n/en/main.ftl: "), "{e}");
+
+        assert!(super::bundle("not a language", &sources(EN)).is_err());
+    }
+
+    #[test]
+    fn message_ids() {
+        assert_eq!(
+            super::message_ids(&sources(
+                "-brand = fastn\nhello = Hello { -brand }\nbye = Bye\n"
+            )),
+            std::collections::BTreeSet::from(["bye".to_string(), "hello".to_string()])
+        );
+    }
+
+    #[test]
+    fn argument() {
+        assert_eq!(
+            super::argument(&serde_json::json!("3"), true),
+            Some(fluent::FluentValue::from(3.0))
+        );
+        assert_eq!(
+            super::argument(&serde_json::json!(2), true),
+            Some(fluent::FluentValue::from(2.0))
+        );
+        assert_eq!(
+            super::argument(&serde_json::json!("007"), false),
+            Some(fluent::FluentValue::from("007"))
+        );
+        assert_eq!(
+            super::argument(&serde_json::json!("Alice"), false),
+            Some(fluent::FluentValue::from("Alice"))
+        );
+        assert_eq!(super::argument(&serde_json::Value::Null, true), None);
+    }
+}
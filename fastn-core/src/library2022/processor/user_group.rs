@@ -1,55 +1,86 @@
+/// processor: user-groups
+/// All the `fastn.user-group`s of the package, as a list of `fastn.user-group-compat`
 pub fn process(
-    _value: ftd_ast::VariableValue,
-    _kind: fastn_resolved::Kind,
-    _doc: &ftd::interpreter::TDoc,
-    _req_config: &fastn_core::RequestConfig,
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc,
+    req_config: &fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    Err(ftd::interpreter::Error::OtherError(
-        "user-groups is not implemented in this version. Switch to an older  \
-            version."
-            .into(),
-    ))
+    let groups = req_config
+        .config
+        .package
+        .user_groups
+        .iter()
+        .map(fastn_core::package::user_group::UserGroup::to_compat)
+        .collect::<Vec<_>>();
+
+    doc.from_json(&groups, &kind, &value)
 }
 
 /// processor: user-group-by-id
 pub fn process_by_id(
-    _value: ftd_ast::VariableValue,
-    _kind: fastn_resolved::Kind,
-    _doc: &ftd::interpreter::TDoc,
-    _req_config: &fastn_core::RequestConfig,
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc,
+    req_config: &fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    Err(ftd::interpreter::Error::OtherError(
-        "user-group-by-id is not implemented in this version. Switch to an \
-            older version."
-            .into(),
-    ))
+    let line_number = value.line_number();
+    let headers = match value.get_record(doc.name) {
+        Ok(val) => val.2.to_owned(),
+        Err(_e) => ftd_ast::HeaderValues::new(vec![]),
+    };
+
+    let Some(id) = headers.get_optional_string_by_key("id", doc.name, line_number)? else {
+        return ftd::interpreter::utils::e2(
+            "user-group-by-id needs the `id` of the group",
+            doc.name,
+            line_number,
+        );
+    };
+
+    match fastn_core::package::user_group::find(&req_config.config.package.user_groups, &id) {
+        Some(group) => doc.from_json(&group.to_compat(), &kind, &value),
+        None => ftd::interpreter::utils::e2(
+            format!("user-group `{id}` not found"),
+            doc.name,
+            line_number,
+        ),
+    }
 }
 
 /// processor: get-identities
-/// This is used to get all the identities of the current document
+/// This is used to get all the identities of the current user, as a list of
+/// `fastn.key-value-data`, eg `email: alice@example.com`
 pub async fn get_identities(
-    _value: ftd_ast::VariableValue,
-    _kind: fastn_resolved::Kind,
-    _doc: &ftd::interpreter::TDoc<'_>,
-    _req_config: &fastn_core::RequestConfig,
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    Err(ftd::interpreter::Error::OtherError(
-        "get-identities is not implemented in this version. Switch to an \
-            older version."
-            .into(),
-    ))
+    match req_config.user_identities().await {
+        Ok(identities) => doc.from_json(&identities, &kind, &value),
+        Err(e) => ftd::interpreter::utils::e2(
+            format!("failed to get user identities: {e:?}"),
+            doc.name,
+            value.line_number(),
+        ),
+    }
 }
 
 // is user can_read the document or not based on defined readers in sitemap
 pub async fn is_reader(
-    _value: ftd_ast::VariableValue,
-    _kind: fastn_resolved::Kind,
-    _doc: &ftd::interpreter::TDoc<'_>,
-    _req_config: &fastn_core::RequestConfig,
+    value: ftd_ast::VariableValue,
+    kind: fastn_resolved::Kind,
+    doc: &ftd::interpreter::TDoc<'_>,
+    req_config: &mut fastn_core::RequestConfig,
 ) -> ftd::interpreter::Result<fastn_resolved::Value> {
-    Err(ftd::interpreter::Error::OtherError(
-        "is-reader is not implemented in this version. Switch to an older \
-            version."
-            .into(),
-    ))
+    let path = req_config.request.path().to_string();
+    match req_config.can_read(path.as_str()).await {
+        Ok(is_reader) => doc.from_json(&is_reader, &kind, &value),
+        Err(e) => ftd::interpreter::utils::e2(
+            format!("failed to check readers: {e:?}"),
+            doc.name,
+            value.line_number(),
+        ),
+    }
 }
@@ -111,6 +111,7 @@ pub async fn write_manifest_file(
         .write_content(
             &build_dir.join(fastn_core::manifest::MANIFEST_FILE),
             &serialized_manifest,
+            &None,
         )
         .await?;
 
@@ -9,6 +9,7 @@ pub struct App {
     pub config: std::collections::HashMap<String, String>,
     pub readers: Vec<String>,
     pub writers: Vec<String>,
+    pub wasm_limits: fastn_wasm::Limits,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -24,6 +25,12 @@ pub struct AppTemp {
     pub config: Vec<String>,
     pub readers: Vec<String>,
     pub writers: Vec<String>,
+    #[serde(rename = "wasm-fuel")]
+    pub wasm_fuel: Option<i64>,
+    #[serde(rename = "wasm-memory")]
+    pub wasm_memory: Option<i64>,
+    #[serde(rename = "wasm-timeout")]
+    pub wasm_timeout: Option<i64>,
 }
 
 impl AppTemp {
@@ -93,6 +100,11 @@ impl AppTemp {
             config: Self::parse_config(&self.config).await?,
             readers: self.readers,
             writers: self.writers,
+            wasm_limits: fastn_core::utils::wasm_limits(
+                self.wasm_fuel,
+                self.wasm_memory,
+                self.wasm_timeout,
+            ),
         })
     }
 }
@@ -0,0 +1,89 @@
+/// One `-- fastn.cache-control: <path>` entry in `FASTN.ftd`.
+///
+/// Applies to every static file and document whose url starts with `path`. When more than one
+/// entry matches, the one with the longest `path` wins.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheControl {
+    pub path: String,
+    /// seconds the response can be used without checking back with the server. When not set,
+    /// the browser has to revalidate (using the `ETag`) on every use.
+    #[serde(rename = "max-age")]
+    pub max_age: Option<i64>,
+    /// only the browser can cache, not shared caches like CDNs
+    pub private: bool,
+    #[serde(rename = "no-store")]
+    pub no_store: bool,
+    /// the content at this url never changes, useful with hashed file names
+    pub immutable: bool,
+}
+
+impl CacheControl {
+    /// Value for the `Cache-Control` header.
+    pub fn header_value(&self) -> String {
+        if self.no_store {
+            return "no-store".to_string();
+        }
+
+        let mut directives = vec![if self.private { "private" } else { "public" }.to_string()];
+        match self.max_age {
+            Some(max_age) => directives.push(format!("max-age={max_age}")),
+            None => directives.push("no-cache".to_string()),
+        }
+        if self.immutable {
+            directives.push("immutable".to_string());
+        }
+        directives.join(", ")
+    }
+}
+
+/// The rule with the longest `path` that is a prefix of `url`.
+pub fn find<'a>(rules: &'a [CacheControl], url: &str) -> Option<&'a CacheControl> {
+    let url = format!("/{}", url.trim_start_matches('/'));
+    rules
+        .iter()
+        .filter(|rule| url.starts_with(&format!("/{}", rule.path.trim_start_matches('/'))))
+        .max_by_key(|rule| rule.path.trim_start_matches('/').len())
+}
+
+#[cfg(test)]
+mod tests {
+    fn rule(path: &str, max_age: Option<i64>) -> super::CacheControl {
+        super::CacheControl {
+            path: path.to_string(),
+            max_age,
+            private: false,
+            no_store: false,
+            immutable: false,
+        }
+    }
+
+    #[test]
+    fn find() {
+        let rules = vec![
+            rule("/", None),
+            rule("/static/", Some(60)),
+            rule("static/img/", Some(600)),
+        ];
+
+        assert_eq!(super::find(&rules, "/blog/"), Some(&rules[0]));
+        assert_eq!(super::find(&rules, "/static/a.css"), Some(&rules[1]));
+        assert_eq!(super::find(&rules, "static/img/a.png"), Some(&rules[2]));
+        assert_eq!(super::find(&rules[1..], "/blog/"), None);
+    }
+
+    #[test]
+    fn header_value() {
+        assert_eq!(rule("/", None).header_value(), "public, no-cache");
+        assert_eq!(rule("/", Some(60)).header_value(), "public, max-age=60");
+
+        let mut r = rule("/", Some(31536000));
+        r.immutable = true;
+        assert_eq!(r.header_value(), "public, max-age=31536000, immutable");
+
+        r.private = true;
+        assert_eq!(r.header_value(), "private, max-age=31536000, immutable");
+
+        r.no_store = true;
+        assert_eq!(r.header_value(), "no-store");
+    }
+}
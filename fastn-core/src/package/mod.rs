@@ -1,11 +1,16 @@
 pub mod app;
+pub mod cache_control;
 pub mod dependency;
 pub mod package_doc;
 pub mod redirects;
+pub mod user_group;
 
 #[derive(Debug, Clone)]
 pub struct Package {
     pub name: String,
+    /// The semver version this package is published as, dependents can constrain it with the
+    /// `version` header of `fastn.dependency`.
+    pub version: Option<String>,
     /// The `versioned` stores the boolean value storing of the fastn package is versioned or not
     pub files: Vec<String>,
     pub versioned: bool,
@@ -34,9 +39,8 @@ pub struct Package {
     pub fonts: Vec<fastn_core::Font>,
     pub import_auto_imports_from_original: bool,
 
-    // TODO: this needs to be moved to another fastn + wasm package or would require a redesign
-    // if we move this: think about how we can design it mostly in ftd land
-    // pub groups: std::collections::BTreeMap<String, crate::user_group::UserGroup>,
+    /// `-- fastn.user-group:` entries, the groups sitemap `readers` and `writers` refer to
+    pub user_groups: Vec<user_group::UserGroup>,
     /// sitemap stores the structure of the package. The structure includes sections, sub_sections
     /// and table of content (`toc`). This automatically converts the documents in package into the
     /// corresponding to structure.
@@ -66,6 +70,8 @@ pub struct Package {
 
     /// Redirect URLs
     pub redirects: Option<ftd::Map<String>>,
+    /// `Cache-Control` rules for `fastn serve`, from `-- fastn.cache-control:` entries
+    pub cache_control: Vec<cache_control::CacheControl>,
     pub system: Option<String>,
     pub system_is_confidential: Option<bool>,
 
@@ -83,6 +89,7 @@ impl Package {
     pub fn new(name: &str) -> fastn_core::Package {
         fastn_core::Package {
             name: name.to_string(),
+            version: None,
             files: vec![],
             versioned: false,
             translation_of: None,
@@ -110,6 +117,8 @@ impl Package {
             apps: vec![],
             icon: None,
             redirects: None,
+            cache_control: vec![],
+            user_groups: vec![],
             system: None,
             system_is_confidential: None,
             migrations: vec![],
@@ -762,6 +771,8 @@ impl Package {
         package.fonts = fastn_doc.get("fastn#font")?;
         package.sitemap_temp = fastn_doc.get("fastn#sitemap")?;
         package.dynamic_urls_temp = fastn_doc.get("fastn#dynamic-urls")?;
+        package.cache_control = fastn_doc.get("fastn#cache-control")?;
+        package.user_groups = user_group::from_fastn_doc(fastn_doc)?;
         package.migrations = get_migration_data(fastn_doc)?;
 
         // validation logic TODO: It should be ordered
@@ -996,6 +1007,7 @@ impl PackageTempIntoPackage for fastn_package::old_fastn::PackageTemp {
 
         Package {
             name: self.name.clone(),
+            version: self.version,
             files: vec![],
             versioned: self.versioned,
             translation_of: translation_of.map(Box::new),
@@ -1023,6 +1035,8 @@ impl PackageTempIntoPackage for fastn_package::old_fastn::PackageTemp {
             apps: vec![],
             icon: self.icon,
             redirects: None,
+            cache_control: vec![],
+            user_groups: vec![],
             system: self.system,
             system_is_confidential: self.system_is_confidential,
             migrations: vec![],
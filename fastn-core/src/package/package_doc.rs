@@ -451,9 +451,13 @@ pub(crate) async fn read_ftd_2022(
         Ok(v) => v,
         Err(e) => {
             tracing::error!(msg = "failed to parse", doc = main.id.as_str());
-            return Err(fastn_core::Error::PackageError {
-                message: format!("failed to parse {:?}", &e),
-            });
+            return Err(fastn_core::doc::error_diagnostics(
+                e,
+                main.id_with_package().as_str(),
+                doc_content.as_str(),
+                line_number,
+            )
+            .into());
         }
     };
 
@@ -545,9 +549,13 @@ pub(crate) async fn read_ftd_2023(
         Ok(v) => v,
         Err(e) => {
             tracing::error!(msg = "failed to parse", doc = main.id.as_str());
-            return Err(fastn_core::Error::PackageError {
-                message: format!("failed to parse {:?}", &e),
-            });
+            return Err(fastn_core::doc::error_diagnostics(
+                e,
+                main.id_with_package().as_str(),
+                doc_content.as_str(),
+                line_number,
+            )
+            .into());
         }
     };
     if let Some((url, code)) = main_ftd_doc.get_redirect()? {
@@ -566,6 +574,18 @@ pub(crate) async fn read_ftd_2023(
         return Ok(FTDResult::Json(data));
     }
 
+    if config.collect_page_data {
+        config.search_document = Some(fastn_core::search::Document::from_tree(
+            fastn_core::search::url(main.id.as_str()).as_str(),
+            main_ftd_doc.tree.as_slice(),
+        ));
+        config.page_metadata = Some(fastn_core::sitemap::seo::Metadata::from_document(
+            fastn_core::search::url(main.id.as_str()).as_str(),
+            &main_ftd_doc,
+            main.content.as_str(),
+        ));
+    }
+
     let js_ast_data = ftd::js::document_into_js_ast(main_ftd_doc);
     let js_document_script = fastn_js::to_js(js_ast_data.asts.as_slice(), package_name.as_str());
     let js_ftd_script = fastn_js::to_js(
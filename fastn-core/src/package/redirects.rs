@@ -76,6 +76,9 @@ impl UrlMappingsTemp {
                         endpoint: endpoint.trim().trim_end_matches('*').to_string(),
                         mountpoint: mountpoint.trim().trim_end_matches('*').to_string(),
                         user_id: None,
+                        wasm_fuel: None,
+                        wasm_memory: None,
+                        wasm_timeout: None,
                     });
                 }
                 continue;
@@ -165,11 +168,17 @@ mod tests {
                 endpoint: "http://fastn.com/ftd/".to_string(),
                 mountpoint: "/ftd/".to_string(),
                 user_id: None,
+                wasm_fuel: None,
+                wasm_memory: None,
+                wasm_timeout: None,
             },
             fastn_package::old_fastn::EndpointData {
                 endpoint: "http://127.0.0.1:7999/".to_string(),
                 mountpoint: "/slides/".to_string(),
                 user_id: None,
+                wasm_fuel: None,
+                wasm_memory: None,
+                wasm_timeout: None,
             },
         ];
 
@@ -0,0 +1,262 @@
+/// Anyone, logged in or not, can read a document that lists this group in its `readers`.
+pub const EVERYONE: &str = "everyone";
+/// Listed in `readers` or `writers` of a sitemap item, it drops the groups inherited from the
+/// parents of that item.
+pub const NOT_INHERITED: &str = "not-inherited";
+
+/// One `-- fastn.user-group: <id>` entry in `FASTN.ftd`.
+///
+/// ```ftd
+/// -- fastn.user-group: editors
+/// title: Editors
+/// email: alice@example.com
+/// group: admins
+/// -email: bob@example.com
+/// ```
+///
+/// Members are the listed identities and the members of the listed groups, minus the identities
+/// and groups with a `-` prefix.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct UserGroupTemp {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub group: Vec<String>,
+    #[serde(rename = "-group")]
+    pub excluded_group: Vec<String>,
+    pub email: Vec<String>,
+    #[serde(rename = "-email")]
+    pub excluded_email: Vec<String>,
+    /// identity kinds (`github`, `discord-role` etc.) we can not resolve yet
+    #[serde(flatten)]
+    pub other: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserGroup {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub identities: Vec<UserIdentity>,
+    pub excluded_identities: Vec<UserIdentity>,
+    pub groups: Vec<String>,
+    pub excluded_groups: Vec<String>,
+}
+
+/// An identity of a user, eg `email: alice@example.com`.
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserIdentity {
+    pub key: String,
+    pub value: String,
+}
+
+/// What `user-groups` and `user-group-by-id` processors return, the `fastn.user-group-compat`
+/// record.
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct UserGroupCompat {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub groups: Vec<String>,
+    #[serde(rename = "group-members")]
+    pub group_members: Vec<UserIdentity>,
+}
+
+impl UserIdentity {
+    pub fn email(email: &str) -> Self {
+        Self {
+            key: "email".to_string(),
+            value: email.trim().to_lowercase(),
+        }
+    }
+
+    /// Identities of a logged in user. Only a verified email is an identity, anyone can sign up
+    /// with someone else's address.
+    pub fn from_user_data(ud: &ft_sys_shared::UserData) -> Vec<Self> {
+        if ud.verified_email && !ud.email.trim().is_empty() {
+            vec![Self::email(ud.email.as_str())]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl UserGroupTemp {
+    pub fn into_user_group(self) -> fastn_core::Result<UserGroup> {
+        let unsupported = self
+            .other
+            .iter()
+            .filter(|(_, v)| v.as_array().is_some_and(|v| !v.is_empty()))
+            .map(|(k, _)| format!("`{k}`"))
+            .collect::<Vec<_>>();
+        if !unsupported.is_empty() {
+            return Err(fastn_core::Error::PackageError {
+                message: format!(
+                    "user-group `{}`: {} identities are not supported, only `email` and `group` \
+                    can be used",
+                    self.id,
+                    unsupported.join(", ")
+                ),
+            });
+        }
+
+        Ok(UserGroup {
+            id: self.id,
+            title: self.title,
+            description: self.description,
+            identities: self.email.iter().map(|e| UserIdentity::email(e)).collect(),
+            excluded_identities: self
+                .excluded_email
+                .iter()
+                .map(|e| UserIdentity::email(e))
+                .collect(),
+            groups: self.group,
+            excluded_groups: self.excluded_group,
+        })
+    }
+}
+
+impl UserGroup {
+    pub fn to_compat(&self) -> UserGroupCompat {
+        UserGroupCompat {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            description: self.description.clone(),
+            groups: self.groups.clone(),
+            group_members: self.identities.clone(),
+        }
+    }
+}
+
+/// Reads the `-- fastn.user-group:` entries, and checks every group they refer to exists.
+pub fn from_fastn_doc(
+    fastn_doc: &ftd::ftd2021::p2::Document,
+) -> fastn_core::Result<Vec<UserGroup>> {
+    let groups = fastn_doc
+        .get::<Vec<UserGroupTemp>>("fastn#user-group")?
+        .into_iter()
+        .map(UserGroupTemp::into_user_group)
+        .collect::<fastn_core::Result<Vec<_>>>()?;
+
+    for group in groups.iter() {
+        if groups.iter().filter(|g| g.id == group.id).count() > 1 {
+            return Err(fastn_core::Error::PackageError {
+                message: format!("user-group `{}` is defined more than once", group.id),
+            });
+        }
+        for id in group.groups.iter().chain(group.excluded_groups.iter()) {
+            if !groups.iter().any(|g| &g.id == id) {
+                return Err(fastn_core::Error::GroupNotFound {
+                    id: id.to_string(),
+                    message: format!("used in user-group `{}`", group.id),
+                });
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+pub fn find<'a>(groups: &'a [UserGroup], id: &str) -> Option<&'a UserGroup> {
+    groups.iter().find(|g| g.id == id)
+}
+
+/// Is the user with `identities` a member of group `id`. Unknown groups have no members.
+pub fn is_member(groups: &[UserGroup], id: &str, identities: &[UserIdentity]) -> bool {
+    return is_member_(groups, id, identities, &mut vec![]);
+
+    fn is_member_<'a>(
+        groups: &'a [UserGroup],
+        id: &'a str,
+        identities: &[UserIdentity],
+        visiting: &mut Vec<&'a str>,
+    ) -> bool {
+        // groups including each other would recurse forever, a cycle adds no members
+        if visiting.contains(&id) {
+            return false;
+        }
+        let Some(group) = find(groups, id) else {
+            return false;
+        };
+
+        visiting.push(id);
+        let excluded = group
+            .excluded_identities
+            .iter()
+            .any(|i| identities.contains(i))
+            || group
+                .excluded_groups
+                .iter()
+                .any(|g| is_member_(groups, g, identities, visiting));
+        let member = !excluded
+            && (group.identities.iter().any(|i| identities.contains(i))
+                || group
+                    .groups
+                    .iter()
+                    .any(|g| is_member_(groups, g, identities, visiting)));
+        visiting.pop();
+
+        member
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn group(id: &str, emails: &[&str], groups: &[&str]) -> super::UserGroup {
+        super::UserGroup {
+            id: id.to_string(),
+            title: None,
+            description: None,
+            identities: emails
+                .iter()
+                .map(|e| super::UserIdentity::email(e))
+                .collect(),
+            excluded_identities: vec![],
+            groups: groups.iter().map(ToString::to_string).collect(),
+            excluded_groups: vec![],
+        }
+    }
+
+    #[test]
+    fn is_member() {
+        let mut editors = group("editors", &["Alice@example.com"], &["admins"]);
+        editors
+            .excluded_identities
+            .push(super::UserIdentity::email("carol@example.com"));
+        let groups = vec![
+            editors,
+            group("admins", &["bob@example.com", "carol@example.com"], &[]),
+        ];
+
+        let alice = [super::UserIdentity::email("alice@EXAMPLE.com")];
+        let bob = [super::UserIdentity::email("bob@example.com")];
+        let carol = [super::UserIdentity::email("carol@example.com")];
+
+        assert!(super::is_member(&groups, "editors", &alice));
+        assert!(super::is_member(&groups, "editors", &bob));
+        assert!(!super::is_member(&groups, "editors", &carol));
+        assert!(super::is_member(&groups, "admins", &carol));
+        assert!(!super::is_member(&groups, "admins", &alice));
+        assert!(!super::is_member(&groups, "admins", &[]));
+        assert!(!super::is_member(&groups, "unknown", &alice));
+    }
+
+    #[test]
+    fn cycle() {
+        let groups = vec![
+            group("a", &["alice@example.com"], &["b"]),
+            group("b", &[], &["a"]),
+        ];
+
+        assert!(super::is_member(
+            &groups,
+            "b",
+            &[super::UserIdentity::email("alice@example.com")]
+        ));
+        assert!(!super::is_member(
+            &groups,
+            "b",
+            &[super::UserIdentity::email("bob@example.com")]
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+//! Full-text search over the documents of a package, without an external service.
+//!
+//! `fastn build` extracts the text of every document the world can read from its interpreted
+//! tree, and writes an inverted index of it to `.build/-/search-index.json`. `fastn serve`
+//! searches it for `/-/search/?q=<query>`, which is what `ftd.search` asks from a page, so the
+//! scoring lives only here. There is no index, and so no search, until `fastn build` has run.
+
+pub const INDEX_FILE: &str = "-/search-index.json";
+pub const SEARCH_URL: &str = "/-/search/";
+const INDEX_VERSION: u32 = 1;
+/// a word in the title counts as this many in the text
+const TITLE_WEIGHT: u32 = 5;
+const SUMMARY_LENGTH: usize = 200;
+const DEFAULT_LIMIT: usize = 10;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "if", "in", "is", "it", "of",
+    "on", "or", "that", "the", "this", "to", "was", "we", "with", "you",
+];
+
+/// Headers, other than the caption and the body, whose text is shown on the page.
+const TEXT_HEADERS: &[&str] = &["text", "title", "description", "caption", "body"];
+
+/// The text of a document, as extracted while rendering it.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    /// `/foo/bar/`
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+impl Document {
+    /// The text of the interpreted document `doc`, served at `url`.
+    pub fn from_tree(url: &str, tree: &[fastn_resolved::ComponentInvocation]) -> Document {
+        let mut title = None;
+        let mut text = vec![];
+        for component in tree {
+            collect_component(component, &mut title, &mut text);
+        }
+
+        Document {
+            url: url.to_string(),
+            title: title.unwrap_or_default(),
+            text: text.join("\n"),
+        }
+    }
+}
+
+fn collect_component(
+    component: &fastn_resolved::ComponentInvocation,
+    title: &mut Option<String>,
+    text: &mut Vec<String>,
+) {
+    for property in component.properties.iter() {
+        let is_caption = matches!(property.source, fastn_resolved::PropertySource::Caption);
+        let is_text = match &property.source {
+            fastn_resolved::PropertySource::Caption | fastn_resolved::PropertySource::Body => true,
+            fastn_resolved::PropertySource::Header { name, .. } => {
+                TEXT_HEADERS.contains(&name.as_str())
+            }
+            _ => false,
+        };
+        if let fastn_resolved::PropertyValue::Value { value, .. } = &property.value {
+            collect_value(value, is_text, is_caption, title, text);
+        }
+    }
+
+    for child in component.children.iter() {
+        collect_component(child, title, text);
+    }
+}
+
+fn collect_value(
+    value: &fastn_resolved::Value,
+    is_text: bool,
+    is_caption: bool,
+    title: &mut Option<String>,
+    text: &mut Vec<String>,
+) {
+    match value {
+        fastn_resolved::Value::String { text: t } if is_text && !t.trim().is_empty() => {
+            // the first caption, like that of `-- ds.page:`, is the title
+            if is_caption && title.is_none() {
+                *title = Some(t.trim().to_string());
+            } else {
+                text.push(t.trim().to_string());
+            }
+        }
+        fastn_resolved::Value::Optional { data, .. } => {
+            if let Some(value) = data.as_ref() {
+                collect_value(value, is_text, is_caption, title, text);
+            }
+        }
+        fastn_resolved::Value::List { data, .. } => {
+            for item in data {
+                if let fastn_resolved::PropertyValue::Value { value, .. } = item {
+                    collect_value(value, is_text, false, title, text);
+                }
+            }
+        }
+        fastn_resolved::Value::UI { component, .. } => collect_component(component, title, text),
+        _ => {}
+    }
+}
+
+/// The lowercase words of `text`, without the stop words.
+pub fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && w.chars().count() <= 32)
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() > 1 || w.chars().all(char::is_numeric))
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+}
+
+/// The inverted index: every term maps to the flattened `[document, weight, ...]` pairs of the
+/// documents it is in, the weight being how many times it is there.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Index {
+    pub version: u32,
+    pub documents: Vec<Entry>,
+    pub terms: std::collections::BTreeMap<String, Vec<u32>>,
+}
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+    pub score: f64,
+}
+
+impl Index {
+    pub fn build(documents: &[Document]) -> Index {
+        let mut postings: std::collections::BTreeMap<String, std::collections::BTreeMap<u32, u32>> =
+            Default::default();
+
+        for (i, document) in documents.iter().enumerate() {
+            let i = i as u32;
+            for term in tokens(document.title.as_str()) {
+                *postings.entry(term).or_default().entry(i).or_default() += TITLE_WEIGHT;
+            }
+            for term in tokens(document.text.as_str()) {
+                *postings.entry(term).or_default().entry(i).or_default() += 1;
+            }
+        }
+
+        Index {
+            version: INDEX_VERSION,
+            documents: documents
+                .iter()
+                .map(|d| Entry {
+                    url: d.url.clone(),
+                    title: d.title.clone(),
+                    summary: summary(d.text.as_str()),
+                })
+                .collect(),
+            terms: postings
+                .into_iter()
+                .map(|(term, docs)| (term, docs.into_iter().flat_map(|(d, w)| [d, w]).collect()))
+                .collect(),
+        }
+    }
+
+    /// The documents with every word of `query`, best first. The last word matches the words
+    /// starting with it too, so results show up while the query is being typed.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit> {
+        let words = tokens(query);
+        let prefix = !query.ends_with(char::is_whitespace);
+        let total = self.documents.len() as f64;
+
+        let mut scores: Option<std::collections::HashMap<u32, f64>> = None;
+        for (i, word) in words.iter().enumerate() {
+            let matches: Vec<(&String, &Vec<u32>)> = if prefix && i + 1 == words.len() {
+                self.terms
+                    .range::<String, _>(word..)
+                    .take_while(|(term, _)| term.starts_with(word.as_str()))
+                    .collect()
+            } else {
+                self.terms.get_key_value(word).into_iter().collect()
+            };
+
+            let mut word_scores: std::collections::HashMap<u32, f64> = Default::default();
+            for (term, postings) in matches {
+                let idf = (1.0 + total / (postings.len() / 2) as f64).ln();
+                // a word that only starts with the query word is a weaker match
+                let boost = if term == word { 1.0 } else { 0.5 };
+                for pair in postings.chunks_exact(2) {
+                    *word_scores.entry(pair[0]).or_default() += pair[1] as f64 * idf * boost;
+                }
+            }
+
+            scores = Some(match scores {
+                None => word_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(d, s)| word_scores.get(&d).map(|w| (d, s + w)))
+                    .collect(),
+            });
+        }
+
+        let mut scores = scores.unwrap_or_default().into_iter().collect::<Vec<_>>();
+        scores.sort_by(|(d1, s1), (d2, s2)| s2.total_cmp(s1).then(d1.cmp(d2)));
+        scores
+            .into_iter()
+            .take(limit)
+            .filter_map(|(d, score)| {
+                let entry = self.documents.get(d as usize)?;
+                Some(Hit {
+                    url: entry.url.clone(),
+                    title: entry.title.clone(),
+                    summary: entry.summary.clone(),
+                    score,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The start of `text`, cut at a word.
+fn summary(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SUMMARY_LENGTH {
+        return text;
+    }
+    let cut = text
+        .char_indices()
+        .nth(SUMMARY_LENGTH)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let cut = text[..cut].rfind(' ').unwrap_or(cut);
+    format!("{}…", &text[..cut])
+}
+
+/// The url a document with `id` (`foo/bar.ftd`, `foo/index.ftd`) is served at.
+pub fn url(id: &str) -> String {
+    format!(
+        "/{}",
+        fastn_core::utils::id_to_path(id)
+            .replace(std::path::MAIN_SEPARATOR, "/")
+            .trim_start_matches('/')
+    )
+}
+
+/// Only the documents the world can read are searchable.
+pub fn is_searchable(package: &fastn_core::Package, url: &str) -> bool {
+    fastn_core::sitemap::access::find(package, url).is_public()
+}
+
+/// Writes the index of `documents` to `build_dir`.
+pub async fn write(
+    config: &fastn_core::Config,
+    build_dir: &fastn_ds::Path,
+    documents: &[Document],
+) -> fastn_core::Result<()> {
+    let documents = documents
+        .iter()
+        .filter(|d| is_searchable(&config.package, d.url.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let index = Index::build(&documents);
+    fastn_core::utils::update(
+        &build_dir.join(INDEX_FILE),
+        serde_json::to_string(&index)?.as_bytes(),
+        &config.ds,
+    )
+    .await
+}
+
+/// `fastn serve` responses for `/-/search/?q=<query>&limit=<n>` and `/-/search-index.json`,
+/// `None` for other paths.
+pub async fn serve(
+    config: &fastn_core::Config,
+    req: &fastn_core::http::Request,
+    session_id: &Option<String>,
+) -> Option<fastn_core::Result<fastn_core::http::Response>> {
+    let path = req.path();
+    if path != SEARCH_URL
+        && path != SEARCH_URL.trim_end_matches('/')
+        && path.trim_start_matches('/') != INDEX_FILE
+    {
+        return None;
+    }
+
+    Some(serve_(config, req, session_id).await)
+}
+
+async fn serve_(
+    config: &fastn_core::Config,
+    req: &fastn_core::http::Request,
+    session_id: &Option<String>,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    // the index is only built by `fastn build`: building it here would mean rendering every
+    // document, running their processors, from a GET
+    let index = match config
+        .ds
+        .read_content(&config.build_dir().join(INDEX_FILE), session_id)
+        .await
+    {
+        Ok(index) => index,
+        Err(fastn_ds::ReadError::NotFound(_)) => {
+            return Ok(fastn_core::not_found!(
+                "there is no search index, run `fastn build` to build it"
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if req.path().trim_start_matches('/') == INDEX_FILE {
+        return Ok(fastn_core::http::ok_with_content_type(
+            index,
+            mime_guess::mime::APPLICATION_JSON,
+        ));
+    }
+
+    let index: Index = serde_json::from_slice(&index)?;
+    let query = req.query();
+    let q = query
+        .get("q")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let limit = query
+        .get("limit")
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_LIMIT);
+
+    Ok(fastn_core::http::ok_with_content_type(
+        serde_json::to_vec(&serde_json::json!({
+            "query": q,
+            "results": index.search(q.as_str(), limit),
+        }))?,
+        mime_guess::mime::APPLICATION_JSON,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    fn doc(url: &str, title: &str, text: &str) -> super::Document {
+        super::Document {
+            url: url.to_string(),
+            title: title.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    fn urls(hits: Vec<super::Hit>) -> Vec<String> {
+        hits.into_iter().map(|h| h.url).collect()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            super::tokens("The fastn-stack: Install fastn, in 5 minutes!"),
+            vec!["fastn", "stack", "install", "fastn", "5", "minutes"]
+        );
+        assert_eq!(super::tokens("Über Straße"), vec!["über", "straße"]);
+    }
+
+    #[test]
+    fn search() {
+        let index = super::Index::build(&[
+            doc("/", "Home", "Welcome to fastn, read the install guide"),
+            doc(
+                "/install/",
+                "Install",
+                "Install fastn with the installer script",
+            ),
+            doc("/sql/", "SQL", "Query a database from ftd"),
+        ]);
+
+        assert_eq!(urls(index.search("install", 10)), vec!["/install/", "/"]);
+        // every word must match
+        assert_eq!(urls(index.search("install guide ", 10)), vec!["/"]);
+        // the last word is a prefix while typing
+        assert_eq!(urls(index.search("datab", 10)), vec!["/sql/"]);
+        assert!(index.search("datab ", 10).is_empty());
+        assert_eq!(urls(index.search("install", 1)), vec!["/install/"]);
+        assert!(index.search("", 10).is_empty());
+        assert!(index.search("the", 10).is_empty());
+    }
+
+    #[test]
+    fn index() {
+        let index = super::Index::build(&[
+            doc("/alpha/", "Alpha", "beta, the beta"),
+            doc("/gamma/", "Gamma beta", ""),
+        ]);
+        // the title counts five times
+        assert_eq!(index.terms["beta"], vec![0, 2, 1, 5]);
+        assert_eq!(index.terms["alpha"], vec![0, 5]);
+        assert!(!index.terms.contains_key("the"));
+
+        let json = serde_json::to_string(&index).unwrap();
+        assert_eq!(
+            serde_json::from_str::<super::Index>(json.as_str()).unwrap(),
+            index
+        );
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(super::summary("a\n  b"), "a b");
+        let long = "word ".repeat(100);
+        let s = super::summary(long.as_str());
+        assert!(s.ends_with("word…"), "{s}");
+        assert!(s.chars().count() <= super::SUMMARY_LENGTH + 1);
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(super::url("index.ftd"), "/");
+        assert_eq!(super::url("foo/index.ftd"), "/foo/");
+        assert_eq!(super::url("foo/bar.ftd"), "/foo/bar/");
+    }
+
+    #[test]
+    fn from_tree() {
+        let mut page = fastn_resolved::ComponentInvocation::from_name("ds.page");
+        page.properties.push(fastn_resolved::Property {
+            value: fastn_resolved::PropertyValue::Value {
+                value: fastn_resolved::Value::new_string("Getting Started"),
+                is_mutable: false,
+                line_number: 0,
+            },
+            source: fastn_resolved::PropertySource::Caption,
+            condition: None,
+            line_number: 0,
+        });
+        let mut markdown = fastn_resolved::ComponentInvocation::from_name("ds.markdown");
+        markdown.properties.push(fastn_resolved::Property {
+            value: fastn_resolved::PropertyValue::Value {
+                value: fastn_resolved::Value::new_string("Install fastn first."),
+                is_mutable: false,
+                line_number: 0,
+            },
+            source: fastn_resolved::PropertySource::Body,
+            condition: None,
+            line_number: 0,
+        });
+        markdown.properties.push(fastn_resolved::Property {
+            value: fastn_resolved::PropertyValue::Value {
+                value: fastn_resolved::Value::new_string("#ff0000"),
+                is_mutable: false,
+                line_number: 0,
+            },
+            source: fastn_resolved::PropertySource::Header {
+                name: "color".to_string(),
+                mutable: false,
+            },
+            condition: None,
+            line_number: 0,
+        });
+        page.children.push(markdown);
+
+        assert_eq!(
+            super::Document::from_tree("/start/", &[page]),
+            doc("/start/", "Getting Started", "Install fastn first.")
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Who can read and write the documents of a package, from the `readers` and `writers` of the
+//! `fastn.sitemap` and `fastn.dynamic-urls` items.
+//!
+//! An item inherits the groups of its parents, and of the sitemap itself, unless it lists the
+//! `not-inherited` group. A document without readers can be read by the world, and so can one
+//! with the `everyone` group in its readers.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Access {
+    pub readers: Vec<String>,
+    pub writers: Vec<String>,
+}
+
+impl Access {
+    /// The access of an item with `readers` and `writers`, whose parent has this access.
+    pub fn inherit(&self, readers: &[String], writers: &[String]) -> Access {
+        Access {
+            readers: merge(&self.readers, readers),
+            writers: merge(&self.writers, writers),
+        }
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.readers.is_empty()
+            || self
+                .readers
+                .iter()
+                .any(|r| r == fastn_core::package::user_group::EVERYONE)
+    }
+
+    /// Writers can read too.
+    pub fn can_read(
+        &self,
+        groups: &[fastn_core::package::user_group::UserGroup],
+        identities: &[fastn_core::package::user_group::UserIdentity],
+    ) -> bool {
+        self.is_public()
+            || self.can_write(groups, identities)
+            || self.is_in(&self.readers, groups, identities)
+    }
+
+    pub fn can_write(
+        &self,
+        groups: &[fastn_core::package::user_group::UserGroup],
+        identities: &[fastn_core::package::user_group::UserIdentity],
+    ) -> bool {
+        self.is_in(&self.writers, groups, identities)
+    }
+
+    /// Unsafe requests (`POST` etc) need this. A document without writers can be changed by
+    /// whoever can read it.
+    pub fn can_change(
+        &self,
+        groups: &[fastn_core::package::user_group::UserGroup],
+        identities: &[fastn_core::package::user_group::UserIdentity],
+    ) -> bool {
+        if self.writers.is_empty() {
+            return self.can_read(groups, identities);
+        }
+        self.can_write(groups, identities)
+    }
+
+    fn is_in(
+        &self,
+        ids: &[String],
+        groups: &[fastn_core::package::user_group::UserGroup],
+        identities: &[fastn_core::package::user_group::UserIdentity],
+    ) -> bool {
+        ids.iter()
+            .any(|id| fastn_core::package::user_group::is_member(groups, id, identities))
+    }
+}
+
+fn merge(inherited: &[String], own: &[String]) -> Vec<String> {
+    let mut groups = if own
+        .iter()
+        .any(|g| g == fastn_core::package::user_group::NOT_INHERITED)
+    {
+        vec![]
+    } else {
+        inherited.to_vec()
+    };
+    for group in own {
+        if group != fastn_core::package::user_group::NOT_INHERITED && !groups.contains(group) {
+            groups.push(group.to_string());
+        }
+    }
+    groups
+}
+
+/// The access of the document at `path` (eg `/foo/bar/`). Documents not in the sitemap or the
+/// dynamic urls get the access of the sitemap itself.
+pub fn find(package: &fastn_core::Package, path: &str) -> Access {
+    let mut access = Access::default();
+
+    if let Some(sitemap) = package.sitemap.as_ref() {
+        access = access.inherit(&sitemap.readers, &sitemap.writers);
+        let matches = |id: Option<&str>, _: &[fastn_core::sitemap::PathParams]| {
+            id.is_some_and(|id| fastn_core::utils::ids_matches(id, path))
+        };
+        if let Some(found) = find_in_sections(&sitemap.sections, &access, &matches) {
+            return found;
+        }
+    }
+
+    if let Some(dynamic_urls) = package.dynamic_urls.as_ref() {
+        let matches = |_: Option<&str>, params: &[fastn_core::sitemap::PathParams]| {
+            !params.is_empty()
+                && fastn_core::sitemap::utils::url_match(path, params).is_ok_and(|(m, _)| m)
+        };
+        if let Some(found) = find_in_sections(&dynamic_urls.sections, &access, &matches) {
+            return found;
+        }
+    }
+
+    access
+}
+
+type Matches<'a> = dyn Fn(Option<&str>, &[fastn_core::sitemap::PathParams]) -> bool + 'a;
+
+fn find_in_sections(
+    sections: &[fastn_core::sitemap::section::Section],
+    parent: &Access,
+    matches: &Matches,
+) -> Option<Access> {
+    for section in sections {
+        let access = parent.inherit(&section.readers, &section.writers);
+        if matches(Some(section.id.as_str()), &section.path_parameters) {
+            return Some(access);
+        }
+        for subsection in section.subsections.iter() {
+            let access = access.inherit(&subsection.readers, &subsection.writers);
+            if matches(subsection.id.as_deref(), &subsection.path_parameters) {
+                return Some(access);
+            }
+            if let Some(found) = find_in_toc(&subsection.toc, &access, matches) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+fn find_in_toc(
+    items: &[fastn_core::sitemap::toc::TocItem],
+    parent: &Access,
+    matches: &Matches,
+) -> Option<Access> {
+    for item in items {
+        let access = parent.inherit(&item.readers, &item.writers);
+        if matches(Some(item.id.as_str()), &item.path_parameters) {
+            return Some(access);
+        }
+        if let Some(found) = find_in_toc(&item.children, &access, matches) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Can the world read every item of the sitemap.
+pub fn is_public(sitemap: &fastn_core::sitemap::Sitemap) -> bool {
+    let mut public = true;
+    readable(sitemap, &mut |access| {
+        public &= access.is_public();
+        true
+    });
+    public
+}
+
+/// The sitemap without the items `can_read` rejects. An item is dropped along with its children.
+pub fn readable(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    can_read: &mut dyn FnMut(&Access) -> bool,
+) -> fastn_core::sitemap::Sitemap {
+    let root = Access::default().inherit(&sitemap.readers, &sitemap.writers);
+    let mut sitemap = sitemap.clone();
+
+    sitemap.sections.retain_mut(|section| {
+        let access = root.inherit(&section.readers, &section.writers);
+        if !can_read(&access) {
+            return false;
+        }
+        section.subsections.retain_mut(|subsection| {
+            let access = access.inherit(&subsection.readers, &subsection.writers);
+            if !can_read(&access) {
+                return false;
+            }
+            readable_toc(&mut subsection.toc, &access, can_read);
+            true
+        });
+        true
+    });
+
+    sitemap
+}
+
+fn readable_toc(
+    items: &mut Vec<fastn_core::sitemap::toc::TocItem>,
+    parent: &Access,
+    can_read: &mut dyn FnMut(&Access) -> bool,
+) {
+    items.retain_mut(|item| {
+        let access = parent.inherit(&item.readers, &item.writers);
+        if !can_read(&access) {
+            return false;
+        }
+        readable_toc(&mut item.children, &access, can_read);
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    fn s(v: &[&str]) -> Vec<String> {
+        v.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn inherit() {
+        let root = super::Access::default().inherit(&s(&["foo"]), &[]);
+        assert!(!root.is_public());
+
+        let child = root.inherit(&s(&["bar"]), &s(&["baz"]));
+        assert_eq!(child.readers, s(&["foo", "bar"]));
+        assert_eq!(child.writers, s(&["baz"]));
+
+        let reset = child.inherit(&s(&["not-inherited", "qux"]), &[]);
+        assert_eq!(reset.readers, s(&["qux"]));
+        assert_eq!(reset.writers, s(&["baz"]));
+
+        assert!(child.inherit(&s(&["everyone"]), &[]).is_public());
+        assert!(super::Access::default().is_public());
+    }
+
+    #[test]
+    fn can_read() {
+        let groups = vec![fastn_core::package::user_group::UserGroup {
+            id: "editors".to_string(),
+            title: None,
+            description: None,
+            identities: vec![fastn_core::package::user_group::UserIdentity::email(
+                "alice@example.com",
+            )],
+            excluded_identities: vec![],
+            groups: vec![],
+            excluded_groups: vec![],
+        }];
+        let alice = [fastn_core::package::user_group::UserIdentity::email(
+            "alice@example.com",
+        )];
+
+        let access = super::Access {
+            readers: s(&["readers"]),
+            writers: s(&["editors"]),
+        };
+        assert!(access.can_read(&groups, &alice));
+        assert!(access.can_write(&groups, &alice));
+        assert!(!access.can_read(&groups, &[]));
+
+        let bob = [fastn_core::package::user_group::UserIdentity::email(
+            "bob@example.com",
+        )];
+        assert!(access.can_change(&groups, &alice));
+        assert!(!access.can_change(&groups, &bob));
+
+        let readers_only = super::Access {
+            readers: s(&["editors"]),
+            writers: vec![],
+        };
+        assert!(readers_only.can_change(&groups, &alice));
+        assert!(!readers_only.can_change(&groups, &bob));
+        assert!(super::Access::default().can_change(&groups, &[]));
+    }
+}
@@ -13,8 +13,10 @@
 /// In above example, the id starts with `#` becomes the section. Similarly the id
 /// starts with `##` becomes the subsection and then the id starts with `-` becomes
 /// the table of content (TOC).
+pub mod access;
 pub mod dynamic_urls;
 pub mod section;
+pub mod seo;
 pub mod toc;
 pub mod utils;
 
@@ -0,0 +1,887 @@
+//! `sitemap.xml`, `robots.txt` and the RSS/Atom feeds of the sitemap sections, which `fastn build`
+//! writes to `.build` and `fastn serve` serves, unless the package has files of its own with those
+//! names.
+//!
+//! Only the documents the world can read are listed, and dynamic urls only when they have no path
+//! parameters. A section opts in to feeds with `feed`:
+//!
+//! ```ftd
+//! -- fastn.sitemap:
+//!
+//! # Blog: /blog/
+//! feed: rss, atom
+//!
+//! - Hello World: /blog/hello-world/
+//! ```
+//!
+//! This serves `/blog/rss.xml` and `/blog/atom.xml`, with an entry for every document in the
+//! section. The title, summary and date of a page come from its document, see [`Metadata`]:
+//!
+//! ```ftd
+//! -- string date: 2024-01-15
+//!
+//! -- ftd.document: Hello World
+//! description: The first post
+//! ```
+//!
+//! The `date` (a `YYYY-MM-DD` date, or RFC 3339 time) defaults to when the document was last
+//! modified, and the title to the one in the sitemap.
+//!
+//! `fastn build` writes the files with the rest of the package. `fastn serve` serves the ones in
+//! `.build`, and when there are none generates them from the sitemap alone, without the metadata
+//! of the documents.
+
+pub const SITEMAP_XML: &str = "sitemap.xml";
+pub const ROBOTS_TXT: &str = "robots.txt";
+pub const RSS_FEED: &str = "rss.xml";
+pub const ATOM_FEED: &str = "atom.xml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// `/blog/hello-world/`
+    pub path: String,
+    pub title: Option<String>,
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+    pub summary: Option<String>,
+    pub file_location: Option<fastn_ds::Path>,
+    pub public: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedKind {
+    Rss,
+    Atom,
+}
+
+impl FeedKind {
+    fn file_name(&self) -> &'static str {
+        match self {
+            FeedKind::Rss => RSS_FEED,
+            FeedKind::Atom => ATOM_FEED,
+        }
+    }
+
+    /// The `feed` of a section: `rss`, `atom`, `rss, atom`, or `true` for rss.
+    pub fn parse(value: &str) -> Vec<FeedKind> {
+        let mut kinds = vec![];
+        for v in value.split([',', ' ']).map(str::trim) {
+            let kind = match v.to_lowercase().as_str() {
+                "rss" | "true" => FeedKind::Rss,
+                "atom" => FeedKind::Atom,
+                _ => continue,
+            };
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        kinds
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    /// the section the feed is of
+    pub section: Page,
+    pub kinds: Vec<FeedKind>,
+    /// the documents of the section the world can read, newest first
+    pub entries: Vec<Page>,
+}
+
+/// What a document says about itself, for its page in the sitemap and the feeds: the `title`,
+/// `description` and `date` of the root component of the document (`ftd.document`, or a
+/// component like `ds.page`), or a `date` variable of the document.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Metadata {
+    /// the url of the document, as in the search index
+    pub url: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Metadata {
+    /// `source` is the content of the document `doc` is interpreted from.
+    pub fn from_document(url: &str, doc: &ftd::interpreter::Document, source: &str) -> Metadata {
+        let tdoc = doc.tdoc();
+        let header = |names: &[&str]| {
+            let root = doc.tree.first()?;
+            names.iter().find_map(|name| argument(root, name, &tdoc))
+        };
+
+        Metadata {
+            url: url.to_string(),
+            title: header(&["title", "og-title"]),
+            summary: header(&["description", "og-description"]),
+            date: header(&["date"])
+                .or_else(|| date_variable(source))
+                .and_then(|d| parse_date(&d)),
+        }
+    }
+}
+
+/// The value of the argument `name` of `component`, `None` if it does not have one.
+fn argument(
+    component: &fastn_resolved::ComponentInvocation,
+    name: &str,
+    tdoc: &ftd::interpreter::TDoc,
+) -> Option<String> {
+    use ftd::interpreter::ComponentExt;
+
+    let definition = tdoc.get_component(component.name.as_str(), 0).ok()?;
+    if !definition.arguments.iter().any(|a| a.name == name) {
+        return None;
+    }
+    component
+        .get_interpreter_value_of_argument(name, tdoc)
+        .ok()
+        .flatten()
+        .as_ref()
+        .and_then(text)
+}
+
+/// The caption of the `date` variable of the document, `-- string date: 2024-01-15`. It is read
+/// from the source, the interpreter leaves out the variables no component uses.
+fn date_variable(source: &str) -> Option<String> {
+    ftd_p1::parse(source, "")
+        .ok()?
+        .into_iter()
+        .find(|s| s.name == "date" && s.kind.is_some())?
+        .caption?
+        .get_value("")
+        .ok()
+        .flatten()
+}
+
+fn text(value: &fastn_resolved::Value) -> Option<String> {
+    match value {
+        fastn_resolved::Value::String { text } if !text.trim().is_empty() => {
+            Some(text.trim().to_string())
+        }
+        fastn_resolved::Value::Optional { data, .. } => data.as_ref().as_ref().and_then(text),
+        _ => None,
+    }
+}
+
+/// A file generated from the sitemap, `path` is relative to the package root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub path: String,
+    pub content_type: &'static str,
+    pub content: String,
+}
+
+/// Every page in the sitemap and the dynamic urls, in order, without duplicates.
+pub fn pages(package: &fastn_core::Package) -> Vec<Page> {
+    let mut pages = vec![];
+    if let Some(sitemap) = package.sitemap.as_ref() {
+        for section in sitemap.sections.iter() {
+            section_pages(section, &mut pages);
+        }
+    }
+    if let Some(dynamic_urls) = package.dynamic_urls.as_ref() {
+        for section in dynamic_urls.sections.iter() {
+            section_pages(section, &mut pages);
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    pages.retain(|p| seen.insert(p.path.clone()));
+    for page in pages.iter_mut() {
+        page.public = fastn_core::sitemap::access::find(package, page.path.as_str()).is_public();
+    }
+    pages
+}
+
+/// The feeds of the sitemap sections with a `feed`.
+pub fn feeds(package: &fastn_core::Package) -> Vec<Feed> {
+    let Some(sitemap) = package.sitemap.as_ref() else {
+        return vec![];
+    };
+
+    let mut feeds = vec![];
+    for section in sitemap.sections.iter() {
+        let kinds = section
+            .extra_data
+            .get("feed")
+            .map(|v| FeedKind::parse(v))
+            .unwrap_or_default();
+        if kinds.is_empty() {
+            continue;
+        }
+        let Some(section_page) = page(
+            section.id.as_str(),
+            &section.title,
+            &section.file_location,
+            &section.path_parameters,
+        ) else {
+            continue;
+        };
+
+        let mut entries = vec![];
+        section_pages(section, &mut entries);
+        let mut seen = std::collections::HashSet::from([section_page.path.clone()]);
+        entries.retain(|p| seen.insert(p.path.clone()));
+        entries.retain_mut(|p| {
+            p.public = fastn_core::sitemap::access::find(package, p.path.as_str()).is_public();
+            p.public
+        });
+
+        feeds.push(Feed {
+            section: section_page,
+            kinds,
+            entries,
+        });
+    }
+    feeds
+}
+
+fn section_pages(section: &fastn_core::sitemap::section::Section, pages: &mut Vec<Page>) {
+    pages.extend(page(
+        section.id.as_str(),
+        &section.title,
+        &section.file_location,
+        &section.path_parameters,
+    ));
+    for subsection in section.subsections.iter() {
+        if let Some(id) = subsection.id.as_deref() {
+            pages.extend(page(
+                id,
+                &subsection.title,
+                &subsection.file_location,
+                &subsection.path_parameters,
+            ));
+        }
+        toc_pages(&subsection.toc, pages);
+    }
+}
+
+fn toc_pages(items: &[fastn_core::sitemap::toc::TocItem], pages: &mut Vec<Page>) {
+    for item in items {
+        pages.extend(page(
+            item.id.as_str(),
+            &item.title,
+            &item.file_location,
+            &item.path_parameters,
+        ));
+        toc_pages(&item.children, pages);
+    }
+}
+
+/// `None` for ids that are not pages of the package: external links, and urls with path
+/// parameters.
+fn page(
+    id: &str,
+    title: &Option<String>,
+    file_location: &Option<fastn_ds::Path>,
+    path_parameters: &[fastn_core::sitemap::PathParams],
+) -> Option<Page> {
+    let id = id.trim();
+    if id.contains("://")
+        || id.starts_with("mailto:")
+        || path_parameters.iter().any(|p| p.is_named_param())
+    {
+        return None;
+    }
+
+    let id = id.trim_matches('/');
+    let path = if id.is_empty() {
+        "/".to_string()
+    } else if id.rsplit('/').next().is_some_and(|last| last.contains('.')) {
+        format!("/{id}")
+    } else {
+        format!("/{id}/")
+    };
+
+    Some(Page {
+        path,
+        title: title.clone(),
+        date: None,
+        summary: None,
+        file_location: file_location.clone(),
+        public: true,
+    })
+}
+
+/// `2024-01-15`, or `2024-01-15T10:00:00+05:30`.
+pub fn parse_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    if let Ok(d) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(d.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| d.and_utc())
+}
+
+/// The absolute url of `path`, `base` being the canonical url of the package.
+pub fn url(base: &str, path: &str) -> String {
+    format!("{}{path}", base.trim_end_matches('/'))
+}
+
+/// The files to generate for the package, the ones it does not have already. `metadata` is of
+/// the documents of the package.
+pub async fn files(
+    config: &fastn_core::Config,
+    base: &str,
+    metadata: &[Metadata],
+    session_id: &Option<String>,
+) -> Vec<File> {
+    let mut pages = pages(&config.package);
+    let mut feeds = feeds(&config.package);
+    apply(&mut pages, metadata);
+    fill_dates(&mut pages, &config.ds, session_id).await;
+    for feed in feeds.iter_mut() {
+        apply(std::slice::from_mut(&mut feed.section), metadata);
+        apply(&mut feed.entries, metadata);
+        fill_dates(&mut feed.entries, &config.ds, session_id).await;
+        // newest first, the undated ones last in sitemap order
+        feed.entries.sort_by(|a, b| b.date.cmp(&a.date));
+    }
+
+    let mut files = vec![File {
+        path: ROBOTS_TXT.to_string(),
+        content_type: content_type(ROBOTS_TXT),
+        content: robots_txt(base, &pages, config.package.sitemap.is_some()),
+    }];
+    if config.package.sitemap.is_some() {
+        files.push(File {
+            path: SITEMAP_XML.to_string(),
+            content_type: content_type(SITEMAP_XML),
+            content: sitemap_xml(base, &pages),
+        });
+    }
+    for feed in feeds.iter() {
+        for kind in feed.kinds.iter() {
+            files.push(File {
+                path: format!(
+                    "{}{}",
+                    feed.section.path.trim_start_matches('/'),
+                    kind.file_name()
+                ),
+                content_type: content_type(kind.file_name()),
+                content: match kind {
+                    FeedKind::Rss => rss(base, feed),
+                    FeedKind::Atom => atom(base, feed),
+                },
+            });
+        }
+    }
+
+    let mut own = vec![];
+    for file in files {
+        if !config
+            .ds
+            .exists(&config.ds.root().join(file.path.as_str()), session_id)
+            .await
+        {
+            own.push(file);
+        }
+    }
+    own
+}
+
+fn content_type(path: &str) -> &'static str {
+    if path.ends_with(RSS_FEED) {
+        "application/rss+xml"
+    } else if path.ends_with(ATOM_FEED) {
+        "application/atom+xml"
+    } else if path.ends_with(".xml") {
+        "application/xml"
+    } else {
+        "text/plain"
+    }
+}
+
+/// The title, summary and date of the pages, from the metadata of their documents. The title in
+/// the sitemap is kept for the documents without one.
+fn apply(pages: &mut [Page], metadata: &[Metadata]) {
+    for page in pages.iter_mut() {
+        let Some(m) = metadata.iter().find(|m| m.url == page.path) else {
+            continue;
+        };
+        if m.title.is_some() {
+            page.title = m.title.clone();
+        }
+        page.summary = m.summary.clone();
+        page.date = m.date;
+    }
+}
+
+/// Pages without a `date` get the time their document was last modified.
+async fn fill_dates(pages: &mut [Page], ds: &fastn_ds::DocumentStore, session_id: &Option<String>) {
+    for page in pages.iter_mut() {
+        if page.date.is_some() {
+            continue;
+        }
+        if let Some(file_location) = page.file_location.as_ref() {
+            page.date = ds
+                .modified(file_location, session_id)
+                .await
+                .map(chrono::DateTime::<chrono::Utc>::from);
+        }
+    }
+}
+
+/// `fastn serve` response for the generated files, `None` for other paths.
+pub async fn serve(
+    config: &fastn_core::Config,
+    req: &fastn_core::http::Request,
+    session_id: &Option<String>,
+) -> Option<fastn_core::Result<fastn_core::http::Response>> {
+    let path = req.path().trim_start_matches('/');
+    if path != SITEMAP_XML
+        && path != ROBOTS_TXT
+        && !path.ends_with(RSS_FEED)
+        && !path.ends_with(ATOM_FEED)
+    {
+        return None;
+    }
+
+    // the package's own files are served as they are
+    if config
+        .ds
+        .exists(&config.ds.root().join(path), session_id)
+        .await
+    {
+        return None;
+    }
+
+    // what `fastn build` wrote, else the files from the sitemap alone: rendering the documents
+    // for their metadata would run their processors from a GET
+    let content = match config
+        .ds
+        .read_content(&config.build_dir().join(path), session_id)
+        .await
+    {
+        Ok(content) => content,
+        Err(_) => files(config, base(config).as_str(), &[], session_id)
+            .await
+            .into_iter()
+            .find(|f| f.path == path)?
+            .content
+            .into_bytes(),
+    };
+
+    Some(Ok(fastn_core::http::ok_with_content_type(
+        content,
+        content_type(path)
+            .parse()
+            .unwrap_or(mime_guess::mime::TEXT_PLAIN),
+    )))
+}
+
+/// The canonical url of the package, the urls in the generated files start with it.
+fn base(config: &fastn_core::Config) -> String {
+    config
+        .package
+        .canonical_url
+        .clone()
+        .unwrap_or_else(|| format!("https://{}", config.package.name))
+}
+
+/// Writes the generated files to `build_dir`, `metadata` is of the documents of the package.
+pub async fn build(
+    config: &fastn_core::Config,
+    build_dir: &fastn_ds::Path,
+    metadata: &[Metadata],
+    session_id: &Option<String>,
+) -> fastn_core::Result<()> {
+    let files = files(config, base(config).as_str(), metadata, session_id).await;
+    for file in files.iter() {
+        println!("Processing {} ... Done", file.path);
+    }
+    write(config, build_dir, &files).await
+}
+
+async fn write(
+    config: &fastn_core::Config,
+    build_dir: &fastn_ds::Path,
+    files: &[File],
+) -> fastn_core::Result<()> {
+    for file in files {
+        fastn_core::utils::update(
+            &build_dir.join(file.path.as_str()),
+            file.content.as_bytes(),
+            &config.ds,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub fn sitemap_xml(base: &str, pages: &[Page]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages.iter().filter(|p| p.public) {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            escape(&url(base, page.path.as_str()))
+        ));
+        if let Some(date) = page.date {
+            xml.push_str(&format!(
+                "    <lastmod>{}</lastmod>\n",
+                date.format("%Y-%m-%d")
+            ));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// The documents only some can read are disallowed, crawlers would only get the login page.
+///
+/// A `Disallow` rule is a prefix, so a private folder is disallowed as a whole only when none of
+/// the pages in it are public. Else only the page itself is, with a `$` rule, so a private `/`
+/// does not hide the whole site. `$` is understood by the major crawlers, the others skip such a
+/// rule and crawl the page.
+pub fn robots_txt(base: &str, pages: &[Page], has_sitemap: bool) -> String {
+    let mut txt = String::from("User-agent: *\n");
+    let mut private = pages.iter().filter(|p| !p.public).collect::<Vec<_>>();
+    if private.is_empty() {
+        txt.push_str("Allow: /\n");
+    }
+    // a folder comes before the pages in it
+    private.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut folders: Vec<&str> = vec![];
+    for page in private {
+        let path = page.path.as_str();
+        if folders.iter().any(|f| path.starts_with(f)) {
+            continue;
+        }
+        if path.ends_with('/') && !pages.iter().any(|p| p.public && p.path.starts_with(path)) {
+            folders.push(path);
+            txt.push_str(&format!("Disallow: {path}\n"));
+        } else {
+            txt.push_str(&format!("Disallow: {path}$\n"));
+        }
+    }
+    if has_sitemap {
+        txt.push_str(&format!("\nSitemap: {}\n", url(base, "/sitemap.xml")));
+    }
+    txt
+}
+
+pub fn rss(base: &str, feed: &Feed) -> String {
+    let link = url(base, feed.section.path.as_str());
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n  <channel>\n",
+    );
+    xml.push_str(&format!(
+        "    <title>{}</title>\n    <link>{}</link>\n    <description>{}</description>\n",
+        escape(feed.section.title.as_deref().unwrap_or_default()),
+        escape(&link),
+        escape(feed.section.summary.as_deref().unwrap_or_default()),
+    ));
+    xml.push_str(&format!(
+        "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\" />\n",
+        escape(&format!("{link}{RSS_FEED}"))
+    ));
+    if let Some(date) = updated(feed) {
+        xml.push_str(&format!(
+            "    <lastBuildDate>{}</lastBuildDate>\n",
+            date.to_rfc2822()
+        ));
+    }
+
+    for entry in feed.entries.iter() {
+        let link = url(base, entry.path.as_str());
+        xml.push_str("    <item>\n");
+        xml.push_str(&format!(
+            "      <title>{}</title>\n      <link>{}</link>\n      <guid>{}</guid>\n",
+            escape(entry.title.as_deref().unwrap_or(entry.path.as_str())),
+            escape(&link),
+            escape(&link),
+        ));
+        if let Some(summary) = entry.summary.as_deref() {
+            xml.push_str(&format!(
+                "      <description>{}</description>\n",
+                escape(summary)
+            ));
+        }
+        if let Some(date) = entry.date {
+            xml.push_str(&format!("      <pubDate>{}</pubDate>\n", date.to_rfc2822()));
+        }
+        xml.push_str("    </item>\n");
+    }
+
+    xml.push_str("  </channel>\n</rss>\n");
+    xml
+}
+
+pub fn atom(base: &str, feed: &Feed) -> String {
+    let link = url(base, feed.section.path.as_str());
+    // atom needs an `updated` for the feed and every entry, a feed without dates was updated
+    // when it was generated
+    let updated = updated(feed)
+        .unwrap_or_else(chrono::Utc::now)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+    xml.push_str(&format!(
+        "  <id>{}</id>\n  <title>{}</title>\n  <updated>{updated}</updated>\n",
+        escape(&link),
+        escape(feed.section.title.as_deref().unwrap_or_default()),
+    ));
+    if let Some(summary) = feed.section.summary.as_deref() {
+        xml.push_str(&format!("  <subtitle>{}</subtitle>\n", escape(summary)));
+    }
+    xml.push_str(&format!(
+        "  <link href=\"{}\" />\n  <link href=\"{}\" rel=\"self\" />\n",
+        escape(&link),
+        escape(&format!("{link}{ATOM_FEED}"))
+    ));
+
+    for entry in feed.entries.iter() {
+        let link = url(base, entry.path.as_str());
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!(
+            "    <id>{}</id>\n    <title>{}</title>\n    <link href=\"{}\" />\n",
+            escape(&link),
+            escape(entry.title.as_deref().unwrap_or(entry.path.as_str())),
+            escape(&link),
+        ));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            entry
+                .date
+                .map(|d| d.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .unwrap_or_else(|| updated.clone())
+        ));
+        if let Some(summary) = entry.summary.as_deref() {
+            xml.push_str(&format!("    <summary>{}</summary>\n", escape(summary)));
+        }
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// When the newest entry of the feed was published.
+fn updated(feed: &Feed) -> Option<chrono::DateTime<chrono::Utc>> {
+    feed.entries.iter().filter_map(|e| e.date).max()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    fn page(path: &str, title: &str, date: Option<&str>, public: bool) -> super::Page {
+        super::Page {
+            path: path.to_string(),
+            title: Some(title.to_string()),
+            date: date.and_then(super::parse_date),
+            summary: None,
+            file_location: None,
+            public,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            super::FeedKind::parse("rss, atom"),
+            vec![super::FeedKind::Rss, super::FeedKind::Atom]
+        );
+        assert_eq!(super::FeedKind::parse("true"), vec![super::FeedKind::Rss]);
+        assert_eq!(super::FeedKind::parse("Atom"), vec![super::FeedKind::Atom]);
+        assert_eq!(super::FeedKind::parse("false"), vec![]);
+
+        assert_eq!(
+            super::parse_date("2024-01-15").unwrap().to_rfc3339(),
+            "2024-01-15T00:00:00+00:00"
+        );
+        assert_eq!(
+            super::parse_date("2024-01-15T10:00:00+05:30")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-15T04:30:00+00:00"
+        );
+        assert_eq!(super::parse_date("15/01/2024"), None);
+    }
+
+    #[test]
+    fn page_of_id() {
+        let p = super::page("blog/hello/", &Some("Hello".to_string()), &None, &[]).unwrap();
+        assert_eq!(p.path, "/blog/hello/");
+        assert_eq!(p.title.as_deref(), Some("Hello"));
+        assert_eq!(p.date, None);
+
+        assert_eq!(super::page("/", &None, &None, &[]).unwrap().path, "/");
+        assert_eq!(
+            super::page("/a/b.html", &None, &None, &[]).unwrap().path,
+            "/a/b.html"
+        );
+        assert!(super::page("https://example.com/", &None, &None, &[]).is_none());
+        assert!(
+            super::page(
+                "/books/<string:name>/",
+                &None,
+                &None,
+                &[fastn_core::sitemap::PathParams::named(
+                    1,
+                    "name".to_string(),
+                    "string".to_string()
+                )]
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let mut pages = vec![
+            page("/blog/hello/", "Hello", None, true),
+            page("/blog/untitled/", "Untitled", None, true),
+            page("/about/", "About", None, true),
+        ];
+        super::apply(
+            &mut pages,
+            &[
+                super::Metadata {
+                    url: "/blog/hello/".to_string(),
+                    title: Some("Hello World".to_string()),
+                    summary: Some("The first post".to_string()),
+                    date: super::parse_date("2024-01-15"),
+                },
+                super::Metadata {
+                    url: "/blog/untitled/".to_string(),
+                    date: super::parse_date("2024-01-16"),
+                    ..Default::default()
+                },
+            ],
+        );
+
+        assert_eq!(pages[0].title.as_deref(), Some("Hello World"));
+        assert_eq!(pages[0].summary.as_deref(), Some("The first post"));
+        assert_eq!(pages[0].date, super::parse_date("2024-01-15"));
+        // the title in the sitemap, for a document without one
+        assert_eq!(pages[1].title.as_deref(), Some("Untitled"));
+        assert_eq!(pages[1].date, super::parse_date("2024-01-16"));
+        assert_eq!(pages[2], page("/about/", "About", None, true));
+    }
+
+    #[test]
+    fn sitemap_and_robots() {
+        let pages = vec![
+            page("/", "Home", Some("2024-01-15"), true),
+            page("/a&b/", "A & B", None, true),
+            page("/private/", "Private", None, false),
+        ];
+
+        assert_eq!(
+            super::sitemap_xml("https://example.com/", &pages),
+            indoc::indoc! {r#"
+                <?xml version="1.0" encoding="UTF-8"?>
+                <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                  <url>
+                    <loc>https://example.com/</loc>
+                    <lastmod>2024-01-15</lastmod>
+                  </url>
+                  <url>
+                    <loc>https://example.com/a&amp;b/</loc>
+                  </url>
+                </urlset>
+            "#}
+        );
+
+        assert_eq!(
+            super::robots_txt("https://example.com", &pages, true),
+            "User-agent: *\nDisallow: /private/\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+        assert_eq!(
+            super::robots_txt("https://example.com", &pages[..1], false),
+            "User-agent: *\nAllow: /\n"
+        );
+
+        // a private page with public pages under it does not hide them
+        let pages = vec![
+            page("/", "Home", None, false),
+            page("/docs/", "Docs", None, true),
+            page("/team/", "Team", None, false),
+            page("/team/notes/", "Notes", None, false),
+            page("/team/about.html", "About", None, false),
+            page("/blog/", "Blog", None, false),
+            page("/blog/hello/", "Hello", None, true),
+        ];
+        assert_eq!(
+            super::robots_txt("https://example.com", &pages, false),
+            "User-agent: *\nDisallow: /$\nDisallow: /blog/$\nDisallow: /team/\n"
+        );
+        // everything is private
+        assert_eq!(
+            super::robots_txt("https://example.com", &pages[..1], false),
+            "User-agent: *\nDisallow: /\n"
+        );
+    }
+
+    #[test]
+    fn feeds() {
+        let feed = super::Feed {
+            section: page("/blog/", "Blog", None, true),
+            kinds: vec![super::FeedKind::Rss, super::FeedKind::Atom],
+            entries: vec![
+                page("/blog/second/", "Second", Some("2024-02-01"), true),
+                page("/blog/first/", "First", None, true),
+            ],
+        };
+
+        let rss = super::rss("https://example.com", &feed);
+        assert!(rss.contains("<title>Blog</title>"), "{rss}");
+        assert!(
+            rss.contains("<atom:link href=\"https://example.com/blog/rss.xml\""),
+            "{rss}"
+        );
+        assert!(
+            rss.contains("<lastBuildDate>Thu, 1 Feb 2024 00:00:00 +0000</lastBuildDate>"),
+            "{rss}"
+        );
+        assert!(
+            rss.contains("<guid>https://example.com/blog/second/</guid>"),
+            "{rss}"
+        );
+        assert_eq!(rss.matches("<item>").count(), 2);
+        assert_eq!(rss.matches("<pubDate>").count(), 1);
+
+        let atom = super::atom("https://example.com", &feed);
+        assert!(
+            atom.contains("<id>https://example.com/blog/</id>"),
+            "{atom}"
+        );
+        assert!(
+            atom.contains("<link href=\"https://example.com/blog/atom.xml\" rel=\"self\" />"),
+            "{atom}"
+        );
+        // the undated entry gets the date of the feed
+        assert_eq!(
+            atom.matches("<updated>2024-02-01T00:00:00Z</updated>")
+                .count(),
+            3
+        );
+
+        // a feed without dates was updated when it was generated, not in 1970
+        let undated = super::Feed {
+            entries: vec![page("/blog/first/", "First", None, true)],
+            ..feed
+        };
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let atom = super::atom("https://example.com", &undated);
+        assert!(!atom.contains("1970"), "{atom}");
+        assert!(atom.contains(&format!("<updated>{today}T")), "{atom}");
+    }
+}
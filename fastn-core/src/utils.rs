@@ -598,14 +598,22 @@ async fn get_extra_js(
     session_id: &Option<String>,
 ) -> String {
     format!(
-        "{}{}{}{}",
+        "{}{}{}{}{}",
         get_external_js_html(external_js),
         get_inline_js_html(config, inline_js, session_id).await,
         js,
-        rive_data
+        rive_data,
+        get_live_reload_js_html(config),
     )
 }
 
+fn get_live_reload_js_html(config: &fastn_core::Config) -> String {
+    if !config.live_reload {
+        return "".to_string();
+    }
+    format!("<script>{}</script>", fastn_core::watcher::live_reload_js())
+}
+
 async fn get_extra_css(
     config: &fastn_core::Config,
     external_css: &[String],
@@ -822,7 +830,7 @@ pub async fn update1(
     };
 
     Ok(ds
-        .write_content(&root.join(file_root).join(file_name), data)
+        .write_content(&root.join(file_root).join(file_name), data, &None)
         .await?)
 }
 
@@ -854,7 +862,9 @@ pub async fn update(
         });
     };
 
-    Ok(ds.write_content(&file_root.join(file_name), data).await?)
+    Ok(ds
+        .write_content(&file_root.join(file_name), data, &None)
+        .await?)
 }
 
 pub(crate) fn ids_matches(id1: &str, id2: &str) -> bool {
@@ -897,11 +907,11 @@ pub async fn remove_except(
 ) -> fastn_core::Result<()> {
     use itertools::Itertools;
     let except = except.iter().map(|x| root.join(x)).collect_vec();
-    for path in ds.get_all_file_path(root, &[]).await {
+    for path in ds.get_all_file_path(root, &[], &None).await? {
         if except.contains(&path) {
             continue;
         }
-        ds.remove(&path).await?;
+        ds.remove(&path, &None).await?;
     }
     Ok(())
 }
@@ -1133,6 +1143,36 @@ pub async fn secret_key(ds: &fastn_ds::DocumentStore) -> String {
     }
 }
 
+/// The secret keys of the package, newest first: the comma separated `FASTN_SECRET_KEYS`, then
+/// the [secret_key]. Server side sessions are signed with these.
+pub async fn secret_keys(ds: &fastn_ds::DocumentStore) -> fastn_wasm::crypto::Keys {
+    let keys = ds.env("FASTN_SECRET_KEYS").await.unwrap_or_default();
+    let key = secret_key(ds).await;
+    fastn_wasm::crypto::Keys::new(keys.split(',').chain(std::iter::once(key.as_str())))
+}
+
 pub fn fifthtry_site_zip_url(site_slug: &str) -> String {
     format!("https://www.fifthtry.com/{site_slug}.zip")
 }
+
+/// `fastn_wasm::Limits` from the `wasm-fuel`, `wasm-memory` (megabytes) and `wasm-timeout`
+/// (seconds) headers of a `fastn.endpoint` or `fastn.app`. Missing values use the defaults, zero
+/// or negative values turn the limit off.
+pub fn wasm_limits(
+    fuel: Option<i64>,
+    memory: Option<i64>,
+    timeout: Option<i64>,
+) -> fastn_wasm::Limits {
+    let default = fastn_wasm::Limits::default();
+    let positive = |v: i64| u64::try_from(v).ok().filter(|v| *v > 0);
+
+    fastn_wasm::Limits {
+        fuel: fuel.map_or(default.fuel, positive),
+        memory: memory.map_or(default.memory, |v| {
+            positive(v).map(|mb| (mb as usize).saturating_mul(1024 * 1024))
+        }),
+        timeout: timeout.map_or(default.timeout, |v| {
+            positive(v).map(std::time::Duration::from_secs)
+        }),
+    }
+}
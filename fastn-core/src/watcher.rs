@@ -0,0 +1,234 @@
+/// Browser tabs opened against `fastn serve --watch` keep an `EventSource` open on this path, and
+/// reload themselves when the server sends an event on it. Like the other files fastn adds to a
+/// page (`default-<hash>.js` etc.) it is relative to the base url of the page, so it is served at
+/// the end of any path.
+pub const LIVE_RELOAD_PATH: &str = "/-/live-reload/";
+
+/// Brings `.packages` in line with the dependencies in `FASTN.ftd`, what `fastn update` does.
+/// fastn-update depends on this crate, so `fastn serve` passes it in.
+pub type UpdateDependencies = for<'a> fn(
+    &'a fastn_ds::DocumentStore,
+) -> futures::future::BoxFuture<'a, fastn_core::Result<()>>;
+
+/// How long we wait for more file system events before acting on a change. Editors tend to write
+/// a file in multiple steps (truncate, write, rename), we want to reload once per save.
+const DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Folders inside the package that are written by fastn itself, or are not part of the package.
+const IGNORED_FOLDERS: &[&str] = &[".build", ".git", "node_modules", "target"];
+
+/// Files written while serving: the sqlite db (migrations run on every config read, so watching
+/// it would reload forever), and editor backup files.
+const IGNORED_SUFFIXES: &[&str] = &[
+    ".sqlite",
+    ".sqlite-journal",
+    ".sqlite-wal",
+    ".sqlite-shm",
+    "~",
+];
+
+/// The config that is currently being served.
+///
+/// Without `--watch` this never changes. With `--watch` the watcher replaces the config whenever
+/// a file in the package changes, so edits to `FASTN.ftd` (dependencies, sitemap, redirects etc.)
+/// are picked up without restarting the server.
+pub struct SharedConfig {
+    config: antidote::RwLock<std::sync::Arc<fastn_core::Config>>,
+    reload: tokio::sync::broadcast::Sender<()>,
+}
+
+impl SharedConfig {
+    pub fn new(config: std::sync::Arc<fastn_core::Config>) -> Self {
+        let (reload, _) = tokio::sync::broadcast::channel(16);
+        Self {
+            config: antidote::RwLock::new(config),
+            reload,
+        }
+    }
+
+    pub fn get(&self) -> std::sync::Arc<fastn_core::Config> {
+        std::sync::Arc::clone(&self.config.read())
+    }
+
+    fn set(&self, config: fastn_core::Config) {
+        *self.config.write() = std::sync::Arc::new(config);
+    }
+
+    /// Tells every connected browser tab to reload.
+    fn notify(&self) {
+        // send only fails if there are no subscribers, which means no open tab, nothing to do
+        let _ = self.reload.send(());
+    }
+
+    /// Server-sent events stream, one `reload` event per change in the package.
+    pub fn live_reload_response(&self) -> fastn_core::http::Response {
+        let receiver = self.reload.subscribe();
+
+        let connected = futures::stream::once(async {
+            Ok::<_, actix_web::Error>(bytes::Bytes::from_static(b": connected\n\n"))
+        });
+
+        let events = futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(()) => {
+                        return Some((
+                            Ok::<_, actix_web::Error>(bytes::Bytes::from_static(
+                                b"event: reload\ndata: reload\n\n",
+                            )),
+                            receiver,
+                        ));
+                    }
+                    // we only care that something changed, not how many times
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        actix_web::HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .append_header(("Cache-Control", "no-cache"))
+            // the compress middleware buffers the body, which would hold back the events
+            .append_header((
+                actix_web::http::header::CONTENT_ENCODING,
+                actix_web::http::header::ContentEncoding::Identity,
+            ))
+            .streaming(futures::StreamExt::chain(connected, events))
+    }
+}
+
+/// The script added to every page rendered by `fastn serve --watch`.
+pub fn live_reload_js() -> String {
+    format!(
+        indoc::indoc! {r#"
+            (function () {{
+                if (!window.EventSource) {{
+                    return;
+                }}
+                let source = new EventSource("{path}");
+                source.addEventListener("reload", function () {{
+                    source.close();
+                    window.location.reload();
+                }});
+            }})();
+        "#},
+        // relative, so that it resolves against the `<base>` of the page
+        path = LIVE_RELOAD_PATH.trim_start_matches('/'),
+    )
+}
+
+/// Watches the package folder (including `.packages`), reloads the config and notifies the
+/// browser on every change. Runs till the server shuts down.
+///
+/// When `FASTN.ftd` of the package changes, `update_dependencies`, if given, runs before the
+/// config is read again, so that added or changed dependencies are downloaded.
+pub async fn watch(
+    shared: std::sync::Arc<SharedConfig>,
+    update_dependencies: Option<UpdateDependencies>,
+) -> fastn_core::Result<()> {
+    let root: std::path::PathBuf = shared.get().ds.root().to_string().into();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // the receiver goes away only when the server is shutting down
+        let _ = sender.send(event);
+    })?;
+    notify::Watcher::watch(&mut watcher, &root, notify::RecursiveMode::Recursive)?;
+
+    tracing::info!("watching {} for changes", root.display());
+
+    while let Some(event) = receiver.recv().await {
+        let mut changed = changed_paths(&root, event);
+
+        // wait for the editor to be done writing, and collect everything that changed meanwhile
+        while let Ok(Some(event)) = tokio::time::timeout(DEBOUNCE, receiver.recv()).await {
+            changed.extend(changed_paths(&root, event));
+        }
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        if let Some(update_dependencies) = update_dependencies
+            && changed.iter().any(|path| path == "FASTN.ftd")
+            && let Err(e) = update_dependencies(&shared.get().ds).await
+        {
+            // the config is still read again, the error is likely in the FASTN.ftd the user is
+            // editing and reading it will say so
+            eprintln!("failed to update dependencies: {e}");
+        }
+
+        // every document is read from disk on each request, but FASTN.ftd, the sitemap and the
+        // dependencies are read once in the config, so we read the config again
+        match shared.get().reload().await {
+            Ok(config) => shared.set(config),
+            Err(e) => {
+                // keep serving the last good config, the user will likely fix the file soon
+                eprintln!("failed to reload package: {e}");
+                continue;
+            }
+        }
+
+        shared.notify();
+    }
+
+    Ok(())
+}
+
+fn changed_paths(root: &std::path::Path, event: notify::Result<notify::Event>) -> Vec<String> {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::error!("watch error: {e}");
+            return vec![];
+        }
+    };
+
+    if !matches!(
+        event.kind,
+        notify::EventKind::Create(_) | notify::EventKind::Modify(_) | notify::EventKind::Remove(_)
+    ) {
+        return vec![];
+    }
+
+    event
+        .paths
+        .iter()
+        .filter_map(|path| path.strip_prefix(root).ok())
+        .filter(|path| !is_ignored(path))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn is_ignored(path: &std::path::Path) -> bool {
+    path.components().any(|c| match c {
+        std::path::Component::Normal(name) => name
+            .to_str()
+            .map(|name| {
+                IGNORED_FOLDERS.contains(&name)
+                    || IGNORED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+            })
+            .unwrap_or(false),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn is_ignored() {
+        assert!(super::is_ignored(std::path::Path::new(".build/index.html")));
+        assert!(super::is_ignored(std::path::Path::new(
+            "a/node_modules/b.js"
+        )));
+        assert!(super::is_ignored(std::path::Path::new("index.ftd~")));
+        assert!(super::is_ignored(std::path::Path::new(
+            "fastn.sqlite-journal"
+        )));
+        assert!(!super::is_ignored(std::path::Path::new("index.ftd")));
+        assert!(!super::is_ignored(std::path::Path::new(
+            ".packages/fastn-community.github.io/doc-site/index.ftd"
+        )));
+    }
+}
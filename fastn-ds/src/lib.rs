@@ -3,9 +3,12 @@
 
 extern crate self as fastn_ds;
 pub mod http;
+mod pg;
 pub mod reqwest_util;
+mod storage;
 mod user_data;
 mod utils;
+pub use storage::{LocalStorage, MemoryStorage, OverlayStorage, Storage};
 pub use user_data::UserDataError;
 
 #[derive(Debug, Clone)]
@@ -13,6 +16,10 @@ pub struct DocumentStore {
     pub wasm_modules: scc::HashMap<String, wasmtime::Module>,
     pub pg_pools: actix_web::web::Data<scc::HashMap<String, deadpool_postgres::Pool>>,
     root: Path,
+    storage: std::sync::Arc<dyn Storage>,
+    /// uncommitted edits of each preview session (see `create_preview_session`), layered over
+    /// `storage`
+    preview_sessions: std::sync::Arc<scc::HashMap<String, std::sync::Arc<OverlayStorage>>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -83,7 +90,7 @@ impl std::fmt::Display for fastn_ds::Path {
 
 fn package_ignores(
     ignore_paths: &[String],
-    root_path: &camino::Utf8PathBuf,
+    root_path: &camino::Utf8Path,
 ) -> Result<ignore::overrides::Override, ignore::Error> {
     let mut overrides = ignore::overrides::OverrideBuilder::new(root_path);
     for ignored_path in ignore_paths {
@@ -96,12 +103,16 @@ fn package_ignores(
 pub enum RemoveError {
     #[error("io error {0}")]
     IOError(#[from] std::io::Error),
+    #[error("{0}")]
+    UnknownPreviewSession(#[from] UnknownPreviewSession),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum RenameError {
     #[error("io error {0}")]
     IOError(#[from] std::io::Error),
+    #[error("{0}")]
+    UnknownPreviewSession(#[from] UnknownPreviewSession),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -124,6 +135,22 @@ pub enum ReadStringError {
 pub enum WriteError {
     #[error("pool error {0}")]
     IOError(#[from] std::io::Error),
+    #[error("{0}")]
+    UnknownPreviewSession(#[from] UnknownPreviewSession),
+}
+
+/// A change was made with the id of a preview session that was never created, or has been
+/// discarded already.
+#[derive(thiserror::Error, Debug)]
+#[error("preview session {0} does not exist")]
+pub struct UnknownPreviewSession(pub String);
+
+#[derive(thiserror::Error, Debug)]
+pub enum ListFilesError {
+    #[error("invalid ignore pattern: {0}")]
+    InvalidIgnorePattern(#[from] ignore::Error),
+    #[error("non utf-8 path: {0:?}")]
+    NonUtf8Path(std::path::PathBuf),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -132,6 +159,13 @@ pub enum WasmReadError {
     ReadError(#[from] ReadError),
     #[error("wasm error {0}")]
     WasmError(#[from] wasmtime::Error),
+    #[error(
+        "{path} was compiled by another version of fastn, run `fastn wasmc` on the .wasm again: {source}"
+    )]
+    StaleWasmc {
+        path: String,
+        source: wasmtime::Error,
+    },
     #[error("env error {0}")]
     BoolEnvironmentError(#[from] BoolEnvironmentError),
 }
@@ -197,27 +231,91 @@ impl DocumentStore {
                 .unwrap_or_else(|_| "sqlite:///fastn.sqlite".to_string()),
         };
 
-        let db_path = initialize_sqlite_db(&db_url).await?;
-
-        if let Some(p) = self.pg_pools.get(db_path.as_str()) {
-            return Ok(p.get().clone());
+        if !fastn_utils::sql::is_postgres(db_url.as_str()) {
+            return Err(fastn_utils::SqlError::UnknownDB.into());
         }
 
-        let pool = fastn_wasm::pg::create_pool(db_path.as_str()).await?;
-
-        fastn_wasm::insert_or_update(&self.pg_pools, db_path.to_string(), pool.clone());
-
-        Ok(pool)
+        Ok(self.pg_pool(db_url.as_str()).await?)
     }
 
     pub fn new<T: AsRef<camino::Utf8Path>>(
         root: T,
         pg_pools: actix_web::web::Data<scc::HashMap<String, deadpool_postgres::Pool>>,
+    ) -> Self {
+        Self::with_storage(root, pg_pools, std::sync::Arc::new(LocalStorage))
+    }
+
+    /// A document store backed by something other than the local disk, eg `MemoryStorage` to run
+    /// a package fully in memory in tests.
+    pub fn with_storage<T: AsRef<camino::Utf8Path>>(
+        root: T,
+        pg_pools: actix_web::web::Data<scc::HashMap<String, deadpool_postgres::Pool>>,
+        storage: std::sync::Arc<dyn Storage>,
     ) -> Self {
         Self {
             wasm_modules: Default::default(),
             pg_pools,
             root: Path::new(root.as_ref().as_str()),
+            storage,
+            preview_sessions: Default::default(),
+        }
+    }
+
+    /// The storage to use for a request: the overlay of the preview session
+    /// `preview_session_id`, if it has one, else the underlying storage.
+    fn storage(&self, preview_session_id: &Option<String>) -> std::sync::Arc<dyn Storage> {
+        preview_session_id
+            .as_ref()
+            .and_then(|id| self.preview_sessions.get(id))
+            .map(|overlay| overlay.get().clone() as std::sync::Arc<dyn Storage>)
+            .unwrap_or_else(|| self.storage.clone())
+    }
+
+    /// The absolute path of `path`, relative paths are relative to the package root. Every file
+    /// operation goes through this, whichever storage ends up handling it.
+    fn resolve(&self, path: &fastn_ds::Path) -> camino::Utf8PathBuf {
+        self.root.join(&path.path).path
+    }
+
+    /// Starts a preview session and returns its id. Changes made with this id as the
+    /// `preview_session_id` are only seen by requests made with the same id, they never reach
+    /// the underlying storage. The caller has to `discard_preview_session` once it is done.
+    pub async fn create_preview_session(&self) -> String {
+        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+        let id = format!(
+            "preview-{}",
+            NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        );
+        let _ = self
+            .preview_sessions
+            .insert_async(
+                id.clone(),
+                std::sync::Arc::new(OverlayStorage::new(self.storage.clone())),
+            )
+            .await;
+        id
+    }
+
+    /// Drops all the uncommitted edits of a preview session.
+    pub async fn discard_preview_session(&self, preview_session_id: &str) {
+        self.preview_sessions.remove_async(preview_session_id).await;
+    }
+
+    /// The storage changes made for a request go to: the preview session's overlay, so they
+    /// never reach the underlying storage, or the underlying storage if the request is not made
+    /// in a preview session. Changes for a session that does not exist (anymore) are refused
+    /// instead of silently going to the underlying storage.
+    fn storage_mut(
+        &self,
+        preview_session_id: &Option<String>,
+    ) -> Result<std::sync::Arc<dyn Storage>, UnknownPreviewSession> {
+        match preview_session_id {
+            Some(id) => match self.preview_sessions.get(id) {
+                Some(overlay) => Ok(overlay.get().clone()),
+                None => Err(UnknownPreviewSession(id.to_string())),
+            },
+            None => Ok(self.storage.clone()),
         }
     }
 
@@ -225,39 +323,39 @@ impl DocumentStore {
     pub async fn get_wasm(
         &self,
         path: &str,
-        _session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<wasmtime::Module, WasmReadError> {
         // TODO: implement wasm module on disc caching, so modules load faster across
         //       cache purge
-        match self.wasm_modules.get(path) {
-            Some(module) => Ok(module.get().clone()),
-            None => {
-                let wasmc_path = fastn_ds::Path::new(format!("{path}c").as_str());
-                let module = match unsafe {
-                    wasmtime::Module::from_trusted_file(&fastn_wasm::WASM_ENGINE, &wasmc_path.path)
-                } {
-                    Ok(m) => m,
-                    Err(e) => {
-                        tracing::debug!(
-                            "could not read {wasmc_path:?} file: {e:?}, trying to read {path:?} file"
-                        );
-                        let source = self.read_content(&fastn_ds::Path::new(path), &None).await?;
-                        wasmtime::Module::from_binary(&fastn_wasm::WASM_ENGINE, &source)?
-                    }
-                };
-
-                // we are only storing compiled module if we are not in debug mode
-                if !self.env_bool("FASTN_DEBUG", false).await? {
-                    fastn_wasm::insert_or_update(
-                        &self.wasm_modules,
-                        path.to_string(),
-                        module.clone(),
-                    )
-                }
-
-                Ok(module)
-            }
+        let path = self.resolve(&fastn_ds::Path::new(path));
+        if let Some(module) = self.wasm_modules.get(path.as_str()) {
+            return Ok(module.get().clone());
+        }
+
+        let storage = self.storage(preview_session_id);
+        let wasmc_path = camino::Utf8PathBuf::from(format!("{path}c"));
+        let module = if storage.exists(&wasmc_path).await {
+            let compiled = storage.read(&wasmc_path).await?;
+            // wasmtime refuses modules compiled by another version or with other engine
+            // settings, compiling the .wasm instead would hide that the .wasmc is stale
+            unsafe { wasmtime::Module::deserialize(&fastn_wasm::WASM_ENGINE, compiled) }.map_err(
+                |source| WasmReadError::StaleWasmc {
+                    path: wasmc_path.to_string(),
+                    source,
+                },
+            )?
+        } else {
+            let source = storage.read(&path).await?;
+            wasmtime::Module::from_binary(&fastn_wasm::WASM_ENGINE, &source)?
+        };
+
+        // we are only storing compiled module if we are not in debug mode, and never the modules
+        // of a preview session, its .wasm may not be the one on disk
+        if preview_session_id.is_none() && !self.env_bool("FASTN_DEBUG", false).await? {
+            fastn_wasm::insert_or_update(&self.wasm_modules, path.to_string(), module.clone())
         }
+
+        Ok(module)
     }
 
     pub async fn sql_query(
@@ -266,6 +364,10 @@ impl DocumentStore {
         query: &str,
         params: &[ft_sys_shared::SqliteRawValue],
     ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        if fastn_utils::sql::is_postgres(db_url) {
+            return self.pg_query(db_url, query, params).await;
+        }
+
         let db_path = initialize_sqlite_db(db_url).await?;
         let conn = rusqlite::Connection::open_with_flags(
             db_path,
@@ -288,6 +390,10 @@ impl DocumentStore {
         query: &str,
         params: &[ft_sys_shared::SqliteRawValue],
     ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        if fastn_utils::sql::is_postgres(db_url) {
+            return self.pg_execute(db_url, query, params).await;
+        }
+
         let db_path = initialize_sqlite_db(db_url).await?;
         let conn = rusqlite::Connection::open_with_flags(
             db_path,
@@ -307,6 +413,10 @@ impl DocumentStore {
         db_url: &str,
         query: &str,
     ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        if fastn_utils::sql::is_postgres(db_url) {
+            return self.pg_batch(db_url, query).await;
+        }
+
         let db_path = initialize_sqlite_db(db_url).await?;
         let conn = rusqlite::Connection::open_with_flags(
             db_path,
@@ -339,35 +449,26 @@ impl DocumentStore {
     pub async fn read_content(
         &self,
         path: &fastn_ds::Path,
-        _session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<Vec<u8>, ReadError> {
-        use tokio::io::AsyncReadExt;
-
         tracing::debug!("read_content {}", &path);
 
-        let mut file = tokio::fs::File::open(self.root.join(&path.path).path)
-            .await
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    ReadError::NotFound(path.to_string())
-                } else {
-                    ReadError::IOError(e, path.to_string())
-                }
-            })?;
-        let mut contents = vec![];
-        file.read_to_end(&mut contents)
+        self.storage(preview_session_id)
+            .read(&self.resolve(path))
             .await
-            .map_err(|e| ReadError::IOError(e, path.to_string()))?;
-        Ok(contents)
+            .map_err(|e| match e {
+                ReadError::NotFound(_) => ReadError::NotFound(path.to_string()),
+                ReadError::IOError(e, _) => ReadError::IOError(e, path.to_string()),
+            })
     }
 
     // #[tracing::instrument]
     pub async fn read_to_string(
         &self,
         path: &fastn_ds::Path,
-        session_id: &Option<String>,
+        preview_session_id: &Option<String>,
     ) -> Result<String, ReadStringError> {
-        self.read_content(path, session_id)
+        self.read_content(path, preview_session_id)
             .await
             .map_err(ReadStringError::ReadError)
             .and_then(|v| {
@@ -375,90 +476,98 @@ impl DocumentStore {
             })
     }
 
-    pub async fn copy(&self, from: &fastn_ds::Path, to: &fastn_ds::Path) -> Result<(), WriteError> {
+    pub async fn copy(
+        &self,
+        from: &fastn_ds::Path,
+        to: &fastn_ds::Path,
+        preview_session_id: &Option<String>,
+    ) -> Result<(), WriteError> {
         tracing::debug!("copy from {} to {}", from, to);
 
-        tokio::fs::copy(&from.path, &to.path).await?;
-        Ok(())
+        self.storage_mut(preview_session_id)?
+            .copy(&self.resolve(from), &self.resolve(to))
+            .await
     }
 
     pub async fn write_content(
         &self,
         path: &fastn_ds::Path,
         data: &[u8],
+        preview_session_id: &Option<String>,
     ) -> Result<(), WriteError> {
-        use tokio::io::AsyncWriteExt;
-
         tracing::debug!("write_content {}", &path);
 
-        let full_path = self.root.join(&path.path);
-
-        // Create the directory if it doesn't exist
-        if let Some(parent) = full_path.parent()
-            && !parent.path.exists()
-        {
-            tokio::fs::create_dir_all(parent.path).await?;
-        }
-
-        let mut file = tokio::fs::File::create(full_path.path).await?;
-        file.write_all(data).await?;
-        Ok(())
+        self.storage_mut(preview_session_id)?
+            .write(&self.resolve(path), data)
+            .await
     }
 
-    pub async fn read_dir(&self, path: &fastn_ds::Path) -> std::io::Result<tokio::fs::ReadDir> {
-        // Todo: Return type should be ftd::interpreter::Result<Vec<fastn_ds::Dir>> not ftd::interpreter::Result<tokio::fs::ReadDir>
+    pub async fn read_dir(
+        &self,
+        path: &fastn_ds::Path,
+        preview_session_id: &Option<String>,
+    ) -> std::io::Result<Vec<fastn_ds::Path>> {
         tracing::debug!("read_dir {}", &path);
 
-        tokio::fs::read_dir(&path.path).await
+        Ok(self
+            .storage(preview_session_id)
+            .read_dir(&self.resolve(path))
+            .await?
+            .into_iter()
+            .map(|path| fastn_ds::Path { path })
+            .collect())
     }
 
     pub async fn rename(
         &self,
         from: &fastn_ds::Path,
         to: &fastn_ds::Path,
+        preview_session_id: &Option<String>,
     ) -> Result<(), RenameError> {
-        Ok(tokio::fs::rename(&from.path, &to.path).await?)
+        self.storage_mut(preview_session_id)?
+            .rename(&self.resolve(from), &self.resolve(to))
+            .await
     }
 
-    pub async fn remove(&self, path: &fastn_ds::Path) -> Result<(), RemoveError> {
-        if !path.path.exists() {
-            return Ok(());
-        }
-        if path.path.is_file() {
-            tokio::fs::remove_file(&path.path).await?;
-        } else if path.path.is_dir() {
-            tokio::fs::remove_dir_all(&path.path).await?
-        } else if path.path.is_symlink() {
-            todo!("symlinks are not handled yet")
-        }
-        Ok(())
+    pub async fn remove(
+        &self,
+        path: &fastn_ds::Path,
+        preview_session_id: &Option<String>,
+    ) -> Result<(), RemoveError> {
+        self.storage_mut(preview_session_id)?
+            .remove(&self.resolve(path))
+            .await
     }
 
     pub async fn get_all_file_path(
         &self,
         path: &fastn_ds::Path,
         ignore_paths: &[String],
-    ) -> Vec<fastn_ds::Path> {
-        let path = &path.path;
-        let mut ignore_path = ignore::WalkBuilder::new(path);
-        // ignore_paths.hidden(false); // Allow the linux hidden files to be evaluated
-        ignore_path.overrides(package_ignores(ignore_paths, path).unwrap());
-        ignore_path
-            .build()
-            .flatten()
-            .filter_map(|x| {
-                let path = camino::Utf8PathBuf::from_path_buf(x.into_path()).unwrap();
-                if path.is_dir() {
-                    None
-                } else {
-                    Some(fastn_ds::Path { path })
-                }
-            }) //todo: improve error message
-            .collect::<Vec<fastn_ds::Path>>()
-    }
-
-    pub async fn exists(&self, path: &fastn_ds::Path, _session_id: &Option<String>) -> bool {
-        path.path.exists()
+        preview_session_id: &Option<String>,
+    ) -> Result<Vec<fastn_ds::Path>, ListFilesError> {
+        Ok(self
+            .storage(preview_session_id)
+            .list_files(&self.resolve(path), ignore_paths)
+            .await?
+            .into_iter()
+            .map(|path| fastn_ds::Path { path })
+            .collect())
+    }
+
+    pub async fn exists(&self, path: &fastn_ds::Path, preview_session_id: &Option<String>) -> bool {
+        self.storage(preview_session_id)
+            .exists(&self.resolve(path))
+            .await
+    }
+
+    pub async fn modified(
+        &self,
+        path: &fastn_ds::Path,
+        preview_session_id: &Option<String>,
+    ) -> Option<std::time::SystemTime> {
+        self.storage(preview_session_id)
+            .modified(&self.resolve(path))
+            .await
     }
 
     pub async fn env_bool(&self, key: &str, default: bool) -> Result<bool, BoolEnvironmentError> {
@@ -474,7 +583,10 @@ impl DocumentStore {
         std::env::var(key).map_err(|_| EnvironmentError::NotSet(key.to_string()))
     }
 
-    #[tracing::instrument(skip(self))]
+    /// Runs the wasm app at `wasm_url` for `req`. `secret_keys` are the keys of the package, for
+    /// the session cookie.
+    #[tracing::instrument(skip(self, secret_keys))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn handle_wasm<T>(
         &self,
         main_package: String,
@@ -482,7 +594,9 @@ impl DocumentStore {
         req: &T,
         app_url: String,
         app_mounts: std::collections::HashMap<String, String>,
-        session_id: &Option<String>,
+        limits: fastn_wasm::Limits,
+        preview_session_id: &Option<String>,
+        secret_keys: fastn_wasm::crypto::Keys,
     ) -> Result<ft_sys_shared::Request, HttpError>
     where
         T: RequestType,
@@ -495,15 +609,11 @@ impl DocumentStore {
             .unwrap_or_else(|| main_package.clone());
 
         let module = self
-            .get_wasm(format!("{wasm_file}.wasm").as_str(), session_id)
+            .get_wasm(format!("{wasm_file}.wasm").as_str(), preview_session_id)
             .await?;
 
-        let db_url = self
-            .env("DATABASE_URL")
-            .await
-            .unwrap_or_else(|_| "sqlite:///fastn.sqlite".to_string());
-
-        let db_path = initialize_sqlite_db(db_url.as_str())
+        let db_path = self
+            .wasm_db_path()
             .await
             .inspect_err(|e| tracing::error!("failed to create db: {e}"))?;
 
@@ -526,18 +636,58 @@ impl DocumentStore {
             fastn_wasm::StoreImpl,
             app_url,
             app_mounts,
+            secret_keys,
+            self.tejar().await,
         );
-        Ok(fastn_wasm::process_http_request(&wasm_url, module, store).await?)
+        Ok(fastn_wasm::process_http_request(&wasm_url, module, store, limits).await?)
+    }
+
+    /// The blob store of the wasm apps, `.fastn/tejar` in the package. `FASTN_TEJAR_MAX_SIZE` (in
+    /// bytes) limits the size of a blob.
+    pub async fn tejar(&self) -> fastn_wasm::tejar::BlobStore {
+        let max_size = self
+            .env("FASTN_TEJAR_MAX_SIZE")
+            .await
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(fastn_wasm::tejar::DEFAULT_MAX_SIZE);
+        fastn_wasm::tejar::BlobStore::new(self.root.join(".fastn/tejar").path, max_size)
+    }
+
+    /// The sqlite db wasm apps get when they connect to the `default` db. The outgoing email
+    /// queue lives here too.
+    pub async fn wasm_db_path(&self) -> Result<String, fastn_utils::SqlError> {
+        let db_url = self
+            .env("DATABASE_URL")
+            .await
+            .unwrap_or_else(|_| "sqlite:///fastn.sqlite".to_string());
+
+        initialize_sqlite_db(db_url.as_str()).await
     }
 
     // This method will connect client request to the out of the world
-    #[tracing::instrument(skip(req, extra_headers))]
     pub async fn http<T>(
         &self,
         url: url::Url,
         req: &T,
         extra_headers: &std::collections::HashMap<String, String>,
     ) -> Result<fastn_ds::HttpResponse, HttpError>
+    where
+        T: RequestType,
+    {
+        self.http_with_timeout(url, req, extra_headers, None).await
+    }
+
+    /// Same as `http`, but gives up with an error if the whole request, body included, takes
+    /// longer than `timeout`.
+    #[tracing::instrument(skip(req, extra_headers))]
+    pub async fn http_with_timeout<T>(
+        &self,
+        url: url::Url,
+        req: &T,
+        extra_headers: &std::collections::HashMap<String, String>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<fastn_ds::HttpResponse, HttpError>
     where
         T: RequestType,
     {
@@ -610,6 +760,7 @@ impl DocumentStore {
         );
 
         *proxy_request.body_mut() = Some(req.body().to_vec().into());
+        *proxy_request.timeout_mut() = timeout;
         let response = fastn_ds::http::DEFAULT_CLIENT
             .execute(proxy_request)
             .await?;
@@ -661,3 +812,88 @@ fn home() -> camino::Utf8PathBuf {
     };
     camino::Utf8PathBuf::from_path_buf(home).expect("Issue while reading your home directory")
 }
+
+#[cfg(test)]
+mod tests {
+    fn names(paths: Vec<fastn_ds::Path>) -> Vec<String> {
+        let mut names: Vec<_> = paths.iter().map(|p| p.to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn preview_session() {
+        let ds = fastn_ds::DocumentStore::with_storage(
+            "/pkg",
+            actix_web::web::Data::new(Default::default()),
+            std::sync::Arc::new(fastn_ds::MemoryStorage::with_files([
+                ("/pkg/index.ftd", "-- ftd.text: hello"),
+                ("/pkg/posts/first.ftd", "-- ftd.text: first"),
+            ])),
+        );
+        let session = Some(ds.create_preview_session().await);
+        let posts = ds.root().join("posts");
+        let second = ds.root().join("posts/second.ftd");
+        let index = ds.root().join("index.ftd");
+
+        ds.write_content(&second, b"-- ftd.text: second", &session)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            names(ds.get_all_file_path(&posts, &[], &session).await.unwrap()),
+            vec!["/pkg/posts/first.ftd", "/pkg/posts/second.ftd"]
+        );
+        assert_eq!(
+            names(ds.get_all_file_path(&posts, &[], &None).await.unwrap()),
+            vec!["/pkg/posts/first.ftd"]
+        );
+        assert_eq!(
+            names(ds.read_dir(&posts, &session).await.unwrap()),
+            vec!["/pkg/posts/first.ftd", "/pkg/posts/second.ftd"]
+        );
+        assert!(ds.exists(&second, &session).await);
+        assert!(!ds.exists(&second, &None).await);
+
+        // paths relative to the package root work the same
+        let relative = fastn_ds::Path {
+            path: camino::Utf8PathBuf::from("posts/second.ftd"),
+        };
+        assert!(ds.exists(&relative, &session).await);
+
+        // changes made in the session stay in the session
+        ds.remove(&index, &session).await.unwrap();
+        ds.write_content(&relative, b"-- ftd.text: edited", &session)
+            .await
+            .unwrap();
+        assert_eq!(
+            names(
+                ds.get_all_file_path(&ds.root(), &[], &session)
+                    .await
+                    .unwrap()
+            ),
+            vec!["/pkg/posts/first.ftd", "/pkg/posts/second.ftd"]
+        );
+        assert_eq!(
+            ds.read_content(&second, &session).await.unwrap(),
+            b"-- ftd.text: edited"
+        );
+        assert_eq!(
+            names(ds.get_all_file_path(&ds.root(), &[], &None).await.unwrap()),
+            vec!["/pkg/index.ftd", "/pkg/posts/first.ftd"]
+        );
+
+        // once discarded, the edits are gone, and changes made with the old id are refused
+        // instead of reaching the underlying storage
+        ds.discard_preview_session(session.as_deref().unwrap())
+            .await;
+        assert!(ds.exists(&index, &session).await);
+        assert!(!ds.exists(&second, &session).await);
+        assert!(matches!(
+            ds.write_content(&second, b"-- ftd.text: second", &session)
+                .await,
+            Err(fastn_ds::WriteError::UnknownPreviewSession(_))
+        ));
+        assert!(!ds.exists(&second, &None).await);
+    }
+}
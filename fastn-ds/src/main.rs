@@ -1,5 +1,6 @@
 #[tokio::main]
 async fn main() {
+    fastn_wasm::start_epoch_ticker();
     let req = ft_sys_shared::Request {
         uri: "/".to_string(),
         method: "get".to_string(),
@@ -26,8 +27,10 @@ async fn main() {
         fastn_wasm::StoreImpl,
         "/".to_string(),
         Default::default(),
+        Default::default(),
+        fastn_wasm::tejar::BlobStore::new(".fastn/tejar", fastn_wasm::tejar::DEFAULT_MAX_SIZE),
     );
-    let resp = fastn_wasm::process_http_request("/", module, store)
+    let resp = fastn_wasm::process_http_request("/", module, store, Default::default())
         .await
         .unwrap();
 
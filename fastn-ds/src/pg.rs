@@ -0,0 +1,571 @@
+//! Postgres side of `sql_query`, `sql_execute` and `sql_batch`, used when the db url is a
+//! `postgres://` url.
+//!
+//! The processors resolve named parameters to `SqliteRawValue`s, and header values are always
+//! text, so binds are converted to whatever type Postgres inferred for the placeholder. Rows come
+//! back with the same json typing SQLite rows get, so `result_to_value` works for both.
+
+impl fastn_ds::DocumentStore {
+    /// One pool per db url, shared with the wasm apps through `pg_pools`.
+    pub(crate) async fn pg_pool(
+        &self,
+        db_url: &str,
+    ) -> Result<deadpool_postgres::Pool, fastn_utils::SqlError> {
+        if let Some(p) = self.pg_pools.get(db_url) {
+            return Ok(p.get().clone());
+        }
+
+        let pool = fastn_wasm::pg::create_pool(db_url)
+            .await
+            .map_err(fastn_utils::SqlError::PgCreatePool)?;
+
+        fastn_wasm::insert_or_update(&self.pg_pools, db_url.to_string(), pool.clone());
+
+        Ok(pool)
+    }
+
+    pub(crate) async fn pg_query(
+        &self,
+        db_url: &str,
+        query: &str,
+        params: &[ft_sys_shared::SqliteRawValue],
+    ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        let mut client = self
+            .pg_pool(db_url)
+            .await?
+            .get()
+            .await
+            .map_err(fastn_utils::SqlError::PgPool)?;
+
+        // sql-query opens sqlite databases read only, a read only transaction is the closest
+        // postgres has
+        let transaction = client
+            .build_transaction()
+            .read_only(true)
+            .start()
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+        let stmt = transaction
+            .prepare_cached(query)
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+        let binds = binds(params);
+        let rows = transaction
+            .query(&stmt, &pg_args(&binds))
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+        transaction
+            .commit()
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+
+        rows.iter().map(row_to_json).collect()
+    }
+
+    pub(crate) async fn pg_execute(
+        &self,
+        db_url: &str,
+        query: &str,
+        params: &[ft_sys_shared::SqliteRawValue],
+    ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        let client = self
+            .pg_pool(db_url)
+            .await?
+            .get()
+            .await
+            .map_err(fastn_utils::SqlError::PgPool)?;
+
+        let stmt = client
+            .prepare_cached(query)
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+        let binds = binds(params);
+        let count = client
+            .execute(&stmt, &pg_args(&binds))
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+
+        Ok(vec![vec![count.into()]])
+    }
+
+    pub(crate) async fn pg_batch(
+        &self,
+        db_url: &str,
+        query: &str,
+    ) -> Result<Vec<Vec<serde_json::Value>>, fastn_utils::SqlError> {
+        let client = self
+            .pg_pool(db_url)
+            .await?
+            .get()
+            .await
+            .map_err(fastn_utils::SqlError::PgPool)?;
+
+        client
+            .batch_execute(query)
+            .await
+            .map_err(fastn_utils::SqlError::Pg)?;
+
+        // same as sqlite, the processor has to return something
+        Ok(vec![vec![1.into()]])
+    }
+}
+
+fn binds(params: &[ft_sys_shared::SqliteRawValue]) -> Vec<Bind<'_>> {
+    params.iter().map(Bind).collect()
+}
+
+fn pg_args<'a>(binds: &'a [Bind<'a>]) -> Vec<&'a (dyn tokio_postgres::types::ToSql + Sync)> {
+    binds
+        .iter()
+        .map(|b| b as &(dyn tokio_postgres::types::ToSql + Sync))
+        .collect()
+}
+
+/// A named parameter, converted to the type of the placeholder it is bound to.
+#[derive(Debug)]
+struct Bind<'a>(&'a ft_sys_shared::SqliteRawValue);
+
+impl tokio_postgres::types::ToSql for Bind<'_> {
+    fn to_sql(
+        &self,
+        ty: &tokio_postgres::types::Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        match (self.0, ty) {
+            (ft_sys_shared::SqliteRawValue::Null, _) => Ok(tokio_postgres::types::IsNull::Yes),
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::INT2) => {
+                write(i16::try_from(*v)?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::INT4) => {
+                write(i32::try_from(*v)?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::INT8) => {
+                write(*v, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::FLOAT4) => {
+                write(*v as f32, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::FLOAT8) => {
+                write(*v as f64, ty, out)
+            }
+            // booleans are bound as 0 / 1, that is how sqlite stores them
+            (ft_sys_shared::SqliteRawValue::Integer(v), &tokio_postgres::types::Type::BOOL) => {
+                write(*v != 0, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Real(v), &tokio_postgres::types::Type::FLOAT4) => {
+                write(*v as f32, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Real(v), &tokio_postgres::types::Type::FLOAT8) => {
+                write(*v, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::INT2) => {
+                write(v.trim().parse::<i16>()?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::INT4) => {
+                write(v.trim().parse::<i32>()?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::INT8) => {
+                write(v.trim().parse::<i64>()?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::FLOAT4) => {
+                write(v.trim().parse::<f32>()?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::FLOAT8) => {
+                write(v.trim().parse::<f64>()?, ty, out)
+            }
+            (ft_sys_shared::SqliteRawValue::Text(v), &tokio_postgres::types::Type::BOOL) => {
+                let v = match v.trim() {
+                    "true" | "1" => true,
+                    "false" | "0" => false,
+                    v => return Err(format!("can not bind `{v}` to a postgres BOOL").into()),
+                };
+                write(v, ty, out)
+            }
+            (
+                ft_sys_shared::SqliteRawValue::Text(v),
+                &tokio_postgres::types::Type::JSON | &tokio_postgres::types::Type::JSONB,
+            ) => write(serde_json::from_str::<serde_json::Value>(v)?, ty, out),
+            (ft_sys_shared::SqliteRawValue::Blob(v), &tokio_postgres::types::Type::BYTEA) => {
+                write(v.as_slice(), ty, out)
+            }
+            (v, ty) if <&str as tokio_postgres::types::ToSql>::accepts(ty) => match v {
+                ft_sys_shared::SqliteRawValue::Integer(v) => write(v.to_string(), ty, out),
+                ft_sys_shared::SqliteRawValue::Real(v) => write(v.to_string(), ty, out),
+                ft_sys_shared::SqliteRawValue::Text(v) => write(v.as_str(), ty, out),
+                v => Err(format!("can not bind {v:?} to a postgres {ty}").into()),
+            },
+            (v, ty) => Err(format!("can not bind {v:?} to a postgres {ty}").into()),
+        }
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
+fn write<T: tokio_postgres::types::ToSql>(
+    v: T,
+    ty: &tokio_postgres::types::Type,
+    out: &mut bytes::BytesMut,
+) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+    v.to_sql(ty, out)
+}
+
+/// Same json typing as `fastn_utils::row_to_json` gives sqlite rows.
+fn row_to_json(r: &tokio_postgres::Row) -> Result<Vec<serde_json::Value>, fastn_utils::SqlError> {
+    let mut row: Vec<serde_json::Value> = Vec::with_capacity(r.len());
+    for (i, column) in r.columns().iter().enumerate() {
+        let value = match column.type_() {
+            &tokio_postgres::types::Type::BOOL => get::<bool>(r, i)?.map(serde_json::Value::Bool),
+            &tokio_postgres::types::Type::INT2 => get::<i16>(r, i)?.map(Into::into),
+            &tokio_postgres::types::Type::INT4 => get::<i32>(r, i)?.map(Into::into),
+            &tokio_postgres::types::Type::INT8 => get::<i64>(r, i)?.map(Into::into),
+            &tokio_postgres::types::Type::FLOAT4 => get::<f32>(r, i)?
+                .and_then(|v| serde_json::Number::from_f64(v as f64))
+                .map(serde_json::Value::Number),
+            &tokio_postgres::types::Type::FLOAT8 => get::<f64>(r, i)?
+                .and_then(serde_json::Number::from_f64)
+                .map(serde_json::Value::Number),
+            &tokio_postgres::types::Type::OID => get::<u32>(r, i)?.map(Into::into),
+            // an integer if it is one, like sqlite, which stores NUMERIC as INTEGER or REAL
+            &tokio_postgres::types::Type::NUMERIC => {
+                get::<Numeric>(r, i)?.map(|Numeric(v)| match v.parse::<i64>() {
+                    Ok(v) => v.into(),
+                    Err(_) => v
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                        .map(serde_json::Value::Number)
+                        // NaN and Infinity
+                        .unwrap_or(serde_json::Value::String(v)),
+                })
+            }
+            &tokio_postgres::types::Type::TEXT
+            | &tokio_postgres::types::Type::VARCHAR
+            | &tokio_postgres::types::Type::BPCHAR
+            | &tokio_postgres::types::Type::NAME => {
+                get::<String>(r, i)?.map(serde_json::Value::String)
+            }
+            &tokio_postgres::types::Type::JSON | &tokio_postgres::types::Type::JSONB => {
+                get::<serde_json::Value>(r, i)?
+            }
+            // dates and times are text in sqlite too
+            &tokio_postgres::types::Type::TIMESTAMP => {
+                get::<chrono::NaiveDateTime>(r, i)?.map(|v| v.to_string().into())
+            }
+            &tokio_postgres::types::Type::TIMESTAMPTZ => {
+                get::<chrono::DateTime<chrono::Utc>>(r, i)?.map(|v| v.to_rfc3339().into())
+            }
+            &tokio_postgres::types::Type::DATE => {
+                get::<chrono::NaiveDate>(r, i)?.map(|v| v.to_string().into())
+            }
+            &tokio_postgres::types::Type::TIME => {
+                get::<chrono::NaiveTime>(r, i)?.map(|v| v.to_string().into())
+            }
+            &tokio_postgres::types::Type::UUID => {
+                get::<uuid::Uuid>(r, i)?.map(|v| v.to_string().into())
+            }
+            t => return Err(fastn_utils::SqlError::PgUnsupportedType(t.to_string())),
+        };
+        row.push(value.unwrap_or(serde_json::Value::Null));
+    }
+    Ok(row)
+}
+
+/// The decimal text of a NUMERIC, tokio-postgres only reads it into `rust_decimal`, which can
+/// not hold every NUMERIC.
+#[derive(Debug)]
+struct Numeric(String);
+
+impl<'a> tokio_postgres::types::FromSql<'a> for Numeric {
+    /// The binary format is that of `numeric_send` in postgres: the number of digits, the weight
+    /// of the first one, the sign and the number of decimal places, all `i16`, then the digits,
+    /// `i16`s in base 10000.
+    fn from_sql(
+        _ty: &tokio_postgres::types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let word = |i: usize| {
+            raw.get(i * 2..i * 2 + 2)
+                .map(|v| i16::from_be_bytes([v[0], v[1]]))
+                .ok_or("invalid NUMERIC")
+        };
+        let (count, weight, sign, scale) = (word(0)?, word(1)?, word(2)? as u16, word(3)?);
+        let sign = match sign {
+            0x0000 => "",
+            0x4000 => "-",
+            0xC000 => return Ok(Numeric("NaN".to_string())),
+            0xD000 => return Ok(Numeric("Infinity".to_string())),
+            0xF000 => return Ok(Numeric("-Infinity".to_string())),
+            _ => return Err("invalid NUMERIC sign".into()),
+        };
+        let digits = (0..count.max(0) as usize)
+            .map(|i| word(4 + i))
+            .collect::<Result<Vec<_>, _>>()?;
+        // the digit at `position`, counted from the first one, zero before and after them
+        let digit = |position: i32| {
+            usize::try_from(position)
+                .ok()
+                .and_then(|position| digits.get(position).copied())
+                .unwrap_or(0)
+        };
+
+        let mut integer = (0..=weight as i32)
+            .map(|i| format!("{:04}", digit(i)))
+            .collect::<String>();
+        integer = integer.trim_start_matches('0').to_string();
+        if integer.is_empty() {
+            integer = "0".to_string();
+        }
+
+        let scale = scale.max(0) as usize;
+        let mut fraction = String::new();
+        let mut position = weight as i32 + 1;
+        while fraction.len() < scale {
+            fraction.push_str(format!("{:04}", digit(position)).as_str());
+            position += 1;
+        }
+        fraction.truncate(scale);
+
+        Ok(Numeric(if fraction.is_empty() {
+            format!("{sign}{integer}")
+        } else {
+            format!("{sign}{integer}.{fraction}")
+        }))
+    }
+
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        ty == &tokio_postgres::types::Type::NUMERIC
+    }
+}
+
+fn get<'a, T: tokio_postgres::types::FromSql<'a>>(
+    r: &'a tokio_postgres::Row,
+    i: usize,
+) -> Result<Option<T>, fastn_utils::SqlError> {
+    r.try_get(i)
+        .map_err(|e| fastn_utils::SqlError::PgColumn(i, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_postgres::types::ToSql;
+
+    fn bind(
+        v: ft_sys_shared::SqliteRawValue,
+        ty: tokio_postgres::types::Type,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Sync + Send>> {
+        let mut out = bytes::BytesMut::new();
+        super::Bind(&v).to_sql(&ty, &mut out)?;
+        Ok(out.to_vec())
+    }
+
+    #[test]
+    fn binds() {
+        assert_eq!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Text("42".to_string()),
+                tokio_postgres::types::Type::INT4
+            )
+            .unwrap(),
+            42i32.to_be_bytes()
+        );
+        assert_eq!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Integer(42),
+                tokio_postgres::types::Type::INT8
+            )
+            .unwrap(),
+            42i64.to_be_bytes()
+        );
+        assert_eq!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Integer(1),
+                tokio_postgres::types::Type::BOOL
+            )
+            .unwrap(),
+            [1]
+        );
+        assert_eq!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Text("2.5".to_string()),
+                tokio_postgres::types::Type::FLOAT8
+            )
+            .unwrap(),
+            2.5f64.to_be_bytes()
+        );
+        assert_eq!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Integer(7),
+                tokio_postgres::types::Type::VARCHAR
+            )
+            .unwrap(),
+            b"7"
+        );
+        assert!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Text("jack".to_string()),
+                tokio_postgres::types::Type::INT4
+            )
+            .is_err()
+        );
+        assert!(
+            bind(
+                ft_sys_shared::SqliteRawValue::Integer(1 << 40),
+                tokio_postgres::types::Type::INT4
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn numeric() {
+        fn numeric(words: &[i16]) -> String {
+            let raw = words
+                .iter()
+                .flat_map(|w| w.to_be_bytes())
+                .collect::<Vec<_>>();
+            <super::Numeric as tokio_postgres::types::FromSql>::from_sql(
+                &tokio_postgres::types::Type::NUMERIC,
+                &raw,
+            )
+            .unwrap()
+            .0
+        }
+
+        // count, weight, sign, scale, digits
+        assert_eq!(numeric(&[2, 0, 0, 2, 123, 4500]), "123.45");
+        assert_eq!(numeric(&[1, 1, 0, 0, 1]), "10000");
+        assert_eq!(numeric(&[3, 1, 0, 3, 12, 3456, 7800]), "123456.780");
+        assert_eq!(numeric(&[1, -2, 0x4000, 6, 1200]), "-0.000012");
+        assert_eq!(numeric(&[0, 0, 0, 2]), "0.00");
+        assert_eq!(numeric(&[0, 0, 0xC000u16 as i16, 0]), "NaN");
+    }
+
+    /// Runs against the throwaway database in `FASTN_TEST_PG_URL`, which
+    /// `.github/scripts/test-postgres.sh` starts in docker. Skipped when it is not set, except in
+    /// CI, which has a postgres for it.
+    #[tokio::test]
+    async fn processors() {
+        let Ok(db_url) = std::env::var("FASTN_TEST_PG_URL") else {
+            assert!(
+                std::env::var("CI").is_err(),
+                "FASTN_TEST_PG_URL is not set, CI has to run the postgres tests"
+            );
+            eprintln!("FASTN_TEST_PG_URL is not set, skipping postgres test");
+            return;
+        };
+        let ds = fastn_ds::DocumentStore::new(".", actix_web::web::Data::new(Default::default()));
+        let table = format!("fastn_pg_test_{}", std::process::id());
+
+        ds.sql_batch(
+            &db_url,
+            &format!(
+                "DROP TABLE IF EXISTS {table}; CREATE TABLE {table} (id INT8, name TEXT, \
+                score FLOAT8, active BOOL, data JSONB, note VARCHAR(10));"
+            ),
+        )
+        .await
+        .unwrap();
+
+        let insert = format!("INSERT INTO {table} VALUES ($1, $2, $3, $4, $5, $6)");
+        assert_eq!(
+            ds.sql_execute(
+                &db_url,
+                &insert,
+                &[
+                    ft_sys_shared::SqliteRawValue::Integer(1),
+                    ft_sys_shared::SqliteRawValue::Text("jack".to_string()),
+                    ft_sys_shared::SqliteRawValue::Real(1.5),
+                    ft_sys_shared::SqliteRawValue::Integer(1),
+                    ft_sys_shared::SqliteRawValue::Text(r#"{"a": 1}"#.to_string()),
+                    ft_sys_shared::SqliteRawValue::Null,
+                ],
+            )
+            .await
+            .unwrap(),
+            vec![vec![serde_json::json!(1)]]
+        );
+        // header values always come as text
+        ds.sql_execute(
+            &db_url,
+            &insert,
+            &[
+                ft_sys_shared::SqliteRawValue::Text("2".to_string()),
+                ft_sys_shared::SqliteRawValue::Text("jill".to_string()),
+                ft_sys_shared::SqliteRawValue::Text("2.5".to_string()),
+                ft_sys_shared::SqliteRawValue::Text("false".to_string()),
+                ft_sys_shared::SqliteRawValue::Text("[]".to_string()),
+                ft_sys_shared::SqliteRawValue::Text("hello".to_string()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            ds.sql_query(
+                &db_url,
+                &format!("SELECT * FROM {table} WHERE id >= $1 ORDER BY id"),
+                &[ft_sys_shared::SqliteRawValue::Text("1".to_string())],
+            )
+            .await
+            .unwrap(),
+            vec![
+                vec![
+                    serde_json::json!(1),
+                    serde_json::json!("jack"),
+                    serde_json::json!(1.5),
+                    serde_json::json!(true),
+                    serde_json::json!({"a": 1}),
+                    serde_json::Value::Null,
+                ],
+                vec![
+                    serde_json::json!(2),
+                    serde_json::json!("jill"),
+                    serde_json::json!(2.5),
+                    serde_json::json!(false),
+                    serde_json::json!([]),
+                    serde_json::json!("hello"),
+                ],
+            ]
+        );
+
+        // sql-query can not write
+        assert!(
+            ds.sql_query(&db_url, &format!("DELETE FROM {table}"), &[])
+                .await
+                .is_err()
+        );
+        // the types sqlite has no column type for are numbers or text, as they would be in sqlite
+        assert_eq!(
+            ds.sql_query(
+                &db_url,
+                "SELECT 12::NUMERIC, 12.50::NUMERIC, '2024-01-02 03:04:05'::TIMESTAMP, \
+                '2024-01-02 03:04:05+00'::TIMESTAMPTZ, '2024-01-02'::DATE, '03:04:05'::TIME, \
+                'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::UUID",
+                &[],
+            )
+            .await
+            .unwrap(),
+            vec![vec![
+                serde_json::json!(12),
+                serde_json::json!(12.5),
+                serde_json::json!("2024-01-02 03:04:05"),
+                serde_json::json!("2024-01-02T03:04:05+00:00"),
+                serde_json::json!("2024-01-02"),
+                serde_json::json!("03:04:05"),
+                serde_json::json!("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"),
+            ]]
+        );
+        assert!(matches!(
+            ds.sql_query(&db_url, "SELECT '1 day'::INTERVAL", &[]).await,
+            Err(fastn_utils::SqlError::PgUnsupportedType(_))
+        ));
+
+        ds.sql_batch(&db_url, &format!("DROP TABLE {table};"))
+            .await
+            .unwrap();
+    }
+}
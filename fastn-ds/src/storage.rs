@@ -0,0 +1,582 @@
+/// Where the files of a package live.
+///
+/// `DocumentStore` resolves paths against the package root and then delegates the actual file
+/// operations to a `Storage`. All paths passed to a `Storage` are absolute.
+#[async_trait::async_trait]
+pub trait Storage: std::fmt::Debug + Send + Sync {
+    async fn read(&self, path: &camino::Utf8Path) -> Result<Vec<u8>, fastn_ds::ReadError>;
+
+    async fn write(&self, path: &camino::Utf8Path, data: &[u8])
+    -> Result<(), fastn_ds::WriteError>;
+
+    async fn exists(&self, path: &camino::Utf8Path) -> bool;
+
+    /// When the file was last changed, if the storage keeps track of it. Used for the
+    /// `Last-Modified` header.
+    async fn modified(&self, _path: &camino::Utf8Path) -> Option<std::time::SystemTime> {
+        None
+    }
+
+    /// Removes a file, or a folder with everything inside it. Removing something that does not
+    /// exist is not an error.
+    async fn remove(&self, path: &camino::Utf8Path) -> Result<(), fastn_ds::RemoveError>;
+
+    async fn rename(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::RenameError>;
+
+    async fn copy(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::WriteError> {
+        let content = self.read(from).await.map_err(|e| match e {
+            fastn_ds::ReadError::IOError(e, _) => fastn_ds::WriteError::IOError(e),
+            fastn_ds::ReadError::NotFound(path) => fastn_ds::WriteError::IOError(
+                std::io::Error::new(std::io::ErrorKind::NotFound, path),
+            ),
+        })?;
+        self.write(to, &content).await
+    }
+
+    /// Immediate children (files and folders) of `path`.
+    async fn read_dir(&self, path: &camino::Utf8Path) -> std::io::Result<Vec<camino::Utf8PathBuf>>;
+
+    /// All files under `path`, recursively. Hidden files and folders are skipped, as are paths
+    /// matching `ignore_paths` (glob patterns relative to `path`).
+    async fn list_files(
+        &self,
+        path: &camino::Utf8Path,
+        ignore_paths: &[String],
+    ) -> Result<Vec<camino::Utf8PathBuf>, fastn_ds::ListFilesError>;
+}
+
+/// Files on the local disk, this is what `fastn` uses by default.
+#[derive(Debug, Default)]
+pub struct LocalStorage;
+
+#[async_trait::async_trait]
+impl Storage for LocalStorage {
+    async fn read(&self, path: &camino::Utf8Path) -> Result<Vec<u8>, fastn_ds::ReadError> {
+        use tokio::io::AsyncReadExt;
+
+        let mut file = tokio::fs::File::open(path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                fastn_ds::ReadError::NotFound(path.to_string())
+            } else {
+                fastn_ds::ReadError::IOError(e, path.to_string())
+            }
+        })?;
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)
+            .await
+            .map_err(|e| fastn_ds::ReadError::IOError(e, path.to_string()))?;
+        Ok(contents)
+    }
+
+    async fn write(
+        &self,
+        path: &camino::Utf8Path,
+        data: &[u8],
+    ) -> Result<(), fastn_ds::WriteError> {
+        use tokio::io::AsyncWriteExt;
+
+        // Create the directory if it doesn't exist
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = tokio::fs::File::create(path).await?;
+        file.write_all(data).await?;
+        Ok(())
+    }
+
+    async fn exists(&self, path: &camino::Utf8Path) -> bool {
+        path.exists()
+    }
+
+    async fn modified(&self, path: &camino::Utf8Path) -> Option<std::time::SystemTime> {
+        tokio::fs::metadata(path).await.ok()?.modified().ok()
+    }
+
+    async fn remove(&self, path: &camino::Utf8Path) -> Result<(), fastn_ds::RemoveError> {
+        let metadata = match tokio::fs::symlink_metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        // `symlink_metadata` does not follow symlinks, so a symlink is removed itself and never
+        // what it points to
+        if metadata.is_dir() {
+            tokio::fs::remove_dir_all(path).await?;
+        } else {
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn rename(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::RenameError> {
+        Ok(tokio::fs::rename(from, to).await?)
+    }
+
+    async fn copy(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::WriteError> {
+        tokio::fs::copy(from, to).await?;
+        Ok(())
+    }
+
+    async fn read_dir(&self, path: &camino::Utf8Path) -> std::io::Result<Vec<camino::Utf8PathBuf>> {
+        let mut entries = tokio::fs::read_dir(path).await?;
+        let mut children = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            children.push(
+                camino::Utf8PathBuf::from_path_buf(entry.path()).map_err(|p| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("non utf-8 path: {p:?}"),
+                    )
+                })?,
+            );
+        }
+        Ok(children)
+    }
+
+    async fn list_files(
+        &self,
+        path: &camino::Utf8Path,
+        ignore_paths: &[String],
+    ) -> Result<Vec<camino::Utf8PathBuf>, fastn_ds::ListFilesError> {
+        let mut ignore_path = ignore::WalkBuilder::new(path);
+        // ignore_paths.hidden(false); // Allow the linux hidden files to be evaluated
+        ignore_path.overrides(fastn_ds::package_ignores(ignore_paths, path)?);
+        let mut files = vec![];
+        for entry in ignore_path.build().flatten() {
+            let path = camino::Utf8PathBuf::from_path_buf(entry.into_path())
+                .map_err(fastn_ds::ListFilesError::NonUtf8Path)?;
+            if !path.is_dir() {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Files kept in memory, nothing touches the disk. Useful for tests, and as the top layer of an
+/// `OverlayStorage`.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: std::sync::RwLock<std::collections::BTreeMap<camino::Utf8PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A storage pre-populated with `files`, keyed by their absolute path.
+    pub fn with_files<P, C>(files: impl IntoIterator<Item = (P, C)>) -> Self
+    where
+        P: Into<camino::Utf8PathBuf>,
+        C: Into<Vec<u8>>,
+    {
+        Self {
+            files: std::sync::RwLock::new(
+                files
+                    .into_iter()
+                    .map(|(p, c)| (p.into(), c.into()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn files(
+        &self,
+    ) -> std::sync::RwLockReadGuard<'_, std::collections::BTreeMap<camino::Utf8PathBuf, Vec<u8>>>
+    {
+        // a panic while holding the lock can not leave the map half updated, so we ignore poison
+        self.files.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn files_mut(
+        &self,
+    ) -> std::sync::RwLockWriteGuard<'_, std::collections::BTreeMap<camino::Utf8PathBuf, Vec<u8>>>
+    {
+        self.files.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_dir(&self, path: &camino::Utf8Path) -> bool {
+        self.files()
+            .keys()
+            .any(|p| p != path && p.starts_with(path))
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    async fn read(&self, path: &camino::Utf8Path) -> Result<Vec<u8>, fastn_ds::ReadError> {
+        self.files()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| fastn_ds::ReadError::NotFound(path.to_string()))
+    }
+
+    async fn write(
+        &self,
+        path: &camino::Utf8Path,
+        data: &[u8],
+    ) -> Result<(), fastn_ds::WriteError> {
+        self.files_mut().insert(path.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    async fn exists(&self, path: &camino::Utf8Path) -> bool {
+        self.files().contains_key(path) || self.is_dir(path)
+    }
+
+    async fn remove(&self, path: &camino::Utf8Path) -> Result<(), fastn_ds::RemoveError> {
+        self.files_mut().retain(|p, _| !p.starts_with(path));
+        Ok(())
+    }
+
+    async fn rename(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::RenameError> {
+        let mut files = self.files_mut();
+        let moved: Vec<_> = files
+            .keys()
+            .filter(|p| p.starts_with(from))
+            .cloned()
+            .collect();
+
+        if moved.is_empty() {
+            return Err(fastn_ds::RenameError::IOError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                from.to_string(),
+            )));
+        }
+
+        for old in moved {
+            // unwrap: we just checked that `old` starts with `from`, and that it is in the map
+            let new = to.join(old.strip_prefix(from).unwrap());
+            let content = files.remove(&old).unwrap();
+            files.insert(new, content);
+        }
+
+        Ok(())
+    }
+
+    async fn read_dir(&self, path: &camino::Utf8Path) -> std::io::Result<Vec<camino::Utf8PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                path.to_string(),
+            ));
+        }
+
+        let mut children: Vec<_> = self
+            .files()
+            .keys()
+            .filter_map(|p| p.strip_prefix(path).ok())
+            .filter_map(|p| p.components().next())
+            .map(|c| path.join(c.as_str()))
+            .collect();
+        children.sort();
+        children.dedup();
+        Ok(children)
+    }
+
+    async fn list_files(
+        &self,
+        path: &camino::Utf8Path,
+        ignore_paths: &[String],
+    ) -> Result<Vec<camino::Utf8PathBuf>, fastn_ds::ListFilesError> {
+        let ignores = fastn_ds::package_ignores(ignore_paths, path)?;
+        Ok(self
+            .files()
+            .keys()
+            .filter(|p| match p.strip_prefix(path) {
+                // mimic `ignore::WalkBuilder`, which skips hidden files by default
+                Ok(relative) => !relative.components().any(|c| c.as_str().starts_with('.')),
+                Err(_) => false,
+            })
+            .filter(|p| !ignores.matched(p.as_std_path(), false).is_ignore())
+            .cloned()
+            .collect())
+    }
+}
+
+/// Changes layered on top of another storage. Reads look at the overlay first and fall back to
+/// `base`, writes and removals only touch the overlay, so `base` never changes.
+///
+/// Used for preview sessions: the edits made in a session are visible to requests made with that
+/// session id, but are not written to disk.
+#[derive(Debug)]
+pub struct OverlayStorage {
+    base: std::sync::Arc<dyn Storage>,
+    overlay: MemoryStorage,
+    /// files removed in the overlay, that may still exist in `base`
+    removed: std::sync::RwLock<std::collections::BTreeSet<camino::Utf8PathBuf>>,
+}
+
+impl OverlayStorage {
+    pub fn new(base: std::sync::Arc<dyn Storage>) -> Self {
+        Self {
+            base,
+            overlay: MemoryStorage::new(),
+            removed: Default::default(),
+        }
+    }
+
+    fn is_removed(&self, path: &camino::Utf8Path) -> bool {
+        self.removed
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|r| path.starts_with(r))
+    }
+
+    fn mark_removed(&self, path: &camino::Utf8Path) {
+        self.removed
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf());
+    }
+
+    fn unmark_removed(&self, path: &camino::Utf8Path) {
+        // writing a file inside a removed folder brings back just that file
+        self.removed
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|r| r != path);
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for OverlayStorage {
+    async fn read(&self, path: &camino::Utf8Path) -> Result<Vec<u8>, fastn_ds::ReadError> {
+        match self.overlay.read(path).await {
+            Ok(v) => Ok(v),
+            Err(_) if self.is_removed(path) => Err(fastn_ds::ReadError::NotFound(path.to_string())),
+            Err(_) => self.base.read(path).await,
+        }
+    }
+
+    async fn write(
+        &self,
+        path: &camino::Utf8Path,
+        data: &[u8],
+    ) -> Result<(), fastn_ds::WriteError> {
+        self.unmark_removed(path);
+        self.overlay.write(path, data).await
+    }
+
+    async fn exists(&self, path: &camino::Utf8Path) -> bool {
+        if self.overlay.exists(path).await {
+            return true;
+        }
+        !self.is_removed(path) && self.base.exists(path).await
+    }
+
+    async fn modified(&self, path: &camino::Utf8Path) -> Option<std::time::SystemTime> {
+        if self.overlay.exists(path).await || self.is_removed(path) {
+            // the edit is not on disk, we do not know when it was made
+            return None;
+        }
+        self.base.modified(path).await
+    }
+
+    async fn remove(&self, path: &camino::Utf8Path) -> Result<(), fastn_ds::RemoveError> {
+        self.overlay.remove(path).await?;
+        self.mark_removed(path);
+        Ok(())
+    }
+
+    async fn rename(
+        &self,
+        from: &camino::Utf8Path,
+        to: &camino::Utf8Path,
+    ) -> Result<(), fastn_ds::RenameError> {
+        let moved = match self.read(from).await {
+            Ok(content) => vec![(to.to_path_buf(), content)],
+            Err(_) => {
+                let mut moved = vec![];
+                let files = self
+                    .list_files(from, &[])
+                    .await
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+                for file in files {
+                    let content = self
+                        .read(&file)
+                        .await
+                        .map_err(|e| std::io::Error::other(e.to_string()))?;
+                    // unwrap: list_files only returns paths inside `from`
+                    moved.push((to.join(file.strip_prefix(from).unwrap()), content));
+                }
+                moved
+            }
+        };
+
+        if moved.is_empty() {
+            return Err(fastn_ds::RenameError::IOError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                from.to_string(),
+            )));
+        }
+
+        self.remove(from)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        for (path, content) in moved {
+            self.write(&path, &content)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn read_dir(&self, path: &camino::Utf8Path) -> std::io::Result<Vec<camino::Utf8PathBuf>> {
+        let mut children = match self.base.read_dir(path).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+        children.retain(|c| !self.is_removed(c));
+        if let Ok(overlay) = self.overlay.read_dir(path).await {
+            children.extend(overlay);
+        }
+
+        if children.is_empty() && !self.exists(path).await {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                path.to_string(),
+            ));
+        }
+
+        children.sort();
+        children.dedup();
+        Ok(children)
+    }
+
+    async fn list_files(
+        &self,
+        path: &camino::Utf8Path,
+        ignore_paths: &[String],
+    ) -> Result<Vec<camino::Utf8PathBuf>, fastn_ds::ListFilesError> {
+        let mut files = self.base.list_files(path, ignore_paths).await?;
+        files.retain(|f| !self.is_removed(f));
+        files.extend(self.overlay.list_files(path, ignore_paths).await?);
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastn_ds::Storage;
+
+    fn p(path: &str) -> &camino::Utf8Path {
+        camino::Utf8Path::new(path)
+    }
+
+    #[tokio::test]
+    async fn memory() {
+        let storage = fastn_ds::MemoryStorage::with_files([
+            ("/pkg/FASTN.ftd", "-- import: fastn"),
+            ("/pkg/index.ftd", "-- ftd.text: hello"),
+            ("/pkg/blog/first.ftd", "-- ftd.text: first"),
+            ("/pkg/.packages/dep/index.ftd", "-- ftd.text: dep"),
+        ]);
+
+        assert_eq!(
+            storage.read(p("/pkg/index.ftd")).await.unwrap(),
+            b"-- ftd.text: hello"
+        );
+        assert!(storage.exists(p("/pkg/blog")).await);
+        assert!(!storage.exists(p("/pkg/about.ftd")).await);
+        assert_eq!(
+            storage
+                .list_files(p("/pkg"), &["blog/*".to_string()])
+                .await
+                .unwrap(),
+            vec![
+                camino::Utf8PathBuf::from("/pkg/FASTN.ftd"),
+                camino::Utf8PathBuf::from("/pkg/index.ftd")
+            ]
+        );
+        assert_eq!(
+            storage.read_dir(p("/pkg")).await.unwrap(),
+            vec![
+                camino::Utf8PathBuf::from("/pkg/.packages"),
+                camino::Utf8PathBuf::from("/pkg/FASTN.ftd"),
+                camino::Utf8PathBuf::from("/pkg/blog"),
+                camino::Utf8PathBuf::from("/pkg/index.ftd"),
+            ]
+        );
+
+        storage
+            .rename(p("/pkg/blog"), p("/pkg/posts"))
+            .await
+            .unwrap();
+        assert!(storage.exists(p("/pkg/posts/first.ftd")).await);
+        assert!(!storage.exists(p("/pkg/blog")).await);
+
+        storage.remove(p("/pkg/posts")).await.unwrap();
+        assert!(!storage.exists(p("/pkg/posts/first.ftd")).await);
+    }
+
+    #[tokio::test]
+    async fn overlay() {
+        let base: std::sync::Arc<dyn fastn_ds::Storage> =
+            std::sync::Arc::new(fastn_ds::MemoryStorage::with_files([
+                ("/pkg/index.ftd", "-- ftd.text: hello"),
+                ("/pkg/about.ftd", "-- ftd.text: about"),
+            ]));
+        let overlay = fastn_ds::OverlayStorage::new(base.clone());
+
+        overlay
+            .write(p("/pkg/index.ftd"), b"-- ftd.text: edited")
+            .await
+            .unwrap();
+        overlay
+            .write(p("/pkg/new.ftd"), b"-- ftd.text: new")
+            .await
+            .unwrap();
+        overlay.remove(p("/pkg/about.ftd")).await.unwrap();
+
+        assert_eq!(
+            overlay.read(p("/pkg/index.ftd")).await.unwrap(),
+            b"-- ftd.text: edited"
+        );
+        assert!(!overlay.exists(p("/pkg/about.ftd")).await);
+        assert_eq!(
+            overlay.list_files(p("/pkg"), &[]).await.unwrap(),
+            vec![
+                camino::Utf8PathBuf::from("/pkg/index.ftd"),
+                camino::Utf8PathBuf::from("/pkg/new.ftd")
+            ]
+        );
+
+        // the base is untouched
+        assert_eq!(
+            base.read(p("/pkg/index.ftd")).await.unwrap(),
+            b"-- ftd.text: hello"
+        );
+        assert!(base.exists(p("/pkg/about.ftd")).await);
+        assert!(!base.exists(p("/pkg/new.ftd")).await);
+    }
+}
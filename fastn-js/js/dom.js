@@ -2993,7 +2993,7 @@ class ForLoop {
                 this.#nodes[index - 1],
             );
         }
-        let v = this.#list.get(index);
+        let v = this.#list.getList()[index];
         let node = this.#node_constructor(parentWithSibiling, v.item, v.index);
         this.#nodes.splice(index, 0, node);
         if (resizeBodyHeight) {
@@ -82,6 +82,7 @@ const fastn = (function (fastn) {
                 !fastn_utils.isNull(key) &&
                 (this.#value instanceof RecordInstance ||
                     this.#value instanceof MutableList ||
+                    this.#value instanceof MutableMap ||
                     this.#value instanceof Mutable)
             ) {
                 return this.#value.get(key);
@@ -111,7 +112,10 @@ const fastn = (function (fastn) {
                 // The `this.#value.replace(value);` will replace the value of
                 // `orange-green` with `{light: red, dark: red}`
                 this.#value = value;
-            } else if (this.#value instanceof MutableList) {
+            } else if (
+                this.#value instanceof MutableList ||
+                this.#value instanceof MutableMap
+            ) {
                 if (value instanceof fastn.mutableClass) {
                     value = value.get();
                 }
@@ -206,6 +210,7 @@ const fastn = (function (fastn) {
                 !!key &&
                 (this.#cached_value instanceof RecordInstance ||
                     this.#cached_value instanceof MutableList ||
+                    this.#cached_value instanceof MutableMap ||
                     this.#cached_value instanceof Mutable)
             ) {
                 return this.#cached_value.get(key);
@@ -413,6 +418,189 @@ const fastn = (function (fastn) {
         }
     }
 
+    // The server sorts keys by their utf-8 bytes, that is by code points,
+    // while comparing js strings compares utf-16 code units, which puts keys
+    // outside the basic multilingual plane (eg emoji) before `\uE000`-`\uFFFF`.
+    function compareKeys(a, b) {
+        let left = Array.from(a);
+        let right = Array.from(b);
+        for (let i = 0; i < Math.min(left.length, right.length); i++) {
+            let diff = left[i].codePointAt(0) - right[i].codePointAt(0);
+            if (diff !== 0) {
+                return diff;
+            }
+        }
+        return left.length - right.length;
+    }
+
+    // Entries are kept sorted by key, matching the order the server renders
+    // them in. Each entry looks like a `MutableList` entry, with the key in
+    // place of the index, so `ForLoop` can render both.
+    class MutableMap {
+        #entries;
+        #watchers;
+        #closures;
+
+        constructor(obj) {
+            this.#entries = [];
+            for (let key of Object.keys(obj || {}).sort(compareKeys)) {
+                this.#entries.push({
+                    key: key,
+                    item: fastn.wrapMutable(obj[key]),
+                    index: new Mutable(key),
+                });
+            }
+            this.#watchers = [];
+            this.#closures = [];
+        }
+
+        addClosure(closure) {
+            this.#closures.push(closure);
+        }
+
+        unlinkNode(node) {
+            this.#closures = this.#closures.filter(
+                (closure) => closure.getNode() !== node,
+            );
+        }
+
+        forLoop(root, dom_constructor) {
+            let l = fastn_dom.forLoop(root, dom_constructor, this);
+            this.#watchers.push(l);
+            return l;
+        }
+
+        getList() {
+            return this.#entries;
+        }
+
+        getLength() {
+            return this.#entries.length;
+        }
+
+        keys() {
+            return this.#entries.map((entry) => entry.key);
+        }
+
+        findPosition(key) {
+            key = String(fastn_utils.getFlattenStaticValue(key));
+            return this.#entries.findIndex((entry) => entry.key === key);
+        }
+
+        contains(key) {
+            return this.findPosition(key) !== -1;
+        }
+
+        get(key) {
+            if (fastn_utils.isNull(key)) {
+                return this.getList();
+            }
+            let position = this.findPosition(key);
+            return position === -1 ? null : this.#entries[position].item;
+        }
+
+        set(key, value) {
+            if (value === undefined) {
+                value = key;
+                if (!(value instanceof MutableMap)) {
+                    value = new MutableMap(value);
+                }
+                this.#entries = [...value.#entries];
+
+                this.deleteEmptyWatchers();
+                for (let i in this.#watchers) {
+                    this.#watchers[i].createAllNode();
+                }
+            } else {
+                let position = this.findPosition(key);
+                if (position !== -1) {
+                    this.#entries[position].item.set(value);
+                } else {
+                    key = String(fastn_utils.getFlattenStaticValue(key));
+                    position = this.#entries.findIndex(
+                        (entry) => compareKeys(entry.key, key) > 0,
+                    );
+                    if (position === -1) {
+                        position = this.#entries.length;
+                    }
+                    this.#entries.splice(position, 0, {
+                        key: key,
+                        item: fastn.wrapMutable(value),
+                        index: new Mutable(key),
+                    });
+
+                    this.deleteEmptyWatchers();
+                    for (let i in this.#watchers) {
+                        this.#watchers[i].createNode(position);
+                    }
+                }
+            }
+
+            this.#closures.forEach((closure) => closure.update());
+        }
+
+        // See `MutableList.deleteEmptyWatchers`
+        deleteEmptyWatchers() {
+            this.#watchers = this.#watchers.filter((w) => {
+                let to_delete = false;
+                if (!!w.getParent) {
+                    let parent = w.getParent();
+                    while (!!parent && !!parent.getParent) {
+                        parent = parent.getParent();
+                    }
+                    if (!parent) {
+                        to_delete = true;
+                    }
+                }
+                if (to_delete) {
+                    w.deleteAllNode();
+                }
+                return !to_delete;
+            });
+        }
+
+        remove(key) {
+            let position = this.findPosition(key);
+            if (position === -1) {
+                return;
+            }
+            this.#entries.splice(position, 1);
+
+            this.deleteEmptyWatchers();
+            for (let i in this.#watchers) {
+                this.#watchers[i].deleteNode(position);
+            }
+            this.#closures.forEach((closure) => closure.update());
+        }
+
+        clearAll() {
+            this.#entries = [];
+
+            this.deleteEmptyWatchers();
+            for (let i in this.#watchers) {
+                this.#watchers[i].deleteAllNode();
+            }
+            this.#closures.forEach((closure) => closure.update());
+        }
+
+        toObject() {
+            return Object.fromEntries(
+                this.#entries.map((entry) => [
+                    entry.key,
+                    fastn_utils.getFlattenStaticValue(entry.item),
+                ]),
+            );
+        }
+
+        getClone() {
+            let cloned = {};
+            for (let entry of this.#entries) {
+                cloned[entry.key] = fastn_utils.clone(entry.item);
+            }
+            return new MutableMap(cloned);
+        }
+    }
+
     fastn.mutable = function (val) {
         return new Mutable(val);
     };
@@ -451,7 +639,8 @@ const fastn = (function (fastn) {
         if (
             !(obj instanceof Mutable) &&
             !(obj instanceof RecordInstance) &&
-            !(obj instanceof MutableList)
+            !(obj instanceof MutableList) &&
+            !(obj instanceof MutableMap)
         ) {
             obj = new Mutable(obj);
         }
@@ -462,6 +651,10 @@ const fastn = (function (fastn) {
         return new MutableList(list);
     };
 
+    fastn.mutableMap = function (obj) {
+        return new MutableMap(obj);
+    };
+
     class RecordInstance {
         #fields;
         #closures;
@@ -491,7 +684,8 @@ const fastn = (function (fastn) {
                 if (
                     field_value instanceof fastn.recordInstanceClass ||
                     field_value instanceof fastn.mutableClass ||
-                    field_value instanceof fastn.mutableListClass
+                    field_value instanceof fastn.mutableListClass ||
+                    field_value instanceof fastn.mutableMapClass
                 ) {
                     clonedFields[key] = this.#fields[key].getClone();
                 } else {
@@ -605,6 +799,7 @@ const fastn = (function (fastn) {
 
     fastn.mutableClass = Mutable;
     fastn.mutableListClass = MutableList;
+    fastn.mutableMapClass = MutableMap;
     fastn.recordInstanceClass = RecordInstance;
     fastn.module = function (name, global) {
         return new Module(name, global);
@@ -14,12 +14,20 @@ const ftd = (function () {
     exports.riveNodes = riveNodes;
 
     exports.is_empty = (value) => {
+        let map = fastn_utils.getter(fastn_utils.flattenMutable(value));
+        if (map instanceof fastn.mutableMapClass) {
+            return map.getLength() === 0;
+        }
         value = fastn_utils.getFlattenStaticValue(value);
         return fastn_utils.isNull(value) || value.length === 0;
     };
 
     exports.len = (data) => {
-        if (!!data && data instanceof fastn.mutableListClass) {
+        if (
+            !!data &&
+            (data instanceof fastn.mutableListClass ||
+                data instanceof fastn.mutableMapClass)
+        ) {
             if (data.getLength) return data.getLength();
             return -1;
         }
@@ -230,6 +238,18 @@ const ftd = (function () {
     exports.set_list = function (list, value) {
         list.set(value);
     };
+    exports.map_insert = function (map, key, value) {
+        map.set(fastn_utils.getFlattenStaticValue(key), value);
+    };
+    exports.map_remove = function (map, key) {
+        map.remove(fastn_utils.getFlattenStaticValue(key));
+    };
+    exports.map_contains = function (map, key) {
+        return map.contains(fastn_utils.getFlattenStaticValue(key));
+    };
+    exports.map_keys = function (map) {
+        return fastn.mutableList(map.keys());
+    };
 
     exports.http = function (url, method, headers, ...body) {
         if (url instanceof fastn.mutableClass) url = url.get();
@@ -456,6 +476,7 @@ const ftd = (function () {
         return (
             obj instanceof fastn.mutableClass ||
             obj instanceof fastn.mutableListClass ||
+            obj instanceof fastn.mutableMapClass ||
             obj instanceof fastn.recordInstanceClass
         );
     }
@@ -521,6 +542,44 @@ const ftd = (function () {
         return fastn_utils.private.getCookie("fastn-lang");
     };
 
+    // Search related functions -----------------------------------------------
+    // the matches come from `/-/search/` (fastn-core/src/search.rs), so pages
+    // and the server rank the same way
+    let lastSearch = 0;
+
+    exports.search = function (args) {
+        let query = args.query;
+        if (query instanceof fastn.mutableClass)
+            query = fastn_utils.getStaticValue(query);
+        const results = args.results;
+
+        // a site built with a base url lives under it, see the `<base>` tag
+        let base = document.querySelector("base")?.getAttribute("href") || "/";
+        if (!base.endsWith("/")) base += "/";
+        // results of an older query may arrive after those of a newer one
+        const current = ++lastSearch;
+        fetch(base + "-/search/?q=" + encodeURIComponent(query || ""))
+            .then((res) => {
+                if (!res.ok) {
+                    throw new Error("[search]: Search failed: " + res.status);
+                }
+                return res.json();
+            })
+            .then((response) => {
+                if (current !== lastSearch) return;
+                results.set(
+                    response.results.map((hit) =>
+                        fastn.recordInstance({
+                            title: hit.title,
+                            url: hit.url,
+                            summary: hit.summary || null,
+                        }),
+                    ),
+                );
+            })
+            .catch(console.error);
+    };
+
     exports.submit_form = function (url_part, ...args) {
         let url = url_part;
 
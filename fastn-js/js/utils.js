@@ -71,6 +71,8 @@ let fastn_utils = {
             return this.getStaticValue(obj.get());
         } else if (obj instanceof fastn.mutableListClass) {
             return obj.getList();
+        } else if (obj instanceof fastn.mutableMapClass) {
+            return obj.toObject();
         } /*
         Todo: Make this work
         else if (obj instanceof fastn.recordInstanceClass) {
@@ -130,6 +132,7 @@ let fastn_utils = {
         if (
             !(obj instanceof fastn.mutableClass) &&
             !(obj instanceof fastn.mutableListClass) &&
+            !(obj instanceof fastn.mutableMapClass) &&
             !(obj instanceof fastn.recordInstanceClass)
         ) {
             if (Array.isArray(obj)) {
@@ -160,6 +163,15 @@ let fastn_utils = {
         } else if (obj instanceof fastn.mutableListClass) {
             let list = obj.getList();
             return list.map((func) => this.mutableToStaticValue(func.item));
+        } else if (obj instanceof fastn.mutableMapClass) {
+            return Object.fromEntries(
+                obj
+                    .getList()
+                    .map((entry) => [
+                        entry.key,
+                        this.mutableToStaticValue(entry.item),
+                    ]),
+            );
         } else if (obj instanceof fastn.recordInstanceClass) {
             let fields = obj.getAllFields();
             return Object.fromEntries(
@@ -208,6 +220,7 @@ let fastn_utils = {
     getterByKey(value, index) {
         if (
             value instanceof fastn.mutableClass ||
+            value instanceof fastn.mutableMapClass ||
             value instanceof fastn.recordInstanceClass
         ) {
             return value.get(index);
@@ -261,7 +274,8 @@ let fastn_utils = {
         }
         if (
             value instanceof fastn.mutableClass ||
-            value instanceof fastn.mutableListClass
+            value instanceof fastn.mutableListClass ||
+            value instanceof fastn.mutableMapClass
         ) {
             return value.getClone();
         }
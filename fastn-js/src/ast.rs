@@ -5,6 +5,7 @@ pub enum Ast {
     StaticVariable(fastn_js::StaticVariable),
     MutableVariable(fastn_js::MutableVariable),
     MutableList(fastn_js::MutableList),
+    MutableMap(fastn_js::MutableMap),
     RecordInstance(fastn_js::RecordInstance),
     OrType(fastn_js::OrType),
     Export { from: String, to: String },
@@ -11,6 +11,7 @@ pub enum ComponentStatement {
     },
     ConditionalComponent(fastn_js::ConditionalComponent),
     MutableList(fastn_js::MutableList),
+    MutableMap(fastn_js::MutableMap),
     ForLoop(fastn_js::ForLoop),
     RecordInstance(fastn_js::RecordInstance),
     OrType(fastn_js::OrType),
@@ -37,6 +38,7 @@ impl ComponentStatement {
             }
             ComponentStatement::OrType(or_type) => Some(or_type.name.clone()),
             ComponentStatement::MutableList(mutable_list) => Some(mutable_list.name.clone()),
+            ComponentStatement::MutableMap(mutable_map) => Some(mutable_map.name.clone()),
             _ => None,
         }
     }
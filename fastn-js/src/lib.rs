@@ -33,7 +33,9 @@ pub use constants::*;
 pub use device::{DeviceBlock, DeviceType};
 pub use event::{Event, EventHandler, Function, FunctionData};
 pub use loop_component::ForLoop;
-pub use mutable_variable::{MutableList, MutableVariable, mutable_integer, mutable_string};
+pub use mutable_variable::{
+    MutableList, MutableMap, MutableVariable, mutable_integer, mutable_string,
+};
 pub use or_type::OrType;
 pub use property::{
     ConditionalValue, Formula, FormulaType, PropertyKind, SetProperty, SetPropertyValue, Value,
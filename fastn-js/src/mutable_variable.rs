@@ -27,3 +27,10 @@ pub struct MutableList {
     pub value: fastn_js::SetPropertyValue,
     pub prefix: Option<String>,
 }
+
+#[derive(Debug)]
+pub struct MutableMap {
+    pub name: String,
+    pub value: fastn_js::SetPropertyValue,
+    pub prefix: Option<String>,
+}
@@ -198,6 +198,9 @@ pub enum Value {
     List {
         value: Vec<SetPropertyValue>,
     },
+    Map {
+        value: Vec<(String, SetPropertyValue)>,
+    },
     Record {
         fields: Vec<(String, SetPropertyValue)>,
         other_references: Vec<String>,
@@ -243,6 +246,17 @@ impl Value {
                     .map(|v| v.to_js_with_element_name(element_name))
                     .join(", ")
             ),
+            Value::Map { value } => format!(
+                "fastn.mutableMap({{{}}})",
+                value
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "\"{}\": {}",
+                        fastn_js::utils::escape_string(k.to_string()),
+                        v.to_js_with_element_name(element_name)
+                    ))
+                    .join(", ")
+            ),
             Value::Record {
                 fields,
                 other_references,
@@ -302,6 +316,7 @@ impl Value {
                 .map(|v| v.is_local_value_dependent())
                 .unwrap_or_default(),
             Value::List { value } => value.iter().any(|v| v.is_local_value_dependent()),
+            Value::Map { value } => value.iter().any(|v| v.1.is_local_value_dependent()),
             Value::Record { fields, .. } => fields.iter().any(|v| v.1.is_local_value_dependent()),
             Value::UI { .. } => {
                 //Todo: Check for UI
@@ -28,6 +28,7 @@ impl fastn_js::Ast {
             fastn_js::Ast::StaticVariable(s) => s.to_js(),
             fastn_js::Ast::MutableVariable(m) => m.to_js(),
             fastn_js::Ast::MutableList(ml) => ml.to_js(),
+            fastn_js::Ast::MutableMap(mm) => mm.to_js(),
             fastn_js::Ast::RecordInstance(ri) => ri.to_js(),
             fastn_js::Ast::OrType(ot) => ot.to_js(),
             fastn_js::Ast::Export { from, to } => variable_to_js(
@@ -229,6 +230,7 @@ impl fastn_js::ComponentStatement {
             }
             fastn_js::ComponentStatement::ConditionalComponent(c) => c.to_js(),
             fastn_js::ComponentStatement::MutableList(ml) => ml.to_js(),
+            fastn_js::ComponentStatement::MutableMap(mm) => mm.to_js(),
             fastn_js::ComponentStatement::ForLoop(fl) => fl.to_js(),
             fastn_js::ComponentStatement::RecordInstance(ri) => ri.to_js(),
             fastn_js::ComponentStatement::OrType(ot) => ot.to_js(),
@@ -633,6 +635,17 @@ impl fastn_js::MutableList {
     }
 }
 
+impl fastn_js::MutableMap {
+    pub fn to_js(&self) -> pretty::RcDoc<'static> {
+        variable_to_js(
+            self.name.as_str(),
+            &self.prefix,
+            text(self.value.to_js().as_str()),
+            false,
+        )
+    }
+}
+
 impl fastn_js::RecordInstance {
     pub fn to_js(&self) -> pretty::RcDoc<'static> {
         variable_to_js(
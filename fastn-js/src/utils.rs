@@ -22,6 +22,11 @@ pub fn reference_to_js(s: &str) -> String {
     while let Some(ref remaining) = p2 {
         let (p21, p22) = get_doc_name_and_remaining(remaining);
         match p21.parse::<i64>() {
+            // the keys of maps come quoted, they are looked up as they are
+            _ if p21.starts_with('"') => {
+                p1 = format!("{p1}.get({p21})");
+                wrapper_function = None;
+            }
             Ok(num) if p22.is_none() => {
                 p1 = format!("{p1}.get({num})");
                 wrapper_function = Some("fastn_utils.getListItem");
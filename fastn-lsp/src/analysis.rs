@@ -0,0 +1,596 @@
+//! Answers about a document, from the `ftd::interpreter::Document` it interprets to.
+
+/// The id of the document at `path`, `None` for files that are not ftd documents of the package.
+pub(crate) fn document_id(config: &fastn_core::Config, path: &fastn_ds::Path) -> Option<String> {
+    let id = path.strip_prefix(&config.ds.root())?.to_string();
+    let id = id.trim_start_matches('/');
+    if !id.ends_with(".ftd")
+        || id.eq("FASTN.ftd")
+        || id.starts_with(".packages/")
+        || id.starts_with(".build/")
+    {
+        return None;
+    }
+    Some(id.to_string())
+}
+
+fn document(config: &fastn_core::Config, id: &str, content: &str) -> fastn_core::Document {
+    fastn_core::Document {
+        package_name: config.package.name.clone(),
+        id: id.to_string(),
+        content: content.to_string(),
+        parent_path: config.ds.root(),
+    }
+}
+
+/// The name the document `id` is interpreted with, and that its errors refer to it by.
+pub(crate) fn document_name(config: &fastn_core::Config, id: &str) -> String {
+    document(config, id, "").id_with_package()
+}
+
+/// Interprets `content`, the text of the document `id` in the editor, the way `fastn serve`
+/// would: with the auto imports of the package, and with imports resolved from the package and
+/// its dependencies in `.packages`.
+///
+/// This runs on every keystroke, so like `fastn check` the processors which talk to the network
+/// or a database are not run, their variables get an empty value instead.
+pub(crate) async fn interpret(
+    config: &fastn_core::Config,
+    id: &str,
+    content: &str,
+) -> fastn_core::Result<ftd::interpreter::Document> {
+    let main = document(config, id, content);
+    let mut req_config = fastn_core::RequestConfig::new(
+        config,
+        &fastn_core::http::Request::default(),
+        main.id.as_str(),
+        "/",
+    );
+    req_config.current_document = Some(main.id.clone());
+    req_config.static_check = true;
+
+    let mut current_package = config.package.clone();
+    current_package.auto_import_language(
+        config.package.requested_language.clone(),
+        config.package.selected_language.clone(),
+    )?;
+    let doc_content = current_package.get_prefixed_body(
+        &config.package,
+        main.content.as_str(),
+        main.id.as_str(),
+        true,
+    );
+    let doc_content =
+        current_package.fix_imports_in_body(doc_content.as_str(), main.id.as_str())?;
+    let line_number = doc_content.split('\n').count() - main.content.split('\n').count();
+
+    Ok(fastn_core::doc::interpret_collecting_errors(
+        main.id_with_package().as_str(),
+        doc_content.as_str(),
+        &mut req_config,
+        "/",
+        false,
+        line_number,
+        &None,
+    )
+    .await?)
+}
+
+/// The diagnostics for an `error` found while interpreting the document `doc_name`, one for each
+/// error the interpreter collected. Errors in an imported document are shown on the first line.
+pub(crate) fn diagnostics(doc_name: &str, error: &fastn_core::Error) -> Vec<lsp_types::Diagnostic> {
+    if let fastn_core::Error::FTDInterpreterError(ftd::interpreter::Error::Diagnostics(
+        diagnostics,
+    )) = error
+    {
+        return diagnostics
+            .iter()
+            .map(|d| {
+                diagnostic(
+                    doc_name,
+                    Some(d.doc_id.as_str()),
+                    d.line_number,
+                    d.span,
+                    d.message.clone(),
+                )
+            })
+            .collect();
+    }
+    let span = match error {
+        fastn_core::Error::FTDInterpreterError(ftd::interpreter::Error::Spanned {
+            span, ..
+        }) => Some(*span),
+        _ => None,
+    };
+    let (doc_id, line_number, message) = match error {
+        fastn_core::Error::FTDInterpreterError(e) => interpreter_error(e),
+        fastn_core::Error::FTDP1Error(e) => p1_error(e),
+        fastn_core::Error::FTDAstError(e) => ast_error(e),
+        e => (None, 0, e.to_string()),
+    };
+    vec![diagnostic(doc_name, doc_id, line_number, span, message)]
+}
+
+fn diagnostic(
+    doc_name: &str,
+    doc_id: Option<&str>,
+    line_number: usize,
+    span: Option<ftd_p1::Span>,
+    message: String,
+) -> lsp_types::Diagnostic {
+    let (range, message) = match doc_id {
+        Some(doc_id) if doc_id.trim_matches('/') != doc_name.trim_matches('/') => {
+            (line_range(0), format!("{doc_id}:{line_number}: {message}"))
+        }
+        _ => {
+            let line = line_number.saturating_sub(1) as u32;
+            let range = match span {
+                Some(span) => lsp_types::Range {
+                    start: lsp_types::Position {
+                        line,
+                        character: span.start as u32,
+                    },
+                    end: lsp_types::Position {
+                        line,
+                        character: span.end as u32,
+                    },
+                },
+                None => line_range(line),
+            };
+            (range, message)
+        }
+    };
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+        source: Some("fastn".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// `(doc_id, line_number, message)` of an interpreter error.
+fn interpreter_error(e: &ftd::interpreter::Error) -> (Option<&str>, usize, String) {
+    match e {
+        ftd::interpreter::Error::InvalidKind {
+            doc_id,
+            line_number,
+            message,
+        }
+        | ftd::interpreter::Error::ValueNotFound {
+            doc_id,
+            line_number,
+            message,
+        }
+        | ftd::interpreter::Error::ParseError {
+            doc_id,
+            line_number,
+            message,
+        } => (Some(doc_id.as_str()), *line_number, message.clone()),
+        ftd::interpreter::Error::P1Error(e) => p1_error(e),
+        ftd::interpreter::Error::ASTError(e) => ast_error(e),
+        ftd::interpreter::Error::Spanned { error, .. } => interpreter_error(error),
+        ftd::interpreter::Error::InvalidAccessError {
+            message,
+            line_number,
+        }
+        | ftd::interpreter::Error::FoundCycle {
+            message,
+            line_number,
+        } => (None, *line_number, message.clone()),
+        e => (None, 0, e.to_string()),
+    }
+}
+
+fn ast_error(e: &ftd_ast::Error) -> (Option<&str>, usize, String) {
+    match e {
+        ftd_ast::Error::Parse {
+            message,
+            doc_id,
+            line_number,
+        } => (Some(doc_id.as_str()), *line_number, message.clone()),
+        ftd_ast::Error::P1(e) => p1_error(e),
+        e => (None, 0, e.to_string()),
+    }
+}
+
+fn p1_error(e: &ftd_p1::Error) -> (Option<&str>, usize, String) {
+    match e {
+        ftd_p1::Error::SectionNotFound {
+            doc_id,
+            line_number,
+        } => (
+            Some(doc_id.as_str()),
+            *line_number,
+            "Section not found".to_string(),
+        ),
+        ftd_p1::Error::MoreThanOneCaption {
+            doc_id,
+            line_number,
+        } => (
+            Some(doc_id.as_str()),
+            *line_number,
+            "More than one caption".to_string(),
+        ),
+        ftd_p1::Error::ParseError {
+            message,
+            doc_id,
+            line_number,
+        } => (Some(doc_id.as_str()), *line_number, message.clone()),
+        ftd_p1::Error::MoreThanOneHeader {
+            key,
+            doc_id,
+            line_number,
+        } => (
+            Some(doc_id.as_str()),
+            *line_number,
+            format!("More than one `{key}` header"),
+        ),
+        ftd_p1::Error::HeaderNotFound {
+            key,
+            doc_id,
+            line_number,
+        } => (
+            Some(doc_id.as_str()),
+            *line_number,
+            format!("Header `{key}` not found"),
+        ),
+    }
+}
+
+/// The whole of the 0 based `line`.
+pub(crate) fn line_range(line: u32) -> lsp_types::Range {
+    lsp_types::Range {
+        start: lsp_types::Position { line, character: 0 },
+        // clients treat a character past the end of the line as the end of the line
+        end: lsp_types::Position {
+            line,
+            character: u32::MAX,
+        },
+    }
+}
+
+/// The definition `name`, as written in the document, refers to: `ds.page`, `name` defined in the
+/// document itself, or `person.name`, a field of the record variable `person`.
+pub(crate) fn lookup<'a>(
+    document: &'a ftd::interpreter::Document,
+    name: &str,
+) -> Option<(String, &'a ftd::interpreter::Thing)> {
+    let mut name = name;
+    loop {
+        let full_name = document.tdoc().resolve_name(name);
+        if let Some(thing) = document.data.get(&full_name) {
+            return Some((full_name, thing));
+        }
+        name = name.rsplit_once('.')?.0;
+    }
+}
+
+/// The headers `section` can have, and the full name of the component or record they belong
+/// to: the arguments of the component in `-- ds.page:`, or the fields of the record in
+/// `-- person amitu:`.
+pub(crate) fn fields<'a>(
+    document: &'a ftd::interpreter::Document,
+    section: &fastn_lsp::syntax::Section,
+) -> Option<(String, Vec<&'a fastn_resolved::Field>)> {
+    let (full_name, thing) = lookup(document, section.kind.unwrap_or(section.name))?;
+    let fields = match thing {
+        ftd::interpreter::Thing::Record(r) if section.kind.is_some() => r.fields.iter().collect(),
+        ftd::interpreter::Thing::Component(c) if section.kind.is_none() => {
+            c.arguments.iter().collect()
+        }
+        ftd::interpreter::Thing::WebComponent(c) if section.kind.is_none() => {
+            c.arguments.iter().collect()
+        }
+        _ => return None,
+    };
+    Some((full_name, fields))
+}
+
+/// The fields of the record the variable `name` holds.
+pub(crate) fn record_fields<'a>(
+    document: &'a ftd::interpreter::Document,
+    name: &str,
+) -> Option<Vec<&'a fastn_resolved::Field>> {
+    let (_, ftd::interpreter::Thing::Variable(v)) = lookup(document, name)? else {
+        return None;
+    };
+    let mut kind = &v.kind.kind;
+    while let fastn_resolved::Kind::Optional { kind: inner }
+    | fastn_resolved::Kind::List { kind: inner } = kind
+    {
+        kind = inner;
+    }
+    let fastn_resolved::Kind::Record { name } = kind else {
+        return None;
+    };
+    match document.data.get(name)? {
+        ftd::interpreter::Thing::Record(r) => Some(r.fields.iter().collect()),
+        _ => None,
+    }
+}
+
+/// `ds.page` for `fastn-community.github.io/doc-site#page`, in a document that imports the doc
+/// site as `ds`. Things not reachable from the document keep their full name.
+pub(crate) fn short_name(document: &ftd::interpreter::Document, full_name: &str) -> String {
+    let Some((module, thing)) = full_name.split_once('#') else {
+        return full_name.to_string();
+    };
+    if module.trim_matches('/') == document.name.trim_matches('/') {
+        return thing.to_string();
+    }
+    match document.aliases.iter().find(|(_, m)| m.as_str() == module) {
+        Some((alias, _)) => format!("{alias}.{thing}"),
+        None => full_name.to_string(),
+    }
+}
+
+/// The kind as it is written in ftd, e.g. `optional caption string`.
+pub(crate) fn kind_text(
+    document: &ftd::interpreter::Document,
+    kind: &fastn_resolved::KindData,
+) -> String {
+    let (optional, inner) = match &kind.kind {
+        fastn_resolved::Kind::Optional { kind } => ("optional ", kind.as_ref()),
+        kind => ("", kind),
+    };
+    let source = match (kind.caption, kind.body) {
+        (true, true) => "caption or body ",
+        (true, false) => "caption ",
+        (false, true) => "body ",
+        (false, false) => "",
+    };
+    format!("{optional}{source}{}", kind_name(document, inner))
+}
+
+fn kind_name(document: &ftd::interpreter::Document, kind: &fastn_resolved::Kind) -> String {
+    match kind {
+        fastn_resolved::Kind::Optional { kind } => {
+            format!("optional {}", kind_name(document, kind))
+        }
+        fastn_resolved::Kind::List { kind } => format!("{} list", kind_name(document, kind)),
+        fastn_resolved::Kind::Constant { kind } => {
+            format!("constant {}", kind_name(document, kind))
+        }
+        fastn_resolved::Kind::Record { name }
+        | fastn_resolved::Kind::OrType { name, .. }
+        | fastn_resolved::Kind::UI {
+            name: Some(name), ..
+        } => short_name(document, name),
+        fastn_resolved::Kind::UI { name: None, .. } => "ftd.ui".to_string(),
+        kind => kind.get_name(),
+    }
+}
+
+/// `optional caption string title:`
+pub(crate) fn field_text(
+    document: &ftd::interpreter::Document,
+    field: &fastn_resolved::Field,
+) -> String {
+    format!(
+        "{} {}{}:",
+        kind_text(document, &field.kind),
+        if field.mutable { "$" } else { "" },
+        field.name
+    )
+}
+
+/// The hover text of `thing`, its definition without the values.
+pub(crate) fn hover_text(
+    document: &ftd::interpreter::Document,
+    full_name: &str,
+    thing: &ftd::interpreter::Thing,
+) -> String {
+    let name = short_name(document, full_name);
+    let fields = |fields: &[fastn_resolved::Field]| {
+        fields
+            .iter()
+            .map(|f| format!("\n{}", field_text(document, f)))
+            .collect::<String>()
+    };
+    let text = match thing {
+        ftd::interpreter::Thing::Variable(v) => format!(
+            "-- {} {}{name}:",
+            kind_text(document, &v.kind),
+            if v.mutable { "$" } else { "" }
+        ),
+        ftd::interpreter::Thing::Record(r) => {
+            format!("-- record {name}:{}", fields(&r.fields))
+        }
+        ftd::interpreter::Thing::Component(c) => {
+            format!("-- component {name}:{}", fields(&c.arguments))
+        }
+        ftd::interpreter::Thing::WebComponent(c) => {
+            format!("-- web-component {name}:{}", fields(&c.arguments))
+        }
+        ftd::interpreter::Thing::Function(f) => format!(
+            "-- {} {name}({}):",
+            kind_text(document, &f.return_kind),
+            f.arguments
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ftd::interpreter::Thing::OrType(o) => format!(
+            "-- or-type {name}:{}",
+            o.variants
+                .iter()
+                .map(|v| {
+                    let variant = v.name();
+                    let variant = variant.rsplit('.').next().unwrap_or_default();
+                    match v {
+                        fastn_resolved::OrTypeVariant::AnonymousRecord(_) => {
+                            format!("\n-- record {variant}:")
+                        }
+                        fastn_resolved::OrTypeVariant::Regular(f)
+                        | fastn_resolved::OrTypeVariant::Constant(f) => {
+                            format!("\n-- {} {variant}:", kind_text(document, &f.kind))
+                        }
+                    }
+                })
+                .collect::<String>()
+        ),
+        ftd::interpreter::Thing::OrTypeWithVariant { variant, .. } => {
+            format!("{name}.{}", variant.name())
+        }
+        ftd::interpreter::Thing::Export { from, .. } => {
+            format!("{name}: exported from {}", short_name(document, from))
+        }
+    };
+    format!("```ftd\n{text}\n```")
+}
+
+/// The file `module` is read from: a document of the package, or of one of the dependencies in
+/// `.packages`. `module` is a package name followed by the path of a document in the package.
+pub(crate) async fn module_path(
+    config: &fastn_core::Config,
+    module: &str,
+) -> Option<fastn_ds::Path> {
+    let module = module.trim_matches('/');
+    // the longest prefix of `module` that is a package we have
+    let mut package = module;
+    let root = loop {
+        if package.eq(config.package.name.as_str()) {
+            break config.ds.root();
+        }
+        let root = config.packages_root.join(package);
+        if config.ds.exists(&root.join("FASTN.ftd"), &None).await {
+            break root;
+        }
+        package = package.rsplit_once('/')?.0;
+    };
+
+    let rest = module[package.len()..].trim_matches('/');
+    let candidates = if rest.is_empty() {
+        vec!["index.ftd".to_string()]
+    } else {
+        vec![format!("{rest}.ftd"), format!("{rest}/index.ftd")]
+    };
+    for candidate in candidates {
+        let path = root.join(candidate);
+        if config.ds.exists(&path, &None).await {
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    const SOURCE: &str = indoc::indoc! {"
+        -- record person:
+        caption name:
+        optional integer age:
+
+        -- person list $people:
+
+        -- component card:
+        caption person who:
+        optional body about:
+
+        -- ftd.text: $card.who.name
+
+        -- end: card
+    "};
+
+    fn document() -> ftd::interpreter::Document {
+        match ftd::interpreter::interpret("foo", SOURCE).unwrap() {
+            ftd::interpreter::Interpreter::Done { document } => document,
+            _ => unreachable!("the document has no imports or processors"),
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        let document = document();
+        let (full_name, thing) = super::lookup(&document, "card").unwrap();
+        assert_eq!(full_name, "foo#card");
+        assert_eq!(thing.line_number(), 7);
+        assert_eq!(
+            super::lookup(&document, "people.name").unwrap().0,
+            "foo#people"
+        );
+        assert!(super::lookup(&document, "nobody").is_none());
+    }
+
+    #[test]
+    fn hover_text() {
+        let document = document();
+        let (full_name, thing) = super::lookup(&document, "person").unwrap();
+        assert_eq!(
+            super::hover_text(&document, &full_name, thing),
+            "```ftd\n-- record person:\ncaption string name:\noptional integer age:\n```"
+        );
+        let (full_name, thing) = super::lookup(&document, "people").unwrap();
+        assert_eq!(
+            super::hover_text(&document, &full_name, thing),
+            "```ftd\n-- person list $people:\n```"
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let document = document();
+        let section = fastn_lsp::syntax::Section {
+            kind: None,
+            name: "card",
+            line: 0,
+        };
+        let (owner, fields) = super::fields(&document, &section).unwrap();
+        assert_eq!(owner, "foo#card");
+        assert_eq!(
+            fields
+                .iter()
+                .map(|f| super::field_text(&document, f))
+                .collect::<Vec<_>>(),
+            vec!["caption person who:", "optional body string about:"]
+        );
+
+        let section = fastn_lsp::syntax::Section {
+            kind: Some("person"),
+            name: "amitu",
+            line: 0,
+        };
+        assert_eq!(super::fields(&document, &section).unwrap().1.len(), 2);
+        assert_eq!(super::record_fields(&document, "people").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn processors_with_side_effects_are_not_run() {
+        let ds = fastn_ds::DocumentStore::with_storage(
+            "/site",
+            actix_web::web::Data::new(Default::default()),
+            std::sync::Arc::new(fastn_ds::MemoryStorage::with_files([(
+                "/site/FASTN.ftd",
+                "-- import: fastn\n\n-- fastn.package: example.com/site\n",
+            )])),
+        );
+        let config = fastn_core::Config::read(ds, false, &None).await.unwrap();
+
+        let content = indoc::indoc! {"
+            -- import: fastn/processors as pr
+
+            -- integer list ids:
+            $processor$: pr.sql-execute
+
+            INSERT INTO users (name) VALUES ('amitu') RETURNING id;
+        "};
+        // the database does not exist, running the query would fail
+        let document = super::interpret(&config, "index.ftd", content)
+            .await
+            .unwrap();
+
+        let (_, ids) = super::lookup(&document, "ids").unwrap();
+        assert!(matches!(
+            ids,
+            ftd::interpreter::Thing::Variable(fastn_resolved::Variable {
+                value: fastn_resolved::PropertyValue::Value {
+                    value: fastn_resolved::Value::List { data, .. },
+                    ..
+                },
+                ..
+            }) if data.is_empty()
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+#![deny(unused_crate_dependencies)]
+
+extern crate self as fastn_lsp;
+
+mod analysis;
+mod server;
+mod syntax;
+
+pub use server::run;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("ProtocolError: {}", _0)]
+    Protocol(#[from] lsp_server::ProtocolError),
+
+    #[error("SerdeJsonError: {}", _0)]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("IoError: {}", _0)]
+    Io(#[from] std::io::Error),
+
+    #[error("The editor closed the connection")]
+    Disconnected,
+}
@@ -0,0 +1,473 @@
+/// Runs the language server over stdio until the editor shuts it down.
+///
+/// `ds` must be rooted at the package, the folder with `FASTN.ftd`. Diagnostics, hover,
+/// go-to-definition and completion work for the ftd documents of the package; imports are
+/// resolved from the package and from its dependencies in `.packages`, so run `fastn update`
+/// first.
+pub async fn run(ds: fastn_ds::DocumentStore) -> Result<(), fastn_lsp::Error> {
+    let (connection, io_threads) = lsp_server::Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server {
+        config: read_config(&ds).await,
+        ds,
+        connection,
+        documents: Default::default(),
+        analyses: Default::default(),
+    };
+    server.serve().await?;
+
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> lsp_types::ServerCapabilities {
+    lsp_types::ServerCapabilities {
+        text_document_sync: Some(lsp_types::TextDocumentSyncCapability::Options(
+            lsp_types::TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(lsp_types::TextDocumentSyncKind::FULL),
+                save: Some(lsp_types::TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        hover_provider: Some(lsp_types::HoverProviderCapability::Simple(true)),
+        definition_provider: Some(lsp_types::OneOf::Left(true)),
+        completion_provider: Some(lsp_types::CompletionOptions {
+            trigger_characters: Some(vec!["$".to_string(), ".".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+async fn read_config(ds: &fastn_ds::DocumentStore) -> Result<fastn_core::Config, String> {
+    fastn_core::Config::read(ds.clone(), false, &None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+struct Server {
+    ds: fastn_ds::DocumentStore,
+    /// Read again when `FASTN.ftd` is saved, the error is shown on every open document.
+    config: Result<fastn_core::Config, String>,
+    connection: lsp_server::Connection,
+    /// The text of the open documents, as it is in the editor.
+    documents: std::collections::HashMap<lsp_types::Url, String>,
+    /// The last version of each open document that could be interpreted. It is kept while the
+    /// document does not interpret, so completion keeps working while something is being typed.
+    analyses: std::collections::HashMap<lsp_types::Url, ftd::interpreter::Document>,
+}
+
+impl Server {
+    async fn serve(&mut self) -> Result<(), fastn_lsp::Error> {
+        loop {
+            let receiver = self.connection.receiver.clone();
+            let message = tokio::task::spawn_blocking(move || receiver.recv())
+                .await
+                .expect("the receiver does not panic");
+            let Ok(message) = message else {
+                return Ok(());
+            };
+            match message {
+                lsp_server::Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request).await?;
+                }
+                lsp_server::Message::Notification(notification) => {
+                    self.handle_notification(notification).await?;
+                }
+                lsp_server::Message::Response(_) => {}
+            }
+        }
+    }
+
+    async fn handle_request(
+        &mut self,
+        request: lsp_server::Request,
+    ) -> Result<(), fastn_lsp::Error> {
+        use lsp_types::request::Request;
+
+        let response = match request.method.as_str() {
+            lsp_types::request::HoverRequest::METHOD => {
+                let params: lsp_types::HoverParams = serde_json::from_value(request.params)?;
+                lsp_server::Response::new_ok(request.id, self.hover(params))
+            }
+            lsp_types::request::GotoDefinition::METHOD => {
+                let params: lsp_types::GotoDefinitionParams =
+                    serde_json::from_value(request.params)?;
+                lsp_server::Response::new_ok(request.id, self.definition(params).await)
+            }
+            lsp_types::request::Completion::METHOD => {
+                let params: lsp_types::CompletionParams = serde_json::from_value(request.params)?;
+                lsp_server::Response::new_ok(request.id, self.completion(params))
+            }
+            method => lsp_server::Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("`{method}` is not supported"),
+            ),
+        };
+        self.send(response.into())
+    }
+
+    async fn handle_notification(
+        &mut self,
+        notification: lsp_server::Notification,
+    ) -> Result<(), fastn_lsp::Error> {
+        use lsp_types::notification::Notification;
+
+        match notification.method.as_str() {
+            lsp_types::notification::DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.check(uri).await
+            }
+            lsp_types::notification::DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // the server asks for full text sync, the last change has the whole document
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                self.check(uri).await
+            }
+            lsp_types::notification::DidSaveTextDocument::METHOD => {
+                let params: lsp_types::DidSaveTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if params.text_document.uri.path().ends_with("/FASTN.ftd") {
+                    self.config = read_config(&self.ds).await;
+                    let uris = self.documents.keys().cloned().collect::<Vec<_>>();
+                    for uri in uris {
+                        self.check(uri).await?;
+                    }
+                }
+                Ok(())
+            }
+            lsp_types::notification::DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.analyses.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Interprets the open document `uri`, and publishes the errors it has, if any.
+    async fn check(&mut self, uri: lsp_types::Url) -> Result<(), fastn_lsp::Error> {
+        let Some(content) = self.documents.get(&uri) else {
+            return Ok(());
+        };
+        let config = match self.config {
+            Ok(ref config) => config,
+            Err(ref e) => {
+                let diagnostic = lsp_types::Diagnostic {
+                    range: fastn_lsp::analysis::line_range(0),
+                    severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+                    source: Some("fastn".to_string()),
+                    message: format!("Failed to read FASTN.ftd: {e}"),
+                    ..Default::default()
+                };
+                return self.publish(uri, vec![diagnostic]);
+            }
+        };
+        let Some(id) = path(&uri).and_then(|p| fastn_lsp::analysis::document_id(config, &p)) else {
+            return Ok(());
+        };
+
+        let diagnostics = match fastn_lsp::analysis::interpret(config, &id, content).await {
+            Ok(document) => {
+                self.analyses.insert(uri.clone(), document);
+                vec![]
+            }
+            Err(e) => fastn_lsp::analysis::diagnostics(
+                &fastn_lsp::analysis::document_name(config, &id),
+                &e,
+            ),
+        };
+        self.publish(uri, diagnostics)
+    }
+
+    fn hover(&self, params: lsp_types::HoverParams) -> Option<lsp_types::Hover> {
+        let position = params.text_document_position_params;
+        let uri = &position.text_document.uri;
+        let document = self.analyses.get(uri)?;
+        let lines = lines(self.documents.get(uri)?);
+        let line = lines.get(position.position.line as usize)?;
+        let offset = fastn_lsp::syntax::byte_offset(line, position.position.character);
+        let name = fastn_lsp::syntax::name_at(line, offset)?;
+
+        let value = match header_field(document, &lines, position.position.line as usize, offset) {
+            Some((_, field)) => format!(
+                "```ftd\n{}\n```",
+                fastn_lsp::analysis::field_text(document, field)
+            ),
+            None => {
+                let (full_name, thing) = fastn_lsp::analysis::lookup(document, name)?;
+                fastn_lsp::analysis::hover_text(document, &full_name, thing)
+            }
+        };
+        Some(lsp_types::Hover {
+            contents: lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
+                kind: lsp_types::MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    async fn definition(
+        &self,
+        params: lsp_types::GotoDefinitionParams,
+    ) -> Option<lsp_types::GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = &position.text_document.uri;
+        let config = self.config.as_ref().ok()?;
+        let document = self.analyses.get(uri)?;
+        let lines = lines(self.documents.get(uri)?);
+        let line = lines.get(position.position.line as usize)?;
+        let offset = fastn_lsp::syntax::byte_offset(line, position.position.character);
+        let name = fastn_lsp::syntax::name_at(line, offset)?;
+
+        let (full_name, line_number) =
+            match header_field(document, &lines, position.position.line as usize, offset) {
+                Some((owner, field)) => (owner, field.line_number),
+                None => {
+                    let (full_name, thing) = fastn_lsp::analysis::lookup(document, name)?;
+                    let line_number = thing.line_number();
+                    (full_name, line_number)
+                }
+            };
+        let (module, _) = full_name.split_once('#')?;
+        let target = if module.trim_matches('/') == document.name.trim_matches('/') {
+            uri.clone()
+        } else {
+            let path = fastn_lsp::analysis::module_path(config, module).await?;
+            lsp_types::Url::from_file_path(path.to_string()).ok()?
+        };
+        Some(lsp_types::GotoDefinitionResponse::Scalar(
+            lsp_types::Location {
+                uri: target,
+                range: fastn_lsp::analysis::line_range(line_number.saturating_sub(1) as u32),
+            },
+        ))
+    }
+
+    fn completion(
+        &self,
+        params: lsp_types::CompletionParams,
+    ) -> Option<lsp_types::CompletionResponse> {
+        let position = params.text_document_position;
+        let uri = &position.text_document.uri;
+        let document = self.analyses.get(uri)?;
+        let lines = lines(self.documents.get(uri)?);
+        let line = lines.get(position.position.line as usize)?;
+        let offset = fastn_lsp::syntax::byte_offset(line, position.position.character);
+        let start = fastn_lsp::syntax::name_start(line, offset);
+        let range = lsp_types::Range {
+            start: lsp_types::Position {
+                line: position.position.line,
+                character: fastn_lsp::syntax::character(line, start),
+            },
+            end: position.position,
+        };
+        let item =
+            |label: String, kind, detail: String, new_text: String| lsp_types::CompletionItem {
+                label,
+                kind: Some(kind),
+                detail: Some(detail),
+                text_edit: Some(lsp_types::CompletionTextEdit::Edit(lsp_types::TextEdit {
+                    range,
+                    new_text,
+                })),
+                ..Default::default()
+            };
+
+        let items = match fastn_lsp::syntax::completion_context(
+            &lines,
+            position.position.line as usize,
+            offset,
+        )? {
+            fastn_lsp::syntax::Context::Section => {
+                let mut items = KEYWORDS
+                    .iter()
+                    .map(|k| {
+                        item(
+                            k.to_string(),
+                            lsp_types::CompletionItemKind::KEYWORD,
+                            "keyword".to_string(),
+                            k.to_string(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                items.extend(things(document).filter_map(|(name, thing)| {
+                    let kind = match thing {
+                        ftd::interpreter::Thing::Component(_)
+                        | ftd::interpreter::Thing::WebComponent(_) => {
+                            lsp_types::CompletionItemKind::CLASS
+                        }
+                        ftd::interpreter::Thing::Record(_) => lsp_types::CompletionItemKind::STRUCT,
+                        ftd::interpreter::Thing::OrType(_) => lsp_types::CompletionItemKind::ENUM,
+                        _ => return None,
+                    };
+                    Some(item(name.clone(), kind, detail(document, thing), name))
+                }));
+                items
+            }
+            fastn_lsp::syntax::Context::Reference => {
+                let typed = &line[start..offset];
+                match typed.rsplit_once('.') {
+                    // `$person.` lists the fields of the record in `person`
+                    Some((variable, _)) => fastn_lsp::analysis::record_fields(document, variable)?
+                        .into_iter()
+                        .map(|f| {
+                            let name = format!("{variable}.{}", f.name);
+                            item(
+                                name.clone(),
+                                lsp_types::CompletionItemKind::FIELD,
+                                fastn_lsp::analysis::kind_text(document, &f.kind),
+                                name,
+                            )
+                        })
+                        .collect(),
+                    None => things(document)
+                        .filter_map(|(name, thing)| {
+                            let kind = match thing {
+                                ftd::interpreter::Thing::Variable(_) => {
+                                    lsp_types::CompletionItemKind::VARIABLE
+                                }
+                                ftd::interpreter::Thing::Function(_) => {
+                                    lsp_types::CompletionItemKind::FUNCTION
+                                }
+                                _ => return None,
+                            };
+                            Some(item(name.clone(), kind, detail(document, thing), name))
+                        })
+                        .collect(),
+                }
+            }
+            fastn_lsp::syntax::Context::Header(section) => {
+                fastn_lsp::analysis::fields(document, &section)?
+                    .1
+                    .into_iter()
+                    .map(|f| {
+                        item(
+                            f.name.clone(),
+                            lsp_types::CompletionItemKind::PROPERTY,
+                            fastn_lsp::analysis::kind_text(document, &f.kind),
+                            format!("{}: ", f.name),
+                        )
+                    })
+                    .collect()
+            }
+        };
+        Some(lsp_types::CompletionResponse::Array(items))
+    }
+
+    fn publish(
+        &self,
+        uri: lsp_types::Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), fastn_lsp::Error> {
+        use lsp_types::notification::Notification;
+
+        self.send(
+            lsp_server::Notification::new(
+                lsp_types::notification::PublishDiagnostics::METHOD.to_string(),
+                lsp_types::PublishDiagnosticsParams {
+                    uri,
+                    diagnostics,
+                    version: None,
+                },
+            )
+            .into(),
+        )
+    }
+
+    fn send(&self, message: lsp_server::Message) -> Result<(), fastn_lsp::Error> {
+        self.connection
+            .sender
+            .send(message)
+            .map_err(|_| fastn_lsp::Error::Disconnected)
+    }
+}
+
+/// Words that can follow `--` besides the things the document can see.
+const KEYWORDS: &[&str] = &[
+    "import",
+    "component",
+    "record",
+    "or-type",
+    "web-component",
+    "end",
+    "string",
+    "integer",
+    "decimal",
+    "boolean",
+    "optional",
+];
+
+fn path(uri: &lsp_types::Url) -> Option<fastn_ds::Path> {
+    let path = uri.to_file_path().ok()?;
+    Some(fastn_ds::Path::new(path.to_str()?))
+}
+
+/// The lines of `text`; unlike `str::lines` it keeps the empty line after a trailing newline,
+/// where the cursor often is.
+fn lines(text: &str) -> Vec<&str> {
+    text.split('\n').map(|l| l.trim_end_matches('\r')).collect()
+}
+
+/// The field the header at the cursor sets, with the full name of its component or record: the
+/// `title` argument of `ds.page` for `ti|tle: Hello` in a `-- ds.page:` section.
+fn header_field<'a>(
+    document: &'a ftd::interpreter::Document,
+    lines: &[&str],
+    line: usize,
+    offset: usize,
+) -> Option<(String, &'a fastn_resolved::Field)> {
+    let text = lines.get(line)?;
+    let (key, _) = text.split_once(':')?;
+    if text.starts_with('-') || offset > key.len() {
+        return None;
+    }
+    let key = key.trim().trim_start_matches('$');
+    let section = fastn_lsp::syntax::enclosing_section(lines, line)?;
+    let (owner, fields) = fastn_lsp::analysis::fields(document, &section)?;
+    let field = fields.into_iter().find(|f| f.name == key)?;
+    Some((owner, field))
+}
+
+/// The things the document can refer to by a short name, with that name.
+fn things(
+    document: &ftd::interpreter::Document,
+) -> impl Iterator<Item = (String, &ftd::interpreter::Thing)> {
+    document.data.iter().filter_map(|(full_name, thing)| {
+        let name = fastn_lsp::analysis::short_name(document, full_name);
+        (!name.contains('#')).then_some((name, thing))
+    })
+}
+
+fn detail(document: &ftd::interpreter::Document, thing: &ftd::interpreter::Thing) -> String {
+    match thing {
+        ftd::interpreter::Thing::Variable(v) => fastn_lsp::analysis::kind_text(document, &v.kind),
+        ftd::interpreter::Thing::Function(f) => format!(
+            "function returning {}",
+            fastn_lsp::analysis::kind_text(document, &f.return_kind)
+        ),
+        ftd::interpreter::Thing::Component(_) => "component".to_string(),
+        ftd::interpreter::Thing::WebComponent(_) => "web-component".to_string(),
+        ftd::interpreter::Thing::Record(_) => "record".to_string(),
+        ftd::interpreter::Thing::OrType(_) => "or-type".to_string(),
+        thing => fastn_lsp::analysis::short_name(document, &thing.name()),
+    }
+}
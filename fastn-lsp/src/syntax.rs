@@ -0,0 +1,199 @@
+//! Text level helpers: what is under the cursor, and which section the cursor is in. These work
+//! on the text in the editor, which is often incomplete while it is being typed.
+
+/// A `-- <kind> <name>:` line.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Section<'a> {
+    /// `person` in `-- person amitu:`, `None` in `-- ftd.text:`.
+    pub kind: Option<&'a str>,
+    pub name: &'a str,
+    /// 0 based.
+    pub line: usize,
+}
+
+/// What is being typed at the cursor.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Context<'a> {
+    /// The kind or the component after `-- `.
+    Section,
+    /// A header of the section.
+    Header(Section<'a>),
+    /// A reference after `$`.
+    Reference,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+/// The byte offset in `line` of the utf-16 `character` of an LSP position.
+pub(crate) fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// The utf-16 character of an LSP position at the byte `offset` of `line`.
+pub(crate) fn character(line: &str, offset: usize) -> u32 {
+    line[..offset].chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+/// Where the name ending at `offset` starts: `-- ds.pa|` -> the offset of `d`.
+pub(crate) fn name_start(line: &str, offset: usize) -> usize {
+    line[..offset]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_name_char(*c))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+/// The name under the cursor, without the `$` of a reference: `ds.page` in `-- ds.pa|ge:`.
+pub(crate) fn name_at(line: &str, offset: usize) -> Option<&str> {
+    let start = name_start(line, offset);
+    let end = line[offset..]
+        .find(|c| !is_name_char(c))
+        .map(|i| offset + i)
+        .unwrap_or(line.len());
+    let name = line[start..end].trim_matches('.');
+    (!name.is_empty()).then_some(name)
+}
+
+/// Parses a section line, `None` for other lines and for `-- end:`.
+pub(crate) fn section(line: &str, line_number: usize) -> Option<Section<'_>> {
+    let rest = line.strip_prefix("-- ")?;
+    let head = rest.split_once(':').map(|(head, _)| head).unwrap_or(rest);
+    // `-- string full-name(first, last):`
+    let head = head.split_once('(').map(|(head, _)| head).unwrap_or(head);
+    let mut words = head.split_whitespace().collect::<Vec<_>>();
+    let name = words.pop()?;
+    if words.is_empty() && name == "end" {
+        return None;
+    }
+    let kind = words
+        .into_iter()
+        .find(|w| !matches!(*w, "optional" | "caption" | "body" | "or"));
+    Some(Section {
+        kind,
+        name,
+        line: line_number,
+    })
+}
+
+/// The section whose headers include `line`: the closest section line above it, if there is no
+/// empty line in between, as the body of a section starts after an empty line.
+pub(crate) fn enclosing_section<'a>(lines: &[&'a str], line: usize) -> Option<Section<'a>> {
+    for number in (0..line).rev() {
+        let text = lines[number];
+        if text.trim().is_empty() {
+            return None;
+        }
+        if text.starts_with("-- ") {
+            return section(text, number);
+        }
+    }
+    None
+}
+
+/// What is being typed at the byte `offset` of `line`.
+pub(crate) fn completion_context<'a>(
+    lines: &[&'a str],
+    line: usize,
+    offset: usize,
+) -> Option<Context<'a>> {
+    let before = &lines.get(line)?[..offset];
+    if let Some(start) = before.rfind('$')
+        && before[start + 1..].chars().all(is_name_char)
+    {
+        return Some(Context::Reference);
+    }
+    if let Some(rest) = before.strip_prefix("-- ") {
+        return (!rest.contains(':')).then_some(Context::Section);
+    }
+    if before.contains(':') || before.starts_with('-') || before.starts_with(' ') {
+        return None;
+    }
+    enclosing_section(lines, line).map(Context::Header)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn offsets() {
+        let line = "-- ftd.text: नमस्ते $name";
+        let offset = line.find('$').unwrap();
+        let character = super::character(line, offset);
+        assert_eq!(character, 20);
+        assert_eq!(super::byte_offset(line, character), offset);
+        assert_eq!(super::byte_offset(line, 100), line.len());
+    }
+
+    #[test]
+    fn name_at() {
+        assert_eq!(super::name_at("-- ds.page: Hello", 6), Some("ds.page"));
+        assert_eq!(super::name_at("-- ds.page: Hello", 3), Some("ds.page"));
+        assert_eq!(
+            super::name_at("value: $person.name", 10),
+            Some("person.name")
+        );
+        assert_eq!(super::name_at("-- ds.page: Hello", 10), Some("ds.page"));
+        assert_eq!(super::name_at("-- ds.page:  Hello", 11), None);
+    }
+
+    #[test]
+    fn section() {
+        assert_eq!(
+            super::section("-- ds.page: Hello", 3),
+            Some(super::Section {
+                kind: None,
+                name: "ds.page",
+                line: 3
+            })
+        );
+        assert_eq!(
+            super::section("-- optional person list people:", 0),
+            Some(super::Section {
+                kind: Some("person"),
+                name: "people",
+                line: 0
+            })
+        );
+        assert_eq!(
+            super::section("-- string full-name(first, last):", 0)
+                .unwrap()
+                .name,
+            "full-name"
+        );
+        assert_eq!(super::section("-- end: ftd.column", 0), None);
+        assert_eq!(super::section("\\-- ftd.text: escaped", 0), None);
+    }
+
+    #[test]
+    fn completion_context() {
+        let source = "-- person amitu:\nname: Amit\nag\n\n-- ftd.text: $amitu.\n-- ds.\n\nbody";
+        let lines = source.lines().collect::<Vec<_>>();
+        assert_eq!(
+            super::completion_context(&lines, 2, 2),
+            Some(super::Context::Header(super::Section {
+                kind: Some("person"),
+                name: "amitu",
+                line: 0
+            }))
+        );
+        assert_eq!(super::completion_context(&lines, 1, 10), None);
+        assert_eq!(
+            super::completion_context(&lines, 4, 20),
+            Some(super::Context::Reference)
+        );
+        assert_eq!(
+            super::completion_context(&lines, 5, 6),
+            Some(super::Context::Section)
+        );
+        assert_eq!(super::completion_context(&lines, 7, 4), None);
+    }
+}
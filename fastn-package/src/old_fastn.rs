@@ -70,6 +70,14 @@ pub struct EndpointData {
     pub mountpoint: String,
     #[serde(rename = "user-id")]
     pub user_id: Option<bool>,
+    #[serde(rename = "wasm-fuel")]
+    pub wasm_fuel: Option<i64>,
+    /// in megabytes
+    #[serde(rename = "wasm-memory")]
+    pub wasm_memory: Option<i64>,
+    /// in seconds
+    #[serde(rename = "wasm-timeout")]
+    pub wasm_timeout: Option<i64>,
 }
 
 /// PackageTemp is a struct that is used for mapping the `fastn.package` data in FASTN.ftd file. It is
@@ -78,6 +86,7 @@ pub struct EndpointData {
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct PackageTemp {
     pub name: String,
+    pub version: Option<String>,
     pub versioned: bool,
     #[serde(rename = "translation-of")]
     pub translation_of: Option<String>,
@@ -16,6 +16,10 @@ pub enum Kind {
     List {
         kind: Box<Kind>,
     },
+    /// A key-value map, `map<string, T>`, the keys are always strings.
+    Map {
+        kind: Box<Kind>,
+    },
     Optional {
         kind: Box<Kind>,
     },
@@ -42,6 +46,7 @@ impl Kind {
             Kind::Decimal { .. } => "decimal".to_string(),
             Kind::Constant { .. } => "constant".to_string(),
             Kind::List { .. } => "list".to_string(),
+            Kind::Map { .. } => "map".to_string(),
             Kind::Object { .. } => "object".to_string(),
             Kind::OrType { name, .. } => name.clone(),
             Kind::Optional { .. } => "optional".to_string(),
@@ -61,6 +66,7 @@ impl Kind {
             (Self::Optional { kind, .. }, _) => kind.is_same_as(other),
             (_, Self::Optional { kind: other, .. }) => self.is_same_as(other),
             (Self::List { kind: k1 }, Self::List { kind: k2 }) => k1.is_same_as(k2),
+            (Self::Map { kind: k1 }, Self::Map { kind: k2 }) => k1.is_same_as(k2),
             (Self::Template, Self::String) => true,
             (Self::String, Self::Template) => true,
             _ => self.eq(other),
@@ -167,6 +173,12 @@ impl Kind {
         }
     }
 
+    pub fn into_map(self) -> Kind {
+        Kind::Map {
+            kind: Box::new(self),
+        }
+    }
+
     pub fn into_optional(self) -> Kind {
         Kind::Optional {
             kind: Box::new(self),
@@ -212,6 +224,24 @@ impl Kind {
         matches!(self, Kind::List { .. })
     }
 
+    pub fn inner_map(self) -> Kind {
+        match self {
+            Kind::Map { kind } => kind.as_ref().to_owned(),
+            t => t,
+        }
+    }
+
+    pub fn ref_inner_map(&self) -> &Kind {
+        match self {
+            Kind::Map { kind } => kind,
+            t => t,
+        }
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, Kind::Map { .. })
+    }
+
     pub fn is_subsection_ui(&self) -> bool {
         matches!(
             self,
@@ -339,6 +369,10 @@ impl KindData {
         self.kind.is_list()
     }
 
+    pub fn is_map(&self) -> bool {
+        self.kind.is_map()
+    }
+
     pub fn is_or_type(&self) -> bool {
         self.kind.is_or_type()
     }
@@ -415,6 +449,16 @@ impl KindData {
         }
     }
 
+    pub fn map(self) -> KindData {
+        KindData {
+            kind: Kind::Map {
+                kind: Box::new(self.kind),
+            },
+            caption: self.caption,
+            body: self.body,
+        }
+    }
+
     pub fn constant(self) -> KindData {
         KindData {
             kind: Kind::Constant {
@@ -437,6 +481,14 @@ impl KindData {
         }
     }
 
+    pub fn inner_map(self) -> KindData {
+        KindData {
+            kind: self.kind.inner_map(),
+            caption: self.caption,
+            body: self.body,
+        }
+    }
+
     pub fn inner(self) -> KindData {
         let kind = match self.kind {
             Kind::Optional { kind } => kind.as_ref().to_owned(),
@@ -182,6 +182,10 @@ pub enum Value {
         data: Vec<PropertyValue>,
         kind: fastn_resolved::KindData,
     },
+    Map {
+        data: fastn_resolved::Map<PropertyValue>,
+        kind: fastn_resolved::KindData,
+    },
     Optional {
         data: Box<Option<Value>>,
         kind: fastn_resolved::KindData,
@@ -250,6 +254,7 @@ impl Value {
             Value::Record { name, .. } => fastn_resolved::Kind::record(name),
             Value::KwArgs { .. } => fastn_resolved::Kind::kwargs(),
             Value::List { kind, .. } => kind.kind.clone().into_list(),
+            Value::Map { kind, .. } => kind.kind.clone().into_map(),
             Value::Optional { kind, .. } => fastn_resolved::Kind::Optional {
                 kind: Box::new(kind.kind.clone()),
             },
@@ -313,12 +318,11 @@ impl Value {
     }
 
     pub fn is_empty(&self) -> bool {
-        if let Self::List { data, .. } = self {
-            if data.is_empty() {
-                return true;
-            }
+        match self {
+            Self::List { data, .. } => data.is_empty(),
+            Self::Map { data, .. } => data.is_empty(),
+            _ => false,
         }
-        false
     }
 
     pub fn is_equal(&self, other: &Self) -> bool {
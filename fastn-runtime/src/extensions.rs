@@ -92,7 +92,11 @@ pub(crate) trait ValueExt {
 }
 
 pub trait PropertyValueExt {
-    fn get_deps(&self, rdata: &fastn_runtime::ResolverData) -> Vec<String>;
+    fn get_deps(
+        &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
+        rdata: &fastn_runtime::ResolverData,
+    ) -> Vec<String>;
 
     fn to_fastn_js_value_with_none(
         &self,
@@ -127,9 +131,14 @@ pub(crate) trait FunctionCallExt {
 }
 
 pub(crate) trait ExpressionExt {
-    fn get_deps(&self, rdata: &fastn_runtime::ResolverData) -> Vec<String>;
+    fn get_deps(
+        &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
+        rdata: &fastn_runtime::ResolverData,
+    ) -> Vec<String>;
     fn update_node_with_variable_reference_js(
         &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
         rdata: &fastn_runtime::ResolverData,
     ) -> fastn_resolved::evalexpr::ExprNode;
 }
@@ -12,7 +12,7 @@ impl FunctionCallExt for fastn_resolved::FunctionCall {
         if let Some((default_module, module_variable_name)) = &self.module_name {
             function_name =
                 fastn_js::FunctionData::Definition(fastn_js::SetPropertyValue::Reference(
-                    fastn_runtime::utils::update_reference(name.as_str(), rdata),
+                    fastn_runtime::utils::update_reference(name.as_str(), doc, rdata),
                 ));
             name = name.replace(
                 format!("{module_variable_name}.").as_str(),
@@ -38,13 +38,19 @@ impl FunctionCallExt for fastn_resolved::FunctionCall {
 }
 
 impl fastn_runtime::extensions::PropertyValueExt for fastn_resolved::PropertyValue {
-    fn get_deps(&self, rdata: &fastn_runtime::ResolverData) -> Vec<String> {
+    fn get_deps(
+        &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
+        rdata: &fastn_runtime::ResolverData,
+    ) -> Vec<String> {
         let mut deps = vec![];
         if let Some(reference) = self.get_reference_or_clone() {
-            deps.push(fastn_runtime::utils::update_reference(reference, rdata));
+            deps.push(fastn_runtime::utils::update_reference(
+                reference, doc, rdata,
+            ));
         } else if let Some(function) = self.get_function() {
             for value in function.values.values() {
-                deps.extend(value.get_deps(rdata));
+                deps.extend(value.get_deps(doc, rdata));
             }
         }
         deps
@@ -172,6 +178,24 @@ impl fastn_runtime::extensions::ValueExt for fastn_resolved::Value {
                         .collect_vec(),
                 })
             }
+            fastn_resolved::Value::Map { data, .. } => {
+                fastn_js::SetPropertyValue::Value(fastn_js::Value::Map {
+                    value: data
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                k.to_string(),
+                                v.to_fastn_js_value_with_ui(
+                                    doc,
+                                    rdata,
+                                    has_rive_components,
+                                    should_return,
+                                ),
+                            )
+                        })
+                        .collect_vec(),
+                })
+            }
             fastn_resolved::Value::Record {
                 fields: record_fields,
                 name,
@@ -339,14 +363,18 @@ impl fastn_runtime::extensions::ComponentExt for fastn_resolved::ComponentInvoca
                         .references
                         .values()
                         .flat_map(|v| {
-                            v.get_deps(&rdata.clone_with_new_loop_alias(
-                                &loop_alias,
-                                &loop_counter_alias,
-                                doc.name().to_string(),
-                            ))
+                            v.get_deps(
+                                doc,
+                                &rdata.clone_with_new_loop_alias(
+                                    &loop_alias,
+                                    &loop_counter_alias,
+                                    doc.name().to_string(),
+                                ),
+                            )
                         })
                         .collect_vec(),
                     condition: condition.update_node_with_variable_reference_js(
+                        doc,
                         &rdata.clone_with_new_loop_alias(
                             &loop_alias,
                             &loop_counter_alias,
@@ -86,6 +86,14 @@ impl VariableExt for fastn_resolved::Variable {
                         .to_fastn_js_value_with_none(doc, has_rive_components),
                     prefix,
                 });
+            } else if self.kind.is_map() {
+                return fastn_js::Ast::MutableMap(fastn_js::MutableMap {
+                    name: self.name.to_string(),
+                    value: self
+                        .value
+                        .to_fastn_js_value_with_none(doc, has_rive_components),
+                    prefix,
+                });
             } else if self.mutable {
                 return fastn_js::Ast::MutableVariable(fastn_js::MutableVariable {
                     name: self.name.to_string(),
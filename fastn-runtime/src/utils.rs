@@ -107,8 +107,12 @@ pub(crate) fn to_key(key: &str) -> String {
     .to_string()
 }
 
-pub(crate) fn update_reference(reference: &str, rdata: &fastn_runtime::ResolverData) -> String {
-    let name = reference.to_string();
+pub(crate) fn update_reference(
+    reference: &str,
+    doc: &dyn fastn_resolved::tdoc::TDoc,
+    rdata: &fastn_runtime::ResolverData,
+) -> String {
+    let name = quote_map_keys(reference, doc);
 
     if fastn_builtins::constants::FTD_SPECIAL_VALUE
         .trim_start_matches('$')
@@ -178,6 +182,54 @@ pub(crate) fn update_reference(reference: &str, rdata: &fastn_runtime::ResolverD
     format!("{}.{name}", fastn_js::GLOBAL_VARIABLE_MAP)
 }
 
+/// Quotes the map keys of `reference`, `foo#scores.first-name` becomes `foo#scores."first-name"`,
+/// so that [fastn_js::utils::reference_to_js] passes them to the map as they are, and not as the
+/// js names it gives record fields. The kinds come from the definitions of `doc`, the parts of
+/// references it does not define, eg loop aliases, are left as they are.
+fn quote_map_keys(reference: &str, doc: &dyn fastn_resolved::tdoc::TDoc) -> String {
+    let (root, Some(remaining)) = get_doc_name_and_remaining(reference) else {
+        return reference.to_string();
+    };
+    let mut segments = remaining.split('.');
+    let mut quoted = vec![root.to_string()];
+    let mut kind = match doc.definitions().get(root.as_str()) {
+        Some(fastn_resolved::Definition::Variable(variable)) => Some(variable.kind.kind.clone()),
+        // `foo#show.scores.first-name`, an argument of the component `foo#show`
+        Some(fastn_resolved::Definition::Component(component)) => {
+            segments.next().and_then(|name| {
+                quoted.push(name.to_string());
+                component
+                    .arguments
+                    .iter()
+                    .find(|argument| argument.name == name)
+                    .map(|argument| argument.kind.kind.clone())
+            })
+        }
+        _ => return reference.to_string(),
+    };
+    for segment in segments {
+        let (part, next) = match kind.map(fastn_resolved::Kind::inner) {
+            Some(fastn_resolved::Kind::Map { kind }) => (format!("\"{segment}\""), Some(*kind)),
+            Some(fastn_resolved::Kind::List { kind }) => (segment.to_string(), Some(*kind)),
+            Some(fastn_resolved::Kind::Record { name }) => (
+                segment.to_string(),
+                match doc.definitions().get(name.as_str()) {
+                    Some(fastn_resolved::Definition::Record(record)) => record
+                        .fields
+                        .iter()
+                        .find(|field| field.name == segment)
+                        .map(|field| field.kind.kind.clone()),
+                    _ => None,
+                },
+            ),
+            _ => (segment.to_string(), None),
+        };
+        quoted.push(part);
+        kind = next;
+    }
+    quoted.join(".")
+}
+
 fn is_ftd_thing(name: &str) -> bool {
     name.starts_with("ftd#") || name.starts_with("ftd.")
 }
@@ -209,7 +261,7 @@ pub(crate) fn function_call_to_js_formula(
 ) -> fastn_js::Formula {
     let mut deps = vec![];
     for property_value in function_call.values.values() {
-        deps.extend(property_value.get_deps(rdata));
+        deps.extend(property_value.get_deps(doc, rdata));
     }
 
     fastn_js::Formula {
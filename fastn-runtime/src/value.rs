@@ -55,7 +55,11 @@ impl Value {
                         return fastn_js::SetPropertyValue::Value(fastn_js::Value::OrType {
                             variant: js_variant,
                             value: Some(Box::new(fastn_js::SetPropertyValue::Reference(
-                                fastn_runtime::utils::update_reference(data.name.as_str(), rdata),
+                                fastn_runtime::utils::update_reference(
+                                    data.name.as_str(),
+                                    doc,
+                                    rdata,
+                                ),
                             ))),
                         });
                     }
@@ -70,6 +74,7 @@ impl Value {
                 // for other datatypes, simply return a reference
                 fastn_js::SetPropertyValue::Reference(fastn_runtime::utils::update_reference(
                     data.name.as_str(),
+                    doc,
                     rdata,
                 ))
             }
@@ -80,7 +85,7 @@ impl Value {
                 fastn_runtime::utils::function_call_to_js_formula(function_call, doc, rdata),
             ),
             Value::Clone(name) => fastn_js::SetPropertyValue::Clone(
-                fastn_runtime::utils::update_reference(name, rdata),
+                fastn_runtime::utils::update_reference(name, doc, rdata),
             ),
         }
     }
@@ -122,16 +127,16 @@ fn properties_to_js_conditional_formula(
     let mut deps = vec![];
     let mut conditional_values = vec![];
     for property in properties {
-        deps.extend(property.value.get_deps(rdata));
+        deps.extend(property.value.get_deps(doc, rdata));
         if let Some(ref condition) = property.condition {
-            deps.extend(condition.get_deps(rdata));
+            deps.extend(condition.get_deps(doc, rdata));
         }
 
         conditional_values.push(fastn_js::ConditionalValue {
             condition: property
                 .condition
                 .as_ref()
-                .map(|condition| condition.update_node_with_variable_reference_js(rdata)),
+                .map(|condition| condition.update_node_with_variable_reference_js(doc, rdata)),
             expression: property.value.to_fastn_js_value(doc, rdata, false),
         });
     }
@@ -143,23 +148,34 @@ fn properties_to_js_conditional_formula(
 }
 
 impl fastn_runtime::extensions::ExpressionExt for fastn_resolved::Expression {
-    fn get_deps(&self, rdata: &fastn_runtime::ResolverData) -> Vec<String> {
+    fn get_deps(
+        &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
+        rdata: &fastn_runtime::ResolverData,
+    ) -> Vec<String> {
         let mut deps = vec![];
         for property_value in self.references.values() {
-            deps.extend(property_value.get_deps(rdata));
+            deps.extend(property_value.get_deps(doc, rdata));
         }
         deps
     }
 
     fn update_node_with_variable_reference_js(
         &self,
+        doc: &dyn fastn_resolved::tdoc::TDoc,
         rdata: &fastn_runtime::ResolverData,
     ) -> fastn_resolved::evalexpr::ExprNode {
-        return update_node_with_variable_reference_js_(&self.expression, &self.references, rdata);
+        return update_node_with_variable_reference_js_(
+            &self.expression,
+            &self.references,
+            doc,
+            rdata,
+        );
 
         fn update_node_with_variable_reference_js_(
             expr: &fastn_resolved::evalexpr::ExprNode,
             references: &fastn_builtins::Map<fastn_resolved::PropertyValue>,
+            doc: &dyn fastn_resolved::tdoc::TDoc,
             rdata: &fastn_runtime::ResolverData,
         ) -> fastn_resolved::evalexpr::ExprNode {
             let mut operator = expr.operator().clone();
@@ -179,7 +195,7 @@ impl fastn_runtime::extensions::ExpressionExt for fastn_resolved::Expression {
                 } else if let Some(fastn_resolved::PropertyValue::Reference { name, .. }) =
                     references.get(identifier)
                 {
-                    let name = fastn_runtime::utils::update_reference(name, rdata);
+                    let name = fastn_runtime::utils::update_reference(name, doc, rdata);
                     operator = fastn_resolved::evalexpr::Operator::VariableIdentifierRead {
                         identifier: fastn_js::utils::reference_to_js(name.as_str()),
                     }
@@ -188,7 +204,7 @@ impl fastn_runtime::extensions::ExpressionExt for fastn_resolved::Expression {
             let mut children = vec![];
             for child in expr.children() {
                 children.push(update_node_with_variable_reference_js_(
-                    child, references, rdata,
+                    child, references, doc, rdata,
                 ));
             }
             fastn_resolved::evalexpr::ExprNode::new(operator).add_children(children)
@@ -205,6 +221,11 @@ impl fastn_runtime::extensions::ArgumentExt for fastn_resolved::Argument {
                 data: vec![],
                 kind: self.kind.clone(),
             }))
+        } else if self.kind.is_map() {
+            Some(fastn_runtime::Value::Data(fastn_resolved::Value::Map {
+                data: Default::default(),
+                kind: self.kind.clone().inner_map(),
+            }))
         } else if self.kind.is_optional() {
             Some(fastn_runtime::Value::Data(
                 fastn_resolved::Value::Optional {
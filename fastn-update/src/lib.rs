@@ -4,6 +4,8 @@ use snafu::prelude::*;
 
 extern crate self as fastn_update;
 
+pub mod lock;
+mod resolve;
 mod utils;
 
 #[derive(Snafu, Debug)]
@@ -53,6 +55,24 @@ pub enum ArchiveError {
         package: String,
         source: fastn_ds::WriteError,
     },
+    #[snafu(display("Failed to read the installed files of package '{package}'"))]
+    ReadInstalled {
+        package: String,
+        source: fastn_ds::ReadError,
+    },
+    #[snafu(display("Failed to list the installed files of package '{package}'"))]
+    ListInstalled {
+        package: String,
+        source: fastn_ds::ListFilesError,
+    },
+    #[snafu(display(
+        "Checksum of package '{package}' does not match fastn.lock, expected {expected}, found {found}. Remove '{package}' from fastn.lock to accept it"
+    ))]
+    ChecksumMismatch {
+        package: String,
+        expected: String,
+        found: String,
+    },
 }
 
 #[derive(Snafu, Debug)]
@@ -64,9 +84,49 @@ pub enum DependencyError {
     },
 }
 
+#[derive(Snafu, Debug)]
+pub enum ResolveError {
+    #[snafu(display(
+        "Invalid version constraint '{constraint}' for dependency '{package}' of '{required_by}'"
+    ))]
+    InvalidConstraint {
+        package: String,
+        required_by: String,
+        constraint: String,
+        source: semver::Error,
+    },
+    #[snafu(display("Invalid version '{version}' in FASTN.ftd of package '{package}'"))]
+    InvalidVersion {
+        package: String,
+        version: String,
+        source: semver::Error,
+    },
+    #[snafu(display(
+        "No version of '{package}' satisfies all the requirements:\n{requirements}available: {available}"
+    ))]
+    Conflict {
+        package: String,
+        requirements: String,
+        available: String,
+    },
+    #[snafu(display("Dependency versions did not settle after {rounds} rounds"))]
+    Unsettled { rounds: usize },
+}
+
+#[derive(Snafu, Debug)]
+pub enum LockError {
+    #[snafu(display("Failed to read fastn.lock"))]
+    ReadLock { source: fastn_ds::ReadStringError },
+    #[snafu(display("Failed to parse fastn.lock"))]
+    ParseLock { source: serde_json::Error },
+    #[snafu(display("Failed to write fastn.lock"))]
+    WriteLock { source: fastn_ds::WriteError },
+}
+
 #[derive(Debug)]
 pub enum CheckError {
     WriteDuringCheck { package: String, file: String },
+    StaleLock { reason: String },
 }
 
 impl std::fmt::Display for CheckError {
@@ -84,6 +144,15 @@ impl std::fmt::Display for CheckError {
                     "Write Attempt".yellow()
                 )
             }
+            CheckError::StaleLock { reason } => {
+                write!(
+                    f,
+                    "{}\n\n{} is out of date: {}.\n\nRun `fastn update` to update it.",
+                    "Error: Stale Lock File".red().bold(),
+                    fastn_update::lock::LOCK_FILE,
+                    reason
+                )
+            }
         }
     }
 }
@@ -98,6 +167,10 @@ pub enum UpdateError {
     Archive(#[from] ArchiveError),
     #[error("Dependency error: {0}")]
     Dependency(#[from] DependencyError),
+    #[error("Resolve error: {0}")]
+    Resolve(#[from] ResolveError),
+    #[error("Lock error: {0}")]
+    Lock(#[from] LockError),
     #[error("Check error: {0}")]
     Check(#[from] CheckError),
     #[error("Config error: {0}")]
@@ -131,200 +204,226 @@ macro_rules! mdone {
     }
 }
 
-async fn update_dependencies(
+async fn update_dependencies<R: resolve::Registry>(
     ds: &fastn_ds::DocumentStore,
+    registry: &mut R,
     packages_root: fastn_ds::Path,
     current_package: &fastn_core::Package,
     check: bool,
 ) -> Result<(usize, usize), UpdateError> {
-    use colored::Colorize;
-    mprint!("Checking dependencies for {}.\n", current_package.name);
-
-    let mut stack = vec![current_package.clone()];
-    let mut resolved = std::collections::HashSet::new();
-    resolved.insert(current_package.name.to_string());
-    let mut all_packages: Vec<(String, fastn_core::Manifest)> = vec![];
-    let mut updated_packages: usize = 0;
-
-    while let Some(package) = stack.pop() {
-        for dependency in package.dependencies {
-            if resolved.contains(&dependency.package.name) {
-                continue;
-            }
-            mprint!("Checking {}: ", dependency.package.name.blue());
-            let dep_package = &dependency.package;
-            let package_name = dep_package.name.clone();
-            let dependency_path = packages_root.join(&package_name);
-            let updated = if ds.exists(&dependency_path.join(".is-local"), &None).await {
-                mdone!(true, "Local package");
-                all_packages.push((
-                    package_name.to_string(),
-                    update_local_package_manifest(&dependency_path).await?,
-                ));
-                false
-            } else if is_fifthtry_site_package(package_name.as_str()) {
-                update_fifthtry_dependency(
-                    &dependency,
-                    ds,
-                    packages_root.clone(),
-                    &mut all_packages,
-                    check,
-                )
-                .await?
-            } else {
-                update_github_dependency(
-                    &dependency,
-                    ds,
-                    packages_root.clone(),
-                    &mut all_packages,
-                    check,
-                )
-                .await?
-            };
+    let previous = lock::Lock::read(ds).await?;
+    if check
+        && let Some(reason) = previous
+            .as_ref()
+            .and_then(|lock| lock.stale_reason(current_package))
+    {
+        return Err(CheckError::StaleLock { reason }.into());
+    }
 
-            if updated {
-                updated_packages += 1;
-            }
+    mprint!("Checking dependencies for {}.\n", current_package.name);
+    let resolved = resolve::resolve(registry, current_package, previous.as_ref()).await?;
+    let mut checksums = std::collections::BTreeMap::new();
+    let mut downloaded = vec![];
+    for (package_name, release) in resolved.iter() {
+        let files = match release.archive {
+            Some(ref archive) => Some(archive_files(package_name, archive)?),
+            None => None,
+        };
+        let locked = previous.as_ref().and_then(|l| l.packages.get(package_name));
+        let checksum = verify(ds, &packages_root, release, files.as_deref(), locked).await?;
+        checksums.insert(package_name.clone(), checksum);
+        if let Some(files) = files {
+            downloaded.push((release, files));
+        }
+    }
+    let lock = new_lock(current_package, &resolved, &checksums);
+
+    // packages without a `fastn.lock` yet are only checked against what is in `.packages`
+    if check
+        && let Some(ref previous) = previous
+        && previous != &lock
+    {
+        return Err(CheckError::StaleLock {
+            reason: "the resolved dependencies are not the ones recorded in it".to_string(),
+        }
+        .into());
+    }
 
-            // TODO: why are we not updating FASTN_UI_INTERFACE package?
-            if package_name.eq(&fastn_core::FASTN_UI_INTERFACE) {
-                resolved.insert(package_name.to_string());
-                continue;
-            }
+    let updated_packages = downloaded.len();
+    for (release, files) in downloaded {
+        install(ds, &packages_root, release, files, check).await?;
+    }
 
-            let dep_package =
-                utils::resolve_dependency_package(ds, &dependency, &dependency_path).await?;
-            resolved.insert(package_name.to_string());
-            stack.push(dep_package);
-        }
+    if !check {
+        lock.write(ds).await?;
     }
 
-    let total_packages = all_packages.len();
+    let total_packages = lock.packages.len();
     fastn_core::ConfigTemp::write(
         ds,
         current_package.name.clone(),
-        all_packages.into_iter().collect(),
+        lock.packages
+            .into_iter()
+            .map(|(name, locked)| {
+                (
+                    name,
+                    fastn_core::Manifest::new(Default::default(), locked.url, locked.checksum),
+                )
+            })
+            .collect(),
     )
     .await?;
 
     Ok((updated_packages, total_packages))
 }
 
-async fn update_github_dependency(
-    dependency: &fastn_core::package::dependency::Dependency,
-    ds: &fastn_ds::DocumentStore,
-    packages_root: fastn_ds::Path,
-    all_packages: &mut Vec<(String, fastn_core::Manifest)>,
-    check: bool,
-) -> Result<bool, fastn_update::UpdateError> {
-    let dep_package = &dependency.package;
-    let package_name = dep_package.name.clone();
-    let dependency_path = &packages_root.join(&package_name);
-
-    if is_fifthtry_site_package(package_name.as_str()) {
-        return Err(fastn_update::UpdateError::InvalidPackage(format!(
-            "{package_name} is a fifthtry site."
-        )));
+/// The lock for the `resolved` dependencies of `current_package`, with the `checksums` of their
+/// files.
+fn new_lock(
+    current_package: &fastn_core::Package,
+    resolved: &std::collections::BTreeMap<String, resolve::Release>,
+    checksums: &std::collections::BTreeMap<String, String>,
+) -> lock::Lock {
+    let packages = resolved
+        .iter()
+        .map(|(name, release)| {
+            let checksum = checksums.get(name).cloned().unwrap_or_default();
+            let dependencies = release
+                .dependencies
+                .iter()
+                .map(|d| d.package.clone())
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            (
+                name.clone(),
+                lock::LockedPackage {
+                    version: release.version_string(),
+                    url: release.url.clone(),
+                    checksum,
+                    dependencies,
+                },
+            )
+        })
+        .collect();
+
+    lock::Lock {
+        package: current_package.name.clone(),
+        requires: lock::Lock::requires(current_package),
+        packages,
     }
-
-    let (manifest, updated) = download_unpack_zip_and_get_manifest(
-        dependency_path,
-        fastn_core::manifest::utils::get_zipball_url(package_name.as_str())
-            .unwrap()
-            .as_str(),
-        ds,
-        package_name.as_str(),
-        true,
-        check,
-    )
-    .await?;
-
-    all_packages.push((package_name.to_string(), manifest));
-    Ok(updated)
 }
 
-async fn update_fifthtry_dependency(
-    dependency: &fastn_core::package::dependency::Dependency,
-    ds: &fastn_ds::DocumentStore,
+/// Fetches the current archive of each package from github or fifthtry. A package has only one
+/// release: the one in its current archive.
+struct Remote<'a> {
+    ds: &'a fastn_ds::DocumentStore,
     packages_root: fastn_ds::Path,
-    all_packages: &mut Vec<(String, fastn_core::Manifest)>,
-    check: bool,
-) -> Result<bool, fastn_update::UpdateError> {
-    let dep_package = &dependency.package;
-    let package_name = dep_package.name.clone();
+}
 
-    if !is_fifthtry_site_package(package_name.as_str()) {
-        return Err(fastn_update::UpdateError::InvalidPackage(format!(
-            "{package_name} is not a fifthtry site."
-        )));
-    }
+impl resolve::Registry for Remote<'_> {
+    async fn releases(&mut self, package: &str) -> Result<Vec<resolve::Release>, UpdateError> {
+        use colored::Colorize;
 
-    let site_slug = package_name.trim_end_matches(".fifthtry.site");
-    let dependency_path = &packages_root.join(&package_name);
-    let site_zip_url = fastn_core::utils::fifthtry_site_zip_url(site_slug);
+        mprint!("Checking {}: ", package.blue());
+        let dependency_path = self.packages_root.join(package);
+        if self
+            .ds
+            .exists(&dependency_path.join(".is-local"), &None)
+            .await
+        {
+            mdone!(true, "Local package");
+            return Ok(vec![
+                release(self.ds, &self.packages_root, package, String::new(), None).await?,
+            ]);
+        }
 
-    let (manifest, updated) = download_unpack_zip_and_get_manifest(
-        dependency_path,
-        site_zip_url.as_str(),
-        ds,
-        package_name.as_str(),
-        false,
-        check,
-    )
-    .await?;
+        let (url, has_root_folder) = if is_fifthtry_site_package(package) {
+            let site_slug = package.trim_end_matches(".fifthtry.site");
+            (fastn_core::utils::fifthtry_site_zip_url(site_slug), false)
+        } else {
+            match fastn_core::manifest::utils::get_zipball_url(package) {
+                Some(url) => (url, true),
+                None => {
+                    return Err(UpdateError::InvalidPackage(format!(
+                        "{package}: can not find the archive url"
+                    )));
+                }
+            }
+        };
 
-    all_packages.push((package_name.to_string(), manifest));
+        let start = std::time::Instant::now();
+        let resp = utils::download_archive(self.ds, url.as_str(), &dependency_path.join(".etag"))
+            .await
+            .context(DownloadArchiveSnafu { package })?;
+
+        let elapsed = start.elapsed();
+        let elapsed_secs = elapsed.as_secs();
+        let elapsed_millis = elapsed.subsec_millis();
+
+        let red = elapsed_secs > 0 || elapsed_millis > 200;
+
+        let archive = match resp {
+            Some((etag, content)) => {
+                mdone!(red, "downloaded in {}.{:03}s", elapsed_secs, elapsed_millis);
+                Some(resolve::Archive {
+                    content,
+                    etag,
+                    has_root_folder,
+                })
+            }
+            None => {
+                mdone!(red, "checked in {}.{:03}s", elapsed_secs, elapsed_millis);
+                None
+            }
+        };
 
-    // todo: return true only if package is updated
-    Ok(updated)
+        Ok(vec![
+            release(self.ds, &self.packages_root, package, url, archive).await?,
+        ])
+    }
 }
 
-async fn update_local_package_manifest(
-    _path: &fastn_ds::Path,
-) -> Result<fastn_core::Manifest, fastn_update::UpdateError> {
-    Ok(fastn_core::Manifest {
-        files: Default::default(),
-        zip_url: "".to_string(),
-        checksum: "".to_string(),
-    })
+/// The release in `archive`, or in `.packages` if there is no new archive.
+async fn release(
+    ds: &fastn_ds::DocumentStore,
+    packages_root: &fastn_ds::Path,
+    package: &str,
+    url: String,
+    archive: Option<resolve::Archive>,
+) -> Result<resolve::Release, UpdateError> {
+    let fastn_ftd = match archive {
+        Some(ref archive) => archive_files(package, archive)?
+            .into_iter()
+            .find(|(path, _)| path == "FASTN.ftd")
+            .map(|(_, content)| String::from_utf8_lossy(&content).into_owned())
+            .ok_or_else(|| {
+                UpdateError::InvalidPackage(format!("{package}: FASTN.ftd not found in archive"))
+            })?,
+        None => ds
+            .read_to_string(&packages_root.join(package).join("FASTN.ftd"), &None)
+            .await
+            .map_err(fastn_core::Error::from)
+            .context(ResolveDependencySnafu { package })?,
+    };
+    let dep_package = utils::package_from_source(ds, package, &fastn_ftd)?;
+    Ok(resolve::Release::new(&dep_package, url, archive)?)
 }
 
-async fn download_unpack_zip_and_get_manifest(
-    dependency_path: &fastn_ds::Path,
-    zip_url: &str,
-    ds: &fastn_ds::DocumentStore,
+/// The files in `archive`, with paths relative to the package root.
+fn archive_files(
     package_name: &str,
-    is_github_package: bool,
-    check: bool,
-) -> Result<(fastn_core::Manifest, bool), fastn_update::UpdateError> {
-    let etag_file = dependency_path.join(".etag");
-
-    let start = std::time::Instant::now();
-    let resp = utils::download_archive(ds, zip_url, &etag_file)
-        .await
-        .context(DownloadArchiveSnafu {
+    archive: &resolve::Archive,
+) -> Result<Vec<(String, Vec<u8>)>, ArchiveError> {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive.content.clone())).context(
+        ArchiveEntryReadSnafu {
             package: package_name,
-        })?;
+        },
+    )?;
 
-    let elapsed = start.elapsed();
-    let elapsed_secs = elapsed.as_secs();
-    let elapsed_millis = elapsed.subsec_millis();
-
-    let red = elapsed_secs > 0 || elapsed_millis > 200;
-
-    let (etag, mut archive) = match resp {
-        Some(x) => {
-            mdone!(red, "downloaded in {}.{:03}s", elapsed_secs, elapsed_millis);
-            x
-        }
-        None => {
-            mdone!(red, "checked in {}.{:03}s", elapsed_secs, elapsed_millis);
-            return Ok((update_local_package_manifest(dependency_path).await?, false));
-        }
-    };
-
-    for i in 0..archive.len() {
-        let mut entry = archive.by_index(i).context(ArchiveEntryReadSnafu {
+    let mut files = vec![];
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).context(ArchiveEntryReadSnafu {
             package: package_name,
         })?;
 
@@ -345,27 +444,140 @@ async fn download_unpack_zip_and_get_manifest(
             // `package-doc-<commit-id>` which contains all files, so path for `FASTN.ftd` becomes
             // `package-doc-<commit-id>/FASTN.ftd` while fifthtry package zip doesn't have any
             // such folder, so path becomes `FASTN.ftd`
-            let path_without_prefix = if is_github_package {
+            let path_without_prefix = if archive.has_root_folder {
                 match path_normalized.split_once('/') {
-                    Some((_, path)) => path,
-                    None => &path_normalized,
+                    Some((_, path)) => path.to_string(),
+                    None => path_normalized,
                 }
             } else {
                 // For fifthtry packages
-                &path_normalized
+                path_normalized
             };
 
-            let output_path = &dependency_path.join(path_without_prefix);
-            write_archive_content(ds, output_path, &buffer, package_name, check).await?;
+            files.push((path_without_prefix, buffer));
         }
     }
+    Ok(files)
+}
 
-    ds.write_content(&etag_file, etag.as_bytes())
+/// The files of `package` installed in `.packages`, with paths relative to the package root.
+async fn installed_files(
+    ds: &fastn_ds::DocumentStore,
+    packages_root: &fastn_ds::Path,
+    package: &str,
+) -> Result<Vec<(String, Vec<u8>)>, ArchiveError> {
+    let dependency_path = packages_root.join(package);
+    let mut files = vec![];
+    for path in ds
+        .get_all_file_path(&dependency_path, &[], &None)
         .await
-        .inspect_err(|e| eprintln!("failed to write etag file for {package_name}: {e}"))
-        .unwrap_or(());
+        .context(ListInstalledSnafu { package })?
+    {
+        let content = ds
+            .read_content(&path, &None)
+            .await
+            .context(ReadInstalledSnafu { package })?;
+        let relative = path
+            .strip_prefix(&dependency_path)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| path.to_string());
+        files.push((relative, content));
+    }
+    Ok(files)
+}
+
+/// sha256 of the files of a package, hidden files aside. The downloaded archive and the copy
+/// unpacked in `.packages` have the same checksum, so a package that is not downloaded again can
+/// still be verified.
+fn checksum(files: &[(String, Vec<u8>)]) -> String {
+    let mut files = files
+        .iter()
+        .filter(|(path, _)| !path.split('/').any(|part| part.starts_with('.')))
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut content = vec![];
+    for (path, file) in files {
+        content.extend_from_slice(format!("{path}\0{}\0", file.len()).as_bytes());
+        content.extend_from_slice(file);
+    }
+    fastn_core::utils::generate_hash(content)
+}
+
+/// The checksum of `release`, after checking it is the one recorded in `fastn.lock` for the same
+/// version and archive. `files` are the files of the downloaded archive, if it was downloaded,
+/// else the installed files are checked. Local packages have no checksum.
+///
+/// Only a versioned package can be republished: a package without a version is whatever its
+/// archive has now, so a new archive of it is a new resolution, and its lock entry is rewritten.
+/// Its installed files must still match the lock.
+async fn verify(
+    ds: &fastn_ds::DocumentStore,
+    packages_root: &fastn_ds::Path,
+    release: &resolve::Release,
+    files: Option<&[(String, Vec<u8>)]>,
+    locked: Option<&lock::LockedPackage>,
+) -> Result<String, UpdateError> {
+    if release.url.is_empty() {
+        return Ok(String::new());
+    }
 
-    Ok((update_local_package_manifest(dependency_path).await?, true))
+    let checksum = match files {
+        Some(files) => checksum(files),
+        None => checksum(&installed_files(ds, packages_root, &release.package).await?),
+    };
+    if let Some(locked) = locked
+        && (files.is_none() || release.version.is_some())
+        && locked.version == release.version_string()
+        && locked.url == release.url
+        && !locked.checksum.is_empty()
+        && locked.checksum != checksum
+    {
+        return Err(ArchiveError::ChecksumMismatch {
+            package: release.package.clone(),
+            expected: locked.checksum.clone(),
+            found: checksum,
+        }
+        .into());
+    }
+    Ok(checksum)
+}
+
+/// Unpacks the `files` of the downloaded archive of `release` into `.packages`.
+async fn install(
+    ds: &fastn_ds::DocumentStore,
+    packages_root: &fastn_ds::Path,
+    release: &resolve::Release,
+    files: Vec<(String, Vec<u8>)>,
+    check: bool,
+) -> Result<(), UpdateError> {
+    let package_name = release.package.as_str();
+    let Some(ref archive) = release.archive else {
+        return Ok(());
+    };
+
+    let dependency_path = packages_root.join(package_name);
+    for (path, content) in files {
+        write_archive_content(
+            ds,
+            &dependency_path.join(path),
+            &content,
+            package_name,
+            check,
+        )
+        .await?;
+    }
+
+    ds.write_content(
+        &dependency_path.join(".etag"),
+        archive.etag.as_bytes(),
+        &None,
+    )
+    .await
+    .inspect_err(|e| eprintln!("failed to write etag file for {package_name}: {e}"))
+    .unwrap_or(());
+
+    Ok(())
 }
 
 fn is_fifthtry_site_package(package_name: &str) -> bool {
@@ -387,7 +599,7 @@ async fn write_archive_content(
     }
 
     Ok(ds
-        .write_content(output_path, buffer)
+        .write_content(output_path, buffer, &None)
         .await
         .context(WriteArchiveContentSnafu {
             package: package_name,
@@ -408,19 +620,30 @@ pub async fn update(ds: &fastn_ds::DocumentStore, check: bool) -> fastn_core::Re
         return Ok(());
     }
 
-    let (updated_packages, total_packages) =
-        match update_dependencies(ds, packages_root, &current_package, check).await {
-            Ok(n) => n,
-            Err(UpdateError::Check(e)) => {
-                eprintln!("{e}");
-                std::process::exit(7);
-            }
-            Err(e) => {
-                return Err(fastn_core::Error::UpdateError {
-                    message: e.to_string(),
-                });
-            }
-        };
+    let mut registry = Remote {
+        ds,
+        packages_root: packages_root.clone(),
+    };
+    let (updated_packages, total_packages) = match update_dependencies(
+        ds,
+        &mut registry,
+        packages_root,
+        &current_package,
+        check,
+    )
+    .await
+    {
+        Ok(n) => n,
+        Err(UpdateError::Check(e)) => {
+            eprintln!("{e}");
+            std::process::exit(7);
+        }
+        Err(e) => {
+            return Err(fastn_core::Error::UpdateError {
+                message: e.to_string(),
+            });
+        }
+    };
 
     match updated_packages {
         _ if fastn_core::utils::is_test() => println!("Updated N dependencies."),
@@ -431,3 +654,293 @@ pub async fn update(ds: &fastn_ds::DocumentStore, check: bool) -> fastn_core::Re
 
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    /// Package archives made from the fixtures in `t/registry`, laid out as
+    /// `<package>/<version>/`, with an `unversioned` folder for packages without a version. An
+    /// archive that is already installed is not downloaded again, like a `304` from the remote.
+    pub(crate) struct Directory<'a> {
+        pub ds: &'a fastn_ds::DocumentStore,
+        pub dir: std::path::PathBuf,
+    }
+
+    impl fastn_update::resolve::Registry for Directory<'_> {
+        async fn releases(
+            &mut self,
+            package: &str,
+        ) -> Result<Vec<fastn_update::resolve::Release>, fastn_update::UpdateError> {
+            let packages_root = self.ds.root().join(".packages");
+            let installed = self
+                .ds
+                .read_to_string(&packages_root.join(package).join(".etag"), &None)
+                .await
+                .ok();
+
+            let mut versions = std::fs::read_dir(self.dir.join(package))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect::<Vec<_>>();
+            versions.sort();
+
+            let mut releases = vec![];
+            for path in versions {
+                let files = files(&path);
+                let etag = fastn_update::checksum(&files);
+                let archive = if installed.as_ref() == Some(&etag) {
+                    None
+                } else {
+                    Some(fastn_update::resolve::Archive {
+                        content: zip(&files).into(),
+                        etag,
+                        has_root_folder: false,
+                    })
+                };
+                let url = path.display().to_string();
+                releases.push(
+                    fastn_update::release(self.ds, &packages_root, package, url, archive).await?,
+                );
+            }
+            Ok(releases)
+        }
+    }
+
+    /// The files of a package version folder, with paths relative to it.
+    pub(crate) fn files(dir: &std::path::Path) -> Vec<(String, Vec<u8>)> {
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, std::fs::read(&path).unwrap())
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    fn zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in files {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// A copy of `t/registry` that a test can change, removed when dropped.
+    pub(crate) struct Registry(std::path::PathBuf);
+
+    impl std::ops::Deref for Registry {
+        type Target = std::path::Path;
+
+        fn deref(&self) -> &std::path::Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Registry {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    pub(crate) fn registry(name: &str) -> Registry {
+        fn copy(from: &std::path::Path, to: &std::path::Path) {
+            std::fs::create_dir_all(to).unwrap();
+            for entry in std::fs::read_dir(from).unwrap() {
+                let path = entry.unwrap().path();
+                let target = to.join(path.file_name().unwrap());
+                if path.is_dir() {
+                    copy(&path, &target);
+                } else {
+                    std::fs::copy(&path, &target).unwrap();
+                }
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("fastn-update-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        copy(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("t/registry"),
+            &dir,
+        );
+        Registry(dir)
+    }
+
+    pub(crate) fn site(dependencies: &[(&str, &str)]) -> fastn_core::Package {
+        let mut package = fastn_core::Package::new("example.com/site");
+        package.dependencies = dependencies
+            .iter()
+            .map(|(name, version)| fastn_core::Dependency {
+                package: fastn_core::Package::new(name),
+                version: Some(version.to_string()),
+                notes: None,
+                alias: None,
+                implements: vec![],
+                provided_via: None,
+                required_as: None,
+            })
+            .collect();
+        package
+    }
+
+    pub(crate) fn ds() -> fastn_ds::DocumentStore {
+        fastn_ds::DocumentStore::with_storage(
+            "/site",
+            actix_web::web::Data::new(Default::default()),
+            std::sync::Arc::new(fastn_ds::MemoryStorage::new()),
+        )
+    }
+
+    async fn update(
+        ds: &fastn_ds::DocumentStore,
+        dir: &std::path::Path,
+        site: &fastn_core::Package,
+        check: bool,
+    ) -> Result<(usize, usize), fastn_update::UpdateError> {
+        let mut registry = Directory {
+            ds,
+            dir: dir.to_path_buf(),
+        };
+        fastn_update::update_dependencies(
+            ds,
+            &mut registry,
+            ds.root().join(".packages"),
+            site,
+            check,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn resolves_and_locks() {
+        let dir = registry("locks");
+        let ds = ds();
+        let site = site(&[("blog.fifthtry.site", "^1"), ("ui.fifthtry.site", "<2")]);
+
+        assert_eq!(update(&ds, &dir, &site, false).await.unwrap(), (2, 2));
+        let index = ds
+            .read_to_string(
+                &ds.root().join(".packages/ui.fifthtry.site/index.ftd"),
+                &None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(index, "-- ftd.text: ui.fifthtry.site 1.3.0\n");
+
+        let lock = fastn_update::lock::Lock::read(&ds).await.unwrap().unwrap();
+        assert_eq!(lock.requires["ui.fifthtry.site"], "<2");
+        assert_eq!(
+            lock.packages["ui.fifthtry.site"].version.as_deref(),
+            Some("1.3.0")
+        );
+        assert_eq!(
+            lock.packages["blog.fifthtry.site"].dependencies,
+            vec!["ui.fifthtry.site".to_string()]
+        );
+        assert_eq!(
+            lock.packages["ui.fifthtry.site"].checksum,
+            fastn_update::checksum(&files(&dir.join("ui.fifthtry.site/1.3.0")))
+        );
+
+        // nothing changed, the lock is up to date
+        assert_eq!(update(&ds, &dir, &site, true).await.unwrap(), (0, 2));
+
+        // without a lock, `--check` compares with the installed packages
+        ds.remove(&ds.root().join(fastn_update::lock::LOCK_FILE), &None)
+            .await
+            .unwrap();
+        assert_eq!(update(&ds, &dir, &site, true).await.unwrap(), (0, 2));
+        assert!(matches!(
+            update(&self::ds(), &dir, &site, true).await,
+            Err(fastn_update::UpdateError::Check(
+                fastn_update::CheckError::WriteDuringCheck { .. }
+            ))
+        ));
+        update(&ds, &dir, &site, false).await.unwrap();
+
+        // FASTN.ftd changed, `--check` fails without resolving
+        let changed = self::site(&[("blog.fifthtry.site", "^1"), ("ui.fifthtry.site", "~1.0")]);
+        assert!(matches!(
+            update(&ds, &dir, &changed, true).await,
+            Err(fastn_update::UpdateError::Check(
+                fastn_update::CheckError::StaleLock { .. }
+            ))
+        ));
+        // and the new constraints can not be met: blog needs ui ^1.1
+        assert!(matches!(
+            update(&ds, &dir, &changed, false).await,
+            Err(fastn_update::UpdateError::Resolve(
+                fastn_update::ResolveError::Conflict { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn verifies_checksum() {
+        let dir = registry("checksum");
+        let ds = ds();
+        let site = site(&[("ui.fifthtry.site", "^1")]);
+        update(&ds, &dir, &site, false).await.unwrap();
+
+        // an installed file changes, the archive is not downloaded again
+        let index = ds.root().join(".packages/ui.fifthtry.site/index.ftd");
+        let installed = ds.read_content(&index, &None).await.unwrap();
+        ds.write_content(&index, b"changed", &None).await.unwrap();
+        assert!(matches!(
+            update(&ds, &dir, &site, true).await,
+            Err(fastn_update::UpdateError::Archive(
+                fastn_update::ArchiveError::ChecksumMismatch { .. }
+            ))
+        ));
+        ds.write_content(&index, &installed, &None).await.unwrap();
+        assert_eq!(update(&ds, &dir, &site, true).await.unwrap(), (0, 1));
+
+        // the locked version is republished with other files
+        std::fs::write(dir.join("ui.fifthtry.site/1.3.0/index.ftd"), "changed").unwrap();
+        assert!(matches!(
+            update(&ds, &dir, &site, false).await,
+            Err(fastn_update::UpdateError::Archive(
+                fastn_update::ArchiveError::ChecksumMismatch { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn unversioned_archive_changes() {
+        let dir = registry("unversioned");
+        let ds = ds();
+        let site = site(&[("doc.fifthtry.site", "*")]);
+        update(&ds, &dir, &site, false).await.unwrap();
+
+        // a new archive of a package without a version is a new resolution
+        let unversioned = dir.join("doc.fifthtry.site/unversioned");
+        std::fs::write(unversioned.join("index.ftd"), "-- ftd.text: changed\n").unwrap();
+        assert!(matches!(
+            update(&ds, &dir, &site, true).await,
+            Err(fastn_update::UpdateError::Check(
+                fastn_update::CheckError::StaleLock { .. }
+            ))
+        ));
+        assert_eq!(update(&ds, &dir, &site, false).await.unwrap(), (1, 1));
+        let lock = fastn_update::lock::Lock::read(&ds).await.unwrap().unwrap();
+        assert_eq!(
+            lock.packages["doc.fifthtry.site"].checksum,
+            fastn_update::checksum(&files(&unversioned))
+        );
+
+        // but its installed files still have to match the lock
+        let index = ds.root().join(".packages/doc.fifthtry.site/index.ftd");
+        ds.write_content(&index, b"changed", &None).await.unwrap();
+        assert!(matches!(
+            update(&ds, &dir, &site, true).await,
+            Err(fastn_update::UpdateError::Archive(
+                fastn_update::ArchiveError::ChecksumMismatch { .. }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use snafu::ResultExt;
+
+pub const LOCK_FILE: &str = "fastn.lock";
+
+/// `fastn.lock` records the result of the last dependency resolution: the version, archive and
+/// checksum of every package in the dependency tree, so `fastn update --check` can tell when the
+/// resolution or the files of a package are not the ones that were locked.
+///
+/// The archive urls only ever have the latest release of a package, so the lock can not pin an
+/// older one. `fastn update` fails when a versioned package is republished with other files under
+/// the same version, and rewrites the entry of a package without a version when its archive
+/// changes.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Lock {
+    pub package: String,
+    /// The version constraint of each direct dependency in `FASTN.ftd` when the lock was written.
+    pub requires: std::collections::BTreeMap<String, String>,
+    pub packages: std::collections::BTreeMap<String, LockedPackage>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LockedPackage {
+    /// `None` for packages that do not declare a version in their `FASTN.ftd`.
+    pub version: Option<String>,
+    /// The archive the package was installed from, empty for local packages.
+    pub url: String,
+    /// sha256 of the files of the package, empty for local packages.
+    pub checksum: String,
+    pub dependencies: Vec<String>,
+}
+
+impl Lock {
+    pub async fn read(
+        ds: &fastn_ds::DocumentStore,
+    ) -> Result<Option<Lock>, fastn_update::LockError> {
+        let content = match ds.read_to_string(&ds.root().join(LOCK_FILE), &None).await {
+            Ok(v) => v,
+            Err(fastn_ds::ReadStringError::ReadError(fastn_ds::ReadError::NotFound(_))) => {
+                return Ok(None);
+            }
+            Err(e) => return Err(e).context(fastn_update::ReadLockSnafu),
+        };
+        Ok(Some(
+            serde_json::from_str(&content).context(fastn_update::ParseLockSnafu)?,
+        ))
+    }
+
+    pub async fn write(&self, ds: &fastn_ds::DocumentStore) -> Result<(), fastn_update::LockError> {
+        let mut content = serde_json::to_vec_pretty(self).expect("lock is serializable");
+        content.push(b'\n');
+        ds.write_content(&ds.root().join(LOCK_FILE), &content, &None)
+            .await
+            .context(fastn_update::WriteLockSnafu)
+    }
+
+    /// The `requires` of a lock written for `package`.
+    pub fn requires(package: &fastn_core::Package) -> std::collections::BTreeMap<String, String> {
+        package
+            .dependencies
+            .iter()
+            .map(|d| {
+                (
+                    d.package.name.clone(),
+                    d.version.clone().unwrap_or_else(|| "*".to_string()),
+                )
+            })
+            .collect()
+    }
+
+    /// Why this lock can not be used for `package` as is, if it can not.
+    pub fn stale_reason(&self, package: &fastn_core::Package) -> Option<String> {
+        if self.package != package.name {
+            return Some(format!(
+                "{LOCK_FILE} was written for the package '{}'",
+                self.package
+            ));
+        }
+        let requires = Lock::requires(package);
+        if self.requires != requires {
+            let changed = requires
+                .keys()
+                .chain(self.requires.keys())
+                .filter(|name| requires.get(*name) != self.requires.get(*name))
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Some(format!(
+                "dependencies in FASTN.ftd changed since {LOCK_FILE} was written: {changed}"
+            ));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn package(dependencies: &[(&str, Option<&str>)]) -> fastn_core::Package {
+        let mut package = fastn_core::Package::new("example.com/site");
+        package.dependencies = dependencies
+            .iter()
+            .map(|(name, version)| fastn_core::Dependency {
+                package: fastn_core::Package::new(name),
+                version: version.map(|v| v.to_string()),
+                notes: None,
+                alias: None,
+                implements: vec![],
+                provided_via: None,
+                required_as: None,
+            })
+            .collect();
+        package
+    }
+
+    #[test]
+    fn stale_reason() {
+        let site = package(&[("a.fifthtry.site", Some("^1.2")), ("b.fifthtry.site", None)]);
+        let lock = super::Lock {
+            package: site.name.clone(),
+            requires: super::Lock::requires(&site),
+            packages: Default::default(),
+        };
+        assert_eq!(lock.requires["b.fifthtry.site"], "*");
+        assert_eq!(lock.stale_reason(&site), None);
+
+        let changed = package(&[("a.fifthtry.site", Some("^2")), ("c.fifthtry.site", None)]);
+        assert_eq!(
+            lock.stale_reason(&changed).unwrap(),
+            "dependencies in FASTN.ftd changed since fastn.lock was written: a.fifthtry.site, b.fifthtry.site, c.fifthtry.site"
+        );
+    }
+}
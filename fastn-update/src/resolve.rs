@@ -0,0 +1,328 @@
+use snafu::ResultExt;
+
+/// Resolution gives up if the picked versions keep changing after this many rounds.
+const MAX_ROUNDS: usize = 32;
+
+/// A version constraint on `package`, from the `fastn.dependency` of `required_by`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Requirement {
+    pub package: String,
+    pub req: semver::VersionReq,
+    pub required_by: String,
+}
+
+impl Requirement {
+    pub(crate) fn from_dependency(
+        dependency: &fastn_core::package::dependency::Dependency,
+        required_by: &str,
+    ) -> Result<Requirement, fastn_update::ResolveError> {
+        let req = match dependency.version {
+            Some(ref version) => semver::VersionReq::parse(version).context(
+                fastn_update::InvalidConstraintSnafu {
+                    package: dependency.package.name.as_str(),
+                    required_by,
+                    constraint: version.as_str(),
+                },
+            )?,
+            None => semver::VersionReq::STAR,
+        };
+        Ok(Requirement {
+            package: dependency.package.name.clone(),
+            req,
+            required_by: required_by.to_string(),
+        })
+    }
+
+    /// A package that does not declare its version only satisfies `*`.
+    fn matches(&self, version: Option<&semver::Version>) -> bool {
+        match version {
+            Some(version) => self.req.matches(version),
+            None => self.req == semver::VersionReq::STAR,
+        }
+    }
+}
+
+/// A version of a package that can be installed.
+#[derive(Debug, Clone)]
+pub(crate) struct Release {
+    pub package: String,
+    pub version: Option<semver::Version>,
+    pub dependencies: Vec<Requirement>,
+    /// The archive url, empty for local packages.
+    pub url: String,
+    /// The downloaded archive, `None` when the copy in `.packages` is current or local.
+    pub archive: Option<Archive>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Archive {
+    pub content: bytes::Bytes,
+    pub etag: String,
+    /// github archives keep all the files in a `<repo>-<commit>` folder.
+    pub has_root_folder: bool,
+}
+
+impl Release {
+    /// The release described by the `FASTN.ftd` of `package`.
+    pub(crate) fn new(
+        package: &fastn_core::Package,
+        url: String,
+        archive: Option<Archive>,
+    ) -> Result<Release, fastn_update::ResolveError> {
+        let version = match package.version {
+            Some(ref version) => Some(semver::Version::parse(version).context(
+                fastn_update::InvalidVersionSnafu {
+                    package: package.name.as_str(),
+                    version: version.as_str(),
+                },
+            )?),
+            None => None,
+        };
+        let dependencies = if package.name.eq(fastn_core::FASTN_UI_INTERFACE) {
+            // TODO: why are we not updating FASTN_UI_INTERFACE package?
+            vec![]
+        } else {
+            package
+                .dependencies
+                .iter()
+                .map(|d| Requirement::from_dependency(d, &package.name))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        Ok(Release {
+            package: package.name.clone(),
+            version,
+            dependencies,
+            url,
+            archive,
+        })
+    }
+
+    pub(crate) fn version_string(&self) -> Option<String> {
+        self.version.as_ref().map(|v| v.to_string())
+    }
+}
+
+/// Where the releases of the packages come from.
+pub(crate) trait Registry {
+    async fn releases(&mut self, package: &str) -> Result<Vec<Release>, fastn_update::UpdateError>;
+}
+
+/// Picks a release for every package in the dependency tree of `root`, such that every version
+/// constraint on a package is met by its release.
+///
+/// The highest matching version is picked, unless `locked` has a version that still matches.
+pub(crate) async fn resolve<R: Registry>(
+    registry: &mut R,
+    root: &fastn_core::Package,
+    locked: Option<&fastn_update::lock::Lock>,
+) -> Result<std::collections::BTreeMap<String, Release>, fastn_update::UpdateError> {
+    let root_requirements = root
+        .dependencies
+        .iter()
+        .map(|d| Requirement::from_dependency(d, &root.name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut releases: std::collections::HashMap<String, Vec<Release>> = Default::default();
+    let mut chosen: std::collections::BTreeMap<String, Release> = Default::default();
+
+    for _ in 0..MAX_ROUNDS {
+        // walk the dependency tree with the current picks, collecting all the constraints
+        let mut requirements: std::collections::BTreeMap<String, Vec<Requirement>> =
+            Default::default();
+        let mut queue = root_requirements.clone();
+        queue.reverse();
+        while let Some(requirement) = queue.pop() {
+            if requirement.package.eq(&root.name) {
+                continue;
+            }
+            let package = requirement.package.clone();
+            let seen = requirements.contains_key(&package);
+            requirements
+                .entry(package.clone())
+                .or_default()
+                .push(requirement);
+            if seen {
+                continue;
+            }
+
+            let release = match chosen.get(&package) {
+                Some(release) => release.clone(),
+                None => {
+                    if !releases.contains_key(&package) {
+                        releases.insert(package.clone(), registry.releases(&package).await?);
+                    }
+                    pick(
+                        &package,
+                        &requirements[&package],
+                        &releases[&package],
+                        locked,
+                    )?
+                }
+            };
+            queue.extend(release.dependencies.iter().rev().cloned());
+        }
+
+        let mut next = std::collections::BTreeMap::new();
+        for (package, requirements) in requirements.iter() {
+            let release = pick(package, requirements, &releases[package], locked)?;
+            next.insert(package.clone(), release);
+        }
+
+        let same = next.len() == chosen.len()
+            && next.iter().all(|(package, release)| {
+                chosen
+                    .get(package)
+                    .is_some_and(|c| c.version == release.version && c.url == release.url)
+            });
+        if same {
+            return Ok(next);
+        }
+        chosen = next;
+    }
+
+    Err(fastn_update::ResolveError::Unsettled { rounds: MAX_ROUNDS }.into())
+}
+
+fn pick(
+    package: &str,
+    requirements: &[Requirement],
+    releases: &[Release],
+    locked: Option<&fastn_update::lock::Lock>,
+) -> Result<Release, fastn_update::ResolveError> {
+    let candidates = releases
+        .iter()
+        .filter(|r| {
+            requirements
+                .iter()
+                .all(|req| req.matches(r.version.as_ref()))
+        })
+        .collect::<Vec<_>>();
+
+    let locked_version = locked
+        .and_then(|l| l.packages.get(package))
+        .map(|l| l.version.clone());
+    if let Some(locked_version) = locked_version
+        && let Some(release) = candidates
+            .iter()
+            .find(|r| r.version_string() == locked_version)
+    {
+        return Ok((*release).clone());
+    }
+
+    match candidates
+        .into_iter()
+        .max_by(|a, b| a.version.cmp(&b.version))
+    {
+        Some(release) => Ok(release.clone()),
+        None => Err(fastn_update::ResolveError::Conflict {
+            package: package.to_string(),
+            requirements: requirements
+                .iter()
+                .map(|r| format!("  '{}' requires {}\n", r.required_by, r.req))
+                .collect(),
+            available: releases
+                .iter()
+                .map(|r| {
+                    r.version_string()
+                        .unwrap_or_else(|| "unversioned".to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Resolves the `dependencies` of a package against the fixtures in `t/registry`.
+    async fn resolve(
+        dependencies: &[(&str, &str)],
+        locked: Option<&fastn_update::lock::Lock>,
+    ) -> Result<std::collections::BTreeMap<String, super::Release>, fastn_update::UpdateError> {
+        let ds = fastn_update::tests::ds();
+        let mut registry = fastn_update::tests::Directory {
+            ds: &ds,
+            dir: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("t/registry"),
+        };
+        let root = fastn_update::tests::site(dependencies);
+        super::resolve(&mut registry, &root, locked).await
+    }
+
+    fn versions(
+        resolved: &std::collections::BTreeMap<String, super::Release>,
+    ) -> Vec<(String, String)> {
+        resolved
+            .iter()
+            .map(|(p, r)| (p.clone(), r.version_string().unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn highest_matching_version() {
+        let resolved = resolve(&[("ui.fifthtry.site", "^1.2")], None).await;
+        assert_eq!(
+            versions(&resolved.unwrap()),
+            vec![("ui.fifthtry.site".to_string(), "1.3.0".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn transitive() {
+        let resolved = resolve(
+            &[("blog.fifthtry.site", "^1"), ("ui.fifthtry.site", "*")],
+            None,
+        )
+        .await;
+        assert_eq!(
+            versions(&resolved.unwrap()),
+            vec![
+                ("blog.fifthtry.site".to_string(), "1.1.0".to_string()),
+                ("ui.fifthtry.site".to_string(), "1.3.0".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_locked_version() {
+        let lock = fastn_update::lock::Lock {
+            packages: std::collections::BTreeMap::from([(
+                "ui.fifthtry.site".to_string(),
+                fastn_update::lock::LockedPackage {
+                    version: Some("1.0.0".to_string()),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let resolved = resolve(&[("ui.fifthtry.site", "^1")], Some(&lock)).await;
+        assert_eq!(versions(&resolved.unwrap())[0].1, "1.0.0");
+
+        // the locked version no longer matches
+        let resolved = resolve(&[("ui.fifthtry.site", "^1.1")], Some(&lock)).await;
+        assert_eq!(versions(&resolved.unwrap())[0].1, "1.3.0");
+    }
+
+    #[tokio::test]
+    async fn conflict() {
+        let err = resolve(
+            &[("theme.fifthtry.site", "^1"), ("ui.fifthtry.site", "^1")],
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Resolve error: No version of 'ui.fifthtry.site' satisfies all the requirements:\n  \
+             'theme.fifthtry.site' requires ^2\n  'example.com/site' requires ^1\n\
+             available: 1.0.0, 1.2.0, 1.3.0, 2.0.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn unversioned_package() {
+        let resolved = resolve(&[("doc.fifthtry.site", "*")], None).await;
+        assert_eq!(resolved.unwrap()["doc.fifthtry.site"].version, None);
+        assert!(resolve(&[("doc.fifthtry.site", "^1")], None).await.is_err());
+    }
+}
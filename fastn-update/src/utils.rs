@@ -5,8 +5,15 @@ pub async fn from_fastn_doc(
     fastn_path: &fastn_ds::Path,
 ) -> fastn_core::Result<fastn_core::Package> {
     let doc = ds.read_to_string(fastn_path, &None).await?;
+    parse_fastn_doc(ds, doc.as_str())
+}
+
+fn parse_fastn_doc(
+    ds: &fastn_ds::DocumentStore,
+    doc: &str,
+) -> fastn_core::Result<fastn_core::Package> {
     let lib = fastn_core::FastnLibrary::default();
-    let fastn_doc = match fastn_core::doc::parse_ftd("fastn", doc.as_str(), &lib) {
+    let fastn_doc = match fastn_core::doc::parse_ftd("fastn", doc, &lib) {
         Ok(v) => Ok(v),
         Err(e) => Err(fastn_core::Error::PackageError {
             message: format!("failed to parse FASTN.ftd 3: {:?}", &e),
@@ -17,6 +24,15 @@ pub async fn from_fastn_doc(
     Ok(package)
 }
 
+/// The dependency `package` described by the content of its `FASTN.ftd`.
+pub(crate) fn package_from_source(
+    ds: &fastn_ds::DocumentStore,
+    package: &str,
+    fastn_ftd: &str,
+) -> Result<fastn_core::Package, fastn_update::DependencyError> {
+    parse_fastn_doc(ds, fastn_ftd).context(fastn_update::ResolveDependencySnafu { package })
+}
+
 pub async fn read_current_package(
     ds: &fastn_ds::DocumentStore,
 ) -> fastn_core::Result<fastn_core::Package> {
@@ -29,9 +45,7 @@ pub(crate) async fn download_archive(
     ds: &fastn_ds::DocumentStore,
     url: &str,
     etag_file: &fastn_ds::Path,
-) -> fastn_core::Result<Option<(String, zip::ZipArchive<std::io::Cursor<bytes::Bytes>>)>> {
-    use std::io::Seek;
-
+) -> fastn_core::Result<Option<(String, bytes::Bytes)>> {
     let mut r = reqwest::Request::new(reqwest::Method::GET, url.parse()?);
 
     match ds.read_to_string(etag_file, &None).await {
@@ -56,24 +70,5 @@ pub(crate) async fn download_archive(
         .and_then(|v| v.to_str().ok())
         .map(|v| v.to_string())
         .unwrap_or_default();
-    // TODO: handle 304 response
-    let mut cursor = std::io::Cursor::new(resp.bytes().await?);
-    cursor.seek(std::io::SeekFrom::Start(0))?;
-    let archive = zip::ZipArchive::new(cursor)?;
-    Ok(Some((etag, archive)))
-}
-
-pub(crate) async fn resolve_dependency_package(
-    ds: &fastn_ds::DocumentStore,
-    dependency: &fastn_core::package::dependency::Dependency,
-    dependency_path: &fastn_ds::Path,
-) -> Result<fastn_core::Package, fastn_update::DependencyError> {
-    let mut dep_package = dependency.package.clone();
-    let fastn_path = dependency_path.join("FASTN.ftd");
-    dep_package.resolve(&fastn_path, ds, &None).await.context(
-        fastn_update::ResolveDependencySnafu {
-            package: dependency.package.name.clone(),
-        },
-    )?;
-    Ok(dep_package)
+    Ok(Some((etag, resp.bytes().await?)))
 }
@@ -19,6 +19,16 @@ pub enum SqlError {
     FoundBlob,
     #[error("unknown db error")]
     UnknownDB,
+    #[error("postgres create pool error {0}")]
+    PgCreatePool(deadpool_postgres::CreatePoolError),
+    #[error("postgres pool error {0}")]
+    PgPool(deadpool_postgres::PoolError),
+    #[error("postgres error {0}")]
+    Pg(tokio_postgres::Error),
+    #[error("postgres column error {0}: {1}")]
+    PgColumn(usize, tokio_postgres::Error),
+    #[error("postgres type {0} is not supported, cast it to text in the query")]
+    PgUnsupportedType(String),
 }
 
 pub fn rows_to_json(
@@ -4,6 +4,21 @@ const BACKSLASH: char = '\\';
 pub const SQLITE_SUB: char = '?';
 pub const POSTGRES_SUB: char = '$';
 
+/// `FASTN_DB_URL`, or the `db$` header of the sql processors, is a Postgres url, everything else
+/// is handled as SQLite.
+pub fn is_postgres(db_url: &str) -> bool {
+    db_url.starts_with("postgres://") || db_url.starts_with("postgresql://")
+}
+
+/// The placeholder `extract_arguments` should use for the db at `db_url`.
+pub fn sub_for(db_url: &str) -> char {
+    if is_postgres(db_url) {
+        POSTGRES_SUB
+    } else {
+        SQLITE_SUB
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
     #[error("Invalid query, quote left open")]
@@ -237,4 +252,17 @@ mod test {
             vec![("name".to_string(), None)],
         );
     }
+    #[test]
+    fn sub_for() {
+        assert_eq!(
+            super::sub_for("postgres://u:p@localhost/db"),
+            super::POSTGRES_SUB
+        );
+        assert_eq!(
+            super::sub_for("postgresql://localhost/db"),
+            super::POSTGRES_SUB
+        );
+        assert_eq!(super::sub_for("sqlite:///fastn.sqlite"), super::SQLITE_SUB);
+        assert_eq!(super::sub_for("postgres.sqlite"), super::SQLITE_SUB);
+    }
 }
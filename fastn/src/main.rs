@@ -12,9 +12,19 @@ pub fn main() {
 }
 
 async fn outer_main() {
-    if let Err(e) = async_main().await {
-        eprintln!("{e:?}");
-        std::process::exit(1);
+    match async_main().await {
+        Ok(()) => {}
+        // ftd diagnostics render as code frames, the Debug output would mangle them
+        Err(Error::FastnCoreError(fastn_core::Error::FTDInterpreterError(
+            e @ ftd::interpreter::Error::Diagnostics(_),
+        ))) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{e:?}");
+            std::process::exit(1);
+        }
     }
 }
 
@@ -75,6 +85,20 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
         return fastn_update::update(&ds, check).await;
     }
 
+    if matches.subcommand_matches("lsp").is_some() {
+        return fastn_lsp::run(ds)
+            .await
+            .map_err(|e| fastn_core::Error::generic(e.to_string()));
+    }
+
+    // formatting stdin needs no package, editors run it on buffers in any directory
+    if let Some(fmt) = matches.subcommand_matches("fmt")
+        && fmt.get_flag("stdin")
+    {
+        let wrap = fmt.value_of_("wrap").map(str::parse).transpose()?;
+        return fastn_core::commands::fmt::fmt_stdin(fmt.get_flag("noindentation"), wrap);
+    }
+
     if let Some(serve) = matches.subcommand_matches("serve") {
         let port = serve.value_of_("port").map(|p| match p.parse::<u16>() {
             Ok(v) => v,
@@ -91,6 +115,7 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
         let external_css = serve.values_of_("external-css");
         let inline_css = serve.values_of_("css");
         let offline = serve.get_flag("offline");
+        let watch = serve.get_flag("watch");
 
         if cfg!(feature = "use-config-json") && !offline {
             fastn_update::update(&ds, false).await?;
@@ -104,7 +129,28 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
             .add_external_css(external_css.clone())
             .add_inline_css(inline_css.clone());
 
-        return fastn_core::listen(std::sync::Arc::new(config), bind.as_str(), port).await;
+        let config = if watch {
+            config.set_live_reload()
+        } else {
+            config
+        };
+
+        // dependencies added to FASTN.ftd while watching are downloaded like on startup
+        let update_dependencies: Option<fastn_core::watcher::UpdateDependencies> =
+            if cfg!(feature = "use-config-json") && !offline {
+                Some(|ds| Box::pin(fastn_update::update(ds, false)))
+            } else {
+                None
+            };
+
+        return fastn_core::listen(
+            std::sync::Arc::new(config),
+            bind.as_str(),
+            port,
+            watch,
+            update_dependencies,
+        )
+        .await;
     }
 
     if let Some(test) = matches.subcommand_matches("test") {
@@ -174,6 +220,7 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
             matches.get_flag("test"),
             build.get_flag("check-build"),
             zip_url,
+            build.get_flag("compress"),
             &None,
         )
         .await;
@@ -186,6 +233,8 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
             &config,
             fmt.value_of_("file"),
             fmt.get_flag("noindentation"),
+            fmt.get_flag("check"),
+            fmt.value_of_("wrap").map(str::parse).transpose()?,
         )
         .await;
     }
@@ -207,8 +256,34 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
         .await;
     }
 
-    if matches.subcommand_matches("check").is_some() {
-        return fastn_core::post_build_check(&config).await;
+    if let Some(check) = matches.subcommand_matches("check") {
+        return fastn_core::commands::check::check(
+            &config,
+            check.value_of_("format").unwrap_or("text"),
+            check.get_flag("deny-warnings"),
+        )
+        .await;
+    }
+
+    if let Some(translation) = matches.subcommand_matches("translation") {
+        return match translation.subcommand() {
+            Some(("mark", mark)) => {
+                fastn_core::commands::translation::mark(&config, &mark.values_of_("file"), &None)
+                    .await
+            }
+            Some(("diff", diff)) => {
+                fastn_core::commands::translation::diff(
+                    &config,
+                    diff.value_of_("file").unwrap(),
+                    &None,
+                )
+                .await
+            }
+            Some(("coverage", _)) => {
+                fastn_core::commands::translation::coverage(&config, &None).await
+            }
+            _ => fastn_core::commands::translation_status::translation_status(&config, &None).await,
+        };
     }
 
     Ok(())
@@ -227,6 +302,11 @@ async fn handle_ssh_commands(_matches: &clap::ArgMatches) -> fastn_core::Result<
 }
 
 async fn check_for_update_cmd(matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    // the language server talks to the editor over stdout
+    if matches.subcommand_matches("lsp").is_some() {
+        return Ok(());
+    }
+
     let env_var_set = {
         if let Ok(val) = std::env::var("FASTN_CHECK_FOR_UPDATES") {
             val != "false"
@@ -292,6 +372,7 @@ fn app(version: &'static str) -> clap::Command {
                 .arg(clap::arg!(--"zip-url" <URL> "The zip archive url for this package"))
                 .arg(clap::arg!(--"ignore-failed" "Ignore failed files."))
                 .arg(clap::arg!(--"check-build" "Checks .build for index files validation."))
+                .arg(clap::arg!(--compress "Also write gzip (.gz) and brotli (.br) copies of html, js, css etc files in .build"))
                 .arg(clap::arg!(--"external-js" <URL> "Script added in ftd files")
                     .action(clap::ArgAction::Append))
                 .arg(clap::arg!(--js <URL> "Script text added in ftd files")
@@ -308,6 +389,9 @@ fn app(version: &'static str) -> clap::Command {
                 .about("Format the fastn package")
                 .arg(clap::arg!(file: [FILE]... "The file to format").required(false))
                 .arg(clap::arg!(-i --noindentation "No indentation added to file/package").required(false))
+                .arg(clap::arg!(--check "Do not write the files, print a diff for each file which is not formatted and fail if there is any"))
+                .arg(clap::arg!(--stdin "Format the document read from stdin and print it, for editors"))
+                .arg(clap::arg!(--wrap <COLUMNS> "Wrap the captions of sections whose line is longer than COLUMNS"))
         )
         .subcommand(
             clap::Command::new("wasmc")
@@ -343,14 +427,48 @@ fn app(version: &'static str) -> clap::Command {
         )
         .subcommand(
             clap::Command::new("check")
-                .about("Check if everything is fine with current fastn package")
-                .hide(true) // hidden since the feature is not being released yet.
+                .about("Type-check every document of the package, without running the processors which talk to the network or a database")
+                .after_help("Errors are what stops a document from rendering. Warnings are unused \
+                imports and variables, `-- end:` lines of sections without children, and the \
+                messages a translation is missing.\n\n\
+                Unused imports and variables are found by looking for their names in the text of \
+                the documents, so a name mentioned only in a body, or in a comment inside a \
+                section, counts as used: some unused ones are missed, but the ones reported are \
+                unused.")
+                .arg(clap::arg!(--format [FORMAT] "The output format, `text` or `json`").default_value("text"))
+                .arg(clap::arg!(--"deny-warnings" "Fail on warnings too, not only on errors"))
+        )
+        .subcommand(
+            clap::Command::new("translation")
+                .about("Track the translations of a fastn package against the original")
+                .subcommand(
+                    clap::Command::new("status")
+                        .about("Show the translation status of every file (default)")
+                )
+                .subcommand(
+                    clap::Command::new("mark")
+                        .about("Mark translated files as up to date with the original")
+                        .arg(clap::arg!(file: <FILE>... "The translated files to mark"))
+                )
+                .subcommand(
+                    clap::Command::new("diff")
+                        .about("Show what changed in the original since the file was last marked")
+                        .arg(clap::arg!(file: <FILE> "The translated file"))
+                )
+                .subcommand(
+                    clap::Command::new("coverage")
+                        .about("Print the translation coverage per language as JSON")
+                )
         )
         .subcommand(
             clap::Command::new("update")
                 .about("Update dependency packages for this fastn package")
                 .arg(clap::arg!(--check "Check if packages are in sync with FASTN.ftd without performing updates."))
         )
+        .subcommand(
+            clap::Command::new("lsp")
+                .about("Start the ftd language server, for editors to talk to over stdio")
+        )
         .subcommand(sub_command::serve())
 }
 
@@ -373,7 +491,8 @@ mod sub_command {
             .arg(clap::arg!(--css <URL> "CSS text added in ftd files")
                 .action(clap::ArgAction::Append))
             .arg(clap::arg!(--"download-base-url" <URL> "If running without files locally, download needed files from here"))
-            .arg(clap::arg!(--offline "Disables automatic package update checks to operate in offline mode"));
+            .arg(clap::arg!(--offline "Disables automatic package update checks to operate in offline mode"))
+            .arg(clap::arg!(--watch "Watch the package for changes and reload open pages automatically"));
         serve
                 .arg(
                     clap::arg!(identities: --identities <IDENTITIES> "Http request identities, fastn allows these identities to access documents")
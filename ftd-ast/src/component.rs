@@ -267,6 +267,7 @@ impl ComponentInvocation {
                         source: ftd_ast::PropertySource::Caption,
                         condition: caption.condition_expression(),
                         line_number,
+                        span: None,
                     });
                 }
                 for header in headers.0.iter() {
@@ -285,6 +286,7 @@ impl ComponentInvocation {
                         },
                         condition: header.condition.to_owned(),
                         line_number,
+                        span: None,
                     });
                 }
                 if let Some(body) = body {
@@ -355,6 +357,9 @@ pub struct Property {
     pub condition: Option<String>,
     #[serde(rename = "line-number")]
     pub line_number: usize,
+    /// Where the header or caption is on its line, for diagnostics, as the parser saw it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<ftd_p1::Span>,
 }
 
 impl Property {
@@ -367,12 +372,14 @@ impl Property {
         source: PropertySource,
         condition: Option<String>,
         line_number: usize,
+        span: Option<ftd_p1::Span>,
     ) -> Property {
         Property {
             value,
             source,
             condition,
             line_number,
+            span,
         }
     }
 
@@ -400,13 +407,14 @@ impl Property {
             source,
             header.get_condition(),
             header.get_line_number(),
+            header.get_span(),
         ))
     }
 
     fn from_value(value: Option<String>, source: PropertySource, line_number: usize) -> Property {
         let value =
             ftd_ast::VariableValue::from_value(&value, source.to_value_source(), line_number);
-        Property::new(value, source, None, line_number)
+        Property::new(value, source, None, line_number, None)
     }
 }
 
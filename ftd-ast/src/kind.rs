@@ -9,11 +9,13 @@ pub enum VariableModifier {
     List,
     Optional,
     Constant,
+    Map,
 }
 
 pub const OPTIONAL: &str = "optional";
 pub const LIST: &str = "list";
 pub const CONSTANT: &str = "constant";
+pub const MAP: &str = "map";
 
 impl VariableModifier {
     pub(crate) fn is_optional_from_expr(expr: &str) -> bool {
@@ -36,6 +38,21 @@ impl VariableModifier {
         matches!(self, VariableModifier::Optional)
     }
 
+    fn is_map(&self) -> bool {
+        matches!(self, VariableModifier::Map)
+    }
+
+    /// Splits `[caption] map<K, V>` into the part before `map`, `K` and `V`.
+    pub(crate) fn get_map_kinds(expr: &str) -> Option<(&str, &str, &str)> {
+        let expr = expr.trim().strip_suffix('>')?;
+        let (before, kinds) = expr.split_once(format!("{MAP}<").as_str())?;
+        if !(before.is_empty() || before.ends_with(char::is_whitespace)) {
+            return None;
+        }
+        let (key, value) = kinds.split_once(',')?;
+        Some((before.trim(), key.trim(), value.trim()))
+    }
+
     pub(crate) fn get_modifier(expr: &str) -> Option<VariableModifier> {
         let expr = expr.split_whitespace().collect::<Vec<&str>>();
         if expr.len() >= 2 {
@@ -69,6 +86,25 @@ impl VariableKind {
             );
         }
 
+        if let Some((before, key, value)) = VariableModifier::get_map_kinds(kind) {
+            if key.ne("string") {
+                return ftd_ast::parse_error(
+                    format!("Only `string` keys are supported in `{MAP}`, found: `{key}`"),
+                    doc_id,
+                    line_number,
+                );
+            }
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                return ftd_ast::parse_error(
+                    format!("Invalid variable kind, found: `{kind}`"),
+                    doc_id,
+                    line_number,
+                );
+            }
+            let kind = format!("{before} {value}");
+            return Ok(VariableKind::new(kind.trim(), Some(VariableModifier::Map)));
+        }
+
         let modifier = VariableModifier::get_modifier(kind);
         let kind = match modifier {
             Some(VariableModifier::Optional) if expr.len() >= 2 => expr[1..].join(" "),
@@ -626,6 +662,55 @@ impl VariableValue {
                     )
                 }
             }
+            Some(modifier) if modifier.is_map() => {
+                if self.is_null() {
+                    Ok(VariableValue::Record {
+                        name: section_name.to_string(),
+                        caption: Box::new(None),
+                        headers: HeaderValues(vec![]),
+                        body: None,
+                        values: vec![],
+                        line_number: self.line_number(),
+                        condition: self.condition().clone(),
+                    })
+                } else if self.is_record() {
+                    Ok(self)
+                } else if let VariableValue::String {
+                    ref value,
+                    ref line_number,
+                    ..
+                } = self
+                {
+                    if value.starts_with('$') {
+                        Ok(self)
+                    } else if has_processor {
+                        Ok(VariableValue::Record {
+                            name: section_name.to_string(),
+                            caption: Box::new(None),
+                            headers: HeaderValues(vec![]),
+                            body: Some(BodyValue {
+                                value: value.to_string(),
+                                line_number: *line_number,
+                            }),
+                            values: vec![],
+                            line_number: self.line_number(),
+                            condition: None,
+                        })
+                    } else {
+                        ftd_ast::parse_error(
+                            format!("Expected `{MAP}` entries as headers, found: `{value}`"),
+                            doc_id,
+                            self.line_number(),
+                        )
+                    }
+                } else {
+                    ftd_ast::parse_error(
+                        format!("Expected Map found: `{self:?}`"),
+                        doc_id,
+                        self.line_number(),
+                    )
+                }
+            }
             Some(modifier) if modifier.is_optional() => Ok(self.into_optional()),
             _ => Ok(self),
         }
@@ -16,6 +16,8 @@ pub struct BlockRecordHeader {
     pub fields: Vec<Header>,
     pub condition: Option<String>,
     pub line_number: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<ftd_p1::Span>,
 }
 
 impl BlockRecordHeader {
@@ -36,6 +38,7 @@ impl BlockRecordHeader {
             fields,
             condition,
             line_number,
+            span: None,
         }
     }
 }
@@ -58,6 +61,8 @@ pub struct KV {
     pub condition: Option<String>,
     pub access_modifier: AccessModifier,
     pub source: KVSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<ftd_p1::Span>,
 }
 
 impl KV {
@@ -84,6 +89,7 @@ impl KV {
             condition,
             access_modifier,
             source: source.unwrap_or_default(),
+            span: None,
         }
     }
 }
@@ -145,6 +151,8 @@ pub struct SectionHeader {
     pub kind: Option<String>,
     pub section: Vec<ftd_p1::Section>,
     pub condition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<ftd_p1::Span>,
 }
 
 impl Header {
@@ -183,6 +191,7 @@ impl Header {
             kind,
             section,
             condition,
+            span: None,
         })
     }
 
@@ -213,11 +222,13 @@ impl Header {
             Header::KV(kv) => {
                 let mut kv = (*kv).clone();
                 kv.line_number = 0;
+                kv.span = None;
                 Header::KV(kv)
             }
             Header::Section(s) => {
                 let mut s = (*s).clone();
                 s.line_number = 0;
+                s.span = None;
                 s.section = s
                     .section
                     .iter()
@@ -228,11 +239,29 @@ impl Header {
             Header::BlockRecordHeader(b) => {
                 let mut blockrecord = (*b).clone();
                 blockrecord.line_number = 0;
+                blockrecord.span = None;
                 Header::BlockRecordHeader(blockrecord)
             }
         }
     }
 
+    /// The span of this header and of the sections or fields nested in it.
+    pub fn spans(&self) -> Vec<ftd_p1::Span> {
+        match self {
+            Header::KV(kv) => kv.span.into_iter().collect(),
+            Header::Section(s) => s
+                .span
+                .into_iter()
+                .chain(s.section.iter().flat_map(|v| v.spans()))
+                .collect(),
+            Header::BlockRecordHeader(b) => b
+                .span
+                .into_iter()
+                .chain(b.fields.iter().flat_map(|v| v.spans()))
+                .collect(),
+        }
+    }
+
     pub fn get_key(&self) -> String {
         match self {
             Header::KV(ftd_p1::header::KV { key, .. })
@@ -352,6 +381,15 @@ impl Header {
         }
     }
 
+    /// Where the header sits on its line, see [ftd_p1::Span::of_header].
+    pub fn get_span(&self) -> Option<ftd_p1::Span> {
+        match self {
+            Header::KV(ftd_p1::header::KV { span, .. })
+            | Header::Section(ftd_p1::header::SectionHeader { span, .. })
+            | Header::BlockRecordHeader(ftd_p1::header::BlockRecordHeader { span, .. }) => *span,
+        }
+    }
+
     pub fn get_condition(&self) -> Option<String> {
         match self {
             Header::KV(ftd_p1::header::KV { condition, .. })
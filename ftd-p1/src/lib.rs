@@ -11,12 +11,14 @@ mod test;
 pub(crate) mod header;
 mod parser;
 mod section;
+mod span;
 pub mod utils;
 
-pub use header::{AccessModifier, BlockRecordHeader, Header, Headers, KV, SectionHeader};
+pub use header::{AccessModifier, BlockRecordHeader, Header, Headers, KV, KVSource, SectionHeader};
 pub use parser::{parse, parse_with_line_number};
 pub use section::Body;
 pub use section::Section;
+pub use span::Span;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -237,6 +237,7 @@ impl State {
             is_commented,
             line_number: ftd_p1::utils::i32_to_usize(self.line_number),
             block_body: false,
+            span: None,
         };
 
         self.state
@@ -816,6 +817,7 @@ pub fn parse_with_line_number(
         state: Default::default(),
     };
     state.next()?;
+    ftd_p1::span::set_spans(&mut state.sections, content, line_number);
     Ok(state.sections)
 }
 
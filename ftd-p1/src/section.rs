@@ -14,6 +14,7 @@ use itertools::Itertools;
  * - `is_commented`: A boolean representing whether the section is commented or not
  * - `line_number`: A usize representing the line number where the section starts in the document
  * - `block_body`: A boolean representing whether the section body is present as a block
+ * - `span`: Where the section name sits on its line, filled in by the parser
  *
  */
 #[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize, Default)]
@@ -28,6 +29,8 @@ pub struct Section {
     pub is_commented: bool,
     pub line_number: usize,
     pub block_body: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<ftd_p1::Span>,
 }
 
 impl Section {
@@ -42,6 +45,7 @@ impl Section {
             line_number: 0,
             headers: ftd_p1::Headers(vec![]),
             block_body: false,
+            span: None,
         }
     }
 
@@ -71,9 +75,19 @@ impl Section {
             is_commented: self.is_commented.to_owned(),
             line_number: 0,
             block_body: false,
+            span: None,
         }
     }
 
+    /// The spans of this section and of everything in it: caption, headers and sub sections.
+    pub fn spans(&self) -> Vec<ftd_p1::Span> {
+        let mut spans: Vec<ftd_p1::Span> = self.span.into_iter().collect();
+        spans.extend(self.caption.iter().flat_map(|v| v.spans()));
+        spans.extend(self.headers.0.iter().flat_map(|v| v.spans()));
+        spans.extend(self.sub_sections.iter().flat_map(|v| v.spans()));
+        spans
+    }
+
     pub fn and_caption(mut self, caption: &str) -> Self {
         self.caption = Some(ftd_p1::Header::from_caption(caption, self.line_number));
         self
@@ -166,6 +180,7 @@ impl Section {
             is_commented: false,
             line_number: self.line_number,
             block_body: self.block_body,
+            span: self.span,
         })
     }
 }
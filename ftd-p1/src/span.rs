@@ -0,0 +1,144 @@
+/// Where a section name or a header sits on its line, so diagnostics can underline it.
+///
+/// `start` and `end` are 0-based character columns on line `line_number`, `end` is exclusive.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Span {
+    pub line_number: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The span of the whole line, without its surrounding whitespace.
+    pub fn of_line(line: &str, line_number: usize) -> Option<Span> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let start = line.len() - line.trim_start().len();
+        Some(Span::from_bytes(
+            line,
+            line_number,
+            start,
+            start + trimmed.len(),
+        ))
+    }
+
+    /// The span of `name` on a `-- kind name: caption` or `kind name if { condition }: value`
+    /// line, or of the whole line if `name` can not be found before the condition and the colon.
+    pub fn of_name(line: &str, line_number: usize, name: &str) -> Option<Span> {
+        let before_colon = line.split(':').next().unwrap_or_default();
+        // the condition may mention `name` too
+        let before_condition = before_colon.split(" if ").next().unwrap_or_default();
+        match before_condition.rfind(name) {
+            Some(start) if !name.is_empty() => Some(Span::from_bytes(
+                line,
+                line_number,
+                start,
+                start + name.len(),
+            )),
+            _ => Span::of_line(line, line_number),
+        }
+    }
+
+    /// The span of the caption on a `-- kind name: caption` line.
+    pub fn of_caption(line: &str, line_number: usize) -> Option<Span> {
+        if !is_section_line(line) {
+            return Span::of_line(line, line_number);
+        }
+        let colon = line.find(':')?;
+        let after_colon = &line[colon + 1..];
+        let value = after_colon.trim();
+        if value.is_empty() {
+            return Span::of_line(line, line_number);
+        }
+        let start = colon + 1 + (after_colon.len() - after_colon.trim_start().len());
+        Some(Span::from_bytes(
+            line,
+            line_number,
+            start,
+            start + value.len(),
+        ))
+    }
+
+    /// The span of the header `key` on its line: the caption if `key` is the caption, else the
+    /// name of the header, whether it is on a line of its own, `color if { flag }: red`, or on
+    /// the section line, `-- ftd.text.color: red`.
+    pub fn of_header(line: &str, line_number: usize, key: &str) -> Option<Span> {
+        if key.eq(ftd_p1::utils::CAPTION) {
+            Span::of_caption(line, line_number)
+        } else {
+            Span::of_name(line, line_number, key)
+        }
+    }
+
+    fn from_bytes(line: &str, line_number: usize, start: usize, end: usize) -> Span {
+        Span {
+            line_number,
+            start: line[..start].chars().count(),
+            end: line[..end].chars().count(),
+        }
+    }
+}
+
+fn is_section_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("-- ") || line.starts_with("/-- ")
+}
+
+/// Fills in the spans of the parsed `sections` from the `content` they were parsed from.
+///
+/// `line_offset` is the number of lines at the start of `content` which do not belong to the
+/// document, the same one [ftd_p1::parse_with_line_number] gets.
+pub(crate) fn set_spans(sections: &mut [ftd_p1::Section], content: &str, line_offset: usize) {
+    let lines: Vec<&str> = content
+        .split('\n')
+        .map(|v| v.trim_end_matches('\r'))
+        .collect();
+    let line = |line_number: usize| -> Option<&str> {
+        if line_number == 0 {
+            return None;
+        }
+        lines.get(line_number - 1 + line_offset).copied()
+    };
+
+    for section in sections.iter_mut() {
+        set_section_spans(section, &line);
+    }
+}
+
+fn set_section_spans<'a>(section: &mut ftd_p1::Section, line: &impl Fn(usize) -> Option<&'a str>) {
+    section.span = line(section.line_number)
+        .and_then(|v| Span::of_name(v, section.line_number, section.name.as_str()));
+
+    if let Some(caption) = section.caption.as_mut() {
+        set_header_spans(caption, line);
+    }
+    for header in section.headers.0.iter_mut() {
+        set_header_spans(header, line);
+    }
+    for sub_section in section.sub_sections.iter_mut() {
+        set_section_spans(sub_section, line);
+    }
+}
+
+fn set_header_spans<'a>(header: &mut ftd_p1::Header, line: &impl Fn(usize) -> Option<&'a str>) {
+    match header {
+        ftd_p1::Header::KV(kv) => {
+            kv.span = line(kv.line_number)
+                .and_then(|v| Span::of_header(v, kv.line_number, kv.key.as_str()));
+        }
+        ftd_p1::Header::Section(s) => {
+            s.span = line(s.line_number).and_then(|v| Span::of_name(v, s.line_number, &s.key));
+            for section in s.section.iter_mut() {
+                set_section_spans(section, line);
+            }
+        }
+        ftd_p1::Header::BlockRecordHeader(b) => {
+            b.span = line(b.line_number).and_then(|v| Span::of_name(v, b.line_number, &b.key));
+            for field in b.fields.iter_mut() {
+                set_header_spans(field, line);
+            }
+        }
+    }
+}
@@ -920,3 +920,47 @@ fn kind() {
             .list(),
     );
 }
+
+#[test]
+fn spans() {
+    let sections = super::parse_with_line_number(
+        indoc!(
+            "
+            -- import: prefix
+
+            -- string title: Hello
+            optional string sub-title: World
+
+            -- ftd.text: $title
+            color if { flag }: red
+
+            -- ftd.text.style: bold
+
+            -- end: ftd.text
+            "
+        ),
+        "foo",
+        2,
+    )
+    .unwrap_or_else(|e| panic!("{e:?}"));
+
+    let span = |line_number, start, end| ftd_p1::Span {
+        line_number,
+        start,
+        end,
+    };
+
+    assert_eq!(
+        sections[1].spans(),
+        vec![span(1, 10, 15), span(1, 17, 22), span(2, 16, 25),]
+    );
+    assert_eq!(
+        sections[2].spans(),
+        vec![
+            span(4, 3, 11),
+            span(4, 13, 19),
+            span(5, 0, 5),
+            span(7, 12, 17),
+        ]
+    );
+}
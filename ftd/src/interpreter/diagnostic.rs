@@ -0,0 +1,254 @@
+/// An error found while interpreting a document, pinned to the line it is about.
+///
+/// Its `Display` is a rustc style code frame: the message, the location, the offending line and
+/// the section name or header on it underlined.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Diagnostic {
+    #[serde(default)]
+    pub severity: Severity,
+    pub doc_id: String,
+    pub line_number: usize,
+    /// The section name or header to underline, when the parser saw one on the line.
+    pub span: Option<ftd_p1::Span>,
+    /// The text of the line, when the source of the document is known.
+    pub line: Option<String>,
+    pub message: String,
+}
+
+/// Errors stop a document from being rendered, warnings are things [ftd::interpreter::lint]
+/// finds, which render fine but are likely mistakes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+impl Diagnostic {
+    /// Pins `error` to its line, and looks up the text of that line in the documents parsed so
+    /// far. The span is the one the error carries, or else the first one the parser saw on the
+    /// line. `current_doc_id` is the document being processed when the error happened,
+    /// for the errors which do not say which document they are about.
+    ///
+    /// Returns `None` for errors which are not about a line of a document, e.g. IO errors.
+    pub fn from_error(
+        error: &ftd::interpreter::Error,
+        current_doc_id: &str,
+        parsed_libs: &ftd::Map<ftd::interpreter::ParsedDocument>,
+    ) -> Option<Diagnostic> {
+        if let ftd::interpreter::Error::Spanned { span, error } = error {
+            return Diagnostic::from_error(error, current_doc_id, parsed_libs).map(|d| {
+                Diagnostic {
+                    span: Some(*span),
+                    ..d
+                }
+            });
+        }
+
+        let (doc_id, line_number, message) = match error {
+            ftd::interpreter::Error::ParseError {
+                message,
+                doc_id,
+                line_number,
+            }
+            | ftd::interpreter::Error::InvalidKind {
+                message,
+                doc_id,
+                line_number,
+            }
+            | ftd::interpreter::Error::ValueNotFound {
+                message,
+                doc_id,
+                line_number,
+            }
+            | ftd::interpreter::Error::ASTError(ftd_ast::Error::Parse {
+                message,
+                doc_id,
+                line_number,
+            })
+            | ftd::interpreter::Error::P1Error(ftd_p1::Error::ParseError {
+                message,
+                doc_id,
+                line_number,
+            })
+            | ftd::interpreter::Error::ASTError(ftd_ast::Error::P1(ftd_p1::Error::ParseError {
+                message,
+                doc_id,
+                line_number,
+            })) => (doc_id.as_str(), *line_number, message.to_string()),
+            ftd::interpreter::Error::FoundCycle {
+                message,
+                line_number,
+            } => (
+                current_doc_id,
+                *line_number,
+                format!("Found Cycle: {message}"),
+            ),
+            ftd::interpreter::Error::InvalidAccessError {
+                message,
+                line_number,
+            } => (
+                current_doc_id,
+                *line_number,
+                format!("Invalid access: {message}"),
+            ),
+            _ => return None,
+        };
+
+        // documents are named after their url, with a trailing `/` some errors leave out
+        let document = parsed_libs
+            .get(doc_id)
+            .or_else(|| parsed_libs.get(format!("{doc_id}/").as_str()));
+        Some(Diagnostic {
+            severity: Severity::Error,
+            doc_id: doc_id.to_string(),
+            line_number,
+            span: document.and_then(|d| {
+                d.spans
+                    .iter()
+                    .find(|span| span.line_number == line_number)
+                    .copied()
+            }),
+            line: document.and_then(|d| d.line(line_number)),
+            message,
+        })
+    }
+
+    /// A warning about line `line_number` of `document`, with `span` underlined, or the whole
+    /// line if it is `None`.
+    pub(crate) fn warning(
+        document: &ftd::interpreter::ParsedDocument,
+        line_number: usize,
+        span: Option<ftd_p1::Span>,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            doc_id: document.name.to_string(),
+            line_number,
+            span,
+            line: document.line(line_number),
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders all `diagnostics`, one code frame after another.
+    pub fn render_all(diagnostics: &[Diagnostic]) -> String {
+        let errors = diagnostics.iter().filter(|v| v.is_error()).count();
+        let warnings = diagnostics.len() - errors;
+        let count = |n: usize, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+        let mut rendered = match (errors, warnings) {
+            (_, 0) => format!("found {}", count(errors, "error")),
+            (0, _) => format!("found {}", count(warnings, "warning")),
+            _ => format!(
+                "found {} and {}",
+                count(errors, "error"),
+                count(warnings, "warning")
+            ),
+        };
+        for diagnostic in diagnostics {
+            rendered.push_str(format!("\n\n{diagnostic}").as_str());
+        }
+        rendered
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        writeln!(f, "{severity}: {}", self.message)?;
+
+        let line = match self.line.as_ref() {
+            Some(line) if !line.trim().is_empty() => line,
+            _ => return write!(f, " --> {}:{}", self.doc_id, self.line_number),
+        };
+        // the span is only missing for lines which are neither a section nor a header, e.g.
+        // body text, so the whole line is underlined
+        let span = self
+            .span
+            .or_else(|| ftd_p1::Span::of_line(line, self.line_number))
+            .unwrap_or_default();
+        let gutter = " ".repeat(self.line_number.to_string().len());
+
+        writeln!(
+            f,
+            "{gutter}--> {}:{}:{}",
+            self.doc_id,
+            self.line_number,
+            span.start + 1
+        )?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line_number, line.trim_end())?;
+        write!(
+            f,
+            "{gutter} | {}{}",
+            " ".repeat(span.start),
+            "^".repeat(span.end.saturating_sub(span.start).max(1))
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn diagnostic(span: Option<ftd_p1::Span>, line: Option<&str>) -> super::Diagnostic {
+        super::Diagnostic {
+            severity: super::Severity::Error,
+            doc_id: "foo".to_string(),
+            line_number: 12,
+            span,
+            line: line.map(|v| v.to_string()),
+            message: "`colr` is not a header of `ftd.text`".to_string(),
+        }
+    }
+
+    #[test]
+    fn code_frame() {
+        let span = ftd_p1::Span {
+            line_number: 12,
+            start: 4,
+            end: 8,
+        };
+        assert_eq!(
+            diagnostic(Some(span), Some("    colr: red")).to_string(),
+            indoc::indoc!(
+                "
+                error: `colr` is not a header of `ftd.text`
+                  --> foo:12:5
+                   |
+                12 |     colr: red
+                   |     ^^^^"
+            )
+        );
+    }
+
+    #[test]
+    fn code_frame_without_span() {
+        assert_eq!(
+            diagnostic(None, Some("  colr: red  ")).to_string(),
+            indoc::indoc!(
+                "
+                error: `colr` is not a header of `ftd.text`
+                  --> foo:12:3
+                   |
+                12 |   colr: red
+                   |   ^^^^^^^^^"
+            )
+        );
+        assert_eq!(
+            diagnostic(None, None).to_string(),
+            indoc::indoc!(
+                "
+                error: `colr` is not a header of `ftd.text`
+                 --> foo:12"
+            )
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Checks for things which interpret fine but are likely mistakes, reported as warnings.
+//!
+//! These look at the text of the documents, so a name mentioned only in a body or a comment
+//! inside a section counts as used: they may miss things, but what they report is unused.
+
+/// The imports of `document` which nothing in it refers to, and its `-- end:` lines which end a
+/// section without children.
+pub fn lint(document: &ftd::interpreter::ParsedDocument) -> Vec<ftd::interpreter::Diagnostic> {
+    let mut diagnostics = unused_imports(document);
+    diagnostics.extend(dead_ends(document));
+    diagnostics.sort_by_key(|v| v.line_number);
+    diagnostics
+}
+
+/// The variables defined in `documents` which none of them refers to.
+///
+/// `documents` should be all the documents of a package, a variable used only by another package
+/// is reported too.
+pub fn unused_variables(
+    documents: &[&ftd::interpreter::ParsedDocument],
+) -> Vec<ftd::interpreter::Diagnostic> {
+    let mut diagnostics = vec![];
+    for document in documents {
+        let module = document.name.trim_end_matches('/');
+        for ast in document.ast.iter() {
+            let ftd_ast::Ast::VariableDefinition(variable) = ast else {
+                continue;
+            };
+            if variable.line_number == 0 || variable.flags.always_include == Some(true) {
+                continue;
+            }
+            let name = variable.name.as_str();
+            let used_here = uses(document, variable.line_number).any(|line| mentions(line, name));
+            let used_elsewhere = || {
+                documents.iter().any(|other| {
+                    other.name.ne(&document.name)
+                        && references_to(other, module, name)
+                            .iter()
+                            .any(|reference| uses(other, 0).any(|line| mentions(line, reference)))
+                })
+            };
+            if used_here || used_elsewhere() {
+                continue;
+            }
+            let span = document
+                .line(variable.line_number)
+                .and_then(|line| ftd_p1::Span::of_name(&line, variable.line_number, name));
+            diagnostics.push(ftd::interpreter::Diagnostic::warning(
+                document,
+                variable.line_number,
+                span,
+                format!("unused variable: `{name}`"),
+            ));
+        }
+    }
+    diagnostics
+}
+
+fn unused_imports(
+    document: &ftd::interpreter::ParsedDocument,
+) -> Vec<ftd::interpreter::Diagnostic> {
+    let mut diagnostics = vec![];
+    for ast in document.ast.iter() {
+        let ftd_ast::Ast::Import(import) = ast else {
+            continue;
+        };
+        // line 0 is the imports the caller prefixed the document with, and re-exported or
+        // exposed-all modules are used in ways the text does not show
+        if import.line_number == 0
+            || import.exports.is_some()
+            || matches!(import.exposing, Some(ftd_ast::Exposing::All))
+        {
+            continue;
+        }
+        let mut names = vec![import.alias.as_str()];
+        if let Some(ftd_ast::Exposing::Things(things)) = import.exposing.as_ref() {
+            names.extend(things.iter().map(|v| v.as_str()));
+        }
+        if uses(document, import.line_number).any(|line| names.iter().any(|n| mentions(line, n))) {
+            continue;
+        }
+        let span = document
+            .line(import.line_number)
+            .and_then(|line| ftd_p1::Span::of_caption(&line, import.line_number));
+        diagnostics.push(ftd::interpreter::Diagnostic::warning(
+            document,
+            import.line_number,
+            span,
+            format!("unused import: `{}`", import.module),
+        ));
+    }
+    diagnostics
+}
+
+/// `-- end: foo` right after `-- foo:` and its headers or body: `foo` has no children, so the
+/// `-- end:` is not needed.
+fn dead_ends(document: &ftd::interpreter::ParsedDocument) -> Vec<ftd::interpreter::Diagnostic> {
+    let mut diagnostics = vec![];
+    for (index, line) in document.lines.iter().enumerate() {
+        let Some(name) = line.trim().strip_prefix("-- end:").map(|v| v.trim()) else {
+            continue;
+        };
+        let opening = document.lines[..index]
+            .iter()
+            .rev()
+            .find(|v| v.trim_start().starts_with("-- ") || v.trim_start().starts_with("/-- "));
+        let Some(opening) = opening else {
+            continue;
+        };
+        let opening_name = opening
+            .trim_start()
+            .strip_prefix("-- ")
+            .and_then(|v| v.split(':').next())
+            .map(|v| v.trim());
+        if opening_name != Some(name) {
+            continue;
+        }
+        let line_number = index + 1;
+        diagnostics.push(ftd::interpreter::Diagnostic::warning(
+            document,
+            line_number,
+            ftd_p1::Span::of_line(line, line_number),
+            format!("`{name}` has no children, this `-- end: {name}` is not needed"),
+        ));
+    }
+    diagnostics
+}
+
+/// The names `document` would use for `name` of `module`: `alias.name` for each alias of
+/// `module`, and `name` if it is exposed.
+fn references_to(
+    document: &ftd::interpreter::ParsedDocument,
+    module: &str,
+    name: &str,
+) -> Vec<String> {
+    let mut references: Vec<String> = document
+        .doc_aliases
+        .iter()
+        .filter(|(_, m)| m.trim_end_matches('/') == module)
+        .map(|(alias, _)| format!("{alias}.{name}"))
+        .collect();
+    if document
+        .exposings
+        .get(name)
+        .is_some_and(|m| m.trim_end_matches('/') == module)
+    {
+        references.push(name.to_string());
+    }
+    references
+}
+
+/// The lines of `document` which may use a name: all but comments, `-- end:` lines and line
+/// `except`, where the name is defined or imported.
+fn uses(document: &ftd::interpreter::ParsedDocument, except: usize) -> impl Iterator<Item = &str> {
+    document
+        .lines
+        .iter()
+        .enumerate()
+        .filter(move |(index, _)| index + 1 != except)
+        .map(|(_, line)| line.as_str())
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.starts_with(";;") && !line.starts_with("-- end:")
+        })
+}
+
+/// Whether `line` has `name` as a whole word. A `.` before it makes it a field of something else.
+fn mentions(line: &str, name: &str) -> bool {
+    let is_part_of_name = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    line.match_indices(name).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + name.len()..].chars().next();
+        !before.is_some_and(|c| is_part_of_name(c) || c == '.')
+            && !after.is_some_and(is_part_of_name)
+    })
+}
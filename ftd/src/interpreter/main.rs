@@ -33,6 +33,12 @@ pub type HostBuiltins = [(String, fastn_resolved::Definition); 3];
 ///
 /// - `instructions`: a `Vec` of `fastn_resolved::Component`s that represents the instructions
 ///   that the interpreter has processed.
+///
+/// - `collect_errors`: whether to keep going past errors, collecting them in `errors`, instead
+///   of stopping at the first one. See [interpret_collecting_errors].
+///
+/// - `broken`: the definitions which failed while collecting errors, so that the things using
+///   them fail too, without reporting the same error again.
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct InterpreterState {
     pub id: String,
@@ -44,6 +50,9 @@ pub struct InterpreterState {
     pub parsed_libs: ftd::Map<ParsedDocument>,
     pub instructions: Vec<fastn_resolved::ComponentInvocation>,
     pub in_process: Vec<(String, usize, ftd_ast::Ast)>,
+    pub collect_errors: bool,
+    pub errors: Vec<ftd::interpreter::Diagnostic>,
+    pub broken: std::collections::HashSet<String>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -275,8 +284,6 @@ impl InterpreterState {
     }
 
     pub fn continue_processing(mut self) -> ftd::interpreter::Result<Interpreter> {
-        use ftd::interpreter::{PropertyValueExt, ValueExt};
-
         let mut count = 0;
         while let Some((doc_name, number_of_scan, ast, exports)) = self.get_next_ast() {
             count += 1;
@@ -298,7 +305,14 @@ impl InterpreterState {
                 ));
             }
 
-            if let Some(interpreter) = self.resolve_pending_imports::<ftd::interpreter::Thing>()? {
+            let pending_import = match self.resolve_pending_imports::<ftd::interpreter::Thing>() {
+                Ok(pending_import) => pending_import,
+                Err(e) => {
+                    self.recover(doc_name.as_str(), e)?;
+                    continue;
+                }
+            };
+            if let Some(interpreter) = pending_import {
                 match interpreter {
                     ftd::interpreter::StateWithThing::State(s) => {
                         return Ok(s.into_interpreter(self));
@@ -310,290 +324,391 @@ impl InterpreterState {
                 }
             }
 
-            self.increase_scan_count();
-            let parsed_document = self.parsed_libs.get(doc_name.as_str()).unwrap();
-            let name = parsed_document.name.to_string();
-            let aliases = parsed_document.doc_aliases.clone();
+            match self.process_ast(doc_name.as_str(), number_of_scan, ast, exports) {
+                Ok(None) => {}
+                Ok(Some(s)) => return Ok(s.into_interpreter(self)),
+                Err(e) => self.recover(doc_name.as_str(), e)?,
+            }
+        }
 
-            let ast_full_name = ftd::interpreter::utils::resolve_name(
-                ast.name().as_str(),
-                &parsed_document.name,
-                &parsed_document.doc_aliases,
-            );
+        if self.to_process.stack.is_empty() {
+            if !self.errors.is_empty() {
+                return Err(ftd::interpreter::Error::Diagnostics(self.errors));
+            }
 
-            self.detect_cycle(ast_full_name.as_str(), number_of_scan, &ast)?;
-            let is_in_bag = self.bag.contains_key(&ast_full_name);
+            let document = Document {
+                data: self.bag,
+                aliases: self
+                    .parsed_libs
+                    .get(self.id.as_str())
+                    .unwrap()
+                    .doc_aliases
+                    .clone(),
+                tree: self.instructions,
+                name: self.id,
+                js: self.js,
+                css: self.css,
+            };
 
-            if is_in_bag {
-                let line_number = self.bag.get(&ast_full_name).unwrap().line_number();
-                ftd::interpreter::utils::insert_export_thing(
-                    exports.as_slice(),
-                    ast_full_name.as_str(),
-                    &mut self.bag,
-                    doc_name.as_str(),
-                    line_number,
-                );
-            }
-            let state = &mut self;
+            Ok(Interpreter::Done { document })
+        } else {
+            self.continue_processing()
+        }
+    }
 
-            let mut doc = ftd::interpreter::TDoc::new_state(&name, &aliases, state);
+    /// Processes `ast`, the next item on the stack, from `doc_name`. Returns the state to hand
+    /// over to the caller when the interpreter gets stuck, e.g. on an import.
+    fn process_ast(
+        &mut self,
+        doc_name: &str,
+        number_of_scan: usize,
+        ast: ftd_ast::Ast,
+        exports: Vec<String>,
+    ) -> ftd::interpreter::Result<Option<Box<InterpreterWithoutState>>> {
+        use ftd::interpreter::{PropertyValueExt, ValueExt};
 
-            if doc.aliases.get(ast.name().as_str()).is_some() {
-                let message = format!(
-                    "Triying to redefine an alias: `{name}`. This is not allowed.",
-                    name = ast.name(),
-                );
+        self.increase_scan_count();
+        let parsed_document = self.parsed_libs.get(doc_name).unwrap();
+        let name = parsed_document.name.to_string();
+        let aliases = parsed_document.doc_aliases.clone();
 
-                return Err(ftd::interpreter::Error::ParseError {
-                    message,
-                    doc_id: doc.name.to_string(),
-                    line_number: ast.line_number(),
-                });
-            }
+        let ast_full_name = ftd::interpreter::utils::resolve_name(
+            ast.name().as_str(),
+            &parsed_document.name,
+            &parsed_document.doc_aliases,
+        );
 
-            if ast.is_record() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::Record::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::Record::from_ast(ast, &mut doc)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(record) => {
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    record.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    record.line_number,
-                                );
-                                self.bag.insert(
-                                    record.name.to_string(),
-                                    ftd::interpreter::Thing::Record(record),
-                                );
-                            }
-                            ftd::interpreter::StateWithThing::Continue => continue,
+        self.detect_cycle(ast_full_name.as_str(), number_of_scan, &ast)?;
+        let is_in_bag = self.bag.contains_key(&ast_full_name);
+
+        if is_in_bag {
+            let line_number = self.bag.get(&ast_full_name).unwrap().line_number();
+            ftd::interpreter::utils::insert_export_thing(
+                exports.as_slice(),
+                ast_full_name.as_str(),
+                &mut self.bag,
+                doc_name,
+                line_number,
+            );
+        }
+        let mut doc = ftd::interpreter::TDoc::new_state(&name, &aliases, self);
+
+        if doc.aliases.get(ast.name().as_str()).is_some() {
+            let message = format!(
+                "Triying to redefine an alias: `{name}`. This is not allowed.",
+                name = ast.name(),
+            );
+
+            return Err(ftd::interpreter::Error::ParseError {
+                message,
+                doc_id: doc.name.to_string(),
+                line_number: ast.line_number(),
+            });
+        }
+
+        if ast.is_record() {
+            if !is_in_bag {
+                if number_of_scan.eq(&1) {
+                    fastn_resolved::Record::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
+                } else {
+                    match fastn_resolved::Record::from_ast(ast, &mut doc)? {
+                        ftd::interpreter::StateWithThing::State(s) => {
+                            return Ok(Some(s));
                         }
-                    }
-                }
-            } else if ast.is_or_type() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::OrType::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::OrType::from_ast(ast, &mut doc)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(or_type) => {
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    or_type.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    or_type.line_number,
-                                );
-                                self.bag.insert(
-                                    or_type.name.to_string(),
-                                    ftd::interpreter::Thing::OrType(or_type),
-                                );
-                            }
-                            ftd::interpreter::StateWithThing::Continue => continue,
+                        ftd::interpreter::StateWithThing::Thing(record) => {
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                record.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                record.line_number,
+                            );
+                            self.bag.insert(
+                                record.name.to_string(),
+                                ftd::interpreter::Thing::Record(record),
+                            );
                         }
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
-            } else if ast.is_function() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::Function::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::Function::from_ast(ast, &mut doc)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(function) => {
-                                if let Some(ref js) = function.js {
-                                    let js = js
-                                        .to_owned()
-                                        .resolve(&doc, function.line_number)?
-                                        .string_list(&doc, function.line_number)?;
-
-                                    for js in js.iter() {
-                                        self.js.insert(js.to_string());
-                                    }
-                                }
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    function.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    function.line_number,
-                                );
-                                self.bag.insert(
-                                    function.name.to_string(),
-                                    ftd::interpreter::Thing::Function(function),
-                                );
-                            }
-                            ftd::interpreter::StateWithThing::Continue => continue,
+            }
+        } else if ast.is_or_type() {
+            if !is_in_bag {
+                if number_of_scan.eq(&1) {
+                    fastn_resolved::OrType::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
+                } else {
+                    match fastn_resolved::OrType::from_ast(ast, &mut doc)? {
+                        ftd::interpreter::StateWithThing::State(s) => {
+                            return Ok(Some(s));
                         }
+                        ftd::interpreter::StateWithThing::Thing(or_type) => {
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                or_type.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                or_type.line_number,
+                            );
+                            self.bag.insert(
+                                or_type.name.to_string(),
+                                ftd::interpreter::Thing::OrType(or_type),
+                            );
+                        }
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
-            } else if ast.is_variable_definition() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::Variable::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::Variable::from_ast(ast, &mut doc, number_of_scan)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(variable) => {
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    variable.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    variable.line_number,
-                                );
-                                self.bag.insert(
-                                    variable.name.to_string(),
-                                    ftd::interpreter::Thing::Variable(variable),
-                                );
+            }
+        } else if ast.is_function() {
+            if !is_in_bag {
+                if number_of_scan.eq(&1) {
+                    fastn_resolved::Function::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
+                } else {
+                    match fastn_resolved::Function::from_ast(ast, &mut doc)? {
+                        ftd::interpreter::StateWithThing::State(s) => {
+                            return Ok(Some(s));
+                        }
+                        ftd::interpreter::StateWithThing::Thing(function) => {
+                            if let Some(ref js) = function.js {
+                                let js = js
+                                    .to_owned()
+                                    .resolve(&doc, function.line_number)?
+                                    .string_list(&doc, function.line_number)?;
+
+                                for js in js.iter() {
+                                    self.js.insert(js.to_string());
+                                }
                             }
-                            ftd::interpreter::StateWithThing::Continue => continue,
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                function.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                function.line_number,
+                            );
+                            self.bag.insert(
+                                function.name.to_string(),
+                                ftd::interpreter::Thing::Function(function),
+                            );
                         }
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
-            } else if ast.is_variable_invocation() {
+            }
+        } else if ast.is_variable_definition() {
+            if !is_in_bag {
                 if number_of_scan.eq(&1) {
-                    fastn_resolved::Variable::scan_update_from_ast(ast, &mut doc)?;
-                    continue;
+                    fastn_resolved::Variable::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
                 } else {
-                    match fastn_resolved::Variable::update_from_ast(ast, &mut doc)? {
+                    match fastn_resolved::Variable::from_ast(ast, &mut doc, number_of_scan)? {
                         ftd::interpreter::StateWithThing::State(s) => {
-                            return Ok(s.into_interpreter(self));
+                            return Ok(Some(s));
                         }
                         ftd::interpreter::StateWithThing::Thing(variable) => {
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                variable.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                variable.line_number,
+                            );
                             self.bag.insert(
                                 variable.name.to_string(),
                                 ftd::interpreter::Thing::Variable(variable),
                             );
                         }
-                        ftd::interpreter::StateWithThing::Continue => continue,
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
-            } else if ast.is_component_definition() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::ComponentDefinition::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::ComponentDefinition::from_ast(ast, &mut doc)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(component) => {
-                                if let Some(ref css) = component.css {
-                                    let css = css
-                                        .to_owned()
-                                        .resolve(&doc, component.line_number)?
-                                        .string(doc.name, component.line_number)?;
-                                    self.css.insert(css);
-                                }
-
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    component.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    component.line_number,
-                                );
-
-                                self.bag.insert(
-                                    component.name.to_string(),
-                                    ftd::interpreter::Thing::Component(component),
-                                );
-                            }
-                            ftd::interpreter::StateWithThing::Continue => continue,
-                        }
+            }
+        } else if ast.is_variable_invocation() {
+            if number_of_scan.eq(&1) {
+                fastn_resolved::Variable::scan_update_from_ast(ast, &mut doc)?;
+                return Ok(None);
+            } else {
+                match fastn_resolved::Variable::update_from_ast(ast, &mut doc)? {
+                    ftd::interpreter::StateWithThing::State(s) => {
+                        return Ok(Some(s));
+                    }
+                    ftd::interpreter::StateWithThing::Thing(variable) => {
+                        self.bag.insert(
+                            variable.name.to_string(),
+                            ftd::interpreter::Thing::Variable(variable),
+                        );
                     }
+                    ftd::interpreter::StateWithThing::Continue => return Ok(None),
                 }
-            } else if ast.is_web_component_definition() {
-                if !is_in_bag {
-                    if number_of_scan.eq(&1) {
-                        fastn_resolved::WebComponentDefinition::scan_ast(ast, &mut doc)?;
-                        continue;
-                    } else {
-                        match fastn_resolved::WebComponentDefinition::from_ast(ast, &mut doc)? {
-                            ftd::interpreter::StateWithThing::State(s) => {
-                                return Ok(s.into_interpreter(self));
-                            }
-                            ftd::interpreter::StateWithThing::Thing(web_component) => {
-                                let js = web_component
-                                    .js
+            }
+        } else if ast.is_component_definition() {
+            if !is_in_bag {
+                if number_of_scan.eq(&1) {
+                    fastn_resolved::ComponentDefinition::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
+                } else {
+                    match fastn_resolved::ComponentDefinition::from_ast(ast, &mut doc)? {
+                        ftd::interpreter::StateWithThing::State(s) => {
+                            return Ok(Some(s));
+                        }
+                        ftd::interpreter::StateWithThing::Thing(component) => {
+                            if let Some(ref css) = component.css {
+                                let css = css
                                     .to_owned()
-                                    .resolve(&doc, web_component.line_number)?
-                                    .string(doc.name, web_component.line_number)?;
-                                self.js.insert(format!("{js}:type=\"module\""));
-                                ftd::interpreter::utils::insert_export_thing(
-                                    exports.as_slice(),
-                                    web_component.name.as_str(),
-                                    &mut self.bag,
-                                    doc_name.as_str(),
-                                    web_component.line_number,
-                                );
-                                self.bag.insert(
-                                    web_component.name.to_string(),
-                                    ftd::interpreter::Thing::WebComponent(web_component),
-                                );
+                                    .resolve(&doc, component.line_number)?
+                                    .string(doc.name, component.line_number)?;
+                                self.css.insert(css);
                             }
-                            ftd::interpreter::StateWithThing::Continue => continue,
+
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                component.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                component.line_number,
+                            );
+
+                            self.bag.insert(
+                                component.name.to_string(),
+                                ftd::interpreter::Thing::Component(component),
+                            );
                         }
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
-            } else if ast.is_component_invocation() {
+            }
+        } else if ast.is_web_component_definition() {
+            if !is_in_bag {
                 if number_of_scan.eq(&1) {
-                    fastn_resolved::ComponentInvocation::scan_ast(ast, &mut doc)?;
-                    continue;
+                    fastn_resolved::WebComponentDefinition::scan_ast(ast, &mut doc)?;
+                    return Ok(None);
                 } else {
-                    match fastn_resolved::ComponentInvocation::from_ast(ast, &mut doc)? {
+                    match fastn_resolved::WebComponentDefinition::from_ast(ast, &mut doc)? {
                         ftd::interpreter::StateWithThing::State(s) => {
-                            return Ok(s.into_interpreter(self));
+                            return Ok(Some(s));
                         }
-                        ftd::interpreter::StateWithThing::Thing(component) => {
-                            self.instructions.push(component);
+                        ftd::interpreter::StateWithThing::Thing(web_component) => {
+                            let js = web_component
+                                .js
+                                .to_owned()
+                                .resolve(&doc, web_component.line_number)?
+                                .string(doc.name, web_component.line_number)?;
+                            self.js.insert(format!("{js}:type=\"module\""));
+                            ftd::interpreter::utils::insert_export_thing(
+                                exports.as_slice(),
+                                web_component.name.as_str(),
+                                &mut self.bag,
+                                doc_name,
+                                web_component.line_number,
+                            );
+                            self.bag.insert(
+                                web_component.name.to_string(),
+                                ftd::interpreter::Thing::WebComponent(web_component),
+                            );
                         }
-                        ftd::interpreter::StateWithThing::Continue => continue,
+                        ftd::interpreter::StateWithThing::Continue => return Ok(None),
                     }
                 }
             }
-            self.remove_last();
+        } else if ast.is_component_invocation() {
+            if number_of_scan.eq(&1) {
+                fastn_resolved::ComponentInvocation::scan_ast(ast, &mut doc)?;
+                return Ok(None);
+            } else {
+                match fastn_resolved::ComponentInvocation::from_ast(ast, &mut doc)? {
+                    ftd::interpreter::StateWithThing::State(s) => {
+                        return Ok(Some(s));
+                    }
+                    ftd::interpreter::StateWithThing::Thing(component) => {
+                        self.instructions.push(component);
+                    }
+                    ftd::interpreter::StateWithThing::Continue => return Ok(None),
+                }
+            }
         }
+        self.remove_last();
+        Ok(None)
+    }
 
-        if self.to_process.stack.is_empty() {
-            let document = Document {
-                data: self.bag,
-                aliases: self
-                    .parsed_libs
-                    .get(self.id.as_str())
-                    .unwrap()
-                    .doc_aliases
-                    .clone(),
-                tree: self.instructions,
-                name: self.id,
-                js: self.js,
-                css: self.css,
+    /// When collecting errors, records `error` and gives up on the item being processed: a top
+    /// level item of the main document, or a definition processed on behalf of one. A failed
+    /// definition is remembered in `broken`, the items using it fail when they look it up, but the
+    /// other definitions they use are still processed and report their own errors. Otherwise, or
+    /// if the error is not about a line of a document, the error is returned.
+    fn recover(
+        &mut self,
+        doc_name: &str,
+        error: ftd::interpreter::Error,
+    ) -> ftd::interpreter::Result<()> {
+        if !self.collect_errors {
+            return Err(error);
+        }
+        // the error of a broken definition is already reported
+        if !matches!(error, ftd::interpreter::Error::BrokenDependency { .. }) {
+            let Some(diagnostic) =
+                ftd::interpreter::Diagnostic::from_error(&error, doc_name, &self.parsed_libs)
+            else {
+                return Err(error);
             };
+            if !self.errors.contains(&diagnostic) {
+                self.errors.push(diagnostic);
+            }
+        }
 
-            Ok(Interpreter::Done { document })
-        } else {
-            self.continue_processing()
+        if let Some(name) = self.processing_definition_name() {
+            self.broken.insert(name);
+        }
+        // the imports pending are the ones of the failed item
+        self.pending_imports = Default::default();
+        self.in_process.clear();
+
+        // a definition is processed in a stack entry of its own, which also has the definitions
+        // of its fields, e.g. `foo` and `foo.bar`, and is dropped as a whole. The bottom entry is
+        // the main document, only its first item is dropped.
+        let depth = self.to_process.stack.len();
+        loop {
+            self.remove_last();
+            if depth == 1 || self.to_process.stack.len() < depth {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The full name of the definition being processed, `None` for component invocations.
+    fn processing_definition_name(&self) -> Option<String> {
+        let (doc_name, ast_list) = self.to_process.stack.last()?;
+        let ast = &ast_list.first()?.ast;
+        ast.get_definition_name()?;
+        let document = self.parsed_libs.get(doc_name)?;
+        let ast_full_name = ftd::interpreter::utils::resolve_name(
+            ast.name().as_str(),
+            document.name.as_str(),
+            &document.doc_aliases,
+        );
+        let (doc_name, thing_name, _remaining) =
+            ftd::interpreter::utils::get_doc_name_and_thing_name_and_remaining(
+                ast_full_name.as_str(),
+                doc_name,
+                ast.line_number(),
+            );
+        Some(format!("{doc_name}#{thing_name}"))
+    }
+
+    /// Fails if `name`, a `doc#thing` full name, is a definition which failed before.
+    pub(crate) fn check_not_broken(
+        &self,
+        name: &str,
+        line_number: usize,
+    ) -> ftd::interpreter::Result<()> {
+        if self.broken.contains(name) {
+            return Err(ftd::interpreter::Error::BrokenDependency {
+                name: name.to_string(),
+                line_number,
+            });
         }
+        Ok(())
     }
 
     /// Returns (doc_name, number_of_scan, last_ast)
@@ -762,6 +877,7 @@ impl InterpreterState {
                 module,
                 line_number,
             );
+        self.check_not_broken(format!("{doc_name}#{thing_name}").as_str(), line_number)?;
 
         if doc_name.ne(self.id.as_str()) {
             let current_document = self.parsed_libs.get(self.id.as_str()).unwrap();
@@ -956,6 +1072,44 @@ impl InterpreterState {
         self.continue_processing()
     }
 
+    /// Gives up on `module`, which the host could not import for `caller_module` because of
+    /// `error`.
+    ///
+    /// When collecting errors, the error is reported at the `-- import:` line of `caller_module`
+    /// and processing goes on with the next top level item. Otherwise `error` is returned.
+    #[tracing::instrument(skip_all)]
+    pub fn continue_after_import_error(
+        mut self,
+        module: &str,
+        caller_module: &str,
+        error: ftd::interpreter::Error,
+    ) -> ftd::interpreter::Result<Interpreter> {
+        if !self.collect_errors {
+            return Err(error);
+        }
+        let line_number = self
+            .parsed_libs
+            .get(caller_module)
+            .and_then(|document| {
+                document.ast.iter().find_map(|ast| match ast {
+                    ftd_ast::Ast::Import(import) if import.module == module => {
+                        Some(import.line_number)
+                    }
+                    _ => None,
+                })
+            })
+            .unwrap_or_default();
+        self.recover(
+            caller_module,
+            ftd::interpreter::Error::ParseError {
+                message: format!("Cannot import `{module}`: {error}"),
+                doc_id: caller_module.to_string(),
+                line_number,
+            },
+        )?;
+        self.continue_processing()
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn continue_after_processor(
         mut self,
@@ -1045,10 +1199,32 @@ pub fn interpret_with_line_number(
     id: &str,
     document: ParsedDocument,
     builtin_overrides: Option<HostBuiltins>,
+) -> ftd::interpreter::Result<Interpreter> {
+    start(id, document, builtin_overrides, false)
+}
+
+/// Like [interpret_with_line_number], but does not stop at the first error. Every definition of
+/// the document is processed, used or not, and every top level item which fails is reported, once
+/// the whole document is processed, as [ftd::interpreter::Error::Diagnostics].
+#[tracing::instrument(skip_all)]
+pub fn interpret_collecting_errors(
+    id: &str,
+    document: ParsedDocument,
+    builtin_overrides: Option<HostBuiltins>,
+) -> ftd::interpreter::Result<Interpreter> {
+    start(id, document, builtin_overrides, true)
+}
+
+fn start(
+    id: &str,
+    document: ParsedDocument,
+    builtin_overrides: Option<HostBuiltins>,
+    collect_errors: bool,
 ) -> ftd::interpreter::Result<Interpreter> {
     use itertools::Itertools;
 
     let mut s = InterpreterState::new_with_expanded_builtins(id.to_string(), builtin_overrides);
+    s.collect_errors = collect_errors;
     s.parsed_libs.insert(id.to_string(), document);
     s.to_process.stack.push((
         id.to_string(),
@@ -1058,7 +1234,11 @@ pub fn interpret_with_line_number(
             .ast
             .iter()
             .filter_map(|v| {
-                if v.is_component_invocation() || v.is_always_included_variable_definition() {
+                // the definitions are processed when used, unless all the errors are wanted
+                if v.is_component_invocation()
+                    || v.is_always_included_variable_definition()
+                    || (collect_errors && v.get_definition_name().is_some())
+                {
                     Some(ftd::interpreter::ToProcessItem {
                         number_of_scan: 0,
                         ast: v.to_owned(),
@@ -1084,6 +1264,12 @@ pub struct ParsedDocument {
     pub exposings: ftd::Map<String>,
     pub foreign_variable: Vec<String>,
     pub foreign_function: Vec<String>,
+    /// Where the section names and headers of the document are, for diagnostics.
+    #[serde(default)]
+    pub spans: Vec<ftd_p1::Span>,
+    /// The lines of the document, without the ones the caller prefixed it with.
+    #[serde(default)]
+    pub lines: Vec<String>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -1102,10 +1288,9 @@ impl ParsedDocument {
         source: &str,
         line_number: usize,
     ) -> ftd::interpreter::Result<ParsedDocument> {
-        let ast = ftd_ast::Ast::from_sections(
-            ftd_p1::parse_with_line_number(source, id, line_number)?.as_slice(),
-            id,
-        )?;
+        let sections = ftd_p1::parse_with_line_number(source, id, line_number)?;
+        let spans = sections.iter().flat_map(|v| v.spans()).collect();
+        let ast = ftd_ast::Ast::from_sections(sections.as_slice(), id)?;
         let (doc_aliases, re_exports, exposings) = {
             let mut doc_aliases = ftd::interpreter::default::default_aliases();
             let mut re_exports = ReExport {
@@ -1155,9 +1340,20 @@ impl ParsedDocument {
             exposings,
             foreign_variable: vec![],
             foreign_function: vec![],
+            spans,
+            lines: source
+                .split('\n')
+                .skip(line_number)
+                .map(|v| v.trim_end_matches('\r').to_string())
+                .collect(),
         })
     }
 
+    /// The text of line `line_number`, which starts at 1 like the line numbers of the AST.
+    pub fn line(&self, line_number: usize) -> Option<String> {
+        self.lines.get(line_number.checked_sub(1)?).cloned()
+    }
+
     pub fn get_doc_aliases(&self) -> ftd::Map<String> {
         self.doc_aliases.clone()
     }
@@ -1539,7 +1735,8 @@ impl Document {
             fastn_resolved::Value::String { text, .. } => {
                 serde_json::Value::String(text.to_owned())
             }
-            fastn_resolved::Value::Record { fields, .. } => self.object_to_json(fields)?,
+            fastn_resolved::Value::Record { fields, .. }
+            | fastn_resolved::Value::Map { data: fields, .. } => self.object_to_json(fields)?,
             fastn_resolved::Value::OrType { variant, value, .. } => {
                 let mut map = serde_json::Map::new();
                 map.insert(
@@ -31,6 +31,8 @@ macro_rules! try_state {
 #[cfg(test)]
 #[macro_use]
 mod test;
+mod diagnostic;
+pub mod lint;
 mod main;
 pub mod prelude;
 mod tdoc;
@@ -39,6 +41,7 @@ pub mod utils;
 
 pub use prelude::*;
 
+pub use diagnostic::{Diagnostic, Severity};
 pub use tdoc::{BagOrState, TDoc};
 pub use things::component::ComponentExt;
 pub use things::component::EventNameExt;
@@ -127,6 +130,57 @@ pub enum Error {
 
     #[error("Found Cycle: {message}, line_number: {line_number}")]
     FoundCycle { message: String, line_number: usize },
+
+    #[error("{}", ftd::interpreter::Diagnostic::render_all(.0))]
+    Diagnostics(Vec<ftd::interpreter::Diagnostic>),
+
+    #[error("`{name}` has errors, line_number: {line_number}")]
+    BrokenDependency { name: String, line_number: usize },
+
+    /// `error`, with the part of its line it is about, e.g. the header whose value is wrong.
+    #[error("{error}")]
+    Spanned {
+        span: ftd_p1::Span,
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Points `self` at `span`, if it is about line `span.line_number` of `doc_id`. Errors about
+    /// some other line, or some other document, are returned as they are.
+    pub(crate) fn with_span(self, doc_id: &str, span: Option<ftd_p1::Span>) -> Error {
+        let span = match span {
+            Some(span) => span,
+            None => return self,
+        };
+        let same_line = match &self {
+            Error::ParseError {
+                doc_id: id,
+                line_number,
+                ..
+            }
+            | Error::InvalidKind {
+                doc_id: id,
+                line_number,
+                ..
+            }
+            | Error::ValueNotFound {
+                doc_id: id,
+                line_number,
+                ..
+            } => id == doc_id && *line_number == span.line_number,
+            Error::InvalidAccessError { line_number, .. }
+            | Error::FoundCycle { line_number, .. } => *line_number == span.line_number,
+            _ => false,
+        };
+        if !same_line {
+            return self;
+        }
+        Error::Spanned {
+            span,
+            error: Box::new(self),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,7 +2,7 @@ pub use fastn_builtins::constants::*;
 pub use ftd::interpreter::main::{
     Document, Interpreter, InterpreterState, InterpreterWithoutState, ParsedDocument,
     PendingImportItem, StateWithThing, ToProcess, ToProcessItem, interpret,
-    interpret_with_line_number,
+    interpret_collecting_errors, interpret_with_line_number,
 };
 
 pub use fastn_builtins as default;
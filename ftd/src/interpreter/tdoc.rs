@@ -310,6 +310,27 @@ impl<'a> TDoc<'a> {
                     }
                     Ok(value)
                 }
+                fastn_resolved::Value::Map { data, kind } => {
+                    let value = data
+                        .get(p1.as_str())
+                        .ok_or(ftd::interpreter::Error::ParseError {
+                            message: format!("Can't find key `{p1}` in map of kind `{kind:?}`"),
+                            doc_id: doc.name.to_string(),
+                            line_number,
+                        })?
+                        .clone()
+                        .resolve_with_inherited(doc, line_number, inherited_variables)?;
+                    if let Some(p2) = p2 {
+                        return resolve_(
+                            p2.as_str(),
+                            &value,
+                            line_number,
+                            doc,
+                            inherited_variables,
+                        );
+                    }
+                    Ok(value)
+                }
                 t => ftd::interpreter::utils::e2(
                     format!("Expected record found `{t:?}`").as_str(),
                     doc.name,
@@ -409,6 +430,14 @@ impl<'a> TDoc<'a> {
                             return Ok(Some(variable));
                         }
                     }
+                    fastn_resolved::Value::Map { data, .. } => {
+                        if let Some(entry_value) = data.get(p1.as_str())
+                            && let Some(variable) =
+                                find_variable_reference(entry_value, p2, doc, line_number)?
+                        {
+                            return Ok(Some(variable));
+                        }
+                    }
                     t => {
                         return ftd::interpreter::utils::e2(
                             format!("Expected record, found `{t:?}` in line number {line_number}")
@@ -455,6 +484,22 @@ impl<'a> TDoc<'a> {
                             )?;
                             change_value(field, set, p2, doc, line_number)?;
                         }
+                        fastn_resolved::Value::Map { data, kind } => {
+                            if p2.is_none() && !data.contains_key(p1.as_str()) {
+                                data.insert(p1, set);
+                                return Ok(());
+                            }
+                            let entry = data.get_mut(p1.as_str()).ok_or(
+                                ftd::interpreter::Error::ParseError {
+                                    message: format!(
+                                        "Can't find key `{p1}` in map of kind `{kind:?}`"
+                                    ),
+                                    doc_id: doc.name.to_string(),
+                                    line_number,
+                                },
+                            )?;
+                            change_value(entry, set, p2, doc, line_number)?;
+                        }
                         t => {
                             return ftd::interpreter::utils::e2(
                                 format!("Expected record, found `{t:?}`").as_str(),
@@ -668,7 +713,7 @@ impl<'a> TDoc<'a> {
                         Ok(ftd::interpreter::StateWithThing::new_thing(field_kind))
                     }
                 }
-                fastn_resolved::Kind::List { kind } => {
+                fastn_resolved::Kind::List { kind } | fastn_resolved::Kind::Map { kind } => {
                     if let Some(remaining) = remaining {
                         get_kind_(*kind, &remaining, doc, line_number)
                     } else {
@@ -892,6 +937,7 @@ impl<'a> TDoc<'a> {
                         Some(fastn_resolved::Value::Record { fields, .. }) => fields,
                         Some(fastn_resolved::Value::Object { values }) => values,
                         Some(fastn_resolved::Value::KwArgs { arguments }) => arguments,
+                        Some(fastn_resolved::Value::Map { data, .. }) => data,
                         Some(fastn_resolved::Value::List { data, .. }) => data
                             .into_iter()
                             .enumerate()
@@ -1328,6 +1374,7 @@ impl<'a> TDoc<'a> {
                         Some(fastn_resolved::Value::Record { fields, .. }) => fields,
                         Some(fastn_resolved::Value::Object { values }) => values,
                         Some(fastn_resolved::Value::KwArgs { arguments }) => arguments,
+                        Some(fastn_resolved::Value::Map { data, .. }) => data,
                         Some(fastn_resolved::Value::List { data, .. }) => data
                             .into_iter()
                             .enumerate()
@@ -1521,6 +1568,7 @@ impl<'a> TDoc<'a> {
         } else {
             return self.err("not found", name, "search_thing", line_number);
         };
+        state.check_not_broken(format!("{doc_name}#{thing_name}").as_str(), line_number)?;
 
         let current_parsed_document = state.parsed_libs.get(state.id.as_str()).unwrap();
 
@@ -1843,6 +1891,33 @@ impl<'a> TDoc<'a> {
                     kind: kind.to_owned().into_kind_data(),
                 }
             }
+            // the first column of each row is the key, the rest is the value
+            fastn_resolved::Kind::Map { kind, .. } => {
+                let mut data: ftd::Map<fastn_resolved::PropertyValue> = Default::default();
+                for row in rows {
+                    let (key, row) = match row.split_first() {
+                        Some((serde_json::Value::String(key), row)) => (key.to_string(), row),
+                        Some((key, row)) if !key.is_null() => (key.to_string(), row),
+                        _ => {
+                            return ftd::interpreter::utils::e2(
+                                "expected a key in the first column, found none",
+                                self.name,
+                                value.line_number(),
+                            );
+                        }
+                    };
+                    data.insert(
+                        key,
+                        self.row_to_value(row, kind, value)?
+                            .into_property_value(false, value.line_number()),
+                    );
+                }
+
+                fastn_resolved::Value::Map {
+                    data,
+                    kind: kind.to_owned().into_kind_data(),
+                }
+            }
             t => unimplemented!(
                 "{:?} not yet implemented, line number: {}, doc: {}",
                 t,
@@ -2115,6 +2190,9 @@ impl<'a> TDoc<'a> {
                             Some(v) => v.to_owned(),
                             None if field.kind.is_optional() => serde_json::Value::Null,
                             None if field.kind.is_list() => serde_json::Value::Array(vec![]),
+                            None if field.kind.is_map() => {
+                                serde_json::Value::Object(Default::default())
+                            }
                             None => {
                                 return ftd::interpreter::utils::e2(
                                     format!("key not found: {}", field.name.as_str()),
@@ -2197,6 +2275,31 @@ impl<'a> TDoc<'a> {
                     kind: kind.to_owned().into_kind_data(),
                 }
             }
+            fastn_resolved::Kind::Map { kind } => {
+                let mut data: ftd::Map<fastn_resolved::PropertyValue> = Default::default();
+                if let serde_json::Value::Object(o) = json {
+                    for (key, item) in o {
+                        data.insert(
+                            key.to_string(),
+                            fastn_resolved::PropertyValue::Value {
+                                value: self.as_json_(kind, item, None, None, line_number)?,
+                                is_mutable: false,
+                                line_number,
+                            },
+                        );
+                    }
+                } else {
+                    return ftd::interpreter::utils::e2(
+                        format!("expected object of map type, found: {json}"),
+                        self.name,
+                        line_number,
+                    );
+                }
+                fastn_resolved::Value::Map {
+                    data,
+                    kind: kind.to_owned().into_kind_data(),
+                }
+            }
             fastn_resolved::Kind::Optional { kind, .. } => {
                 let kind = kind.as_ref();
                 match json {
@@ -273,3 +273,211 @@ fn test_extract_kwargs() {
     assert_eq!(data.get("bar"), Some(&String::from("Hello")));
     assert_eq!(data.get("baz"), Some(&String::from("World")));
 }
+
+#[test]
+fn collect_errors() {
+    let source = indoc::indoc!(
+        "
+        -- ftd.text: Hello
+
+        -- ftd: one
+
+        -- ftd.text: World
+
+        -- ftd: two
+        "
+    );
+
+    // by default the interpreter stops at the first error
+    match ftd::interpreter::interpret("foo", source) {
+        Err(ftd::interpreter::Error::ParseError { line_number, .. }) => assert_eq!(line_number, 3),
+        r => panic!("expected a parse error, found: {r:?}"),
+    }
+
+    let document = ftd::interpreter::ParsedDocument::parse("foo", source).unwrap();
+    let diagnostics = match ftd::interpreter::interpret_collecting_errors("foo", document, None) {
+        Err(ftd::interpreter::Error::Diagnostics(diagnostics)) => diagnostics,
+        r => panic!("expected diagnostics, found: {r:?}"),
+    };
+    let span = |line_number| ftd_p1::Span {
+        line_number,
+        start: 3,
+        end: 6,
+    };
+    assert_eq!(
+        diagnostics,
+        vec![
+            ftd::interpreter::Diagnostic {
+                severity: ftd::interpreter::Severity::Error,
+                doc_id: "foo".to_string(),
+                line_number: 3,
+                span: Some(span(3)),
+                line: Some("-- ftd: one".to_string()),
+                message: "Triying to redefine an alias: `ftd`. This is not allowed.".to_string(),
+            },
+            ftd::interpreter::Diagnostic {
+                severity: ftd::interpreter::Severity::Error,
+                doc_id: "foo".to_string(),
+                line_number: 7,
+                span: Some(span(7)),
+                line: Some("-- ftd: two".to_string()),
+                message: "Triying to redefine an alias: `ftd`. This is not allowed.".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn collect_errors_of_each_definition() {
+    let source = indoc::indoc!(
+        "
+        -- component page:
+
+        -- ftd.column:
+
+        -- first:
+        -- second:
+
+        -- end: ftd.column
+
+        -- end: page
+
+        -- component first:
+
+        -- ftd.text: $missing-one
+
+        -- end: first
+
+        -- component second:
+
+        -- ftd.text: $missing-two
+
+        -- end: second
+
+        -- page:
+
+        -- first:
+        "
+    );
+
+    let document = ftd::interpreter::ParsedDocument::parse("foo", source).unwrap();
+    let diagnostics = match ftd::interpreter::interpret_collecting_errors("foo", document, None) {
+        Err(ftd::interpreter::Error::Diagnostics(diagnostics)) => diagnostics,
+        r => panic!("expected diagnostics, found: {r:?}"),
+    };
+    // both definitions `page` uses are reported, once, and not the things using them
+    let errors = diagnostics
+        .iter()
+        .map(|d| (d.line_number, d.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            (14, "foo: not found (\"foo#missing-one\"), f: search_thing"),
+            (20, "foo: not found (\"foo#missing-two\"), f: search_thing"),
+        ]
+    );
+}
+
+#[test]
+fn lint() {
+    let lib = ftd::interpreter::ParsedDocument::parse(
+        "pkg/lib",
+        indoc::indoc!(
+            "
+            -- string used-elsewhere: a
+
+            -- string unused: b
+
+            -- string used-here: c
+
+            -- ftd.text: $used-here
+            "
+        ),
+    )
+    .unwrap();
+    let page = ftd::interpreter::ParsedDocument::parse(
+        "pkg/page",
+        indoc::indoc!(
+            "
+            -- import: pkg/lib
+            -- import: pkg/other as o
+
+            -- ftd.column:
+            padding.px: 2
+
+            -- end: ftd.column
+
+            -- ftd.column:
+
+            -- ftd.text: $lib.used-elsewhere
+
+            -- end: ftd.column
+            "
+        ),
+    )
+    .unwrap();
+
+    let mut diagnostics = ftd::interpreter::lint::lint(&page);
+    diagnostics.extend(ftd::interpreter::lint::unused_variables(&[&lib, &page]));
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|v| (v.doc_id.as_str(), v.line_number, v.message.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("pkg/page", 2, "unused import: `pkg/other`"),
+            (
+                "pkg/page",
+                7,
+                "`ftd.column` has no children, this `-- end: ftd.column` is not needed"
+            ),
+            ("pkg/lib", 3, "unused variable: `unused`"),
+        ]
+    );
+    assert!(diagnostics.iter().all(|v| !v.is_error()));
+}
+
+#[test]
+fn diagnostic_points_at_the_property() {
+    let source = indoc::indoc!(
+        "
+        -- ftd.column: hello
+
+        -- ftd.text: Hello
+        -- ftd.text.colr: red
+        "
+    );
+
+    let document = ftd::interpreter::ParsedDocument::parse("foo", source).unwrap();
+    let diagnostics = match ftd::interpreter::interpret_collecting_errors("foo", document, None) {
+        Err(ftd::interpreter::Error::Diagnostics(diagnostics)) => diagnostics,
+        r => panic!("expected diagnostics, found: {r:?}"),
+    };
+    // the caption and the header, not the section name on their lines
+    let spans = diagnostics
+        .iter()
+        .map(|d| (d.line_number, d.span))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        vec![
+            (
+                1,
+                Some(ftd_p1::Span {
+                    line_number: 1,
+                    start: 15,
+                    end: 20,
+                })
+            ),
+            (
+                4,
+                Some(ftd_p1::Span {
+                    line_number: 4,
+                    start: 12,
+                    end: 16,
+                })
+            ),
+        ]
+    );
+}
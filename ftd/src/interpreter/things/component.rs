@@ -719,15 +719,18 @@ impl PropertyExt for fastn_resolved::Property {
         let mut kw_args_properties = std::collections::BTreeMap::new();
 
         for property in ast_properties {
-            let v = try_ok_state!(fastn_resolved::Property::from_ast_property(
-                property.clone(),
-                component_name,
-                component_arguments.as_slice(),
-                definition_name_with_arguments,
-                &kw_args,
-                loop_object_name_and_kind,
-                doc,
-            )?);
+            let v = try_ok_state!(
+                fastn_resolved::Property::from_ast_property(
+                    property.clone(),
+                    component_name,
+                    component_arguments.as_slice(),
+                    definition_name_with_arguments,
+                    &kw_args,
+                    loop_object_name_and_kind,
+                    doc,
+                )
+                .map_err(|e| e.with_span(doc.name, property.span))?
+            );
             // so this property could correspond to one of the arguments of the component, or it's
             // corresponding to the kw-args.
             // if kw-args is allowed on this component, how do we know?
@@ -1531,9 +1534,11 @@ impl LoopExt for fastn_resolved::Loop {
     fn loop_object_kind(&self, doc_id: &str) -> ftd::interpreter::Result<fastn_resolved::Kind> {
         let kind = self.on.kind();
         match kind {
-            fastn_resolved::Kind::List { kind } => Ok(kind.as_ref().to_owned()),
+            fastn_resolved::Kind::List { kind } | fastn_resolved::Kind::Map { kind } => {
+                Ok(kind.as_ref().to_owned())
+            }
             t => ftd::interpreter::utils::e2(
-                format!("Expected list kind, found: {t:?}"),
+                format!("Expected list or map kind, found: {t:?}"),
                 doc_id,
                 self.line_number,
             ),
@@ -1565,14 +1570,14 @@ impl LoopExt for fastn_resolved::Loop {
         use ftd::interpreter::PropertyValueExt;
 
         let value = self.on.clone().resolve(doc, self.line_number)?;
-        if let fastn_resolved::Value::List { data, kind } = value {
-            Ok((data, kind))
-        } else {
-            ftd::interpreter::utils::e2(
-                format!("Expected list type data, found: {:?}", self.on),
+        match value {
+            fastn_resolved::Value::List { data, kind } => Ok((data, kind)),
+            fastn_resolved::Value::Map { data, kind } => Ok((data.into_values().collect(), kind)),
+            _ => ftd::interpreter::utils::e2(
+                format!("Expected list or map type data, found: {:?}", self.on),
                 doc.name,
                 self.line_number,
-            )
+            ),
         }
     }
 }
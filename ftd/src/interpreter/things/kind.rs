@@ -150,6 +150,7 @@ impl KindDataExt for fastn_resolved::KindData {
             ftd_ast::VariableModifier::Optional => self.optional(),
             ftd_ast::VariableModifier::List => self.list(),
             ftd_ast::VariableModifier::Constant => self.constant(),
+            ftd_ast::VariableModifier::Map => self.map(),
         }
     }
 }
@@ -418,6 +418,7 @@ impl FieldExt for fastn_resolved::Field {
             line_number,
         ) {
             Ok(swt) => Ok(swt),
+            Err(e1 @ ftd::interpreter::Error::BrokenDependency { .. }) => Err(e1),
             Err(e1) => match Self::for_web_component(
                 component_name,
                 definition_name_with_arguments,
@@ -787,6 +787,54 @@ impl PropertyValueExt for fastn_resolved::PropertyValue {
                         },
                     )
                 }
+                fastn_resolved::Kind::Map { kind } => {
+                    let line_number = value.line_number();
+                    let (_, caption, headers, body, values, _) = value.get_record(doc.name)?;
+                    if caption.is_some() || body.is_some() || !values.is_empty() {
+                        return ftd::interpreter::utils::e2(
+                            "Expected the entries of `map` as headers",
+                            doc.name,
+                            line_number,
+                        );
+                    }
+                    let mut data: ftd::Map<fastn_resolved::PropertyValue> = Default::default();
+                    for header in headers.0.iter() {
+                        if header.condition.is_some() {
+                            return ftd::interpreter::utils::e2(
+                                format!("Conditions are not allowed on map entry `{}`", header.key),
+                                doc.name,
+                                header.line_number,
+                            );
+                        }
+                        data.insert(
+                            header.key.to_string(),
+                            try_ok_state!(
+                                fastn_resolved::PropertyValue::from_ast_value_with_argument(
+                                    header.value.clone(),
+                                    doc,
+                                    is_mutable || header.mutable,
+                                    Some(&fastn_resolved::KindData {
+                                        kind: kind.as_ref().clone(),
+                                        caption: expected_kind.caption,
+                                        body: expected_kind.body,
+                                    }),
+                                    definition_name_with_arguments,
+                                    loop_object_name_and_kind,
+                                )?
+                            ),
+                        );
+                    }
+                    ftd::interpreter::StateWithThing::new_thing(
+                        fastn_resolved::PropertyValue::Value {
+                            value: fastn_resolved::Value::Map {
+                                data,
+                                kind: expected_kind.clone().inner_map(),
+                            },
+                            is_mutable,
+                            line_number,
+                        },
+                    )
+                }
                 fastn_resolved::Kind::Record { name } if value.is_record() || value.is_string() => {
                     let record = try_ok_state!(doc.search_record(name, value.line_number())?);
                     fastn_resolved::PropertyValue::from_record(
@@ -1074,11 +1122,23 @@ impl PropertyValueExt for fastn_resolved::PropertyValue {
                 );
                 continue;
             }
-            if field.kind.is_list() {
-                let mut variable = ftd_ast::VariableValue::List {
-                    value: vec![],
-                    line_number: value.line_number(),
-                    condition: None,
+            if field.kind.is_list() || field.kind.is_map() {
+                let mut variable = if field.kind.is_map() {
+                    ftd_ast::VariableValue::Record {
+                        name: field.name.to_string(),
+                        caption: Box::new(None),
+                        headers: ftd_ast::HeaderValues::new(vec![]),
+                        body: None,
+                        values: vec![],
+                        line_number: value.line_number(),
+                        condition: None,
+                    }
+                } else {
+                    ftd_ast::VariableValue::List {
+                        value: vec![],
+                        line_number: value.line_number(),
+                        condition: None,
+                    }
                 };
                 if let Some(header) = headers {
                     variable = header.value.clone();
@@ -1779,7 +1839,7 @@ impl ValueExt for fastn_resolved::Value {
                 let line_number = value.line_number();
                 value.resolve(doc, line_number)?.into_evalexpr_value(doc)
             }
-            fastn_resolved::Value::Record { .. } => {
+            fastn_resolved::Value::Record { .. } | fastn_resolved::Value::Map { .. } => {
                 if let Ok(Some(value)) = ftd::interpreter::utils::get_value(doc, &self) {
                     Ok(fastn_resolved::evalexpr::Value::String(value.to_string()))
                 } else {
@@ -1912,6 +1972,7 @@ impl ValueExt for fastn_resolved::Value {
             }
             fastn_resolved::Value::Record { fields, .. }
             | fastn_resolved::Value::Object { values: fields, .. }
+            | fastn_resolved::Value::Map { data: fields, .. }
             | fastn_resolved::Value::KwArgs {
                 arguments: fields, ..
             } => {
@@ -2025,7 +2086,8 @@ impl ValueExt for fastn_resolved::Value {
                 }
                 Ok(Some(serde_json::to_value(&new_values)?))
             }
-            fastn_resolved::Value::Record { fields, .. } => {
+            fastn_resolved::Value::Record { fields, .. }
+            | fastn_resolved::Value::Map { data: fields, .. } => {
                 let mut new_values: ftd::Map<serde_json::Value> = Default::default();
                 for (k, v) in fields {
                     let resolved_value = v.clone().resolve(doc, 0)?;
@@ -2093,6 +2155,7 @@ impl ValueExt for fastn_resolved::Value {
             fastn_resolved::Value::Object { .. }
             | fastn_resolved::Value::Record { .. }
             | fastn_resolved::Value::List { .. }
+            | fastn_resolved::Value::Map { .. }
             | fastn_resolved::Value::KwArgs { .. } => {
                 Ok(Some(serde_json::to_string(&self.to_serde_value(doc)?)?))
             }
@@ -382,12 +382,20 @@ pub fn get_argument_for_reference_and_remaining(
         if let Some(loop_counter_alias) = loop_counter_alias
             && name.starts_with(loop_counter_alias.as_str())
         {
+            // when looping over a `map` the counter alias holds the key
+            let counter_kind = if loop_argument
+                .value
+                .as_ref()
+                .is_some_and(|v| v.kind().is_map())
+            {
+                fastn_resolved::Kind::string()
+            } else {
+                fastn_resolved::Kind::integer()
+            };
             return Ok(Some((
                 fastn_resolved::Field::default(
                     loop_counter_alias,
-                    fastn_resolved::Kind::integer()
-                        .into_optional()
-                        .into_kind_data(),
+                    counter_kind.into_optional().into_kind_data(),
                 ),
                 None,
                 fastn_resolved::PropertyValueSource::Loop(loop_name.to_string()),
@@ -448,7 +456,8 @@ pub fn validate_record_value(
 
             if let fastn_resolved::PropertyValue::Value { value, .. } = value {
                 match value.ref_inner() {
-                    Some(fastn_resolved::Value::Record { fields, .. }) => {
+                    Some(fastn_resolved::Value::Record { fields, .. })
+                    | Some(fastn_resolved::Value::Map { data: fields, .. }) => {
                         validate_fields(fields.values().collect(), doc)?;
                     }
                     Some(fastn_resolved::Value::OrType { value, .. }) => {
@@ -545,7 +554,8 @@ pub(crate) fn get_value(
         Some(fastn_resolved::Value::Integer { value }) => serde_json::to_value(value).ok(),
         Some(fastn_resolved::Value::String { text: value, .. }) => serde_json::to_value(value).ok(),
         Some(fastn_resolved::Value::Decimal { value, .. }) => serde_json::to_value(value).ok(),
-        Some(fastn_resolved::Value::Record { fields, .. }) => {
+        Some(fastn_resolved::Value::Record { fields, .. })
+        | Some(fastn_resolved::Value::Map { data: fields, .. }) => {
             let mut value_fields = ftd::Map::new();
             for (k, v) in fields {
                 if let Some(value) = get_value(doc, &v.clone().resolve(doc, v.line_number())?)? {
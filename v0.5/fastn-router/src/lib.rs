@@ -52,6 +52,8 @@ pub enum Kind {
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct DynamicUrl {
     fragments: Vec<Fragment>,
+    /// the document that renders the urls matching fragments, e.g., `person.ftd`
+    document: String,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
@@ -71,8 +73,7 @@ pub enum Method {
     Post,
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 // the router will depend on fastn-section.
 pub enum Route {
     /// not found tells you which ftd document to serve as not found page
@@ -82,6 +83,9 @@ pub enum Route {
     Document(Document),
     Wasm {
         wasm_file: String,
+        /// the path to send to the wasm file: the part of the url after the mount url, prepended
+        /// with wasm_base
+        path: String,
         not_found: Document,
     },
     Redirect(String),
@@ -94,25 +98,56 @@ pub enum Route {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Document {
     // this is private yet
-    #[expect(unused)]
     pub(crate) path: String,
-    #[expect(unused)]
+    /// the data extracted from the url, e.g., the path parameters of a dynamic url
     pub(crate) partial: serde_json::Value,
-    #[expect(unused)]
+    /// the keys of partial, request data can not override them
     pub(crate) keys: Vec<String>,
 }
 
-#[derive(Debug)]
-pub enum RouterError {}
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum RouterError {
+    #[error("request data is not valid json: {message}")]
+    InvalidJson { message: String },
+    #[error("request data must be a json object")]
+    NotAnObject,
+}
 
 impl Document {
+    /// the path of the document, and the data to render it with: the request data (a json object,
+    /// or nothing) merged with the data extracted from the url
     pub fn with_data(
         self,
-        _data: &[u8],
+        data: &[u8],
     ) -> Result<(String, serde_json::Map<String, serde_json::Value>), RouterError> {
-        todo!()
+        let mut map = match self.partial {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+
+        if data.iter().all(u8::is_ascii_whitespace) {
+            return Ok((self.path, map));
+        }
+
+        let data = match serde_json::from_slice(data) {
+            Ok(serde_json::Value::Object(data)) => data,
+            Ok(_) => return Err(RouterError::NotAnObject),
+            Err(e) => {
+                return Err(RouterError::InvalidJson {
+                    message: e.to_string(),
+                });
+            }
+        };
+
+        for (k, v) in data {
+            if !self.keys.contains(&k) {
+                map.insert(k, v);
+            }
+        }
+
+        Ok((self.path, map))
     }
 }
@@ -1,11 +1,545 @@
 impl fastn_router::Router {
     // /foo.png
     // /-/ds.ft.com/foo.png
-    pub fn route(&self, _path: &str, _method: fastn_router::Method) -> fastn_router::Route {
-        fastn_router::Route::Document(fastn_router::Document {
-            path: "index.ftd".to_string(),
+    pub fn route(&self, path: &str, method: fastn_router::Method) -> fastn_router::Route {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let path = format!("/{}", path.trim_start_matches('/'));
+
+        if let Some(destination) = self.redirect(path.as_str()) {
+            return fastn_router::Route::Redirect(destination);
+        }
+
+        if let Some(route) = self.wasm(path.as_str()) {
+            return route;
+        }
+
+        let (package, file) = self.package_of(path.as_str());
+        if let Some(files) = self.file_list.get(package)
+            && let Some(route) = self.file(package, files, file, method)
+        {
+            return route;
+        }
+
+        if package == self.name.as_str()
+            && let Some(document) = self.dynamic(file)
+        {
+            return fastn_router::Route::Document(document);
+        }
+
+        fastn_router::Route::NotFound(self.not_found())
+    }
+
+    /// the destination of the first redirect matching `path`, `*` at the end of a source matches
+    /// the rest of the path, which replaces the `*` at the end of the destination
+    fn redirect(&self, path: &str) -> Option<String> {
+        for redirect in self.redirects.iter() {
+            if !redirect.wildcard {
+                if same_path(redirect.source.as_str(), path) {
+                    return Some(redirect.destination.clone());
+                }
+                continue;
+            }
+
+            let source = redirect.source.trim_end_matches('*');
+            let rest = match path.strip_prefix(source) {
+                Some(rest) => rest,
+                // `/blog/*` matches `/blog` too
+                None if same_path(source, path) => "",
+                None => continue,
+            };
+
+            return Some(match redirect.destination.strip_suffix('*') {
+                Some(destination) => format!("{destination}{rest}"),
+                None => redirect.destination.clone(),
+            });
+        }
+
+        None
+    }
+
+    fn wasm(&self, path: &str) -> Option<fastn_router::Route> {
+        for mount in self.wasm_mounts.iter() {
+            let url = mount.url.trim_end_matches('/');
+            let rest = match path.strip_prefix(url) {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+                _ => continue,
+            };
+
+            return Some(fastn_router::Route::Wasm {
+                wasm_file: mount.wasm_file.clone(),
+                path: format!(
+                    "{}/{}",
+                    mount.wasm_base.trim_end_matches('/'),
+                    rest.trim_start_matches('/')
+                ),
+                not_found: self.not_found(),
+            });
+        }
+
+        None
+    }
+
+    /// `/-/<package>/<file>` is a file of that package, any other path is a file of the current
+    /// package
+    fn package_of<'a>(&'a self, path: &'a str) -> (&'a str, &'a str) {
+        let path = path.trim_start_matches('/');
+        if let Some(rest) = path.strip_prefix("-/") {
+            // package names can have `/` in them, the longest one matching wins
+            let package = self
+                .file_list
+                .keys()
+                .filter(|package| {
+                    rest.strip_prefix(package.as_str())
+                        .is_some_and(|r| r.is_empty() || r.starts_with('/'))
+                })
+                .max_by_key(|package| package.len());
+            if let Some(package) = package {
+                return (
+                    package.as_str(),
+                    rest[package.len()..].trim_start_matches('/'),
+                );
+            }
+        }
+
+        (self.name.as_str(), path)
+    }
+
+    fn file(
+        &self,
+        package: &str,
+        files: &[String],
+        file: &str,
+        method: fastn_router::Method,
+    ) -> Option<fastn_router::Route> {
+        let exists = |f: &str| files.iter().any(|v| v == f);
+
+        let name = file.rsplit('/').next().unwrap_or_default();
+        if let Some((_, extension)) = name.rsplit_once('.') {
+            // ftd files are documents, they are served at their url, not as files
+            if extension == "ftd" || method != fastn_router::Method::Get || !exists(file) {
+                return None;
+            }
+
+            return Some(fastn_router::Route::Static {
+                package: package.to_string(),
+                path: file.to_string(),
+                mime: mime(extension).to_string(),
+                not_found: self.not_found(),
+            });
+        }
+
+        let file = file.trim_end_matches('/');
+        let candidates = if file.is_empty() {
+            vec!["index.ftd".to_string()]
+        } else {
+            vec![format!("{file}.ftd"), format!("{file}/index.ftd")]
+        };
+
+        candidates
+            .into_iter()
+            .find(|f| exists(f))
+            .map(|f| fastn_router::Route::Document(self.document(package, f.as_str())))
+    }
+
+    fn dynamic(&self, file: &str) -> Option<fastn_router::Document> {
+        let segments = file
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        'urls: for url in self.dynamic_urls.iter() {
+            if url.fragments.len() != segments.len() {
+                continue;
+            }
+
+            let mut partial = serde_json::Map::new();
+            let mut keys = vec![];
+            for (fragment, segment) in url.fragments.iter().zip(segments.iter()) {
+                match fragment {
+                    fastn_router::Fragment::Exact(v) if v == segment => {}
+                    fastn_router::Fragment::Exact(_) => continue 'urls,
+                    fastn_router::Fragment::Argument { kind, name } => {
+                        let Some(value) = kind.parse(segment) else {
+                            continue 'urls;
+                        };
+                        partial.insert(name.clone(), value);
+                        keys.push(name.clone());
+                    }
+                }
+            }
+
+            return Some(fastn_router::Document {
+                path: url.document.clone(),
+                partial: serde_json::Value::Object(partial),
+                keys,
+            });
+        }
+
+        None
+    }
+
+    /// documents of dependencies are read from where they are downloaded
+    fn document(&self, package: &str, file: &str) -> fastn_router::Document {
+        let path = if package == self.name {
+            file.to_string()
+        } else {
+            format!(".fastn/packages/{package}/{file}")
+        };
+
+        fastn_router::Document {
+            path,
+            partial: serde_json::Value::Null,
             keys: vec![],
+        }
+    }
+
+    fn not_found(&self) -> fastn_router::Document {
+        self.document(self.name.as_str(), "404.ftd")
+    }
+}
+
+impl fastn_router::Kind {
+    /// the value of a path parameter of this kind, `None` if the segment is not one
+    fn parse(&self, segment: &str) -> Option<serde_json::Value> {
+        match self {
+            fastn_router::Kind::String => Some(serde_json::Value::String(segment.to_string())),
+            fastn_router::Kind::Integer => segment.parse::<i64>().ok().map(Into::into),
+            fastn_router::Kind::Decimal => segment
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(serde_json::Value::Number),
+            fastn_router::Kind::Boolean => segment.parse::<bool>().ok().map(Into::into),
+        }
+    }
+}
+
+/// `/foo` and `/foo/` are the same path
+fn same_path(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+fn mime(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "xml" => "application/xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn router() -> fastn_router::Router {
+        fastn_router::Router {
+            name: "foo.com".to_string(),
+            file_list: std::collections::HashMap::from([
+                (
+                    "foo.com".to_string(),
+                    vec![
+                        "index.ftd".to_string(),
+                        "about.ftd".to_string(),
+                        "blog/index.ftd".to_string(),
+                        "blog/hello.ftd".to_string(),
+                        "logo.png".to_string(),
+                        "static/app.css".to_string(),
+                        "404.ftd".to_string(),
+                    ],
+                ),
+                (
+                    "ds.ft.com".to_string(),
+                    vec!["index.ftd".to_string(), "icon.svg".to_string()],
+                ),
+                (
+                    "github.com/foo/bar".to_string(),
+                    vec!["readme.ftd".to_string()],
+                ),
+            ]),
+            redirects: vec![
+                redirect("/old-about/", "/about/", false),
+                redirect("/old-blog/*", "/blog/*", true),
+                redirect("/archive/*", "/blog/", true),
+            ],
+            dynamic_urls: vec![
+                dynamic(
+                    vec![
+                        exact("person"),
+                        argument("name", fastn_router::Kind::String),
+                    ],
+                    "person.ftd",
+                ),
+                dynamic(
+                    vec![
+                        exact("post"),
+                        argument("id", fastn_router::Kind::Integer),
+                        argument("draft", fastn_router::Kind::Boolean),
+                    ],
+                    "post.ftd",
+                ),
+                dynamic(
+                    vec![
+                        exact("price"),
+                        argument("amount", fastn_router::Kind::Decimal),
+                    ],
+                    "price.ftd",
+                ),
+            ],
+            wasm_mounts: vec![fastn_router::WasmMount {
+                url: "/api/".to_string(),
+                wasm_file: "api.wasm".to_string(),
+                wasm_base: "/v1/".to_string(),
+            }],
+        }
+    }
+
+    fn redirect(source: &str, destination: &str, wildcard: bool) -> fastn_router::Redirect {
+        fastn_router::Redirect {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            wildcard,
+        }
+    }
+
+    fn dynamic(fragments: Vec<fastn_router::Fragment>, document: &str) -> fastn_router::DynamicUrl {
+        fastn_router::DynamicUrl {
+            fragments,
+            document: document.to_string(),
+        }
+    }
+
+    fn exact(v: &str) -> fastn_router::Fragment {
+        fastn_router::Fragment::Exact(v.to_string())
+    }
+
+    fn argument(name: &str, kind: fastn_router::Kind) -> fastn_router::Fragment {
+        fastn_router::Fragment::Argument {
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    fn document(path: &str) -> fastn_router::Route {
+        fastn_router::Route::Document(fastn_router::Document {
+            path: path.to_string(),
             partial: serde_json::Value::Null,
+            keys: vec![],
         })
     }
+
+    fn dynamic_document(
+        path: &str,
+        partial: serde_json::Value,
+        keys: &[&str],
+    ) -> fastn_router::Route {
+        fastn_router::Route::Document(fastn_router::Document {
+            path: path.to_string(),
+            partial,
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+        })
+    }
+
+    fn not_found_document() -> fastn_router::Document {
+        fastn_router::Document {
+            path: "404.ftd".to_string(),
+            partial: serde_json::Value::Null,
+            keys: vec![],
+        }
+    }
+
+    fn not_found() -> fastn_router::Route {
+        fastn_router::Route::NotFound(not_found_document())
+    }
+
+    fn file(package: &str, path: &str, mime: &str) -> fastn_router::Route {
+        fastn_router::Route::Static {
+            package: package.to_string(),
+            path: path.to_string(),
+            mime: mime.to_string(),
+            not_found: not_found_document(),
+        }
+    }
+
+    #[test]
+    fn route() {
+        use fastn_router::Method::{Get, Post};
+
+        let cases = vec![
+            // documents of the current package
+            ("/", Get, document("index.ftd")),
+            ("", Get, document("index.ftd")),
+            ("/about/", Get, document("about.ftd")),
+            ("/about", Get, document("about.ftd")),
+            ("/about/?utm=x", Get, document("about.ftd")),
+            ("/blog/", Get, document("blog/index.ftd")),
+            ("/blog/hello/", Post, document("blog/hello.ftd")),
+            ("/-/foo.com/about/", Get, document("about.ftd")),
+            ("/about.ftd", Get, not_found()),
+            ("/missing/", Get, not_found()),
+            // documents of dependencies
+            (
+                "/-/ds.ft.com/",
+                Get,
+                document(".fastn/packages/ds.ft.com/index.ftd"),
+            ),
+            (
+                "/-/github.com/foo/bar/readme/",
+                Get,
+                document(".fastn/packages/github.com/foo/bar/readme.ftd"),
+            ),
+            ("/-/ds.ft.com/missing/", Get, not_found()),
+            ("/-/unknown.com/", Get, not_found()),
+            // static files
+            ("/logo.png", Get, file("foo.com", "logo.png", "image/png")),
+            (
+                "/static/app.css",
+                Get,
+                file("foo.com", "static/app.css", "text/css"),
+            ),
+            (
+                "/-/ds.ft.com/icon.svg",
+                Get,
+                file("ds.ft.com", "icon.svg", "image/svg+xml"),
+            ),
+            ("/logo.png", Post, not_found()),
+            ("/missing.png", Get, not_found()),
+            // redirects
+            (
+                "/old-about/",
+                Get,
+                fastn_router::Route::Redirect("/about/".to_string()),
+            ),
+            (
+                "/old-about",
+                Post,
+                fastn_router::Route::Redirect("/about/".to_string()),
+            ),
+            (
+                "/old-blog/hello/",
+                Get,
+                fastn_router::Route::Redirect("/blog/hello/".to_string()),
+            ),
+            (
+                "/old-blog",
+                Get,
+                fastn_router::Route::Redirect("/blog/".to_string()),
+            ),
+            (
+                "/archive/2020/x/",
+                Get,
+                fastn_router::Route::Redirect("/blog/".to_string()),
+            ),
+            // dynamic urls
+            (
+                "/person/amitu/",
+                Get,
+                dynamic_document(
+                    "person.ftd",
+                    serde_json::json!({"name": "amitu"}),
+                    &["name"],
+                ),
+            ),
+            (
+                "/post/42/true/",
+                Get,
+                dynamic_document(
+                    "post.ftd",
+                    serde_json::json!({"id": 42, "draft": true}),
+                    &["id", "draft"],
+                ),
+            ),
+            (
+                "/price/9.5",
+                Get,
+                dynamic_document("price.ftd", serde_json::json!({"amount": 9.5}), &["amount"]),
+            ),
+            ("/post/abc/true/", Get, not_found()),
+            ("/post/42/maybe/", Get, not_found()),
+            ("/person/", Get, not_found()),
+            ("/person/amitu/extra/", Get, not_found()),
+            ("/-/ds.ft.com/person/amitu/", Get, not_found()),
+            // wasm mounts
+            (
+                "/api/users/1",
+                Post,
+                fastn_router::Route::Wasm {
+                    wasm_file: "api.wasm".to_string(),
+                    path: "/v1/users/1".to_string(),
+                    not_found: not_found_document(),
+                },
+            ),
+            (
+                "/api",
+                Get,
+                fastn_router::Route::Wasm {
+                    wasm_file: "api.wasm".to_string(),
+                    path: "/v1/".to_string(),
+                    not_found: not_found_document(),
+                },
+            ),
+            ("/apiary/", Get, not_found()),
+        ];
+
+        let router = router();
+        for (path, method, expected) in cases {
+            assert_eq!(router.route(path, method), expected, "{method:?} {path}");
+        }
+    }
+
+    #[test]
+    fn with_data() {
+        let fastn_router::Route::Document(doc) =
+            router().route("/person/amitu/", fastn_router::Method::Post)
+        else {
+            panic!("expected a document");
+        };
+
+        let (path, data) = doc.with_data(br#"{"name": "someone", "age": 40}"#).unwrap();
+        assert_eq!(path, "person.ftd");
+        // the path parameters win over the request data
+        assert_eq!(
+            serde_json::Value::Object(data),
+            serde_json::json!({"name": "amitu", "age": 40})
+        );
+
+        let fastn_router::Route::Document(doc) =
+            router().route("/about/", fastn_router::Method::Get)
+        else {
+            panic!("expected a document");
+        };
+        assert_eq!(
+            doc.with_data(b"").unwrap(),
+            ("about.ftd".to_string(), Default::default())
+        );
+
+        let doc = || fastn_router::Document {
+            path: "about.ftd".to_string(),
+            partial: serde_json::Value::Null,
+            keys: vec![],
+        };
+        assert_eq!(
+            doc().with_data(b"[1]"),
+            Err(fastn_router::RouterError::NotAnObject)
+        );
+        assert!(matches!(
+            doc().with_data(b"{"),
+            Err(fastn_router::RouterError::InvalidJson { .. })
+        ));
+    }
 }
@@ -1,14 +1,48 @@
+//! `crypto_encrypt` and `crypto_decrypt`, authenticated encryption (XChaCha20-Poly1305) with the
+//! secret keys of the server.
+//!
+//! Keys come from `FASTN_SECRET_KEYS`, a comma separated list with the newest key first, and
+//! `FASTN_SECRET_KEY`. We always encrypt with the newest key, and decrypt with whichever key the
+//! ciphertext was encrypted with, so keys can be rotated by adding a new key in front and
+//! dropping the old one once the data encrypted with it is gone.
+//!
+//! Each secret is stretched with HKDF-SHA256 into separate keys for encryption and for signing,
+//! and a key id, so no two uses ever share key material.
+//!
+//! Ciphertexts look like `v1.<base64url(key id || nonce || ciphertext and tag)>`. Anything else is
+//! treated as a token from the older `magic_crypt` based scheme, which we can still decrypt (but
+//! not create) so existing data keeps working. Re-encrypting such tokens migrates them.
+//!
+//! `crypto_decrypt` sends the guest a json `Result`, so a bad ciphertext is an error the guest can
+//! handle. `crypto_encrypt` sends the ciphertext itself, as guests expect, so it can only fail by
+//! aborting the request.
+
 use magic_crypt::MagicCryptTrait;
 
+const VERSION: &str = "v1.";
+const KEY_ID_LEN: usize = 4;
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum CryptoError {
+    #[error("no secret key configured, set FASTN_SECRET_KEYS or FASTN_SECRET_KEY")]
+    NoKey,
+    #[error("ciphertext was encrypted with a key we do not have anymore")]
+    UnknownKey,
+    #[error("invalid ciphertext")]
+    Invalid,
+    #[error("decryption failed, the ciphertext was tampered with or the key is wrong")]
+    Decrypt,
+}
+
 pub async fn encrypt<S: Send>(
     mut caller: wasmtime::Caller<'_, S>,
     ptr: i32,
     len: i32,
 ) -> wasmtime::Result<i32> {
     let input = fastn_wasm::helpers::get_str(ptr, len, &mut caller)?;
-    let secret_key = std::env::var("FASTN_SECRET_KEY").unwrap();
-    let mc_obj = magic_crypt::new_magic_crypt!(secret_key, 256);
-    let o = mc_obj.encrypt_to_base64(input.as_str()).as_str().to_owned();
+    // the guest expects the ciphertext back, so errors have to abort the request
+    let o = Keys::from_env().encrypt(input.as_str())?;
     fastn_wasm::helpers::send_bytes(&o.into_bytes(), &mut caller).await
 }
 
@@ -18,10 +52,298 @@ pub async fn decrypt<S: Send>(
     len: i32,
 ) -> wasmtime::Result<i32> {
     let input = fastn_wasm::helpers::get_str(ptr, len, &mut caller)?;
-    let secret_key = std::env::var("FASTN_SECRET_KEY").unwrap();
-    let mc_obj = magic_crypt::new_magic_crypt!(secret_key, 256);
-    let o = mc_obj
-        .decrypt_base64_to_string(input)
-        .map_err(|e| ft_sys_shared::DecryptionError::Generic(format!("{e:?}")));
+    let o = Keys::from_env()
+        .decrypt(input.as_str())
+        .map_err(|e| ft_sys_shared::DecryptionError::Generic(e.to_string()));
     fastn_wasm::helpers::send_json(o, &mut caller).await
 }
+
+#[derive(Clone)]
+struct Key {
+    /// The original secret, for decrypting `magic_crypt` tokens.
+    secret: String,
+    id: [u8; KEY_ID_LEN],
+    /// For `encrypt` and `decrypt`.
+    key: [u8; 32],
+    /// For `sign` and `verify`.
+    mac_key: [u8; 32],
+}
+
+impl Key {
+    fn new(secret: &str) -> Self {
+        let hkdf = hkdf::Hkdf::<sha2::Sha256>::new(None, secret.as_bytes());
+        let derive = |label: &[u8], out: &mut [u8]| {
+            hkdf.expand(label, out)
+                .expect("output is much shorter than the hkdf limit")
+        };
+
+        let mut id = [0; KEY_ID_LEN];
+        let mut key = [0; 32];
+        let mut mac_key = [0; 32];
+        derive(b"fastn key id v1", &mut id);
+        derive(b"fastn encryption v1", &mut key);
+        derive(b"fastn signing v1", &mut mac_key);
+
+        Self {
+            secret: secret.to_string(),
+            id,
+            key,
+            mac_key,
+        }
+    }
+
+    fn mac(&self, value: &str) -> hmac::Hmac<sha2::Sha256> {
+        use hmac::Mac;
+
+        let mut mac =
+            hmac::Hmac::<sha2::Sha256>::new_from_slice(&self.mac_key).expect("hmac takes any key");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    fn cipher(&self) -> chacha20poly1305::XChaCha20Poly1305 {
+        use chacha20poly1305::KeyInit;
+
+        chacha20poly1305::XChaCha20Poly1305::new(&self.key.into())
+    }
+}
+
+/// The secret keys, newest first.
+#[derive(Clone, Default)]
+pub struct Keys(Vec<Key>);
+
+impl Keys {
+    pub fn new<'a>(secrets: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut keys: Vec<Key> = vec![];
+        for secret in secrets.into_iter().map(str::trim) {
+            if !secret.is_empty() && !keys.iter().any(|k| k.secret == secret) {
+                keys.push(Key::new(secret));
+            }
+        }
+        Self(keys)
+    }
+
+    pub fn from_env() -> Self {
+        let keys = std::env::var("FASTN_SECRET_KEYS").unwrap_or_default();
+        let key = std::env::var("FASTN_SECRET_KEY").unwrap_or_default();
+        Self::new(keys.split(',').chain(std::iter::once(key.as_str())))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        use base64::Engine;
+        use chacha20poly1305::aead::Aead;
+
+        let key = self.0.first().ok_or(CryptoError::NoKey)?;
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = key
+            .cipher()
+            .encrypt(
+                &nonce.into(),
+                chacha20poly1305::aead::Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: &key.id,
+                },
+            )
+            .map_err(|_| CryptoError::Invalid)?;
+
+        let mut out = Vec::with_capacity(KEY_ID_LEN + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(&key.id);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+
+        Ok(format!(
+            "{VERSION}{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(out)
+        ))
+    }
+
+    pub fn decrypt(&self, ciphertext: &str) -> Result<String, CryptoError> {
+        use base64::Engine;
+        use chacha20poly1305::aead::Aead;
+
+        if self.0.is_empty() {
+            return Err(CryptoError::NoKey);
+        }
+
+        let Some(encoded) = ciphertext.strip_prefix(VERSION) else {
+            return self.decrypt_legacy(ciphertext);
+        };
+
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| CryptoError::Invalid)?;
+        if bytes.len() < KEY_ID_LEN + NONCE_LEN {
+            return Err(CryptoError::Invalid);
+        }
+
+        let (id, rest) = bytes.split_at(KEY_ID_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let key = self
+            .0
+            .iter()
+            .find(|k| k.id == id)
+            .ok_or(CryptoError::UnknownKey)?;
+
+        let plaintext = key
+            .cipher()
+            .decrypt(
+                chacha20poly1305::XNonce::from_slice(nonce),
+                chacha20poly1305::aead::Payload {
+                    msg: ciphertext,
+                    aad: &key.id,
+                },
+            )
+            .map_err(|_| CryptoError::Decrypt)?;
+
+        String::from_utf8(plaintext).map_err(|_| CryptoError::Invalid)
+    }
+
+    /// `<value>.<base64url(key id || hmac-sha256)>`, for values that need not be secret but must
+    /// not be tampered with, eg session cookies.
+    pub fn sign(&self, value: &str) -> Result<String, CryptoError> {
+        use base64::Engine;
+        use hmac::Mac;
+
+        let key = self.0.first().ok_or(CryptoError::NoKey)?;
+        let mut out = key.id.to_vec();
+        out.extend_from_slice(&key.mac(value).finalize().into_bytes());
+
+        Ok(format!(
+            "{value}.{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(out)
+        ))
+    }
+
+    /// Returns the value if it was signed by any of our keys.
+    pub fn verify(&self, signed: &str) -> Option<String> {
+        use base64::Engine;
+        use hmac::Mac;
+
+        let (value, signature) = signed.rsplit_once('.')?;
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .ok()?;
+        if signature.len() < KEY_ID_LEN {
+            return None;
+        }
+
+        let (id, tag) = signature.split_at(KEY_ID_LEN);
+        let key = self.0.iter().find(|k| k.id == id)?;
+        key.mac(value).verify_slice(tag).ok()?;
+        Some(value.to_string())
+    }
+
+    /// `magic_crypt` tokens carry no key id or tag, so we try every key, a wrong key almost
+    /// always fails on the padding or the utf-8 check.
+    fn decrypt_legacy(&self, ciphertext: &str) -> Result<String, CryptoError> {
+        for key in self.0.iter() {
+            let mc = magic_crypt::new_magic_crypt!(key.secret.as_str(), 256);
+            if let Ok(plaintext) = mc.decrypt_base64_to_string(ciphertext) {
+                tracing::info!("decrypted a magic_crypt token, re-encrypt it to migrate it");
+                return Ok(plaintext);
+            }
+        }
+
+        Err(CryptoError::Decrypt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn round_trip() {
+        let keys = super::Keys::new(["new"]);
+        let ciphertext = keys.encrypt("hello").unwrap();
+
+        assert!(ciphertext.starts_with(super::VERSION));
+        assert_ne!(
+            ciphertext,
+            keys.encrypt("hello").unwrap(),
+            "nonce is random"
+        );
+        assert_eq!(keys.decrypt(ciphertext.as_str()).unwrap(), "hello");
+    }
+
+    #[test]
+    fn rotation() {
+        let old = super::Keys::new(["old"]);
+        let ciphertext = old.encrypt("hello").unwrap();
+
+        let rotated = super::Keys::new(["new", "old"]);
+        assert_eq!(rotated.decrypt(ciphertext.as_str()).unwrap(), "hello");
+
+        // new data uses the new key, the old key can not read it
+        let ciphertext = rotated.encrypt("hello").unwrap();
+        assert_eq!(
+            old.decrypt(ciphertext.as_str()),
+            Err(super::CryptoError::UnknownKey)
+        );
+        assert_eq!(
+            super::Keys::new(["new"])
+                .decrypt(ciphertext.as_str())
+                .unwrap(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn tampered() {
+        use base64::Engine;
+
+        let keys = super::Keys::new(["key"]);
+        let ciphertext = keys.encrypt("hello").unwrap();
+
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let mut bytes = engine.decode(&ciphertext[super::VERSION.len()..]).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let tampered = format!("{}{}", super::VERSION, engine.encode(bytes));
+
+        assert_eq!(keys.decrypt(&tampered), Err(super::CryptoError::Decrypt));
+        assert_eq!(keys.decrypt("v1.AAAA"), Err(super::CryptoError::Invalid));
+    }
+
+    #[test]
+    fn no_key() {
+        let keys = super::Keys::new(["", " "]);
+        assert_eq!(keys.encrypt("hello"), Err(super::CryptoError::NoKey));
+        assert_eq!(keys.decrypt("anything"), Err(super::CryptoError::NoKey));
+    }
+
+    #[test]
+    fn derived_keys() {
+        let key = super::Key::new("secret");
+        assert_ne!(key.key, key.mac_key);
+        assert_ne!(key.key[..super::KEY_ID_LEN], key.id);
+
+        let other = super::Key::new("other secret");
+        assert_ne!(key.id, other.id);
+        assert_ne!(key.key, other.key);
+    }
+
+    #[test]
+    fn legacy() {
+        use magic_crypt::MagicCryptTrait;
+
+        let token = magic_crypt::new_magic_crypt!("old", 256).encrypt_str_to_base64("hello");
+
+        let keys = super::Keys::new(["new", "old"]);
+        assert_eq!(keys.decrypt(token.as_str()).unwrap(), "hello");
+        assert_eq!(
+            super::Keys::new(["new"]).decrypt(token.as_str()),
+            Err(super::CryptoError::Decrypt)
+        );
+    }
+
+    #[test]
+    fn sign() {
+        let keys = super::Keys::new(["new", "old"]);
+        let signed = super::Keys::new(["old"]).sign("abc.def").unwrap();
+
+        assert!(signed.starts_with("abc.def."));
+        assert_eq!(keys.verify(signed.as_str()).unwrap(), "abc.def");
+        assert_eq!(super::Keys::new(["new"]).verify(signed.as_str()), None);
+        assert_eq!(keys.verify(signed.replace("abc", "abd").as_str()), None);
+        assert_eq!(keys.verify("abc"), None);
+        assert_eq!(keys.verify("abc."), None);
+    }
+}
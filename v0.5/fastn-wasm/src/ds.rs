@@ -1,15 +1,58 @@
-pub async fn tejar_write<S: Send>(
-    _caller: wasmtime::Caller<'_, S>,
-    _ptr: i32,
-    _len: i32,
+/// Input is a [frame](fastn_wasm::tejar::frame): a json [WriteRequest](fastn_wasm::tejar::WriteRequest)
+/// header followed by a chunk of the content. Returns
+/// `Result<WriteResponse, TejarError>` as json.
+pub async fn tejar_write<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
 ) -> wasmtime::Result<i32> {
-    unimplemented!()
+    let frame = fastn_wasm::helpers::get_bytes(ptr, len, &mut caller)?;
+    let conn = caller.data().default_connection()?;
+
+    let response = match fastn_wasm::tejar::split_frame(&frame).and_then(|(header, chunk)| {
+        Ok((
+            serde_json::from_slice::<fastn_wasm::tejar::WriteRequest>(header).map_err(|e| {
+                fastn_wasm::tejar::TejarError::InvalidRequest {
+                    message: e.to_string(),
+                }
+            })?,
+            chunk,
+        ))
+    }) {
+        Ok((request, chunk)) => {
+            let tejar = caller.data().tejar.clone();
+            tejar.write(&conn, request, chunk).await
+        }
+        Err(e) => Err(e),
+    };
+
+    if let Err(ref e) = response {
+        tracing::info!("tejar write failed: {e}");
+    }
+
+    fastn_wasm::helpers::send_json(response, &mut caller).await
 }
 
-pub async fn tejar_read<S: Send>(
-    _caller: wasmtime::Caller<'_, S>,
-    _ptr: i32,
-    _len: i32,
+/// Input is a json [ReadRequest](fastn_wasm::tejar::ReadRequest). Returns a frame: a json
+/// `Result<Blob, TejarError>` header followed by the requested part of the content.
+pub async fn tejar_read<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
 ) -> wasmtime::Result<i32> {
-    unimplemented!()
+    let request: fastn_wasm::tejar::ReadRequest =
+        fastn_wasm::helpers::get_json(ptr, len, &mut caller)?;
+    let conn = caller.data().default_connection()?;
+
+    let tejar = caller.data().tejar.clone();
+
+    let (header, content) = match tejar.read(&conn, request).await {
+        Ok((blob, content)) => (serde_json::to_vec(&Ok::<_, ()>(blob))?, content),
+        Err(e) => {
+            tracing::info!("tejar read failed: {e}");
+            (serde_json::to_vec(&Err::<(), _>(e))?, vec![])
+        }
+    };
+
+    fastn_wasm::helpers::send_bytes(&fastn_wasm::tejar::frame(&header, &content), &mut caller).await
 }
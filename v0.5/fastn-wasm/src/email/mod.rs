@@ -0,0 +1,58 @@
+pub mod queue;
+mod transport;
+pub mod worker;
+
+pub use transport::{EmlDir, Smtp, Tls, Transport, TransportError, from_env};
+
+/// Seconds an email waits in the queue before it is sent, the window in which `email_cancel`
+/// works. Can be changed using the `FASTN_EMAIL_DELAY` environment variable.
+const DEFAULT_DELAY: i64 = 10;
+
+pub async fn send<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<i32> {
+    let e: ft_sys_shared::Email = fastn_wasm::helpers::get_json(ptr, len, &mut caller)?;
+    tracing::info!("queueing email: {:?}: {}", e.to, e.mkind);
+
+    let handle = format!("{:032x}", rand::random::<u128>());
+    let send_after = queue::now() + delay();
+
+    let conn = caller.data().default_connection()?;
+    queue::enqueue(
+        &conn,
+        handle.as_str(),
+        e.mkind.as_str(),
+        serde_json::to_string(&e)?.as_str(),
+        send_after,
+    )?;
+
+    let response = ft_sys_shared::EmailHandle::new(handle);
+    fastn_wasm::helpers::send_json(response, &mut caller).await
+}
+
+pub async fn cancel<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<()> {
+    let e: ft_sys_shared::EmailHandle = fastn_wasm::helpers::get_json(ptr, len, &mut caller)?;
+    let conn = caller.data().default_connection()?;
+
+    if queue::cancel(&conn, e.inner())? {
+        tracing::info!("cancelled email: {}", e.inner());
+    } else {
+        // already sent, or being sent right now
+        tracing::info!("too late to cancel email: {}", e.inner());
+    }
+
+    Ok(())
+}
+
+fn delay() -> i64 {
+    std::env::var("FASTN_EMAIL_DELAY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_DELAY)
+}
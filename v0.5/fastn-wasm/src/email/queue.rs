@@ -0,0 +1,233 @@
+//! The outgoing email queue, a table in the app's sqlite database.
+//!
+//! `email_send` only inserts a row here, the [worker](fastn_wasm::email::worker) sends it once
+//! `send_after` has passed. Till then `email_cancel` can still stop it.
+
+/// How many times we try to send an email before giving up on it.
+pub const MAX_ATTEMPTS: i64 = 5;
+
+const CREATE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS fastn_email_queue (
+    id          INTEGER PRIMARY KEY,
+    handle      TEXT NOT NULL UNIQUE,
+    mkind       TEXT NOT NULL,
+    -- ft_sys_shared::Email as json
+    email       TEXT NOT NULL,
+    -- pending, sending, sent, failed or cancelled
+    status      TEXT NOT NULL DEFAULT 'pending',
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    last_error  TEXT,
+    -- unix timestamps, in seconds
+    send_after  INTEGER NOT NULL,
+    created_at  INTEGER NOT NULL,
+    sent_at     INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS fastn_email_queue_due ON fastn_email_queue (status, send_after);
+"#;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Queued {
+    pub id: i64,
+    pub handle: String,
+    pub mkind: String,
+    pub email: String,
+    pub attempts: i64,
+}
+
+pub fn create_table(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(CREATE_TABLE)
+}
+
+pub fn enqueue(
+    conn: &rusqlite::Connection,
+    handle: &str,
+    mkind: &str,
+    email: &str,
+    send_after: i64,
+) -> rusqlite::Result<()> {
+    create_table(conn)?;
+    conn.execute(
+        "INSERT INTO fastn_email_queue (handle, mkind, email, send_after, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![handle, mkind, email, send_after, now()],
+    )?;
+    Ok(())
+}
+
+/// Returns `false` if there is no such email, or it is too late to cancel it.
+pub fn cancel(conn: &rusqlite::Connection, handle: &str) -> rusqlite::Result<bool> {
+    create_table(conn)?;
+    let updated = conn.execute(
+        "UPDATE fastn_email_queue SET status = 'cancelled'
+         WHERE handle = ?1 AND status = 'pending'",
+        rusqlite::params![handle],
+    )?;
+    Ok(updated == 1)
+}
+
+/// Pending emails whose `send_after` has passed, oldest first.
+pub fn due(conn: &rusqlite::Connection, now: i64, limit: i64) -> rusqlite::Result<Vec<Queued>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, handle, mkind, email, attempts FROM fastn_email_queue
+         WHERE status = 'pending' AND send_after <= ?1
+         ORDER BY send_after, id
+         LIMIT ?2",
+    )?;
+
+    stmt.query_map(rusqlite::params![now, limit], |row| {
+        Ok(Queued {
+            id: row.get(0)?,
+            handle: row.get(1)?,
+            mkind: row.get(2)?,
+            email: row.get(3)?,
+            attempts: row.get(4)?,
+        })
+    })?
+    .collect()
+}
+
+/// Marks the email as being sent, so a concurrent `email_cancel` can not cancel it anymore.
+/// Returns `false` if it got cancelled since we read it.
+pub fn claim(conn: &rusqlite::Connection, id: i64) -> rusqlite::Result<bool> {
+    let updated = conn.execute(
+        "UPDATE fastn_email_queue SET status = 'sending' WHERE id = ?1 AND status = 'pending'",
+        rusqlite::params![id],
+    )?;
+    Ok(updated == 1)
+}
+
+pub fn mark_sent(conn: &rusqlite::Connection, id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE fastn_email_queue
+         SET status = 'sent', sent_at = ?2, attempts = attempts + 1, last_error = NULL
+         WHERE id = ?1",
+        rusqlite::params![id, now()],
+    )?;
+    Ok(())
+}
+
+/// Schedules another attempt with exponential backoff, or gives up after [MAX_ATTEMPTS].
+/// `permanent` errors (eg an invalid address) are not retried.
+pub fn mark_failed(
+    conn: &rusqlite::Connection,
+    email: &Queued,
+    error: &str,
+    permanent: bool,
+) -> rusqlite::Result<()> {
+    let attempts = email.attempts + 1;
+    let (status, send_after) = if permanent || attempts >= MAX_ATTEMPTS {
+        ("failed", now())
+    } else {
+        ("pending", now() + 60 * 2_i64.pow(attempts as u32))
+    };
+
+    conn.execute(
+        "UPDATE fastn_email_queue
+         SET status = ?2, attempts = ?3, last_error = ?4, send_after = ?5
+         WHERE id = ?1",
+        rusqlite::params![email.id, status, attempts, error, send_after],
+    )?;
+    Ok(())
+}
+
+/// Emails left in `sending` by a server that died mid-send. We send them again, the alternative
+/// is not sending them at all.
+pub fn reset_sending(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    create_table(conn)?;
+    conn.execute(
+        "UPDATE fastn_email_queue SET status = 'pending' WHERE status = 'sending'",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    fn conn() -> rusqlite::Connection {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        super::create_table(&conn).unwrap();
+        conn
+    }
+
+    fn status(conn: &rusqlite::Connection, handle: &str) -> String {
+        conn.query_row(
+            "SELECT status FROM fastn_email_queue WHERE handle = ?1",
+            [handle],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cancel() {
+        let conn = conn();
+        super::enqueue(&conn, "a", "welcome", "{}", 100).unwrap();
+        super::enqueue(&conn, "b", "welcome", "{}", 100).unwrap();
+
+        assert!(super::cancel(&conn, "a").unwrap());
+        assert!(!super::cancel(&conn, "a").unwrap());
+        assert!(!super::cancel(&conn, "unknown").unwrap());
+
+        let due = super::due(&conn, 100, 10).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].handle, "b");
+
+        // once the worker has picked it up, it is too late
+        assert!(super::claim(&conn, due[0].id).unwrap());
+        assert!(!super::cancel(&conn, "b").unwrap());
+        assert_eq!(status(&conn, "a"), "cancelled");
+        assert_eq!(status(&conn, "b"), "sending");
+    }
+
+    #[test]
+    fn due() {
+        let conn = conn();
+        super::enqueue(&conn, "later", "welcome", "{}", 200).unwrap();
+        super::enqueue(&conn, "now", "welcome", "{}", 100).unwrap();
+
+        let due = super::due(&conn, 150, 10).unwrap();
+        assert_eq!(
+            due.iter().map(|e| e.handle.as_str()).collect::<Vec<_>>(),
+            vec!["now"]
+        );
+        assert_eq!(super::due(&conn, 200, 10).unwrap().len(), 2);
+        assert_eq!(super::due(&conn, 200, 1).unwrap()[0].handle, "now");
+    }
+
+    #[test]
+    fn retry() {
+        let conn = conn();
+        super::enqueue(&conn, "a", "welcome", "{}", 0).unwrap();
+
+        let email = super::due(&conn, 0, 1).unwrap().remove(0);
+        assert!(super::claim(&conn, email.id).unwrap());
+        super::mark_failed(&conn, &email, "connection refused", false).unwrap();
+        assert_eq!(status(&conn, "a"), "pending");
+        // backed off, not due right away
+        assert!(super::due(&conn, super::now(), 1).unwrap().is_empty());
+
+        let email = super::due(&conn, i64::MAX, 1).unwrap().remove(0);
+        assert_eq!(email.attempts, 1);
+        super::mark_failed(&conn, &email, "invalid address", true).unwrap();
+        assert_eq!(status(&conn, "a"), "failed");
+    }
+
+    #[test]
+    fn reset_sending() {
+        let conn = conn();
+        super::enqueue(&conn, "a", "welcome", "{}", 0).unwrap();
+        let email = super::due(&conn, 0, 1).unwrap().remove(0);
+        assert!(super::claim(&conn, email.id).unwrap());
+
+        super::reset_sending(&conn).unwrap();
+        assert_eq!(status(&conn, "a"), "pending");
+
+        super::mark_sent(&conn, email.id).unwrap();
+        assert_eq!(status(&conn, "a"), "sent");
+    }
+}
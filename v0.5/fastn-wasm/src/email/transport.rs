@@ -0,0 +1,247 @@
+/// Where the [worker](fastn_wasm::email::worker) hands the emails over to.
+#[async_trait::async_trait]
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// `handle` is unique per email, transports can use it to name things.
+    async fn send(&self, handle: &str, message: &lettre::Message) -> Result<(), TransportError>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("smtp error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid email configuration: {0}")]
+    Config(String),
+}
+
+impl TransportError {
+    /// Errors that will not go away if we try again later, eg the server rejecting a recipient.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            TransportError::Smtp(e) => e.is_permanent(),
+            TransportError::Io(_) | TransportError::Config(_) => false,
+        }
+    }
+}
+
+/// Picks the transport based on environment variables:
+///
+/// - `FASTN_EMAIL_TRANSPORT`: `smtp` or `eml`. Defaults to `smtp` if `FASTN_SMTP_HOST` is set,
+///   else `eml`.
+/// - `FASTN_EMAIL_DIR`: folder for the `eml` transport, defaults to `.fastn/emails`. A relative
+///   folder is in the package `root`, not the current directory.
+/// - `FASTN_SMTP_HOST`, `FASTN_SMTP_PORT`, `FASTN_SMTP_USERNAME`, `FASTN_SMTP_PASSWORD`.
+/// - `FASTN_SMTP_TLS`: `starttls` (default), `tls` or `none`.
+pub fn from_env(root: &std::path::Path) -> Result<std::sync::Arc<dyn Transport>, TransportError> {
+    let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+    let kind = env("FASTN_EMAIL_TRANSPORT").unwrap_or_else(|| {
+        if env("FASTN_SMTP_HOST").is_some() {
+            "smtp".to_string()
+        } else {
+            "eml".to_string()
+        }
+    });
+
+    match kind.as_str() {
+        // joining an absolute path replaces the root
+        "eml" => Ok(std::sync::Arc::new(EmlDir::new(root.join(
+            env("FASTN_EMAIL_DIR").unwrap_or_else(|| ".fastn/emails".to_string()),
+        )))),
+        "smtp" => {
+            let host = env("FASTN_SMTP_HOST")
+                .ok_or_else(|| TransportError::Config("FASTN_SMTP_HOST is not set".to_string()))?;
+            let port = env("FASTN_SMTP_PORT")
+                .map(|p| {
+                    p.parse().map_err(|_| {
+                        TransportError::Config(format!("FASTN_SMTP_PORT is not a number: {p}"))
+                    })
+                })
+                .transpose()?;
+            let tls = match env("FASTN_SMTP_TLS").as_deref() {
+                None | Some("starttls") => Tls::StartTls,
+                Some("tls") => Tls::Tls,
+                Some("none") => Tls::None,
+                Some(v) => {
+                    return Err(TransportError::Config(format!(
+                        "FASTN_SMTP_TLS must be one of starttls, tls or none, found: {v}"
+                    )));
+                }
+            };
+            let credentials = env("FASTN_SMTP_USERNAME")
+                .map(|username| (username, env("FASTN_SMTP_PASSWORD").unwrap_or_default()));
+
+            Ok(std::sync::Arc::new(Smtp::new(
+                &host,
+                port,
+                tls,
+                credentials,
+            )?))
+        }
+        v => Err(TransportError::Config(format!(
+            "FASTN_EMAIL_TRANSPORT must be smtp or eml, found: {v}"
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tls {
+    None,
+    StartTls,
+    Tls,
+}
+
+#[derive(Debug)]
+pub struct Smtp {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+}
+
+impl Smtp {
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        tls: Tls,
+        credentials: Option<(String, String)>,
+    ) -> Result<Self, TransportError> {
+        type T = lettre::AsyncSmtpTransport<lettre::Tokio1Executor>;
+
+        let mut builder = match tls {
+            Tls::None => T::builder_dangerous(host),
+            Tls::StartTls => T::starttls_relay(host)?,
+            Tls::Tls => T::relay(host)?,
+        };
+
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(
+                lettre::transport::smtp::authentication::Credentials::new(username, password),
+            );
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for Smtp {
+    async fn send(&self, _handle: &str, message: &lettre::Message) -> Result<(), TransportError> {
+        lettre::AsyncTransport::send(&self.transport, message.clone()).await?;
+        Ok(())
+    }
+}
+
+/// Writes every email to `<dir>/<handle>.eml` instead of sending it, for development. Most mail
+/// clients can open these files.
+#[derive(Debug)]
+pub struct EmlDir {
+    dir: std::path::PathBuf,
+}
+
+impl EmlDir {
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for EmlDir {
+    async fn send(&self, handle: &str, message: &lettre::Message) -> Result<(), TransportError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{handle}.eml"));
+        tokio::fs::write(&path, message.formatted()).await?;
+        tracing::info!("email written to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn message() -> lettre::Message {
+        lettre::Message::builder()
+            .from("Fastn <noreply@example.com>".parse().unwrap())
+            .to("alice@example.com".parse().unwrap())
+            .subject("Welcome")
+            .body("hello alice".to_string())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn eml_dir() {
+        let dir = std::env::temp_dir().join(format!("fastn-eml-{}", rand::random::<u64>()));
+        let transport = super::EmlDir::new(&dir);
+
+        super::Transport::send(&transport, "abc", &message())
+            .await
+            .unwrap();
+
+        let eml = std::fs::read_to_string(dir.join("abc.eml")).unwrap();
+        assert!(eml.contains("To: alice@example.com"));
+        assert!(eml.contains("Subject: Welcome"));
+        assert!(eml.contains("hello alice"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Just enough of an SMTP server to accept one email, returns what the client sent.
+    async fn smtp_server(listener: tokio::net::TcpListener) -> String {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        let mut transcript = String::new();
+        let mut in_data = false;
+
+        writer.write_all(b"220 localhost ready\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            transcript.push_str(&line);
+            transcript.push('\n');
+
+            let reply: &[u8] = if in_data {
+                if line != "." {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if line.starts_with("EHLO") {
+                b"250-localhost\r\n250 8BITMIME\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                writer.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+
+        transcript
+    }
+
+    #[tokio::test]
+    async fn smtp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(smtp_server(listener));
+
+        let transport = super::Smtp::new("127.0.0.1", Some(port), super::Tls::None, None).unwrap();
+        super::Transport::send(&transport, "abc", &message())
+            .await
+            .unwrap();
+        drop(transport);
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("MAIL FROM:<noreply@example.com>"));
+        assert!(transcript.contains("RCPT TO:<alice@example.com>"));
+        assert!(transcript.contains("Subject: Welcome"));
+        assert!(transcript.contains("hello alice"));
+    }
+}
@@ -0,0 +1,118 @@
+/// How often we look for emails that are due.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How many emails we send per poll, so a big backlog does not hold the db for too long.
+const BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+    #[error("invalid email json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("can not build email: {0}")]
+    Build(#[from] lettre::error::Error),
+    #[error("email templates are not supported yet, send rendered content for {0}")]
+    TemplateNotSupported(String),
+}
+
+/// Sends the queued emails from the sqlite db at `db_path` using `transport`. Runs forever,
+/// `fastn serve` spawns it next to the http server.
+pub async fn run(db_path: String, transport: std::sync::Arc<dyn fastn_wasm::email::Transport>) {
+    tracing::info!("sending queued emails from {db_path} using {transport:?}");
+
+    match rusqlite::Connection::open(&db_path)
+        .and_then(|conn| fastn_wasm::email::queue::reset_sending(&conn))
+    {
+        Ok(()) => {}
+        Err(e) => tracing::error!("failed to prepare email queue in {db_path}: {e}"),
+    }
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        if let Err(e) = send_due(db_path.as_str(), transport.as_ref()).await {
+            tracing::error!("failed to send queued emails: {e}");
+        }
+    }
+}
+
+/// Sends every email that is due right now.
+pub async fn send_due(
+    db_path: &str,
+    transport: &dyn fastn_wasm::email::Transport,
+) -> rusqlite::Result<()> {
+    use fastn_wasm::email::queue;
+
+    let conn = rusqlite::Connection::open(db_path)?;
+    queue::create_table(&conn)?;
+
+    for email in queue::due(&conn, queue::now(), BATCH_SIZE)? {
+        if !queue::claim(&conn, email.id)? {
+            // cancelled since we read it
+            continue;
+        }
+
+        let message = match message(&email.email) {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::error!(handle = email.handle, "can not send email: {e}");
+                queue::mark_failed(&conn, &email, e.to_string().as_str(), true)?;
+                continue;
+            }
+        };
+
+        match transport.send(email.handle.as_str(), &message).await {
+            Ok(()) => {
+                tracing::info!(handle = email.handle, mkind = email.mkind, "email sent");
+                queue::mark_sent(&conn, email.id)?;
+            }
+            Err(e) => {
+                tracing::error!(handle = email.handle, "failed to send email: {e}");
+                queue::mark_failed(&conn, &email, e.to_string().as_str(), e.is_permanent())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the message from the `ft_sys_shared::Email` json stored in the queue.
+fn message(email: &str) -> Result<lettre::Message, MessageError> {
+    let email: ft_sys_shared::Email = serde_json::from_str(email)?;
+
+    let mut builder = lettre::Message::builder().from(mailbox(&email.from)?);
+    for to in email.to.iter() {
+        builder = builder.to(mailbox(to)?);
+    }
+    for cc in email.cc.iter() {
+        builder = builder.cc(mailbox(cc)?);
+    }
+    for bcc in email.bcc.iter() {
+        builder = builder.bcc(mailbox(bcc)?);
+    }
+    for reply_to in email.reply_to.iter().flatten() {
+        builder = builder.reply_to(mailbox(reply_to)?);
+    }
+
+    match email.content {
+        ft_sys_shared::EmailContent::Rendered {
+            subject,
+            body_html,
+            body_text,
+        } => Ok(builder.subject(subject).multipart(
+            lettre::message::MultiPart::alternative_plain_html(body_text, body_html),
+        )?),
+        ft_sys_shared::EmailContent::FromMKind { .. } => {
+            Err(MessageError::TemplateNotSupported(email.mkind))
+        }
+    }
+}
+
+fn mailbox(
+    address: &ft_sys_shared::EmailAddress,
+) -> Result<lettre::message::Mailbox, lettre::address::AddressError> {
+    Ok(lettre::message::Mailbox::new(
+        address.name.clone(),
+        address.email.parse()?,
+    ))
+}
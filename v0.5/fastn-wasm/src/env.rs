@@ -11,13 +11,28 @@ pub async fn print<T: Send>(
     Ok(())
 }
 
+/// Secrets the host uses on behalf of the guest, eg to encrypt, to sign sessions and aws requests,
+/// or to send emails. The guest can not read them using `env_var`.
+pub const SECRET_ENV_VARS: &[&str] = &[
+    "FASTN_SECRET_KEY",
+    "FASTN_SECRET_KEYS",
+    "FASTN_SMTP_PASSWORD",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+];
+
 pub async fn var<S: Send>(
     mut caller: wasmtime::Caller<'_, S>,
     ptr: i32,
     len: i32,
 ) -> wasmtime::Result<i32> {
     let key = fastn_wasm::helpers::get_str(ptr, len, &mut caller)?;
-    let value = std::env::var(key).ok();
+    let value = if SECRET_ENV_VARS.contains(&key.as_str()) {
+        None
+    } else {
+        std::env::var(key).ok()
+    };
 
     fastn_wasm::helpers::send_json(value, &mut caller).await
 }
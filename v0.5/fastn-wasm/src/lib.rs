@@ -5,21 +5,25 @@
 extern crate self as fastn_wasm;
 
 pub(crate) mod aws;
-pub(crate) mod crypto;
+pub mod crypto;
 pub(crate) mod ds;
-mod email;
+pub mod email;
 pub(crate) mod env;
 pub(crate) mod helpers;
 pub(crate) mod http;
+pub(crate) mod limits;
 pub(crate) mod macros;
 #[cfg(feature = "postgres")]
 pub mod pg;
 mod process_http_request;
 pub(crate) mod register;
+pub mod session;
 mod sqlite;
 mod store;
+pub(crate) mod tejar;
 
-pub use process_http_request::{WasmError, handle, process_http_request};
+pub use limits::{LimitExceeded, Limiter, Limits};
+pub use process_http_request::{WasmError, process_http_request};
 #[cfg(feature = "postgres")]
 pub(crate) use store::Conn;
 pub use store::{ConnectionExt, SQLError, Store, StoreExt, StoreImpl};
@@ -28,11 +32,38 @@ pub use store::{
     FASTN_WASM_PACKAGE_HEADER,
 };
 
+/// The engine wasm handlers run on. It meters fuel and interrupts on epochs, so a store made
+/// from it needs both set, which [`process_http_request`] does, and the epoch needs to be ticking,
+/// see [`start_epoch_ticker`].
 pub static WASM_ENGINE: once_cell::sync::Lazy<wasmtime::Engine> =
     once_cell::sync::Lazy::new(|| {
-        wasmtime::Engine::new(wasmtime::Config::new().async_support(true)).unwrap()
+        wasmtime::Engine::new(
+            wasmtime::Config::new()
+                .async_support(true)
+                .consume_fuel(true)
+                .epoch_interruption(true),
+        )
+        .unwrap()
     });
 
+/// Starts the thread incrementing the epoch of [`WASM_ENGINE`], so running handlers yield to
+/// tokio and can be timed out. Called when the server starts, calling it again does nothing.
+pub fn start_epoch_ticker() {
+    static STARTED: std::sync::Once = std::sync::Once::new();
+    STARTED.call_once(|| {
+        let engine = WASM_ENGINE.clone();
+        std::thread::Builder::new()
+            .name("wasm-epoch-ticker".to_string())
+            .spawn(move || {
+                loop {
+                    engine.increment_epoch();
+                    std::thread::sleep(limits::EPOCH_TICK);
+                }
+            })
+            .expect("failed to spawn the wasm epoch ticker");
+    });
+}
+
 pub fn insert_or_update<K, V>(map: &scc::HashMap<K, V>, key: K, value: V)
 where
     K: std::hash::Hash,
@@ -0,0 +1,139 @@
+/// How often the engine epoch is incremented. Every tick a running handler yields back to tokio,
+/// so a handler stuck in a loop can not starve the other requests, and can be timed out.
+pub(crate) const EPOCH_TICK: std::time::Duration = std::time::Duration::from_millis(10);
+
+pub const DEFAULT_MEMORY: usize = 512 * 1024 * 1024;
+pub const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Resources a single request to a wasm handler is allowed to use.
+///
+/// The defaults only guard against runaway handlers taking the whole server down, they can be
+/// tightened (or relaxed) per endpoint or app in `FASTN.ftd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Roughly the number of wasm instructions the handler can execute. `None` means no limit.
+    pub fuel: Option<u64>,
+    /// Bytes of linear memory the handler can grow to. `None` means no limit other than the
+    /// 4GB wasm32 can address.
+    pub memory: Option<usize>,
+    /// Wall-clock time for the whole request, including time spent in host calls (database,
+    /// http etc.). `None` means no limit.
+    pub timeout: Option<std::time::Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fuel: None,
+            memory: Some(DEFAULT_MEMORY),
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LimitExceeded {
+    #[error("wasm handler ran out of fuel, limit: {0}")]
+    Fuel(u64),
+    #[error("wasm handler tried to grow its memory to {desired} bytes, limit: {limit} bytes")]
+    Memory { desired: usize, limit: usize },
+    #[error("wasm handler did not finish in {0:?}")]
+    Timeout(std::time::Duration),
+}
+
+impl LimitExceeded {
+    /// The http status code we respond with. Running out of time or fuel is likely load related
+    /// and worth retrying later, running out of memory is likely a bug in the handler.
+    pub fn status(&self) -> u16 {
+        match self {
+            LimitExceeded::Fuel(_) | LimitExceeded::Timeout(_) => 503,
+            LimitExceeded::Memory { .. } => 500,
+        }
+    }
+}
+
+/// Enforces [`Limits::memory`], and keeps track of the most memory the handler used so we can
+/// log it.
+#[derive(Debug, Default)]
+pub struct Limiter {
+    limit: Option<usize>,
+    pub(crate) peak: usize,
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self { limit, peak: 0 }
+    }
+}
+
+impl wasmtime::ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if let Some(limit) = self.limit
+            && desired > limit
+        {
+            // trap instead of failing `memory.grow`, most allocators abort on a failed grow and
+            // the resulting `unreachable` trap would not tell anyone what went wrong
+            return Err(LimitExceeded::Memory { desired, limit }.into());
+        }
+
+        if maximum.is_some_and(|maximum| desired > maximum) {
+            return Ok(false);
+        }
+
+        self.peak = self.peak.max(desired);
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+}
+
+/// The limit that made `e` happen, if any.
+pub(crate) fn limit_exceeded(e: &wasmtime::Error, limits: &Limits) -> Option<LimitExceeded> {
+    if let Some(wasmtime::Trap::OutOfFuel) = e.downcast_ref::<wasmtime::Trap>() {
+        return Some(LimitExceeded::Fuel(limits.fuel.unwrap_or(u64::MAX)));
+    }
+
+    e.chain()
+        .find_map(|cause| cause.downcast_ref::<LimitExceeded>())
+        .map(|limit| match limit {
+            LimitExceeded::Fuel(fuel) => LimitExceeded::Fuel(*fuel),
+            LimitExceeded::Memory { desired, limit } => LimitExceeded::Memory {
+                desired: *desired,
+                limit: *limit,
+            },
+            LimitExceeded::Timeout(timeout) => LimitExceeded::Timeout(*timeout),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::ResourceLimiter;
+
+    #[test]
+    fn memory() {
+        let mut limiter = super::Limiter::new(Some(100));
+
+        assert!(limiter.memory_growing(0, 50, None).unwrap());
+        assert!(limiter.memory_growing(50, 100, None).unwrap());
+        assert!(!limiter.memory_growing(100, 80, Some(60)).unwrap());
+        assert_eq!(limiter.peak, 100);
+
+        let e = limiter.memory_growing(100, 101, None).unwrap_err();
+        assert_eq!(
+            super::limit_exceeded(&e, &super::Limits::default()).map(|e| e.status()),
+            Some(500)
+        );
+    }
+}
@@ -3,22 +3,84 @@ pub async fn process_http_request<STORE: fastn_wasm::StoreExt + 'static>(
     path: &str,
     module: wasmtime::Module,
     store: fastn_wasm::Store<STORE>,
+    limits: fastn_wasm::Limits,
 ) -> wasmtime::Result<ft_sys_shared::Request> {
     let mut linker = wasmtime::Linker::new(module.engine());
     store.register_functions(&mut linker);
-    let wasm_store = wasmtime::Store::new(module.engine(), store);
-    let (wasm_store, r) = handle(wasm_store, module, linker, path).await?;
-    if let Some(r) = r {
-        return Ok(r);
+    let fuel = limits.fuel.unwrap_or(u64::MAX);
+    let wasm_store = limited_store(module.engine(), store, &limits)?;
+
+    let result = match limits.timeout {
+        Some(timeout) => {
+            match tokio::time::timeout(timeout, handle(wasm_store, module, linker, path)).await {
+                Ok(r) => r,
+                Err(_) => Err(fastn_wasm::LimitExceeded::Timeout(timeout).into()),
+            }
+        }
+        None => handle(wasm_store, module, linker, path).await,
+    };
+
+    let (wasm_store, r) = match result {
+        Ok(v) => v,
+        Err(e) => {
+            return match fastn_wasm::limits::limit_exceeded(&e, &limits) {
+                Some(limit) => {
+                    tracing::warn!(path, "{limit}");
+                    Ok(ft_sys_shared::Request {
+                        uri: "server-error".to_string(),
+                        method: limit.status().to_string(),
+                        headers: vec![],
+                        body: limit.to_string().into_bytes(),
+                    })
+                }
+                None => Err(e),
+            };
+        }
+    };
+
+    tracing::info!(
+        path,
+        fuel = fuel - wasm_store.get_fuel().unwrap_or(fuel),
+        memory = wasm_store.data().limiter.peak,
+        "wasm handler done"
+    );
+
+    let store = wasm_store.into_data();
+    let mut response = match r {
+        Some(r) => r,
+        None => store
+            .response
+            .ok_or(WasmError::EndpointDidNotReturnResponse)?,
+    };
+
+    for cookie in store.set_cookies {
+        response
+            .headers
+            .push(("set-cookie".to_string(), cookie.into_bytes()));
     }
 
-    Ok(wasm_store
-        .into_data()
-        .response
-        .ok_or(WasmError::EndpointDidNotReturnResponse)?)
+    Ok(response)
+}
+
+/// The only place wasm stores are made: the engine meters fuel and interrupts on epochs, so every
+/// store needs fuel and an epoch deadline, or it traps right away or never gets interrupted.
+fn limited_store<STORE: fastn_wasm::StoreExt>(
+    engine: &wasmtime::Engine,
+    mut store: fastn_wasm::Store<STORE>,
+    limits: &fastn_wasm::Limits,
+) -> wasmtime::Result<wasmtime::Store<fastn_wasm::Store<STORE>>> {
+    store.limiter = fastn_wasm::Limiter::new(limits.memory);
+
+    let mut wasm_store = wasmtime::Store::new(engine, store);
+    wasm_store.limiter(|s| &mut s.limiter);
+    wasm_store.set_fuel(limits.fuel.unwrap_or(u64::MAX))?;
+    // yield to tokio on every epoch tick, so the timeout gets a chance to fire even if the
+    // handler never calls back into the host
+    wasm_store.epoch_deadline_async_yield_and_update(1);
+    Ok(wasm_store)
 }
 
-pub async fn handle<S: Send>(
+async fn handle<S: Send>(
     mut wasm_store: wasmtime::Store<S>,
     module: wasmtime::Module,
     linker: wasmtime::Linker<S>,
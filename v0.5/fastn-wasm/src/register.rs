@@ -9,6 +9,21 @@ impl<STORE: fastn_wasm::StoreExt + 'static> fastn_wasm::Store<STORE> {
         fastn_wasm::func2ret!(linker, "email_send", fastn_wasm::email::send);
         fastn_wasm::func2!(linker, "email_cancel", fastn_wasm::email::cancel);
 
+        // sessions, shared with the ftd pages
+        fastn_wasm::func0ret!(linker, "hostn_session_get", fastn_wasm::session::get);
+        fastn_wasm::func2ret!(linker, "hostn_session_set", fastn_wasm::session::set);
+        fastn_wasm::func0ret!(linker, "hostn_session_rotate", fastn_wasm::session::rotate);
+        fastn_wasm::func0ret!(
+            linker,
+            "hostn_session_destroy",
+            fastn_wasm::session::destroy
+        );
+        fastn_wasm::func2ret!(
+            linker,
+            "hostn_session_verify_csrf",
+            fastn_wasm::session::verify_csrf
+        );
+
         // cryptography related stuff
         fastn_wasm::func2ret!(linker, "crypto_encrypt", fastn_wasm::crypto::encrypt);
         fastn_wasm::func2ret!(linker, "crypto_decrypt", fastn_wasm::crypto::decrypt);
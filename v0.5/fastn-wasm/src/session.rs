@@ -0,0 +1,509 @@
+//! Server side sessions, shared by ftd pages (the `session` and `csrf-token` processors) and wasm
+//! apps (the `hostn_session_*` host functions).
+//!
+//! Session data lives in the `fastn_host_session` table of the package's sqlite db. The browser
+//! only gets the session id, signed with the secret keys of the package (see
+//! [Keys::sign](fastn_wasm::crypto::Keys::sign)), in the `fastn-session` cookie. The host passes
+//! the keys in [Store::secret_keys](fastn_wasm::Store::secret_keys).
+//!
+//! Sessions expire `FASTN_SESSION_TTL` seconds (default 30 days) after they were last used. Every
+//! session has a CSRF token, pages embed it in their forms and unsafe requests have to send it
+//! back. Rotate the session (new id and CSRF token, same data) when the user logs in or out.
+
+pub const COOKIE_NAME: &str = "fastn-session";
+
+/// Header unsafe requests send the CSRF token in, forms can use a `csrf-token` field instead.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const CSRF_FIELD: &str = "csrf-token";
+
+const DEFAULT_TTL: i64 = 30 * 24 * 3600;
+
+/// We extend the expiry of a session at most this often, so reading a session does not write to
+/// the db on every request.
+const TOUCH_AFTER: i64 = 3600;
+
+const CREATE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS fastn_host_session (
+    id          TEXT PRIMARY KEY,
+    -- json object
+    data        TEXT NOT NULL DEFAULT '{}',
+    csrf_token  TEXT NOT NULL,
+    -- unix timestamps, in seconds
+    created_at  INTEGER NOT NULL,
+    expires_at  INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS fastn_host_session_expires_at ON fastn_host_session (expires_at);
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("session db error: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("session cookie can not be signed: {0}")]
+    Crypto(#[from] fastn_wasm::crypto::CryptoError),
+    #[error("invalid session data: {0}")]
+    Data(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    /// Not sent to wasm apps, the cookie is all they need.
+    #[serde(skip)]
+    pub id: String,
+    #[serde(rename = "csrf-token")]
+    pub csrf_token: String,
+    pub data: serde_json::Map<String, serde_json::Value>,
+    #[serde(rename = "expires-at")]
+    pub expires_at: i64,
+}
+
+impl Session {
+    /// Compares in constant time, so the token can not be guessed byte by byte.
+    pub fn verify_csrf(&self, token: &str) -> bool {
+        let (a, b) = (self.csrf_token.as_bytes(), token.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
+
+pub struct Sessions {
+    keys: fastn_wasm::crypto::Keys,
+    /// Seconds.
+    ttl: i64,
+}
+
+impl Sessions {
+    pub fn new(keys: fastn_wasm::crypto::Keys, ttl: i64) -> Self {
+        Self { keys, ttl }
+    }
+
+    /// Sessions signed with `keys`, the secret keys of the package, which expire after
+    /// `FASTN_SESSION_TTL` seconds.
+    pub fn from_keys(keys: fastn_wasm::crypto::Keys) -> Self {
+        Self::new(
+            keys,
+            std::env::var("FASTN_SESSION_TTL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TTL),
+        )
+    }
+
+    /// The session id from the value of the `fastn-session` cookie, if it was signed by us.
+    pub fn id_from_cookie(&self, cookie: &str) -> Option<String> {
+        self.keys.verify(cookie)
+    }
+
+    /// The session of the request, if any. `cookie` is the value of the `fastn-session` cookie.
+    pub fn get(
+        &self,
+        conn: &rusqlite::Connection,
+        cookie: Option<&str>,
+    ) -> Result<Option<Session>, SessionError> {
+        match cookie.and_then(|c| self.id_from_cookie(c)) {
+            Some(id) => self.load(conn, id.as_str(), now()),
+            None => Ok(None),
+        }
+    }
+
+    /// Also returns the `Set-Cookie` header value if a new session had to be created.
+    pub fn get_or_create(
+        &self,
+        conn: &rusqlite::Connection,
+        cookie: Option<&str>,
+    ) -> Result<(Session, Option<String>), SessionError> {
+        if let Some(session) = self.get(conn, cookie)? {
+            return Ok((session, None));
+        }
+
+        let session = self.create(conn, Default::default(), now())?;
+        let cookie = self.set_cookie(&session)?;
+        Ok((session, Some(cookie)))
+    }
+
+    pub fn save(&self, conn: &rusqlite::Connection, session: &Session) -> Result<(), SessionError> {
+        conn.execute(
+            "UPDATE fastn_host_session SET data = ?2 WHERE id = ?1",
+            rusqlite::params![session.id, serde_json::to_string(&session.data)?],
+        )?;
+        Ok(())
+    }
+
+    /// Moves the data to a new session id with a new CSRF token, so an id or token that leaked
+    /// before (eg before login) is useless after. Returns the new session and its cookie.
+    pub fn rotate(
+        &self,
+        conn: &rusqlite::Connection,
+        session: &Session,
+    ) -> Result<(Session, String), SessionError> {
+        self.destroy(conn, session.id.as_str())?;
+        let session = self.create(conn, session.data.clone(), now())?;
+        let cookie = self.set_cookie(&session)?;
+        Ok((session, cookie))
+    }
+
+    pub fn destroy(&self, conn: &rusqlite::Connection, id: &str) -> Result<(), SessionError> {
+        create_table(conn)?;
+        conn.execute("DELETE FROM fastn_host_session WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        conn: &rusqlite::Connection,
+        id: &str,
+        now: i64,
+    ) -> Result<Option<Session>, SessionError> {
+        use rusqlite::OptionalExtension;
+
+        create_table(conn)?;
+        let row: Option<(String, String, i64)> = conn
+            .query_row(
+                "SELECT data, csrf_token, expires_at FROM fastn_host_session
+                 WHERE id = ?1 AND expires_at > ?2",
+                rusqlite::params![id, now],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let Some((data, csrf_token, mut expires_at)) = row else {
+            return Ok(None);
+        };
+
+        if expires_at - self.ttl + TOUCH_AFTER < now {
+            expires_at = now + self.ttl;
+            conn.execute(
+                "UPDATE fastn_host_session SET expires_at = ?2 WHERE id = ?1",
+                rusqlite::params![id, expires_at],
+            )?;
+        }
+
+        Ok(Some(Session {
+            id: id.to_string(),
+            csrf_token,
+            data: serde_json::from_str(data.as_str())?,
+            expires_at,
+        }))
+    }
+
+    fn create(
+        &self,
+        conn: &rusqlite::Connection,
+        data: serde_json::Map<String, serde_json::Value>,
+        now: i64,
+    ) -> Result<Session, SessionError> {
+        create_table(conn)?;
+        // as good a time as any to clean up
+        conn.execute(
+            "DELETE FROM fastn_host_session WHERE expires_at <= ?1",
+            [now],
+        )?;
+
+        let session = Session {
+            id: random_token(),
+            csrf_token: random_token(),
+            data,
+            expires_at: now + self.ttl,
+        };
+        conn.execute(
+            "INSERT INTO fastn_host_session (id, data, csrf_token, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                session.id,
+                serde_json::to_string(&session.data)?,
+                session.csrf_token,
+                now,
+                session.expires_at
+            ],
+        )?;
+
+        Ok(session)
+    }
+
+    /// `Set-Cookie` header value for the session. Not `Secure`, so it works on `localhost`.
+    pub fn set_cookie(&self, session: &Session) -> Result<String, SessionError> {
+        Ok(format!(
+            "{COOKIE_NAME}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+            self.keys.sign(session.id.as_str())?,
+            self.ttl
+        ))
+    }
+}
+
+/// `Set-Cookie` header value that removes the session cookie.
+pub fn clear_cookie() -> String {
+    format!("{COOKIE_NAME}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax")
+}
+
+/// Value of the `fastn-session` cookie in a `Cookie` header.
+pub fn cookie_from_header(header: &str) -> Option<&str> {
+    header.split(';').find_map(|c| {
+        c.trim()
+            .split_once('=')
+            .filter(|(name, _)| *name == COOKIE_NAME)
+            .map(|(_, value)| value)
+    })
+}
+
+pub fn create_table(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(CREATE_TABLE)
+}
+
+fn random_token() -> String {
+    format!(
+        "{:032x}{:032x}",
+        rand::random::<u128>(),
+        rand::random::<u128>()
+    )
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+// host functions, they work on the session of the request being handled
+
+/// Returns `Result<Session, String>`, creating the session if the request does not have one.
+pub async fn get<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+) -> wasmtime::Result<i32> {
+    let r = current(&mut caller).map_err(|e| e.to_string());
+    fastn_wasm::helpers::send_json(r, &mut caller).await
+}
+
+/// Takes the new session data, a json object. Returns `Result<Session, String>`.
+pub async fn set<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<i32> {
+    let data: serde_json::Map<String, serde_json::Value> =
+        fastn_wasm::helpers::get_json(ptr, len, &mut caller)?;
+
+    let r = current(&mut caller)
+        .and_then(|mut session| {
+            session.data = data;
+            sessions(caller.data()).save(&caller.data().default_connection()?, &session)?;
+            Ok(session)
+        })
+        .map_err(|e| e.to_string());
+    fastn_wasm::helpers::send_json(r, &mut caller).await
+}
+
+/// Returns `Result<Session, String>`, the session with its new CSRF token.
+pub async fn rotate<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+) -> wasmtime::Result<i32> {
+    let r = current(&mut caller)
+        .and_then(|session| {
+            let (session, cookie) =
+                sessions(caller.data()).rotate(&caller.data().default_connection()?, &session)?;
+            let store = caller.data_mut();
+            store.session_id = Some(session.id.clone());
+            store.set_cookies.push(cookie);
+            Ok(session)
+        })
+        .map_err(|e| e.to_string());
+    fastn_wasm::helpers::send_json(r, &mut caller).await
+}
+
+/// Deletes the session and its cookie. Returns `Result<(), String>`.
+pub async fn destroy<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+) -> wasmtime::Result<i32> {
+    let sessions = sessions(caller.data());
+    let r = match request_session_id(caller.data(), &sessions) {
+        Some(id) => caller
+            .data()
+            .default_connection()
+            .map_err(SessionError::from)
+            .and_then(|conn| sessions.destroy(&conn, id.as_str())),
+        None => Ok(()),
+    }
+    .map_err(|e| e.to_string());
+
+    if r.is_ok() {
+        let store = caller.data_mut();
+        store.session_id = None;
+        store.set_cookies.push(clear_cookie());
+    }
+    fastn_wasm::helpers::send_json(r, &mut caller).await
+}
+
+/// Takes the CSRF token the client sent. Returns `bool`.
+pub async fn verify_csrf<STORE: fastn_wasm::StoreExt>(
+    mut caller: wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<i32> {
+    let token = fastn_wasm::helpers::get_str(ptr, len, &mut caller)?;
+    let sessions = sessions(caller.data());
+
+    let valid = match request_session_id(caller.data(), &sessions) {
+        Some(id) => sessions
+            .load(&caller.data().default_connection()?, id.as_str(), now())?
+            .is_some_and(|s| s.verify_csrf(token.as_str())),
+        None => false,
+    };
+    fastn_wasm::helpers::send_json(valid, &mut caller).await
+}
+
+/// The session of the request (or the one created earlier in this request), created if needed.
+fn current<STORE: fastn_wasm::StoreExt>(
+    caller: &mut wasmtime::Caller<'_, fastn_wasm::Store<STORE>>,
+) -> Result<Session, SessionError> {
+    let sessions = sessions(caller.data());
+    let conn = caller.data().default_connection()?;
+
+    if let Some(id) = request_session_id(caller.data(), &sessions)
+        && let Some(session) = sessions.load(&conn, id.as_str(), now())?
+    {
+        return Ok(session);
+    }
+
+    let session = sessions.create(&conn, Default::default(), now())?;
+    let store = caller.data_mut();
+    store.session_id = Some(session.id.clone());
+    store.set_cookies.push(sessions.set_cookie(&session)?);
+    Ok(session)
+}
+
+fn sessions<STORE: fastn_wasm::StoreExt>(store: &fastn_wasm::Store<STORE>) -> Sessions {
+    Sessions::from_keys(store.secret_keys.clone())
+}
+
+fn request_session_id<STORE: fastn_wasm::StoreExt>(
+    store: &fastn_wasm::Store<STORE>,
+    sessions: &Sessions,
+) -> Option<String> {
+    if let Some(ref id) = store.session_id {
+        return Some(id.clone());
+    }
+
+    store
+        .req
+        .headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("cookie"))
+        .filter_map(|(_, v)| std::str::from_utf8(v).ok())
+        .find_map(cookie_from_header)
+        .and_then(|c| sessions.id_from_cookie(c))
+}
+
+#[cfg(test)]
+mod tests {
+    fn sessions(ttl: i64) -> (super::Sessions, rusqlite::Connection) {
+        (
+            super::Sessions::new(fastn_wasm::crypto::Keys::new(["secret"]), ttl),
+            rusqlite::Connection::open_in_memory().unwrap(),
+        )
+    }
+
+    fn cookie_value(set_cookie: &str) -> &str {
+        let cookie = set_cookie.split(';').next().unwrap();
+        super::cookie_from_header(cookie).unwrap()
+    }
+
+    #[test]
+    fn get_or_create() {
+        let (sessions, conn) = sessions(3600);
+
+        let (mut session, set_cookie) = sessions.get_or_create(&conn, None).unwrap();
+        let set_cookie = set_cookie.unwrap();
+        assert!(set_cookie.contains("HttpOnly"));
+        let cookie = cookie_value(set_cookie.as_str());
+
+        session.data.insert("user".to_string(), 42.into());
+        sessions.save(&conn, &session).unwrap();
+
+        let (got, set_cookie) = sessions.get_or_create(&conn, Some(cookie)).unwrap();
+        assert_eq!(set_cookie, None);
+        assert_eq!(got, session);
+
+        // an unsigned or tampered cookie gets a new session
+        assert_eq!(
+            sessions.get(&conn, Some(session.id.as_str())).unwrap(),
+            None
+        );
+        let tampered = format!("x{cookie}");
+        assert_eq!(sessions.get(&conn, Some(tampered.as_str())).unwrap(), None);
+    }
+
+    #[test]
+    fn expiry() {
+        let (sessions, conn) = sessions(100);
+
+        let session = sessions.create(&conn, Default::default(), 1000).unwrap();
+        assert_eq!(session.expires_at, 1100);
+        assert!(sessions.load(&conn, &session.id, 1099).unwrap().is_some());
+        assert!(sessions.load(&conn, &session.id, 1100).unwrap().is_none());
+
+        // creating a session cleans up the expired ones
+        sessions.create(&conn, Default::default(), 2000).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM fastn_host_session", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn sliding_expiry() {
+        let (sessions, conn) = sessions(2 * super::TOUCH_AFTER);
+
+        let session = sessions.create(&conn, Default::default(), 0).unwrap();
+        let touched = sessions
+            .load(&conn, &session.id, super::TOUCH_AFTER + 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(touched.expires_at, 3 * super::TOUCH_AFTER + 1);
+    }
+
+    #[test]
+    fn rotate() {
+        let (sessions, conn) = sessions(3600);
+
+        let (mut session, _) = sessions.get_or_create(&conn, None).unwrap();
+        session.data.insert("cart".to_string(), "3 items".into());
+        sessions.save(&conn, &session).unwrap();
+
+        let (rotated, set_cookie) = sessions.rotate(&conn, &session).unwrap();
+        assert_ne!(rotated.id, session.id);
+        assert_ne!(rotated.csrf_token, session.csrf_token);
+        assert_eq!(rotated.data, session.data);
+
+        assert!(
+            sessions
+                .load(&conn, &session.id, super::now())
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            sessions
+                .get(&conn, Some(cookie_value(set_cookie.as_str())))
+                .unwrap()
+                .unwrap(),
+            rotated
+        );
+    }
+
+    #[test]
+    fn csrf() {
+        let (sessions, conn) = sessions(3600);
+        let (session, _) = sessions.get_or_create(&conn, None).unwrap();
+
+        assert!(session.verify_csrf(session.csrf_token.clone().as_str()));
+        assert!(!session.verify_csrf(""));
+        assert!(!session.verify_csrf(&session.csrf_token[1..]));
+        assert!(!session.verify_csrf(&"0".repeat(64)));
+    }
+
+    #[test]
+    fn cookie_from_header() {
+        assert_eq!(
+            super::cookie_from_header("a=1; fastn-session=abc.def; b=2"),
+            Some("abc.def")
+        );
+        assert_eq!(super::cookie_from_header("fastn-sessions=abc"), None);
+        assert_eq!(super::cookie_from_header(""), None);
+    }
+}
@@ -10,6 +10,16 @@ pub struct Store<STORE: StoreExt> {
     pub response: Option<ft_sys_shared::Request>,
     pub db_url: String,
     pub inner: STORE,
+    pub limiter: fastn_wasm::Limiter,
+    /// Set once a `hostn_session_*` function creates or rotates the session of this request.
+    pub session_id: Option<String>,
+    /// `Set-Cookie` values the host functions want added to the response.
+    pub set_cookies: Vec<String>,
+    /// The secret keys of the package, the `hostn_session_*` functions sign the session cookie
+    /// with them.
+    pub secret_keys: fastn_wasm::crypto::Keys,
+    /// Where `hostn_tejar_*` keep the blobs of the package.
+    pub tejar: fastn_wasm::tejar::BlobStore,
 }
 
 pub struct StoreImpl;
@@ -51,6 +61,8 @@ impl<STORE: StoreExt> Store<STORE> {
         inner: STORE,
         app_url: String,
         app_mounts: std::collections::HashMap<String, String>,
+        secret_keys: fastn_wasm::crypto::Keys,
+        tejar: fastn_wasm::tejar::BlobStore,
     ) -> Store<STORE> {
         req.headers.push((
             FASTN_MAIN_PACKAGE_HEADER.to_string(),
@@ -77,10 +89,16 @@ impl<STORE: StoreExt> Store<STORE> {
             db_url,
             sqlite: None,
             inner,
+            limiter: Default::default(),
+            session_id: None,
+            set_cookies: vec![],
+            secret_keys,
+            tejar,
         }
     }
 
     #[cfg(not(feature = "postgres"))]
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         main_package: String,
         wasm_package: String,
@@ -89,6 +107,8 @@ impl<STORE: StoreExt> Store<STORE> {
         inner: STORE,
         app_url: String,
         app_mounts: std::collections::HashMap<String, String>,
+        secret_keys: fastn_wasm::crypto::Keys,
+        tejar: fastn_wasm::tejar::BlobStore,
     ) -> Store<STORE> {
         req.headers.push((
             FASTN_MAIN_PACKAGE_HEADER.to_string(),
@@ -113,8 +133,19 @@ impl<STORE: StoreExt> Store<STORE> {
             db_url,
             sqlite: None,
             inner,
+            limiter: Default::default(),
+            session_id: None,
+            set_cookies: vec![],
+            secret_keys,
+            tejar,
         }
     }
+
+    /// The app's default sqlite db, the one `sqlite_connect("default")` opens. Host functions
+    /// keep their own tables (eg the email queue) here.
+    pub(crate) fn default_connection(&self) -> rusqlite::Result<rusqlite::Connection> {
+        rusqlite::Connection::open(self.inner.get_db_url(self.db_url.as_str(), "default"))
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,534 @@
+//! Content addressed blob store for wasm apps, exposed as `hostn_tejar_write` and
+//! `hostn_tejar_read`.
+//!
+//! Blobs are files named by the sha256 of their content, under `.fastn/tejar` in the package.
+//! Their size and content type are recorded in the `fastn_tejar_blob` table of the app's default
+//! sqlite db. Storing the same content twice stores it once.
+//!
+//! Writes go through an upload: the app can send the content in as many chunks as it likes (so
+//! it never needs the whole file in its memory), the blob is created when the last chunk arrives.
+//! Uploads that stop getting chunks are dropped after [UPLOAD_TTL].
+
+/// Default for `FASTN_TEJAR_MAX_SIZE`, in bytes.
+pub const DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// How long an upload can go without a chunk before it is dropped.
+pub const UPLOAD_TTL: std::time::Duration = std::time::Duration::from_secs(24 * 3600);
+
+/// The most we return from one `hostn_tejar_read` call, larger blobs have to be read in parts.
+pub const MAX_READ: u64 = 4 * 1024 * 1024;
+
+const CREATE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS fastn_tejar_blob (
+    hash          TEXT PRIMARY KEY,
+    size          INTEGER NOT NULL,
+    content_type  TEXT,
+    -- unix timestamp, in seconds
+    created_at    INTEGER NOT NULL
+);
+"#;
+
+/// Errors are sent back to the wasm app, so they have to be serializable.
+#[derive(Debug, thiserror::Error, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(tag = "error", rename_all = "kebab-case")]
+pub enum TejarError {
+    #[error("blob is larger than the limit of {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("blob not found: {hash}")]
+    NotFound { hash: String },
+    #[error("invalid blob hash: {hash}")]
+    InvalidHash { hash: String },
+    #[error("invalid upload id: {upload}")]
+    InvalidUpload { upload: String },
+    #[error("invalid request: {message}")]
+    InvalidRequest { message: String },
+    #[error("io error: {message}")]
+    Io { message: String },
+    #[error("db error: {message}")]
+    Db { message: String },
+}
+
+impl From<std::io::Error> for TejarError {
+    fn from(e: std::io::Error) -> Self {
+        TejarError::Io {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for TejarError {
+    fn from(e: rusqlite::Error) -> Self {
+        TejarError::Db {
+            message: e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Blob {
+    pub hash: String,
+    pub size: u64,
+    #[serde(rename = "content-type")]
+    pub content_type: Option<String>,
+}
+
+/// Header of a `hostn_tejar_write` call, the chunk of content follows it.
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct WriteRequest {
+    /// `None` for the first chunk, the id returned for the first chunk for the rest.
+    pub upload: Option<String>,
+    /// This is the last chunk, create the blob.
+    #[serde(default)]
+    pub done: bool,
+    #[serde(rename = "content-type")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum WriteResponse {
+    /// More chunks expected, send them with this `upload` id.
+    Uploading {
+        upload: String,
+        received: u64,
+    },
+    Stored(Blob),
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct ReadRequest {
+    pub hash: String,
+    #[serde(default)]
+    pub offset: u64,
+    /// Defaults to (and is capped at) [MAX_READ].
+    pub length: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: std::path::PathBuf,
+    max_size: u64,
+    upload_ttl: std::time::Duration,
+}
+
+impl BlobStore {
+    /// `dir` is absolute, the host resolves it against the package root.
+    pub fn new(dir: impl Into<std::path::PathBuf>, max_size: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size,
+            upload_ttl: UPLOAD_TTL,
+        }
+    }
+
+    /// Handles one `hostn_tejar_write` call.
+    pub async fn write(
+        &self,
+        conn: &rusqlite::Connection,
+        request: WriteRequest,
+        chunk: &[u8],
+    ) -> Result<WriteResponse, TejarError> {
+        let upload = match request.upload {
+            Some(upload) => {
+                validate_upload(upload.as_str())?;
+                if !tokio::fs::try_exists(self.upload_path(upload.as_str())).await? {
+                    return Err(TejarError::InvalidUpload { upload });
+                }
+                upload
+            }
+            None => {
+                self.expire_uploads().await?;
+                format!("{:032x}", rand::random::<u128>())
+            }
+        };
+
+        let received = match self.append(upload.as_str(), chunk).await {
+            Ok(received) => received,
+            Err(e) => {
+                // the upload is not usable anymore, do not leave it lying around
+                let _ = tokio::fs::remove_file(self.upload_path(upload.as_str())).await;
+                return Err(e);
+            }
+        };
+
+        if !request.done {
+            return Ok(WriteResponse::Uploading { upload, received });
+        }
+
+        Ok(WriteResponse::Stored(
+            self.finish(conn, upload.as_str(), request.content_type)
+                .await?,
+        ))
+    }
+
+    /// Handles one `hostn_tejar_read` call: the blob, and the requested part of its content.
+    pub async fn read(
+        &self,
+        conn: &rusqlite::Connection,
+        request: ReadRequest,
+    ) -> Result<(Blob, Vec<u8>), TejarError> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        validate_hash(request.hash.as_str())?;
+        let blob = self
+            .get(conn, request.hash.as_str())?
+            .ok_or_else(|| TejarError::NotFound {
+                hash: request.hash.clone(),
+            })?;
+
+        let offset = request.offset.min(blob.size);
+        let length = request
+            .length
+            .unwrap_or(MAX_READ)
+            .min(MAX_READ)
+            .min(blob.size - offset);
+
+        let mut file = tokio::fs::File::open(self.blob_path(blob.hash.as_str())).await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut content = vec![];
+        file.take(length).read_to_end(&mut content).await?;
+
+        Ok((blob, content))
+    }
+
+    pub fn get(&self, conn: &rusqlite::Connection, hash: &str) -> Result<Option<Blob>, TejarError> {
+        use rusqlite::OptionalExtension;
+
+        conn.execute_batch(CREATE_TABLE)?;
+        Ok(conn
+            .query_row(
+                "SELECT hash, size, content_type FROM fastn_tejar_blob WHERE hash = ?1",
+                [hash],
+                |row| {
+                    Ok(Blob {
+                        hash: row.get(0)?,
+                        size: row.get::<_, i64>(1)? as u64,
+                        content_type: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Removes the uploads that got no chunk for `upload_ttl`, eg because the app failed midway.
+    /// Runs when a new upload starts.
+    async fn expire_uploads(&self) -> Result<(), TejarError> {
+        let mut entries = match tokio::fs::read_dir(self.dir.join("uploads")).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let modified = entry.metadata().await?.modified()?;
+            if modified.elapsed().is_ok_and(|age| age >= self.upload_ttl) {
+                tracing::info!("dropping expired tejar upload {:?}", entry.file_name());
+                // another request may have finished or dropped it in the meantime
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn append(&self, upload: &str, chunk: &[u8]) -> Result<u64, TejarError> {
+        use tokio::io::AsyncWriteExt;
+
+        let path = self.upload_path(upload);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let received = file.metadata().await?.len() + chunk.len() as u64;
+        if received > self.max_size {
+            return Err(TejarError::TooLarge {
+                limit: self.max_size,
+            });
+        }
+
+        file.write_all(chunk).await?;
+        file.flush().await?;
+        Ok(received)
+    }
+
+    async fn finish(
+        &self,
+        conn: &rusqlite::Connection,
+        upload: &str,
+        content_type: Option<String>,
+    ) -> Result<Blob, TejarError> {
+        let upload_path = self.upload_path(upload);
+        let (hash, size) = hash_file(&upload_path).await?;
+
+        let blob_path = self.blob_path(hash.as_str());
+        if tokio::fs::try_exists(&blob_path).await? {
+            // same content was stored before
+            tokio::fs::remove_file(&upload_path).await?;
+        } else {
+            if let Some(parent) = blob_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::rename(&upload_path, &blob_path).await?;
+        }
+
+        conn.execute_batch(CREATE_TABLE)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO fastn_tejar_blob (hash, size, content_type, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                hash,
+                size as i64,
+                content_type,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+
+        // if the content was stored before, the first content type wins
+        self.get(conn, hash.as_str())?
+            .ok_or(TejarError::NotFound { hash })
+    }
+
+    /// `<dir>/blobs/ab/cdef...`, so no folder ends up with too many files.
+    fn blob_path(&self, hash: &str) -> std::path::PathBuf {
+        let (prefix, rest) = hash.split_at(2);
+        self.dir.join("blobs").join(prefix).join(rest)
+    }
+
+    fn upload_path(&self, upload: &str) -> std::path::PathBuf {
+        self.dir.join("uploads").join(upload)
+    }
+}
+
+async fn hash_file(path: &std::path::Path) -> Result<(String, u64), TejarError> {
+    use sha2::Digest;
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// Hashes and upload ids become file names, so we only accept lowercase hex.
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn validate_hash(hash: &str) -> Result<(), TejarError> {
+    if is_hex(hash, 64) {
+        Ok(())
+    } else {
+        Err(TejarError::InvalidHash {
+            hash: hash.to_string(),
+        })
+    }
+}
+
+fn validate_upload(upload: &str) -> Result<(), TejarError> {
+    if is_hex(upload, 32) {
+        Ok(())
+    } else {
+        Err(TejarError::InvalidUpload {
+            upload: upload.to_string(),
+        })
+    }
+}
+
+/// `hostn_tejar_write` input and `hostn_tejar_read` output are a json header followed by raw
+/// bytes, so content does not have to be base64 encoded: `<header length as u32 le><header><bytes>`.
+pub fn split_frame(frame: &[u8]) -> Result<(&[u8], &[u8]), TejarError> {
+    let invalid = || TejarError::InvalidRequest {
+        message: "frame too short".to_string(),
+    };
+
+    let len = frame.get(..4).ok_or_else(invalid)?;
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let header = frame.get(4..4 + len).ok_or_else(invalid)?;
+    Ok((header, &frame[4 + len..]))
+}
+
+pub fn frame(header: &[u8], content: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(4 + header.len() + content.len());
+    frame.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    frame.extend_from_slice(header);
+    frame.extend_from_slice(content);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    fn store(max_size: u64) -> (super::BlobStore, rusqlite::Connection) {
+        let dir = std::env::temp_dir().join(format!("fastn-tejar-{}", rand::random::<u64>()));
+        (
+            super::BlobStore::new(dir, max_size),
+            rusqlite::Connection::open_in_memory().unwrap(),
+        )
+    }
+
+    fn request(upload: Option<String>, done: bool) -> super::WriteRequest {
+        super::WriteRequest {
+            upload,
+            done,
+            content_type: Some("text/plain".to_string()),
+        }
+    }
+
+    fn read(hash: &str, offset: u64, length: Option<u64>) -> super::ReadRequest {
+        super::ReadRequest {
+            hash: hash.to_string(),
+            offset,
+            length,
+        }
+    }
+
+    #[tokio::test]
+    async fn write_and_read() {
+        let (store, conn) = store(1024);
+
+        let blob = match store
+            .write(&conn, request(None, true), b"hello world")
+            .await
+            .unwrap()
+        {
+            super::WriteResponse::Stored(blob) => blob,
+            r => panic!("expected stored, got {r:?}"),
+        };
+        assert_eq!(
+            blob.hash,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(blob.size, 11);
+
+        let (got, content) = store.read(&conn, read(&blob.hash, 0, None)).await.unwrap();
+        assert_eq!(got, blob);
+        assert_eq!(content, b"hello world");
+
+        let (_, content) = store
+            .read(&conn, read(&blob.hash, 6, Some(3)))
+            .await
+            .unwrap();
+        assert_eq!(content, b"wor");
+
+        // same content, same blob
+        let again = store
+            .write(&conn, request(None, true), b"hello world")
+            .await
+            .unwrap();
+        assert_eq!(again, super::WriteResponse::Stored(blob));
+
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn chunks() {
+        let (store, conn) = store(1024);
+
+        let upload = match store
+            .write(&conn, request(None, false), b"hello ")
+            .await
+            .unwrap()
+        {
+            super::WriteResponse::Uploading { upload, received } => {
+                assert_eq!(received, 6);
+                upload
+            }
+            r => panic!("expected uploading, got {r:?}"),
+        };
+
+        let r = store
+            .write(&conn, request(Some(upload), true), b"world")
+            .await
+            .unwrap();
+        let super::WriteResponse::Stored(blob) = r else {
+            panic!("expected stored, got {r:?}");
+        };
+        let (_, content) = store.read(&conn, read(&blob.hash, 0, None)).await.unwrap();
+        assert_eq!(content, b"hello world");
+
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn expired_upload() {
+        let (mut store, conn) = store(1024);
+
+        let upload = match store
+            .write(&conn, request(None, false), b"hello ")
+            .await
+            .unwrap()
+        {
+            super::WriteResponse::Uploading { upload, .. } => upload,
+            r => panic!("expected uploading, got {r:?}"),
+        };
+
+        // a new upload drops the ones that are too old
+        store.upload_ttl = std::time::Duration::ZERO;
+        store
+            .write(&conn, request(None, true), b"other")
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .write(&conn, request(Some(upload.clone()), true), b"world")
+                .await
+                .unwrap_err(),
+            super::TejarError::InvalidUpload { upload }
+        );
+
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let (store, conn) = store(8);
+
+        assert_eq!(
+            store
+                .write(&conn, request(None, true), b"hello world")
+                .await
+                .unwrap_err(),
+            super::TejarError::TooLarge { limit: 8 }
+        );
+        assert!(matches!(
+            store
+                .write(&conn, request(Some("../../etc".to_string()), true), b"")
+                .await,
+            Err(super::TejarError::InvalidUpload { .. })
+        ));
+        assert!(matches!(
+            store.read(&conn, read("../FASTN.ftd", 0, None)).await,
+            Err(super::TejarError::InvalidHash { .. })
+        ));
+        assert!(matches!(
+            store.read(&conn, read(&"a".repeat(64), 0, None)).await,
+            Err(super::TejarError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn frame() {
+        let frame = super::frame(b"{}", b"content");
+        assert_eq!(
+            super::split_frame(&frame).unwrap(),
+            (&b"{}"[..], &b"content"[..])
+        );
+        assert!(super::split_frame(&frame[..3]).is_err());
+        assert!(super::split_frame(&frame[..5]).is_err());
+    }
+}